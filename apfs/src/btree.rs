@@ -153,14 +153,6 @@ impl BTreeNode {
             + node_header.btn_table_space_off as usize
             + node_header.btn_table_space_len as usize;
 
-        // Parse BTreeInfo if this is a root node (it's at the end of the value area)
-        let info = if node_header.is_root() {
-            let info_start = block.len() - BTreeInfo::SIZE;
-            Some(BTreeInfo::parse(&block[info_start..])?)
-        } else {
-            None
-        };
-
         // Value area end: for root nodes, it's before BTreeInfo; for non-root, it's end of block
         let val_area_end = if node_header.is_root() {
             block.len() - BTreeInfo::SIZE
@@ -168,9 +160,20 @@ impl BTreeNode {
             block.len()
         };
 
-        // Parse TOC entries
-        let mut toc = Vec::with_capacity(node_header.btn_nkeys as usize);
-        let mut cursor = Cursor::new(&block[toc_start..]);
+        // Parse BTreeInfo if this is a root node (it's at the end of the value area)
+        let info = if node_header.is_root() {
+            Some(BTreeInfo::parse(&block[val_area_end..])?)
+        } else {
+            None
+        };
+
+        // Parse TOC entries. The key count is untrusted, so the reservation
+        // is capped by what the block could hold.
+        let toc_area = block.get(toc_start..).ok_or_else(|| {
+            ApfsError::InvalidBTree(format!("TOC offset {} past end of {}-byte node", toc_start, block.len()))
+        })?;
+        let mut toc = Vec::with_capacity((node_header.btn_nkeys as usize).min(toc_area.len() / 4));
+        let mut cursor = Cursor::new(toc_area);
 
         for _ in 0..node_header.btn_nkeys {
             if fixed_kv {
@@ -209,7 +212,9 @@ impl BTreeNode {
 
     /// Get the key bytes for a given TOC index.
     pub fn key(&self, index: usize, fixed_key_size: u32) -> Result<&[u8]> {
-        let entry = &self.toc[index];
+        let entry = self.toc.get(index).ok_or_else(|| {
+            ApfsError::InvalidBTree(format!("TOC index {} out of range ({} entries)", index, self.toc.len()))
+        })?;
         let start = self.key_area_off + entry.key_off as usize;
         let len = if self.node_header.is_fixed_kv() {
             fixed_key_size as usize
@@ -232,7 +237,9 @@ impl BTreeNode {
     ///
     /// For internal (non-leaf) nodes, the value is always an oid_t (u64, 8 bytes).
     pub fn value(&self, index: usize, fixed_val_size: u32) -> Result<&[u8]> {
-        let entry = &self.toc[index];
+        let entry = self.toc.get(index).ok_or_else(|| {
+            ApfsError::InvalidBTree(format!("TOC index {} out of range ({} entries)", index, self.toc.len()))
+        })?;
         let len = if !self.node_header.is_leaf() {
             // Internal node values are always an oid_t (8 bytes)
             8
@@ -243,7 +250,9 @@ impl BTreeNode {
         };
 
         let val_off = entry.val_off as usize;
-        let start = self.val_area_end - val_off;
+        let start = self.val_area_end.checked_sub(val_off).ok_or_else(|| {
+            ApfsError::InvalidBTree(format!("value offset {} past val_area_end {}", val_off, self.val_area_end))
+        })?;
         let end = start + len;
         if end > self.block_data.len() || start < self.key_area_off {
             return Err(ApfsError::InvalidBTree(
@@ -410,6 +419,7 @@ where
     Ok(results)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn btree_scan_node<R: Read + Seek, F>(
     reader: &mut R,
     node: &BTreeNode,
@@ -437,10 +447,10 @@ where
         }
         Ok(true)
     } else {
-        // For non-leaf nodes, visit each child subtree
-        // The number of children is btn_nkeys (each key has an associated child pointer)
-        // Plus there may be one more child than keys (rightmost child).
-        // In APFS B-trees, btn_nkeys IS the number of children for internal nodes.
+        // Unlike a textbook B+ tree, APFS index nodes have no extra rightmost
+        // child: every child pointer is stored as the value of a TOC entry whose
+        // key is the smallest key in that subtree, so there are exactly
+        // btn_nkeys children.
         for i in 0..node.node_header.btn_nkeys as usize {
             let child_oid = node.child_oid(i)?;
             let child_block = resolve_child_oid(reader, child_oid, block_size, omap_root)?;
@@ -454,3 +464,125 @@ where
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_SIZE: u32 = 4096;
+
+    /// Build a fixed-size (8-byte key, 8-byte value) node holding `entries`.
+    fn build_node(level: u16, is_root: bool, entries: &[(u64, u64)]) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE as usize];
+        block[0x18..0x1C].copy_from_slice(&object::OBJECT_TYPE_BTREE_NODE.to_le_bytes());
+
+        let mut flags = BTNODE_FIXED_KV_SIZE;
+        if level == 0 {
+            flags |= BTNODE_LEAF;
+        }
+        if is_root {
+            flags |= BTNODE_ROOT;
+        }
+        let toc_len = (entries.len() * 4) as u16;
+        let hdr = ObjectHeader::SIZE;
+        block[hdr..hdr + 2].copy_from_slice(&flags.to_le_bytes());
+        block[hdr + 2..hdr + 4].copy_from_slice(&level.to_le_bytes());
+        block[hdr + 4..hdr + 8].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+        block[hdr + 10..hdr + 12].copy_from_slice(&toc_len.to_le_bytes());
+
+        let toc_start = hdr + BTreeNodeHeader::SIZE;
+        let key_area = toc_start + toc_len as usize;
+        let val_end = if is_root {
+            block.len() - BTreeInfo::SIZE
+        } else {
+            block.len()
+        };
+
+        for (i, (key, val)) in entries.iter().enumerate() {
+            let key_off = (i * 8) as u16;
+            let val_off = ((i + 1) * 8) as u16;
+            let toc = toc_start + i * 4;
+            block[toc..toc + 2].copy_from_slice(&key_off.to_le_bytes());
+            block[toc + 2..toc + 4].copy_from_slice(&val_off.to_le_bytes());
+
+            let k = key_area + key_off as usize;
+            block[k..k + 8].copy_from_slice(&key.to_le_bytes());
+            let v = val_end - val_off as usize;
+            block[v..v + 8].copy_from_slice(&val.to_le_bytes());
+        }
+
+        if is_root {
+            let info = block.len() - BTreeInfo::SIZE;
+            block[info..info + 4].copy_from_slice(&BTREE_PHYSICAL.to_le_bytes());
+            block[info + 4..info + 8].copy_from_slice(&BLOCK_SIZE.to_le_bytes());
+            block[info + 8..info + 12].copy_from_slice(&8u32.to_le_bytes());
+            block[info + 12..info + 16].copy_from_slice(&8u32.to_le_bytes());
+        }
+
+        block
+    }
+
+    /// Two-level tree: root (block 0) -> leaves in blocks 1, 2, 3.
+    fn build_two_level_tree() -> Vec<u8> {
+        let mut image = build_node(1, true, &[(10, 1), (20, 2), (30, 3)]);
+        image.extend(build_node(0, false, &[(10, 100), (11, 110)]));
+        image.extend(build_node(0, false, &[(20, 200), (21, 210)]));
+        image.extend(build_node(0, false, &[(30, 300), (31, 310), (32, 320)]));
+        image
+    }
+
+    #[test]
+    fn test_btree_scan_visits_last_subtree() {
+        let mut reader = Cursor::new(build_two_level_tree());
+
+        let results = btree_scan(&mut reader, 0, BLOCK_SIZE, 8, 8, &|_| Some(true), None).unwrap();
+        let keys: Vec<u64> = results
+            .iter()
+            .map(|(k, _)| u64::from_le_bytes(k[..8].try_into().unwrap()))
+            .collect();
+        assert_eq!(keys, vec![10, 11, 20, 21, 30, 31, 32]);
+    }
+
+    #[test]
+    fn test_btree_lookup_in_last_subtree() {
+        let mut reader = Cursor::new(build_two_level_tree());
+
+        let cmp = |target: u64| {
+            move |k: &[u8]| u64::from_le_bytes(k[..8].try_into().unwrap()).cmp(&target)
+        };
+
+        let val = btree_lookup(&mut reader, 0, BLOCK_SIZE, 8, 8, &cmp(32), None).unwrap();
+        assert_eq!(val, Some(320u64.to_le_bytes().to_vec()));
+
+        let val = btree_lookup(&mut reader, 0, BLOCK_SIZE, 8, 8, &cmp(15), None).unwrap();
+        assert_eq!(val, None);
+    }
+
+    #[test]
+    fn test_node_rejects_out_of_range_offsets() {
+        let leaf = build_node(0, false, &[(10, 100)]);
+        let node = BTreeNode::parse(&leaf).unwrap();
+        assert!(matches!(node.key(1, 8), Err(ApfsError::InvalidBTree(_))));
+        assert!(matches!(node.value(1, 8), Err(ApfsError::InvalidBTree(_))));
+
+        // Value offset reaching before the start of the block
+        let toc = ObjectHeader::SIZE + BTreeNodeHeader::SIZE;
+        let mut bad = leaf.clone();
+        bad[toc + 2..toc + 4].copy_from_slice(&u16::MAX.to_le_bytes());
+        let node = BTreeNode::parse(&bad[..2048]).unwrap();
+        assert!(matches!(node.value(0, 8), Err(ApfsError::InvalidBTree(_))));
+
+        // TOC placed past the end of the block
+        let mut bad = leaf.clone();
+        bad[ObjectHeader::SIZE + 8..ObjectHeader::SIZE + 10].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(BTreeNode::parse(&bad), Err(ApfsError::InvalidBTree(_))));
+
+        // Truncations of a valid node never panic
+        for len in 0..=leaf.len() {
+            if let Ok(node) = BTreeNode::parse(&leaf[..len]) {
+                let _ = node.key(0, 8);
+                let _ = node.value(0, 8);
+            }
+        }
+    }
+}
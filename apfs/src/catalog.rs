@@ -28,8 +28,16 @@ pub const INODE_DIR_TYPE: u16 = 0o040000;      // S_IFDIR
 pub const INODE_FILE_TYPE: u16 = 0o100000;     // S_IFREG
 pub const INODE_SYMLINK_TYPE: u16 = 0o120000;  // S_IFLNK
 
+// Inode internal flags (j_inode_flags)
+pub const INODE_WAS_CLONED: u64 = 0x0000_0010;
+pub const INODE_WAS_EVER_CLONED: u64 = 0x0000_0400;
+
+// Extended attribute value flags (j_xattr_flags)
+pub const XATTR_DATA_STREAM: u16 = 0x0001;
+pub const XATTR_DATA_EMBEDDED: u16 = 0x0002;
+
 // Extended field types (INO_EXT_TYPE_*)
-const INO_EXT_TYPE_DSTREAM: u8 = 8;
+pub(crate) const INO_EXT_TYPE_DSTREAM: u8 = 8;
 
 /// Parsed inode value from a catalog record.
 #[derive(Debug, Clone)]
@@ -172,6 +180,12 @@ impl InodeVal {
     pub fn nlink(&self) -> u32 {
         self.nchildren_or_nlink as u32
     }
+
+    /// Whether this inode was created as, or has ever been, a clone.
+    /// Clones have their own extent records that may share physical blocks.
+    pub fn was_ever_cloned(&self) -> bool {
+        self.internal_flags & (INODE_WAS_CLONED | INODE_WAS_EVER_CLONED) != 0
+    }
 }
 
 /// Directory record value (j_drec_val_t)
@@ -245,6 +259,59 @@ impl FileExtentVal {
     }
 }
 
+/// Where an extended attribute's data lives
+#[derive(Debug, Clone)]
+pub enum XattrData {
+    /// Stored inline in the catalog record
+    Embedded(Vec<u8>),
+    /// Stored in a data stream whose extents are keyed by `xattr_obj_id`
+    Stream { xattr_obj_id: u64, size: u64 },
+}
+
+/// Extended attribute value (j_xattr_val_t)
+#[derive(Debug, Clone)]
+pub struct XattrVal {
+    pub flags: u16,
+    pub data: XattrData,
+}
+
+impl XattrVal {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 4 {
+            return Err(ApfsError::CorruptedData(
+                format!("xattr value too short: {} bytes", data.len()),
+            ));
+        }
+        let flags = u16::from_le_bytes([data[0], data[1]]);
+        let xdata_len = u16::from_le_bytes([data[2], data[3]]) as usize;
+        let xdata = data.get(4..4 + xdata_len).ok_or_else(|| {
+            ApfsError::CorruptedData(format!(
+                "xattr data extends beyond value: {} > {} bytes",
+                xdata_len,
+                data.len() - 4
+            ))
+        })?;
+
+        let data = if flags & XATTR_DATA_STREAM != 0 {
+            // j_xattr_dstream_t: xattr_obj_id (u64) followed by j_dstream_t,
+            // whose first field is the logical size
+            if xdata.len() < 16 {
+                return Err(ApfsError::CorruptedData(
+                    format!("xattr dstream too short: {} bytes", xdata.len()),
+                ));
+            }
+            let mut cursor = Cursor::new(xdata);
+            let xattr_obj_id = cursor.read_u64::<LittleEndian>()?;
+            let size = cursor.read_u64::<LittleEndian>()?;
+            XattrData::Stream { xattr_obj_id, size }
+        } else {
+            XattrData::Embedded(xdata.to_vec())
+        };
+
+        Ok(XattrVal { flags, data })
+    }
+}
+
 /// Decode a catalog key: extract obj_id and type from the combined j_key_t.
 fn decode_catalog_key(key_bytes: &[u8]) -> Result<(u64, u8)> {
     if key_bytes.len() < 8 {
@@ -289,14 +356,38 @@ fn decode_drec_name(key_bytes: &[u8]) -> Result<String> {
     Ok(String::from_utf8_lossy(&name_bytes[..nul_pos]).to_string())
 }
 
+/// Extract the name from an extended attribute key (j_xattr_key_t).
+/// After the 8-byte obj_id_and_type, there's a 2-byte name_len followed by
+/// the null-terminated UTF-8 name.
+fn decode_xattr_name(key_bytes: &[u8]) -> Result<String> {
+    if key_bytes.len() < 10 {
+        return Err(ApfsError::InvalidBTree("xattr key too short for name".into()));
+    }
+
+    let name_len = u16::from_le_bytes([key_bytes[8], key_bytes[9]]) as usize;
+    let name_bytes = key_bytes.get(10..10 + name_len).ok_or_else(|| {
+        ApfsError::InvalidBTree(format!(
+            "xattr name extends beyond key: name_len={}, key_len={}",
+            name_len,
+            key_bytes.len()
+        ))
+    })?;
+
+    let nul_pos = name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len());
+    Ok(String::from_utf8_lossy(&name_bytes[..nul_pos]).to_string())
+}
+
 /// List directory entries for a given parent OID.
 ///
 /// Scans the catalog B-tree for all J_TYPE_DIR_REC entries whose obj_id matches
 /// the parent directory OID. For each, looks up the inode to get size/timestamps.
+///
+/// `omap_root` is the volume OMAP used to resolve child node OIDs, or `None`
+/// when the catalog tree is physically addressed.
 pub fn list_directory<R: Read + Seek>(
     reader: &mut R,
     catalog_root: u64,
-    omap_root: u64,
+    omap_root: Option<u64>,
     block_size: u32,
     parent_oid: u64,
 ) -> Result<Vec<DirEntry>> {
@@ -327,7 +418,7 @@ pub fn list_directory<R: Read + Seek>(
         block_size,
         0, 0, // variable-size keys and values
         &range_fn,
-        Some(omap_root),
+        omap_root,
     )?;
 
     let mut dir_entries = Vec::new();
@@ -369,11 +460,55 @@ pub fn list_directory<R: Read + Seek>(
     Ok(dir_entries)
 }
 
+/// Find the name of the directory record under `parent_oid` that points at `child_oid`.
+///
+/// Unlike `list_directory`, this does not look up the inode of every entry.
+pub fn lookup_drec_name<R: Read + Seek>(
+    reader: &mut R,
+    catalog_root: u64,
+    omap_root: Option<u64>,
+    block_size: u32,
+    parent_oid: u64,
+    child_oid: u64,
+) -> Result<Option<String>> {
+    let range_fn = |key: &[u8]| -> Option<bool> {
+        match decode_catalog_key(key) {
+            Ok((oid, j_type)) => match compare_catalog_keys(oid, j_type, parent_oid, J_TYPE_DIR_REC) {
+                std::cmp::Ordering::Less => Some(false),
+                std::cmp::Ordering::Equal => Some(true),
+                std::cmp::Ordering::Greater => None,
+            },
+            Err(_) => Some(false),
+        }
+    };
+
+    let entries = btree::btree_scan(
+        reader,
+        catalog_root,
+        block_size,
+        0, 0,
+        &range_fn,
+        omap_root,
+    )?;
+
+    for (key, val) in &entries {
+        let drec = match DrecVal::parse(val) {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        if drec.file_id == child_oid {
+            return decode_drec_name(key).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
 /// Look up an inode record in the catalog B-tree.
 pub fn lookup_inode<R: Read + Seek>(
     reader: &mut R,
     catalog_root: u64,
-    omap_root: u64,
+    omap_root: Option<u64>,
     block_size: u32,
     oid: u64,
 ) -> Result<InodeVal> {
@@ -397,7 +532,7 @@ pub fn lookup_inode<R: Read + Seek>(
         block_size,
         0, 0,
         &compare_fn,
-        Some(omap_root),
+        omap_root,
     )?;
 
     match val {
@@ -406,11 +541,36 @@ pub fn lookup_inode<R: Read + Seek>(
     }
 }
 
+/// Look up the reference count of a data stream (its `J_TYPE_DSTREAM_ID` record).
+pub fn lookup_dstream_refcount<R: Read + Seek>(
+    reader: &mut R,
+    catalog_root: u64,
+    omap_root: Option<u64>,
+    block_size: u32,
+    dstream_id: u64,
+) -> Result<u32> {
+    let compare_fn = |key: &[u8]| -> std::cmp::Ordering {
+        match decode_catalog_key(key) {
+            Ok((key_oid, key_type)) => compare_catalog_keys(key_oid, key_type, dstream_id, J_TYPE_DSTREAM_ID),
+            Err(_) => std::cmp::Ordering::Less,
+        }
+    };
+
+    let val = btree::btree_lookup(reader, catalog_root, block_size, 0, 0, &compare_fn, omap_root)?
+        .ok_or_else(|| ApfsError::FileNotFound(format!("dstream ID {}", dstream_id)))?;
+
+    // j_dstream_id_val_t: refcnt (u32)
+    let refcnt = val
+        .get(0..4)
+        .ok_or_else(|| ApfsError::CorruptedData(format!("dstream ID {} record too short", dstream_id)))?;
+    Ok(u32::from_le_bytes([refcnt[0], refcnt[1], refcnt[2], refcnt[3]]))
+}
+
 /// Look up all file extent records for a given file OID (private_id).
 pub fn lookup_extents<R: Read + Seek>(
     reader: &mut R,
     catalog_root: u64,
-    omap_root: u64,
+    omap_root: Option<u64>,
     block_size: u32,
     file_oid: u64,
 ) -> Result<Vec<FileExtentVal>> {
@@ -437,7 +597,7 @@ pub fn lookup_extents<R: Read + Seek>(
         block_size,
         0, 0,
         &range_fn,
-        Some(omap_root),
+        omap_root,
     )?;
 
     let mut extents = Vec::new();
@@ -448,11 +608,49 @@ pub fn lookup_extents<R: Read + Seek>(
     Ok(extents)
 }
 
+/// Look up all extended attribute records for a given inode OID.
+///
+/// Returns `(name, value)` pairs in catalog order, which sorts by name.
+pub fn lookup_xattrs<R: Read + Seek>(
+    reader: &mut R,
+    catalog_root: u64,
+    omap_root: Option<u64>,
+    block_size: u32,
+    inode_oid: u64,
+) -> Result<Vec<(String, XattrVal)>> {
+    let range_fn = |key: &[u8]| -> Option<bool> {
+        match decode_catalog_key(key) {
+            Ok((oid, j_type)) => match compare_catalog_keys(oid, j_type, inode_oid, J_TYPE_XATTR) {
+                std::cmp::Ordering::Less => Some(false),
+                std::cmp::Ordering::Greater => None,
+                std::cmp::Ordering::Equal => Some(true),
+            },
+            Err(_) => Some(false),
+        }
+    };
+
+    let entries = btree::btree_scan(
+        reader,
+        catalog_root,
+        block_size,
+        0, 0,
+        &range_fn,
+        omap_root,
+    )?;
+
+    let mut xattrs = Vec::new();
+    for (key, val) in &entries {
+        xattrs.push((decode_xattr_name(key)?, XattrVal::parse(val)?));
+    }
+
+    Ok(xattrs)
+}
+
 /// Resolve a path like "/Applications/Upscayl.app/Contents/Info.plist" to its (OID, InodeVal).
 pub fn resolve_path<R: Read + Seek>(
     reader: &mut R,
     catalog_root: u64,
-    omap_root: u64,
+    omap_root: Option<u64>,
     block_size: u32,
     path: &str,
 ) -> Result<(u64, InodeVal)> {
@@ -493,7 +691,7 @@ pub fn resolve_path<R: Read + Seek>(
 /// Look up a specific directory record by name under a parent OID.
 fn lookup_drec<R: Read + Seek>(
     reader: &mut R,
-    omap_root: u64,
+    omap_root: Option<u64>,
     catalog_root: u64,
     block_size: u32,
     parent_oid: u64,
@@ -523,7 +721,7 @@ fn lookup_drec<R: Read + Seek>(
         block_size,
         0, 0,
         &range_fn,
-        Some(omap_root),
+        omap_root,
     )?;
 
     for (key, val) in &entries {
@@ -547,13 +745,14 @@ fn compare_catalog_keys(oid_a: u64, type_a: u8, oid_b: u64, type_b: u8) -> std::
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast)]
 mod tests {
     use super::*;
     use crate::superblock;
     use crate::omap as omap_mod;
     use std::io::BufReader;
 
-    fn open_volume() -> (BufReader<std::fs::File>, u64, u64, u32) {
+    fn open_volume() -> (BufReader<std::fs::File>, u64, Option<u64>, u32) {
         let file = std::fs::File::open("../tests/appfs.raw").unwrap();
         let mut reader = BufReader::new(file);
 
@@ -572,7 +771,7 @@ mod tests {
         let vol_omap_root = omap_mod::read_omap_tree_root(&mut reader, vol_sb.omap_oid, block_size).unwrap();
         let catalog_root = omap_mod::omap_lookup(&mut reader, vol_omap_root, block_size, vol_sb.root_tree_oid).unwrap();
 
-        (reader, catalog_root, vol_omap_root, block_size)
+        (reader, catalog_root, Some(vol_omap_root), block_size)
     }
 
     /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
@@ -613,6 +812,23 @@ mod tests {
         assert_eq!(drec.file_type(), DT_DIR);
     }
 
+    #[test]
+    fn test_inode_val_clone_flags() {
+        let mut data = vec![0u8; InodeVal::FIXED_SIZE];
+        data[8..16].copy_from_slice(&77u64.to_le_bytes()); // private_id
+        data[80..82].copy_from_slice(&INODE_FILE_TYPE.to_le_bytes()); // mode
+
+        let inode = InodeVal::parse(&data).unwrap();
+        assert_eq!(inode.private_id, 77);
+        assert!(!inode.was_ever_cloned());
+
+        data[48..56].copy_from_slice(&INODE_WAS_EVER_CLONED.to_le_bytes()); // internal_flags
+        assert!(InodeVal::parse(&data).unwrap().was_ever_cloned());
+
+        data[48..56].copy_from_slice(&INODE_WAS_CLONED.to_le_bytes());
+        assert!(InodeVal::parse(&data).unwrap().was_ever_cloned());
+    }
+
     #[test]
     fn test_file_extent_val_parse() {
         // Construct FileExtentVal bytes: flags_and_length(u64) + phys_block_num(u64) + crypto_id(u64)
@@ -628,4 +844,37 @@ mod tests {
         assert_eq!(extent.phys_block_num, 100);
         assert_eq!(extent.crypto_id, 0);
     }
+
+    #[test]
+    fn test_xattr_val_parse() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&XATTR_DATA_EMBEDDED.to_le_bytes());
+        data.extend_from_slice(&5u16.to_le_bytes());
+        data.extend_from_slice(b"hello");
+        let xattr = XattrVal::parse(&data).unwrap();
+        assert!(matches!(xattr.data, XattrData::Embedded(ref d) if d == b"hello"));
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&XATTR_DATA_STREAM.to_le_bytes());
+        data.extend_from_slice(&48u16.to_le_bytes());
+        data.extend_from_slice(&900u64.to_le_bytes()); // xattr_obj_id
+        data.extend_from_slice(&70000u64.to_le_bytes()); // dstream size
+        data.extend_from_slice(&[0u8; 32]);
+        let xattr = XattrVal::parse(&data).unwrap();
+        assert!(matches!(xattr.data, XattrData::Stream { xattr_obj_id: 900, size: 70000 }));
+
+        // xdata_len past the end of the value
+        assert!(XattrVal::parse(&[2, 0, 10, 0, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_decode_xattr_name() {
+        let mut key = Vec::new();
+        key.extend_from_slice(&((J_TYPE_XATTR as u64) << 60 | 16).to_le_bytes());
+        key.extend_from_slice(&21u16.to_le_bytes());
+        key.extend_from_slice(b"com.apple.quarantine\0");
+        assert_eq!(decode_catalog_key(&key).unwrap(), (16, J_TYPE_XATTR));
+        assert_eq!(decode_xattr_name(&key).unwrap(), "com.apple.quarantine");
+        assert!(decode_xattr_name(&key[..20]).is_err());
+    }
 }
@@ -20,11 +20,23 @@ pub enum ApfsError {
     #[error("not a directory: {0}")]
     NotADirectory(String),
 
+    #[error("not a symbolic link: {0}")]
+    NotASymlink(String),
+
     #[error("corrupted data: {0}")]
     CorruptedData(String),
 
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("directory tree deeper than {max_depth} levels at {path}")]
+    TooDeep { path: String, max_depth: usize },
+
     #[error("no volume found in container")]
     NoVolume,
+
+    #[error("truncated container: expected {expected} bytes, got {actual}")]
+    Truncated { expected: u64, actual: u64 },
 }
 
 pub type Result<T> = std::result::Result<T, ApfsError>;
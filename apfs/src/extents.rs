@@ -1,10 +1,29 @@
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::catalog::FileExtentVal;
-use crate::error::Result;
+use crate::catalog::{FileExtentVal, InodeVal};
+use crate::error::{ApfsError, Result};
+use crate::HashAlgo;
+
+/// Number of bytes to read for a file.
+///
+/// Uses the inode's size, unless it is 0 while extents exist: inodes without
+/// a dstream xfield fall back to `uncompressed_size`, which may be 0 even
+/// though the data is there. The extent lengths are summed instead.
+pub fn file_size(inode: &InodeVal, extents: &[FileExtentVal]) -> u64 {
+    match inode.size() {
+        0 => extents.iter().map(|e| e.length()).sum(),
+        size => size,
+    }
+}
 
 /// Read file data from extents, streaming to a writer.
-/// Returns the number of bytes written.
+///
+/// Extents cover whole blocks, so their summed length usually exceeds the
+/// file; output stops at `logical_size` and the allocation tail is never
+/// written. Returns the number of bytes written.
 pub fn read_file_data<R: Read + Seek, W: Write>(
     reader: &mut R,
     block_size: u32,
@@ -46,6 +65,95 @@ pub fn read_file_data<R: Read + Seek, W: Write>(
     Ok(bytes_written)
 }
 
+/// Writer adapter that reports `(bytes_written, total_size)` after each write.
+///
+/// When `cancel` is set, each write first checks the flag and fails once it
+/// is raised; [`finish`](Self::finish) turns that failure into `Cancelled`.
+pub(crate) struct ProgressWriter<'a, W, F> {
+    inner: W,
+    written: u64,
+    total: u64,
+    progress: F,
+    cancel: Option<&'a AtomicBool>,
+    cancelled: bool,
+}
+
+impl<'a, W: Write, F: FnMut(u64, u64)> ProgressWriter<'a, W, F> {
+    pub(crate) fn new(inner: W, total: u64, progress: F, cancel: Option<&'a AtomicBool>) -> Self {
+        ProgressWriter {
+            inner,
+            written: 0,
+            total,
+            progress,
+            cancel,
+            cancelled: false,
+        }
+    }
+
+    /// Replace the result of a read that wrote through this adapter with
+    /// `Cancelled` if it stopped because of the cancel flag
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        if self.cancelled {
+            return Err(ApfsError::Cancelled);
+        }
+        result
+    }
+}
+
+impl<W: Write, F: FnMut(u64, u64)> Write for ProgressWriter<'_, W, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            self.cancelled = true;
+            return Err(std::io::Error::other("operation cancelled"));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        (self.progress)(self.written, self.total);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer adapter that feeds everything written into a digest.
+pub(crate) enum HashWriter {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl HashWriter {
+    pub(crate) fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha1 => HashWriter::Sha1(Sha1::new()),
+            HashAlgo::Sha256 => HashWriter::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Consume the hasher and return the digest bytes.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            HashWriter::Sha1(h) => h.finalize().to_vec(),
+            HashWriter::Sha256(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            HashWriter::Sha1(h) => h.update(buf),
+            HashWriter::Sha256(h) => h.update(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 /// A reader that presents a file's extents as a contiguous Read + Seek stream.
 pub struct ApfsForkReader<'a, R: Read + Seek> {
     reader: &'a mut R,
@@ -160,6 +268,143 @@ impl<R: Read + Seek> Seek for ApfsForkReader<'_, R> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_file_data_progress() {
+        let block_size = 4096u32;
+        let size = 4096 * 2 + 1000;
+        let mut image = vec![0u8; 4096];
+        image.extend((0..4096 * 3).map(|i| (i % 251) as u8));
+
+        let extents = vec![FileExtentVal {
+            flags_and_length: 4096 * 3,
+            phys_block_num: 1,
+            crypto_id: 0,
+        }];
+
+        let mut calls = Vec::new();
+        let mut out = Vec::new();
+        let written = {
+            let mut writer = ProgressWriter::new(&mut out, size, |done, total| calls.push((done, total)), None);
+            read_file_data(&mut std::io::Cursor::new(&image), block_size, &extents, size, &mut writer).unwrap()
+        };
+
+        assert_eq!(written, size);
+        assert_eq!(&out[..], &image[4096..4096 + size as usize]);
+        assert_eq!(calls, vec![(4096, size), (8192, size), (size, size)]);
+    }
+
+    /// Inode value with `uncompressed_size` and an optional dstream xfield
+    fn inode_bytes(uncompressed_size: u64, dstream_size: Option<u64>) -> Vec<u8> {
+        let mut data = vec![0u8; 92];
+        data[80..82].copy_from_slice(&crate::catalog::INODE_FILE_TYPE.to_le_bytes());
+        data[84..92].copy_from_slice(&uncompressed_size.to_le_bytes());
+        if let Some(size) = dstream_size {
+            data.extend_from_slice(&1u16.to_le_bytes()); // xf_num_exts
+            data.extend_from_slice(&40u16.to_le_bytes()); // xf_used_data
+            data.extend_from_slice(&[crate::catalog::INO_EXT_TYPE_DSTREAM, 0]);
+            data.extend_from_slice(&40u16.to_le_bytes());
+            let mut dstream = [0u8; 40];
+            dstream[..8].copy_from_slice(&size.to_le_bytes());
+            dstream[8..16].copy_from_slice(&8192u64.to_le_bytes()); // alloced_size
+            data.extend_from_slice(&dstream);
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_file_data_over_allocated_extent() {
+        let block_size = 4096u32;
+        let logical = 5000u64;
+        // Two allocated blocks; everything past the logical size is non-zero
+        let mut image = vec![0u8; 4096];
+        image.extend((0..4096 * 2).map(|i| (i % 253) as u8 | 1));
+
+        let extents = vec![FileExtentVal {
+            flags_and_length: 4096 * 2,
+            phys_block_num: 1,
+            crypto_id: 0,
+        }];
+
+        for (uncompressed_size, dstream_size) in [(0, Some(logical)), (99_999, Some(logical)), (logical, None)] {
+            let inode = InodeVal::parse(&inode_bytes(uncompressed_size, dstream_size)).unwrap();
+            assert_eq!(inode.dstream_size, dstream_size);
+            let size = file_size(&inode, &extents);
+            assert_eq!(size, logical);
+
+            let mut out = Vec::new();
+            let mut reader = std::io::Cursor::new(&image);
+            let written = read_file_data(&mut reader, block_size, &extents, size, &mut out).unwrap();
+            assert_eq!(written, logical);
+            assert_eq!(&out[..], &image[4096..4096 + logical as usize]);
+        }
+    }
+
+    #[test]
+    fn test_read_file_data_cancel() {
+        let block_size = 4096u32;
+        let size = 4096 * 3;
+        let mut image = vec![0u8; 4096];
+        image.extend((0..4096 * 3).map(|i| (i % 251) as u8));
+
+        let extents = vec![FileExtentVal {
+            flags_and_length: 4096 * 3,
+            phys_block_num: 1,
+            crypto_id: 0,
+        }];
+
+        // Raise the flag from the progress callback after the first block
+        let cancel = AtomicBool::new(false);
+        let mut out = Vec::new();
+        let mut writer = ProgressWriter::new(&mut out, size, |_, _| cancel.store(true, Ordering::Relaxed), Some(&cancel));
+        let result = read_file_data(&mut std::io::Cursor::new(&image), block_size, &extents, size, &mut writer);
+        assert!(matches!(writer.finish(result), Err(ApfsError::Cancelled)));
+        assert_eq!(out.len(), 4096);
+    }
+
+    #[test]
+    fn test_read_file_data_hash() {
+        let block_size = 4096u32;
+        let size = 4096 + 500;
+        let mut image = vec![0u8; 4096];
+        image.extend((0..4096 * 2).map(|i| (i % 241) as u8));
+
+        let extents = vec![FileExtentVal {
+            flags_and_length: 4096 * 2,
+            phys_block_num: 1,
+            crypto_id: 0,
+        }];
+
+        let mut hasher = HashWriter::new(HashAlgo::Sha256);
+        read_file_data(&mut std::io::Cursor::new(&image), block_size, &extents, size, &mut hasher).unwrap();
+        assert_eq!(hasher.finish(), Sha256::digest(&image[4096..4096 + size as usize]).to_vec());
+    }
+
+    #[test]
+    fn test_file_size_without_dstream() {
+        let block_size = 4096u32;
+        let mut image = vec![0u8; 4096];
+        image.extend((0..4096 * 2).map(|i| (i % 239) as u8));
+
+        // No dstream xfield and a zero uncompressed_size
+        let inode = InodeVal::parse(&[0u8; 92]).unwrap();
+        assert_eq!(inode.size(), 0);
+
+        let extents = vec![
+            FileExtentVal { flags_and_length: 4096, phys_block_num: 2, crypto_id: 0 },
+            FileExtentVal { flags_and_length: 4096, phys_block_num: 1, crypto_id: 0 },
+        ];
+        let size = file_size(&inode, &extents);
+        assert_eq!(size, 8192);
+        assert_eq!(file_size(&inode, &[]), 0);
+
+        let mut out = Vec::new();
+        read_file_data(&mut std::io::Cursor::new(&image), block_size, &extents, size, &mut out).unwrap();
+        assert_eq!(&out[..4096], &image[8192..]);
+        assert_eq!(&out[4096..], &image[4096..8192]);
+    }
+
     /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
     #[test]
     #[ignore]
@@ -1,7 +1,7 @@
-/// Fletcher-64 checksum used by APFS.
-///
-/// Every on-disk object has a 64-bit checksum at offset 0, computed over
-/// bytes 8..block_size using a modular Fletcher-64 variant.
+//! Fletcher-64 checksum used by APFS.
+//!
+//! Every on-disk object has a 64-bit checksum at offset 0, computed over
+//! bytes 8..block_size using a modular Fletcher-64 variant.
 
 /// Compute APFS Fletcher-64 checksum over a byte slice.
 ///
@@ -6,10 +6,15 @@ pub mod btree;
 pub mod omap;
 pub mod catalog;
 pub mod extents;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod testutil;
 
 pub use error::{ApfsError, Result};
 
-use std::io::{Read, Seek, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Entry kind in the filesystem
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,6 +24,13 @@ pub enum EntryKind {
     Symlink,
 }
 
+/// Digest algorithm for `hash_file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgo {
+    Sha1,
+    Sha256,
+}
+
 /// A directory entry returned by list_directory
 #[derive(Debug, Clone)]
 pub struct DirEntry {
@@ -42,6 +54,34 @@ pub struct FileStat {
     pub gid: u32,
     pub mode: u16,
     pub nlink: u32,
+    /// The inode is, or was at some point, a copy-on-write clone
+    pub is_clone: bool,
+    /// ID of the file's data stream (`private_id`), see
+    /// [`ApfsVolume::dstream_refcount`]
+    pub dstream_id: u64,
+}
+
+impl FileStat {
+    /// Metadata of an inode, as returned by [`ApfsVolume::lookup_inode`]
+    pub fn from_inode(oid: u64, inode: &catalog::InodeVal) -> Self {
+        FileStat {
+            oid,
+            kind: match inode.kind() {
+                catalog::INODE_DIR_TYPE => EntryKind::Directory,
+                catalog::INODE_SYMLINK_TYPE => EntryKind::Symlink,
+                _ => EntryKind::File,
+            },
+            size: inode.size(),
+            create_time: inode.create_time,
+            modify_time: inode.modify_time,
+            uid: inode.uid,
+            gid: inode.gid,
+            mode: inode.mode,
+            nlink: inode.nlink(),
+            is_clone: inode.was_ever_cloned(),
+            dstream_id: inode.private_id,
+        }
+    }
 }
 
 /// Entry from walk() — includes full path
@@ -51,6 +91,22 @@ pub struct WalkEntry {
     pub entry: DirEntry,
 }
 
+/// Counts returned by [`ApfsVolume::export_subtree`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Directories created, including the top one
+    pub directories: u64,
+    /// Regular files written
+    pub files: u64,
+    /// Symlinks created
+    pub symlinks: u64,
+    /// Bytes of file data written
+    pub bytes: u64,
+    /// Entries not exported: names that are not valid host path components,
+    /// special files, and symlinks on platforms without them
+    pub skipped: u64,
+}
+
 /// Volume information
 #[derive(Debug, Clone)]
 pub struct VolumeInfo {
@@ -59,29 +115,214 @@ pub struct VolumeInfo {
     pub num_files: u64,
     pub num_directories: u64,
     pub num_symlinks: u64,
+    /// Objects other than files, directories and symlinks (sockets, FIFOs, devices)
+    pub num_other_fsobjects: u64,
+    pub num_snapshots: u64,
+}
+
+impl VolumeInfo {
+    fn from_superblock(sb: &superblock::ApfsSuperblock, block_size: u32) -> Self {
+        VolumeInfo {
+            name: sb.volume_name.clone(),
+            block_size,
+            num_files: sb.num_files,
+            num_directories: sb.num_directories,
+            num_symlinks: sb.num_symlinks,
+            num_other_fsobjects: sb.num_other_fsobjects,
+            num_snapshots: sb.num_snapshots,
+        }
+    }
+}
+
+/// Shape of the catalog B-tree, from the root node's `BTreeInfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BTreeStats {
+    /// Node size in bytes
+    pub node_size: u32,
+    /// Number of nodes in the tree
+    pub node_count: u64,
+    /// Number of records in leaf nodes
+    pub leaf_records: u64,
+    /// Number of levels (root level + 1)
+    pub depth: u16,
+    /// Longest key ever stored, in bytes
+    pub longest_key: u32,
+    /// Longest value ever stored, in bytes
+    pub longest_val: u32,
+}
+
+/// Name of the xattr Gatekeeper sets on downloaded files
+pub const XATTR_QUARANTINE: &str = "com.apple.quarantine";
+
+/// Name of the xattr holding the classic Finder info
+pub const XATTR_FINDER_INFO: &str = "com.apple.FinderInfo";
+
+/// Name of the xattr holding a symlink's NUL-terminated target path
+pub const XATTR_SYMLINK: &str = "com.apple.fs.symlink";
+
+/// Parsed `com.apple.quarantine` xattr
+///
+/// The xattr is a `;`-separated string: hex flags, hex Unix timestamp,
+/// quarantining agent, and an optional event UUID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantineInfo {
+    /// Quarantine flags (e.g. `0x0040` once the user approved the file)
+    pub flags: u32,
+    /// When the file was quarantined, in seconds since the Unix epoch
+    pub timestamp: Option<i64>,
+    /// Application that downloaded the file (e.g. "Safari")
+    pub agent: String,
+    /// UUID of the LaunchServices quarantine event, if recorded
+    pub event_id: Option<String>,
+}
+
+impl QuarantineInfo {
+    /// Parse the raw xattr value
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(data)
+            .map_err(|_| ApfsError::CorruptedData("quarantine xattr is not UTF-8".into()))?;
+        let text = text.trim_end_matches(['\0', '\n']);
+        let mut fields = text.split(';');
+
+        let flags = fields
+            .next()
+            .and_then(|f| u32::from_str_radix(f, 16).ok())
+            .ok_or_else(|| ApfsError::CorruptedData(format!("invalid quarantine flags in {:?}", text)))?;
+        let timestamp = match fields.next().filter(|f| !f.is_empty()) {
+            Some(f) => Some(i64::from_str_radix(f, 16).map_err(|_| {
+                ApfsError::CorruptedData(format!("invalid quarantine timestamp in {:?}", text))
+            })?),
+            None => None,
+        };
+        let agent = fields.next().unwrap_or_default().to_string();
+        let event_id = fields.next().filter(|f| !f.is_empty()).map(str::to_string);
+
+        Ok(QuarantineInfo { flags, timestamp, agent, event_id })
+    }
+}
+
+/// Parsed `com.apple.FinderInfo` xattr (32 bytes, big-endian)
+///
+/// Fields follow the file layout (`FileInfo` + `ExtendedFileInfo`); for a
+/// directory `file_type` and `creator` hold its window rectangle instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinderInfo {
+    /// Classic Mac OS file type code (e.g. `b"APPL"`)
+    pub file_type: [u8; 4],
+    /// Classic Mac OS creator code
+    pub creator: [u8; 4],
+    /// Finder flags (`kIsInvisible`, `kHasCustomIcon`, color label, ...)
+    pub flags: u16,
+    /// Icon position in its window as `(vertical, horizontal)`
+    pub location: (i16, i16),
+    /// Extended Finder flags
+    pub extended_flags: u16,
+}
+
+impl FinderInfo {
+    /// Finder flag marking the item hidden
+    pub const IS_INVISIBLE: u16 = 0x4000;
+
+    /// Parse the raw xattr value
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 32 {
+            return Err(ApfsError::CorruptedData(
+                format!("FinderInfo xattr too short: {} bytes", data.len()),
+            ));
+        }
+        let be16 = |at: usize| u16::from_be_bytes([data[at], data[at + 1]]);
+        Ok(FinderInfo {
+            file_type: [data[0], data[1], data[2], data[3]],
+            creator: [data[4], data[5], data[6], data[7]],
+            flags: be16(8),
+            location: (be16(10) as i16, be16(12) as i16),
+            extended_flags: be16(24),
+        })
+    }
+
+    /// Whether the Finder hides this item
+    pub fn is_invisible(&self) -> bool {
+        self.flags & Self::IS_INVISIBLE != 0
+    }
+}
+
+/// Maximum number of parent links followed when reconstructing a path
+const MAX_PATH_DEPTH: usize = 1024;
+
+/// Reader adapter that shifts all positions by a fixed base offset
+///
+/// Position 0 of the adapter is byte `base` of the inner reader, so a volume
+/// embedded in a larger image (e.g. a whole-disk image) can be parsed in place.
+pub struct OffsetReader<R> {
+    inner: R,
+    base: u64,
+}
+
+impl<R: Seek> OffsetReader<R> {
+    /// Wrap `inner`, placing position 0 at `base` and seeking there
+    pub fn new(mut inner: R, base: u64) -> std::io::Result<Self> {
+        inner.seek(SeekFrom::Start(base))?;
+        Ok(OffsetReader { inner, base })
+    }
+
+    /// Base offset within the inner reader
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Unwrap the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for OffsetReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => SeekFrom::Start(self.base.checked_add(n).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek offset overflow")
+            })?),
+            other => other,
+        };
+        let absolute = self.inner.seek(pos)?;
+        absolute.checked_sub(self.base).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before volume start")
+        })
+    }
 }
 
 /// High-level read-only APFS volume reader
 pub struct ApfsVolume<R: Read + Seek> {
     reader: R,
     block_size: u32,
-    vol_omap_root_block: u64,
+    catalog_omap_root: Option<u64>,
     catalog_root_block: u64,
     info: VolumeInfo,
 }
 
 impl<R: Read + Seek> ApfsVolume<R> {
-    /// Open an APFS container and mount the first volume.
+    /// Open an APFS container and mount the first mountable volume.
     ///
     /// 1. Read block 0 → parse NX superblock, validate NXSB magic + Fletcher-64
     /// 2. Scan checkpoint descriptor area for latest valid NX superblock
     /// 3. Read container OMAP at omap_oid physical block
-    /// 4. Find first non-zero OID in fs_oids array
+    /// 4. Take the non-zero OIDs in the fs_oids array, in order
     /// 5. Resolve volume OID → physical block via container OMAP
     /// 6. Parse volume superblock (APSB magic)
     /// 7. Read volume OMAP at vol.omap_oid physical block
-    /// 8. Resolve vol.root_tree_oid → physical block via volume OMAP → catalog B-tree root
+    /// 8. Locate the catalog B-tree root (via volume OMAP unless root_tree_oid is physical)
+    ///    and detect from its `bt_flags` whether child OIDs need OMAP translation
     /// 9. Store all state
+    ///
+    /// A volume failing any of steps 5-8 (e.g. an encrypted one) is skipped
+    /// in favour of the next OID; if none mounts, the first volume's error
+    /// is returned.
     pub fn open(mut reader: R) -> Result<Self> {
         // Step 1-2: Read and validate container superblock
         let nxsb = superblock::read_nxsb(&mut reader)?;
@@ -91,43 +332,48 @@ impl<R: Read + Seek> ApfsVolume<R> {
         // Step 3: Read container OMAP
         let container_omap_root = omap::read_omap_tree_root(&mut reader, nxsb.omap_oid, block_size)?;
 
-        // Step 4: Find first non-zero volume OID
-        let vol_oid = nxsb.fs_oids.iter()
-            .find(|&&o| o != 0)
-            .copied()
-            .ok_or(ApfsError::NoVolume)?;
-
-        // Step 5: Resolve volume OID via container OMAP
-        let vol_block = omap::omap_lookup(&mut reader, container_omap_root, block_size, vol_oid)?;
-
-        // Step 6: Parse volume superblock
-        let vol_data = object::read_block(&mut reader, vol_block, block_size)?;
-        let vol_sb = superblock::ApfsSuperblock::parse(&vol_data)?;
-
-        // Step 7: Read volume OMAP
-        let vol_omap_root_block = omap::read_omap_tree_root(&mut reader, vol_sb.omap_oid, block_size)?;
-
-        // Step 8: Resolve catalog root tree OID via volume OMAP
-        let catalog_root_block = omap::omap_lookup(
-            &mut reader, vol_omap_root_block, block_size, vol_sb.root_tree_oid,
-        )?;
+        // Step 4: Try each non-zero volume OID in turn
+        let mut first_error = None;
+        for vol_oid in nxsb.fs_oids.iter().copied().filter(|&o| o != 0) {
+            match mount_volume(&mut reader, container_omap_root, block_size, vol_oid) {
+                // Step 9: Store state
+                Ok((catalog_root_block, catalog_omap_root, info)) => {
+                    return Ok(ApfsVolume {
+                        reader,
+                        block_size,
+                        catalog_omap_root,
+                        catalog_root_block,
+                        info,
+                    });
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap_or(ApfsError::NoVolume))
+    }
 
-        // Step 9: Store state
-        let info = VolumeInfo {
-            name: vol_sb.volume_name.clone(),
-            block_size,
-            num_files: vol_sb.num_files,
-            num_directories: vol_sb.num_directories,
-            num_symlinks: vol_sb.num_symlinks,
-        };
+    /// Open like [`open`](Self::open), first checking that the reader holds
+    /// the whole container.
+    ///
+    /// The length of `reader` must be at least `block_count * block_size`
+    /// from the NX superblock; a shorter one yields
+    /// [`ApfsError::Truncated`] instead of a read failing part-way through
+    /// a later walk. Trailing bytes past the container are allowed.
+    pub fn open_checked(mut reader: R) -> Result<Self> {
+        let nxsb = superblock::read_nxsb(&mut reader)?;
+        let expected = nxsb.block_count.saturating_mul(nxsb.block_size as u64);
+        let actual = reader.seek(SeekFrom::End(0))?;
+        if actual < expected {
+            return Err(ApfsError::Truncated { expected, actual });
+        }
+        Self::open(reader)
+    }
 
-        Ok(ApfsVolume {
-            reader,
-            block_size,
-            vol_omap_root_block,
-            catalog_root_block,
-            info,
-        })
+    /// Consume the volume and return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
     }
 
     /// Get volume metadata
@@ -135,6 +381,28 @@ impl<R: Read + Seek> ApfsVolume<R> {
         &self.info
     }
 
+    /// Container block size in bytes
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Node, leaf-record and depth statistics for the catalog B-tree
+    pub fn btree_stats(&mut self) -> Result<BTreeStats> {
+        let block = object::read_block(&mut self.reader, self.catalog_root_block, self.block_size)?;
+        let root = btree::BTreeNode::parse(&block)?;
+        let info = root
+            .info
+            .ok_or_else(|| ApfsError::InvalidBTree("catalog root has no btree info".into()))?;
+        Ok(BTreeStats {
+            node_size: info.bt_fixed.bt_node_size,
+            node_count: info.bt_node_count,
+            leaf_records: info.bt_key_count,
+            depth: root.node_header.btn_level.saturating_add(1),
+            longest_key: info.bt_longest_key,
+            longest_val: info.bt_longest_val,
+        })
+    }
+
     /// List entries in a directory by path
     pub fn list_directory(&mut self, path: &str) -> Result<Vec<DirEntry>> {
         let (oid, _inode) = if path == "/" || path.is_empty() {
@@ -144,7 +412,7 @@ impl<R: Read + Seek> ApfsVolume<R> {
             let (oid, inode) = catalog::resolve_path(
                 &mut self.reader,
                 self.catalog_root_block,
-                self.vol_omap_root_block,
+                self.catalog_omap_root,
                 self.block_size,
                 path,
             )?;
@@ -160,12 +428,21 @@ impl<R: Read + Seek> ApfsVolume<R> {
             oid
         };
 
+        self.list_directory_by_oid(parent)
+    }
+
+    /// List entries in a directory by its inode OID
+    ///
+    /// Skips path resolution, so repeated listings of a known directory
+    /// (e.g. from `DirEntry::oid`) only scan the catalog once. The root
+    /// directory is `2`. An OID that is not a directory yields no entries.
+    pub fn list_directory_by_oid(&mut self, oid: u64) -> Result<Vec<DirEntry>> {
         catalog::list_directory(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
-            parent,
+            oid,
         )
     }
 
@@ -178,91 +455,389 @@ impl<R: Read + Seek> ApfsVolume<R> {
 
     /// Stream a file to a writer
     pub fn read_file_to<W: Write>(&mut self, path: &str, writer: &mut W) -> Result<u64> {
-        let (_oid, inode) = catalog::resolve_path(
-            &mut self.reader,
-            self.catalog_root_block,
-            self.vol_omap_root_block,
-            self.block_size,
-            path,
-        )?;
+        let (_oid, inode) = self.lookup_inode(path)?;
+        self.read_inode_to(&inode, writer)
+    }
 
+    /// [`read_file_to`](Self::read_file_to) for an inode from [`lookup_inode`](Self::lookup_inode)
+    pub fn read_inode_to<W: Write>(&mut self, inode: &catalog::InodeVal, writer: &mut W) -> Result<u64> {
         // File extents are keyed by private_id, not the inode OID
         let file_extents = catalog::lookup_extents(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
             inode.private_id,
         )?;
 
+        let size = extents::file_size(inode, &file_extents);
         extents::read_file_data(
             &mut self.reader,
             self.block_size,
             &file_extents,
-            inode.size(),
+            size,
             writer,
         )
     }
 
-    /// Open a file for streaming Read+Seek access
-    pub fn open_file(&mut self, path: &str) -> Result<extents::ApfsForkReader<'_, R>> {
-        let (_oid, inode) = catalog::resolve_path(
+    /// Stream a file to a writer, reporting progress
+    ///
+    /// `progress` is called after each block is written with
+    /// `(bytes_written, total_size)`. If `cancel` is given and becomes `true`
+    /// (e.g. from another thread), the copy stops before the next block and
+    /// fails with `Cancelled`.
+    pub fn read_file_to_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let (_oid, inode) = self.lookup_inode(path)?;
+        self.read_inode_to_with_progress(&inode, writer, progress, cancel)
+    }
+
+    /// [`read_file_to_with_progress`](Self::read_file_to_with_progress) for an
+    /// inode from [`lookup_inode`](Self::lookup_inode)
+    pub fn read_inode_to_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        inode: &catalog::InodeVal,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let file_extents = catalog::lookup_extents(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
-            path,
+            inode.private_id,
         )?;
 
+        let size = extents::file_size(inode, &file_extents);
+        let mut writer = extents::ProgressWriter::new(writer, size, progress, cancel);
+        let result = extents::read_file_data(
+            &mut self.reader,
+            self.block_size,
+            &file_extents,
+            size,
+            &mut writer,
+        );
+        writer.finish(result)
+    }
+
+    /// Compute the digest of a file's contents
+    ///
+    /// The file is streamed through the hasher block by block, so it is
+    /// never held in memory.
+    pub fn hash_file(&mut self, path: &str, algo: HashAlgo) -> Result<Vec<u8>> {
+        let mut hasher = extents::HashWriter::new(algo);
+        self.read_file_to(path, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
+    /// Open a file for streaming Read+Seek access
+    pub fn open_file(&mut self, path: &str) -> Result<extents::ApfsForkReader<'_, R>> {
+        let (_oid, inode) = self.lookup_inode(path)?;
+        self.open_inode(&inode)
+    }
+
+    /// [`open_file`](Self::open_file) for an inode from [`lookup_inode`](Self::lookup_inode)
+    pub fn open_inode(&mut self, inode: &catalog::InodeVal) -> Result<extents::ApfsForkReader<'_, R>> {
         // File extents are keyed by private_id, not the inode OID
         let file_extents = catalog::lookup_extents(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
             inode.private_id,
         )?;
 
+        let size = extents::file_size(inode, &file_extents);
         Ok(extents::ApfsForkReader::new(
             &mut self.reader,
             self.block_size,
             file_extents,
-            inode.size(),
+            size,
         ))
     }
 
     /// Get metadata for a file or directory
     pub fn stat(&mut self, path: &str) -> Result<FileStat> {
-        let (oid, inode) = catalog::resolve_path(
+        let (oid, inode) = self.lookup_inode(path)?;
+        Ok(FileStat::from_inode(oid, &inode))
+    }
+
+    /// Resolve a path to its inode OID and inode record
+    ///
+    /// The result can be kept and passed to the `*_inode` methods to skip
+    /// resolving the path again.
+    pub fn lookup_inode(&mut self, path: &str) -> Result<(u64, catalog::InodeVal)> {
+        catalog::resolve_path(
+            &mut self.reader,
+            self.catalog_root_block,
+            self.catalog_omap_root,
+            self.block_size,
+            path,
+        )
+    }
+
+    /// Reference count of a data stream
+    ///
+    /// `dstream_id` is a file's [`FileStat::dstream_id`]. A count above one
+    /// means several inodes share the stream. Clones made with `clonefile`
+    /// usually get a stream of their own and share physical extents instead,
+    /// so a count of one does not mean the data is unshared. Fails with
+    /// `FileNotFound` if the volume has no record for the stream.
+    pub fn dstream_refcount(&mut self, dstream_id: u64) -> Result<u32> {
+        catalog::lookup_dstream_refcount(
+            &mut self.reader,
+            self.catalog_root_block,
+            self.catalog_omap_root,
+            self.block_size,
+            dstream_id,
+        )
+    }
+
+    /// Names of the extended attributes on a file or directory
+    pub fn list_xattrs(&mut self, path: &str) -> Result<Vec<String>> {
+        let (oid, _inode) = catalog::resolve_path(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
             path,
         )?;
 
-        Ok(FileStat {
+        let xattrs = catalog::lookup_xattrs(
+            &mut self.reader,
+            self.catalog_root_block,
+            self.catalog_omap_root,
+            self.block_size,
             oid,
-            kind: match inode.kind() {
-                catalog::INODE_DIR_TYPE => EntryKind::Directory,
-                catalog::INODE_SYMLINK_TYPE => EntryKind::Symlink,
-                _ => EntryKind::File,
-            },
-            size: inode.size(),
-            create_time: inode.create_time,
-            modify_time: inode.modify_time,
-            uid: inode.uid,
-            gid: inode.gid,
-            mode: inode.mode,
-            nlink: inode.nlink(),
-        })
+        )?;
+        Ok(xattrs.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Read an extended attribute, or `None` if the item does not have it
+    ///
+    /// Small values are embedded in the catalog; larger ones are read from
+    /// their own data stream.
+    pub fn read_xattr(&mut self, path: &str, name: &str) -> Result<Option<Vec<u8>>> {
+        let (oid, _inode) = catalog::resolve_path(
+            &mut self.reader,
+            self.catalog_root_block,
+            self.catalog_omap_root,
+            self.block_size,
+            path,
+        )?;
+        self.read_xattr_by_oid(oid, name)
     }
 
-    /// Recursive walk of all entries
+    /// Read the target of a symbolic link
+    ///
+    /// APFS stores the target in the `com.apple.fs.symlink` xattr. Fails
+    /// with `NotASymlink` if `path` is not a symlink.
+    pub fn read_link(&mut self, path: &str) -> Result<String> {
+        let (oid, inode) = self.lookup_inode(path)?;
+        self.read_link_inode(path, oid, &inode)
+    }
+
+    /// [`read_link`](Self::read_link) for an inode from [`lookup_inode`](Self::lookup_inode)
+    ///
+    /// `path` is only used in error messages.
+    pub fn read_link_inode(&mut self, path: &str, oid: u64, inode: &catalog::InodeVal) -> Result<String> {
+        if inode.kind() != catalog::INODE_SYMLINK_TYPE {
+            return Err(ApfsError::NotASymlink(path.to_string()));
+        }
+
+        let mut target = self.read_xattr_by_oid(oid, XATTR_SYMLINK)?.ok_or_else(|| {
+            ApfsError::CorruptedData(format!("symlink {} has no {} xattr", path, XATTR_SYMLINK))
+        })?;
+        if let Some(nul) = target.iter().position(|&b| b == 0) {
+            target.truncate(nul);
+        }
+        String::from_utf8(target)
+            .map_err(|_| ApfsError::CorruptedData(format!("symlink target of {} is not UTF-8", path)))
+    }
+
+    /// Read an xattr of the item with inode number `oid`
+    fn read_xattr_by_oid(&mut self, oid: u64, name: &str) -> Result<Option<Vec<u8>>> {
+        let xattrs = catalog::lookup_xattrs(
+            &mut self.reader,
+            self.catalog_root_block,
+            self.catalog_omap_root,
+            self.block_size,
+            oid,
+        )?;
+        let Some((_, xattr)) = xattrs.into_iter().find(|(n, _)| n == name) else {
+            return Ok(None);
+        };
+
+        match xattr.data {
+            catalog::XattrData::Embedded(data) => Ok(Some(data)),
+            catalog::XattrData::Stream { xattr_obj_id, size } => {
+                let file_extents = catalog::lookup_extents(
+                    &mut self.reader,
+                    self.catalog_root_block,
+                    self.catalog_omap_root,
+                    self.block_size,
+                    xattr_obj_id,
+                )?;
+                let mut data = Vec::new();
+                extents::read_file_data(&mut self.reader, self.block_size, &file_extents, size, &mut data)?;
+                Ok(Some(data))
+            }
+        }
+    }
+
+    /// Parsed `com.apple.quarantine` xattr, or `None` if the item is not quarantined
+    pub fn quarantine_info(&mut self, path: &str) -> Result<Option<QuarantineInfo>> {
+        self.read_xattr(path, XATTR_QUARANTINE)?
+            .map(|data| QuarantineInfo::parse(&data))
+            .transpose()
+    }
+
+    /// Parsed `com.apple.FinderInfo` xattr, or `None` if the item has none
+    pub fn finder_info(&mut self, path: &str) -> Result<Option<FinderInfo>> {
+        self.read_xattr(path, XATTR_FINDER_INFO)?
+            .map(|data| FinderInfo::parse(&data))
+            .transpose()
+    }
+
+    /// Walk all entries depth-first
+    ///
+    /// Fails with `TooDeep` if entries are nested more than 1024 levels deep.
     pub fn walk(&mut self) -> Result<Vec<WalkEntry>> {
-        let mut entries = Vec::new();
-        self.walk_recursive(catalog::ROOT_DIR_RECORD, "", &mut entries)?;
-        Ok(entries)
+        self.walk_with_max_depth(MAX_PATH_DEPTH)
+    }
+
+    /// Walk all entries, descending at most `max_depth` directory levels
+    ///
+    /// Entries directly under the root are at depth 1. Fails with `TooDeep`
+    /// if a directory at depth `max_depth` is not empty. The walk uses an
+    /// explicit stack, so deep trees cannot overflow the call stack.
+    pub fn walk_with_max_depth(&mut self, max_depth: usize) -> Result<Vec<WalkEntry>> {
+        let reader = &mut self.reader;
+        let (catalog_root, omap_root, block_size) =
+            (self.catalog_root_block, self.catalog_omap_root, self.block_size);
+        walk_tree(catalog::ROOT_DIR_RECORD, max_depth, |parent| {
+            catalog::list_directory(reader, catalog_root, omap_root, block_size, parent)
+        })
+    }
+
+    /// Walk all entries, reporting progress
+    ///
+    /// `progress` is called after each directory is listed with the number of
+    /// entries found so far. If `cancel` is given and becomes `true`, the walk
+    /// stops before listing the next directory and fails with `Cancelled`.
+    pub fn walk_with_progress<F: FnMut(usize)>(
+        &mut self,
+        mut progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Vec<WalkEntry>> {
+        let reader = &mut self.reader;
+        let (catalog_root, omap_root, block_size) =
+            (self.catalog_root_block, self.catalog_omap_root, self.block_size);
+        let mut found = 0;
+        walk_tree(catalog::ROOT_DIR_RECORD, MAX_PATH_DEPTH, |parent| {
+            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                return Err(ApfsError::Cancelled);
+            }
+            let children = catalog::list_directory(reader, catalog_root, omap_root, block_size, parent)?;
+            found += children.len();
+            progress(found);
+            Ok(children)
+        })
+    }
+
+    /// Walk all entries, collecting per-directory errors instead of failing
+    ///
+    /// A directory that cannot be listed (or is nested deeper than the
+    /// default limit) is reported with its path and its contents skipped;
+    /// everything else is still walked.
+    pub fn walk_lenient(&mut self) -> (Vec<WalkEntry>, Vec<(String, ApfsError)>) {
+        let reader = &mut self.reader;
+        let (catalog_root, omap_root, block_size) =
+            (self.catalog_root_block, self.catalog_omap_root, self.block_size);
+        let mut errors = Vec::new();
+        // `on_error` never aborts, so the walk itself cannot fail
+        let entries = walk_tree_with(
+            catalog::ROOT_DIR_RECORD,
+            MAX_PATH_DEPTH,
+            |parent| catalog::list_directory(reader, catalog_root, omap_root, block_size, parent),
+            |path, e| {
+                errors.push((path, e));
+                Ok(())
+            },
+        )
+        .unwrap_or_default();
+        (entries, errors)
+    }
+
+    /// Recreate the subtree at `src_path` on the host, at `dest`
+    ///
+    /// A directory becomes `dest` with its contents below it; a file or
+    /// symlink is written to `dest` itself. Files are streamed with
+    /// [`read_file_to`](Self::read_file_to), and permission bits are applied
+    /// to directories only after their contents are written. Fails with
+    /// `TooDeep` if directories nest more than 1024 levels below `src_path`.
+    pub fn export_subtree<P: AsRef<Path>>(&mut self, src_path: &str, dest: P) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+        let mut directories = Vec::new();
+        let mut stack = vec![(src_path.to_string(), dest.as_ref().to_path_buf(), 0usize)];
+
+        while let Some((path, host_path, depth)) = stack.pop() {
+            let stat = self.stat(&path)?;
+            let mode = stat.mode as u32;
+            match (stat.kind, mode & S_IFMT) {
+                (EntryKind::Directory, _) => {
+                    std::fs::create_dir_all(&host_path)?;
+                    report.directories += 1;
+
+                    let children = self.list_directory(&path)?;
+                    if !children.is_empty() && depth >= MAX_PATH_DEPTH {
+                        return Err(ApfsError::TooDeep { path, max_depth: MAX_PATH_DEPTH });
+                    }
+                    for child in children.into_iter().rev() {
+                        if !is_host_component(&child.name) {
+                            report.skipped += 1;
+                            continue;
+                        }
+                        let child_path = format!("{}/{}", path.trim_end_matches('/'), child.name);
+                        stack.push((child_path, host_path.join(&child.name), depth + 1));
+                    }
+                    directories.push((host_path, mode));
+                }
+                (EntryKind::Symlink, _) => {
+                    #[cfg(unix)]
+                    {
+                        let target = self.read_link(&path)?;
+                        let _ = std::fs::remove_file(&host_path);
+                        std::os::unix::fs::symlink(target, &host_path)?;
+                        report.symlinks += 1;
+                    }
+                    #[cfg(not(unix))]
+                    {
+                        report.skipped += 1;
+                    }
+                }
+                (_, S_IFREG) => {
+                    let mut out = std::io::BufWriter::new(std::fs::File::create(&host_path)?);
+                    report.bytes += self.read_file_to(&path, &mut out)?;
+                    out.flush()?;
+                    set_host_mode(&host_path, mode)?;
+                    report.files += 1;
+                }
+                _ => report.skipped += 1,
+            }
+        }
+
+        // Deepest first, so a read-only parent does not block its children
+        for (host_path, mode) in directories.iter().rev() {
+            set_host_mode(host_path, *mode)?;
+        }
+        Ok(report)
     }
 
     /// Check if a path exists
@@ -270,7 +845,7 @@ impl<R: Read + Seek> ApfsVolume<R> {
         match catalog::resolve_path(
             &mut self.reader,
             self.catalog_root_block,
-            self.vol_omap_root_block,
+            self.catalog_omap_root,
             self.block_size,
             path,
         ) {
@@ -280,49 +855,266 @@ impl<R: Read + Seek> ApfsVolume<R> {
         }
     }
 
-    fn walk_recursive(
-        &mut self,
-        parent_oid: u64,
-        parent_path: &str,
-        entries: &mut Vec<WalkEntry>,
-    ) -> Result<()> {
-        let dir_entries = catalog::list_directory(
-            &mut self.reader,
-            self.catalog_root_block,
-            self.vol_omap_root_block,
-            self.block_size,
-            parent_oid,
-        )?;
+    /// Reconstruct the absolute path of an inode by following its parent links.
+    ///
+    /// Returns `/` for the root directory. Fails with `CorruptedData` if the
+    /// parent chain does not reach the root within a fixed number of hops.
+    pub fn path_for_oid(&mut self, oid: u64) -> Result<String> {
+        let mut components = Vec::new();
+        let mut current = oid;
 
-        for entry in dir_entries {
-            let full_path = if parent_path.is_empty() {
-                format!("/{}", entry.name)
-            } else {
-                format!("{}/{}", parent_path, entry.name)
-            };
+        while current != catalog::ROOT_DIR_RECORD {
+            if components.len() >= MAX_PATH_DEPTH {
+                return Err(ApfsError::CorruptedData(format!(
+                    "parent chain of OID {} exceeds {} links",
+                    oid, MAX_PATH_DEPTH
+                )));
+            }
 
-            let is_dir = entry.kind == EntryKind::Directory;
-            let oid = entry.oid;
+            let inode = catalog::lookup_inode(
+                &mut self.reader,
+                self.catalog_root_block,
+                self.catalog_omap_root,
+                self.block_size,
+                current,
+            )?;
+            let name = catalog::lookup_drec_name(
+                &mut self.reader,
+                self.catalog_root_block,
+                self.catalog_omap_root,
+                self.block_size,
+                inode.parent_id,
+                current,
+            )?
+            .ok_or_else(|| {
+                ApfsError::FileNotFound(format!(
+                    "no directory entry for OID {} in parent {}",
+                    current, inode.parent_id
+                ))
+            })?;
 
-            entries.push(WalkEntry {
-                path: full_path.clone(),
-                entry,
-            });
+            components.push(name);
+            current = inode.parent_id;
+        }
 
-            if is_dir {
-                self.walk_recursive(oid, &full_path, entries)?;
-            }
+        components.reverse();
+        Ok(format!("/{}", components.join("/")))
+    }
+}
+
+impl<R: Read + Seek> ApfsVolume<OffsetReader<R>> {
+    /// Open a volume that starts `offset` bytes into `reader`
+    ///
+    /// All seeks are relative to `offset`, so a partition inside a disk
+    /// image can be opened without copying it out first.
+    pub fn open_at(reader: R, offset: u64) -> Result<Self> {
+        Self::open(OffsetReader::new(reader, offset)?)
+    }
+}
+
+/// Mount the volume with virtual OID `vol_oid` (steps 5-8 of [`ApfsVolume::open`])
+///
+/// Returns the catalog root block, the OMAP for its child OIDs and the
+/// volume's metadata.
+fn mount_volume<R: Read + Seek>(
+    reader: &mut R,
+    container_omap_root: u64,
+    block_size: u32,
+    vol_oid: u64,
+) -> Result<(u64, Option<u64>, VolumeInfo)> {
+    // Step 5: Resolve volume OID via container OMAP
+    let vol_block = omap::omap_lookup(reader, container_omap_root, block_size, vol_oid)?;
+
+    // Step 6: Parse volume superblock
+    let vol_data = object::read_block(reader, vol_block, block_size)?;
+    let vol_sb = superblock::ApfsSuperblock::parse(&vol_data)?;
+
+    // Step 7: Read volume OMAP
+    let vol_omap_root_block = omap::read_omap_tree_root(reader, vol_sb.omap_oid, block_size)?;
+
+    // Step 8: Locate the catalog root and its child addressing mode
+    let (catalog_root_block, catalog_omap_root) =
+        locate_catalog_root(reader, &vol_sb, vol_omap_root_block, block_size)?;
+
+    Ok((catalog_root_block, catalog_omap_root, VolumeInfo::from_superblock(&vol_sb, block_size)))
+}
+
+/// Locate the catalog B-tree root block and the OMAP used for its child OIDs
+///
+/// `root_tree_oid` is a physical block number when `root_tree_type` carries
+/// no virtual/ephemeral storage bits. Child addressing is taken from the root
+/// node's `bt_flags`: a `BTREE_PHYSICAL` tree stores block numbers directly,
+/// so `None` is returned in place of the volume OMAP.
+fn locate_catalog_root<R: Read + Seek>(
+    reader: &mut R,
+    vol_sb: &superblock::ApfsSuperblock,
+    vol_omap_root_block: u64,
+    block_size: u32,
+) -> Result<(u64, Option<u64>)> {
+    let root_block = if vol_sb.root_tree_type & object::OBJ_STORAGE_TYPE_MASK == object::OBJ_PHYSICAL {
+        vol_sb.root_tree_oid
+    } else {
+        omap::omap_lookup(reader, vol_omap_root_block, block_size, vol_sb.root_tree_oid)?
+    };
+
+    let block = object::read_block(reader, root_block, block_size)?;
+    let root = btree::BTreeNode::parse(&block)?;
+    let info = root
+        .info
+        .ok_or_else(|| ApfsError::InvalidBTree("catalog root has no btree info".into()))?;
+
+    let omap_root = if info.bt_fixed.bt_flags & btree::BTREE_PHYSICAL != 0 {
+        None
+    } else {
+        Some(vol_omap_root_block)
+    };
+    Ok((root_block, omap_root))
+}
+
+/// File type mask of an inode mode
+const S_IFMT: u32 = 0o170000;
+/// Regular file type bits
+const S_IFREG: u32 = 0o100000;
+
+/// Whether a directory entry name can be used as a single host path component
+fn is_host_component(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\0'])
+}
+
+/// Apply the permission bits of `mode` to an exported host path
+fn set_host_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+/// Depth-first walk from `root`, listing directories with `list`
+///
+/// Entries are returned in the same pre-order as a recursive walk. Pending
+/// directories are kept on a heap-allocated stack.
+fn walk_tree<F>(root: u64, max_depth: usize, list: F) -> Result<Vec<WalkEntry>>
+where
+    F: FnMut(u64) -> Result<Vec<DirEntry>>,
+{
+    walk_tree_with(root, max_depth, list, |_, e| Err(e))
+}
+
+/// [`walk_tree`] with a handler for directories that fail to list
+///
+/// `on_error` receives the directory path and error. Returning `Ok` skips
+/// that directory's contents and continues the walk; returning `Err` aborts.
+fn walk_tree_with<F, E>(root: u64, max_depth: usize, mut list: F, mut on_error: E) -> Result<Vec<WalkEntry>>
+where
+    F: FnMut(u64) -> Result<Vec<DirEntry>>,
+    E: FnMut(String, ApfsError) -> Result<()>,
+{
+    let mut entries = Vec::new();
+    let root_children = match list(root) {
+        Ok(children) => children,
+        Err(e) => {
+            on_error("/".to_string(), e)?;
+            Vec::new()
         }
+    };
+    let mut stack = vec![(root_children.into_iter(), String::new())];
+
+    while let Some((children, parent_path)) = stack.last_mut() {
+        let Some(entry) = children.next() else {
+            stack.pop();
+            continue;
+        };
+
+        let full_path = format!("{}/{}", parent_path, entry.name);
+        let subdir = (entry.kind == EntryKind::Directory).then_some(entry.oid);
 
-        Ok(())
+        entries.push(WalkEntry {
+            path: full_path.clone(),
+            entry,
+        });
+
+        if let Some(oid) = subdir {
+            let children = match list(oid) {
+                Ok(children) => children,
+                Err(e) => {
+                    on_error(full_path, e)?;
+                    continue;
+                }
+            };
+            if !children.is_empty() {
+                if stack.len() >= max_depth {
+                    on_error(
+                        full_path.clone(),
+                        ApfsError::TooDeep {
+                            path: full_path,
+                            max_depth,
+                        },
+                    )?;
+                    continue;
+                }
+                stack.push((children.into_iter(), full_path));
+            }
+        }
     }
+
+    Ok(entries)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutil::*;
     use std::io::BufReader;
 
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_export_subtree() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut vol = ApfsVolume::open(BufReader::new(file)).unwrap();
+
+        // Export the directory holding the first symlink
+        let walk = vol.walk().unwrap();
+        let link = walk
+            .iter()
+            .find(|e| e.entry.kind == EntryKind::Symlink)
+            .expect("Should find a symlink in the test image");
+        let src = link.path.rsplit_once('/').unwrap().0.to_string();
+
+        let dest = tempfile::tempdir().unwrap();
+        let root = dest.path().join("export");
+        let report = vol.export_subtree(&src, &root).unwrap();
+        assert_eq!(report.skipped, 0);
+
+        let prefix = format!("{}/", src);
+        let mut expected = ExportReport { directories: 1, ..Default::default() };
+        for entry in walk.iter().filter(|e| e.path.starts_with(&prefix)) {
+            let host = root.join(&entry.path[prefix.len()..]);
+            match entry.entry.kind {
+                EntryKind::Directory => {
+                    assert!(host.is_dir(), "{}", entry.path);
+                    expected.directories += 1;
+                }
+                EntryKind::File => {
+                    let content = vol.read_file(&entry.path).unwrap();
+                    assert_eq!(std::fs::read(&host).unwrap(), content, "{}", entry.path);
+                    expected.files += 1;
+                    expected.bytes += content.len() as u64;
+                }
+                EntryKind::Symlink => {
+                    let target = std::fs::read_link(&host).unwrap();
+                    assert_eq!(target.to_str().unwrap(), vol.read_link(&entry.path).unwrap());
+                    expected.symlinks += 1;
+                }
+            }
+        }
+        assert_eq!(report, expected);
+    }
+
     /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
     #[test]
     #[ignore]
@@ -335,6 +1127,7 @@ mod tests {
 
         assert!(!info.name.is_empty(), "Volume name should not be empty");
         assert_eq!(info.block_size, 4096);
+        assert_eq!(vol.block_size(), 4096);
 
         let entries = vol.list_directory("/").unwrap();
         assert!(!entries.is_empty(), "Root directory should have entries");
@@ -364,4 +1157,593 @@ mod tests {
         let stat = vol.stat(&entry.path).unwrap();
         assert_eq!(stat.size, entry.entry.size);
     }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_path_for_oid() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let reader = BufReader::new(file);
+
+        let mut vol = ApfsVolume::open(reader).unwrap();
+        assert_eq!(vol.path_for_oid(catalog::ROOT_DIR_RECORD).unwrap(), "/");
+
+        let walk = vol.walk().unwrap();
+        let deepest = walk.iter()
+            .max_by_key(|e| e.path.matches('/').count())
+            .expect("Walk should return entries");
+
+        assert_eq!(vol.path_for_oid(deepest.entry.oid).unwrap(), deepest.path);
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_list_directory_by_oid() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let reader = BufReader::new(file);
+
+        let mut vol = ApfsVolume::open(reader).unwrap();
+        let names = |entries: Vec<DirEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.name).collect()
+        };
+
+        let root = vol.list_directory_by_oid(catalog::ROOT_DIR_RECORD).unwrap();
+        assert_eq!(names(root), names(vol.list_directory("/").unwrap()));
+
+        let walk = vol.walk().unwrap();
+        let dir = walk.iter()
+            .find(|e| e.entry.kind == EntryKind::Directory)
+            .expect("Should find a directory in the test image");
+
+        let by_oid = vol.list_directory_by_oid(dir.entry.oid).unwrap();
+        let by_path = vol.list_directory(&dir.path).unwrap();
+        assert_eq!(names(by_oid), names(by_path));
+    }
+
+    fn dir(name: &str, id: u64) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            oid: id,
+            kind: EntryKind::Directory,
+            size: 0,
+            create_time: 0,
+            modify_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_walk_tree_preorder() {
+        // 1 -> {a(2) -> {c(4)}, b(3)}
+        let walk = walk_tree(1, 8, |parent| {
+            Ok(match parent {
+                1 => vec![dir("a", 2), dir("b", 3)],
+                2 => vec![dir("c", 4)],
+                _ => Vec::new(),
+            })
+        })
+        .unwrap();
+
+        let paths: Vec<_> = walk.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/a/c", "/b"]);
+    }
+
+    #[test]
+    fn test_walk_tree_deep() {
+        // A single chain of directories, far deeper than a recursive walk could handle
+        const DEPTH: u64 = 5000;
+        let chain = |parent: u64| -> Result<Vec<DirEntry>> {
+            Ok(if parent < DEPTH { vec![dir("d", parent + 1)] } else { Vec::new() })
+        };
+
+        let walk = walk_tree(0, usize::MAX, chain).unwrap();
+        assert_eq!(walk.len(), DEPTH as usize);
+        assert_eq!(walk.last().unwrap().path.len(), 2 * DEPTH as usize);
+
+        // The chain is DEPTH levels deep, so exactly DEPTH is allowed
+        assert!(walk_tree(0, DEPTH as usize, chain).is_ok());
+        match walk_tree(0, 100, chain) {
+            Err(ApfsError::TooDeep { path, max_depth }) => {
+                assert_eq!(max_depth, 100);
+                assert_eq!(path, "/d".repeat(100));
+            }
+            other => panic!("expected TooDeep, got {:?}", other.map(|w| w.len())),
+        }
+    }
+
+    #[test]
+    fn test_walk_tree_lenient() {
+        // 1 -> {a(2) -> {c(4)}, bad(3) -> <error>, b(5) -> {d(6)}}
+        let list = |parent: u64| -> Result<Vec<DirEntry>> {
+            match parent {
+                1 => Ok(vec![dir("a", 2), dir("bad", 3), dir("b", 5)]),
+                2 => Ok(vec![dir("c", 4)]),
+                3 => Err(ApfsError::CorruptedData("bad node".into())),
+                5 => Ok(vec![dir("d", 6)]),
+                _ => Ok(Vec::new()),
+            }
+        };
+
+        assert!(walk_tree(1, 8, list).is_err());
+
+        let mut errors = Vec::new();
+        let walk = walk_tree_with(1, 8, list, |path, e| {
+            errors.push((path, e));
+            Ok(())
+        })
+        .unwrap();
+
+        let paths: Vec<_> = walk.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/a/c", "/bad", "/b", "/b/d"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "/bad");
+        assert!(matches!(errors[0].1, ApfsError::CorruptedData(_)));
+
+        // Too-deep directories are reported the same way
+        let mut errors = Vec::new();
+        let walk = walk_tree_with(1, 1, list, |path, e| {
+            errors.push((path, e));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(walk.len(), 3);
+        let paths: Vec<_> = errors.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, ["/a", "/bad", "/b"]);
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_read_cloned_file() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let reader = BufReader::new(file);
+
+        let mut vol = ApfsVolume::open(reader).unwrap();
+
+        // Group regular files by the physical block of their first extent;
+        // a clone and its source share it while each has its own extent records
+        let mut by_first_block: std::collections::HashMap<u64, Vec<String>> = Default::default();
+        for entry in vol.walk().unwrap() {
+            if entry.entry.kind != EntryKind::File || entry.entry.size == 0 {
+                continue;
+            }
+            let (_oid, inode) = catalog::resolve_path(
+                &mut vol.reader,
+                vol.catalog_root_block,
+                vol.catalog_omap_root,
+                vol.block_size,
+                &entry.path,
+            ).unwrap();
+            let extents = catalog::lookup_extents(
+                &mut vol.reader,
+                vol.catalog_root_block,
+                vol.catalog_omap_root,
+                vol.block_size,
+                inode.private_id,
+            ).unwrap();
+            if let Some(first) = extents.first().filter(|e| e.phys_block_num != 0) {
+                by_first_block.entry(first.phys_block_num).or_default().push(entry.path);
+            }
+        }
+
+        let (original, clone) = by_first_block.values()
+            .filter(|paths| paths.len() >= 2)
+            .map(|paths| (paths[0].clone(), paths[1].clone()))
+            .find(|(a, b)| vol.stat(a).unwrap().is_clone || vol.stat(b).unwrap().is_clone)
+            .expect("Should find a cloned file in the test image");
+
+        let original_data = vol.read_file(&original).unwrap();
+        let clone_data = vol.read_file(&clone).unwrap();
+        assert_eq!(original_data.len() as u64, vol.stat(&original).unwrap().size);
+        assert_eq!(clone_data.len() as u64, vol.stat(&clone).unwrap().size);
+
+        // Both read through their own extents; the shared first block must match
+        let shared = (vol.block_size as usize).min(original_data.len()).min(clone_data.len());
+        assert_eq!(original_data[..shared], clone_data[..shared]);
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    ///
+    /// The fixture has no data stream known to be shared, so this only checks
+    /// that every file's stream has a record; sharing is covered by
+    /// `test_lookup_dstream_refcount`.
+    #[test]
+    #[ignore]
+    fn test_dstream_record_exists() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let reader = BufReader::new(file);
+
+        let mut vol = ApfsVolume::open(reader).unwrap();
+
+        for entry in vol.walk().unwrap() {
+            if entry.entry.kind != EntryKind::File || entry.entry.size == 0 {
+                continue;
+            }
+            let dstream_id = vol.stat(&entry.path).unwrap().dstream_id;
+            assert!(vol.dstream_refcount(dstream_id).unwrap() >= 1, "{}", entry.path);
+        }
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_hash_file() {
+        use sha2::Digest;
+
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut vol = ApfsVolume::open(BufReader::new(file)).unwrap();
+
+        let walk = vol.walk().unwrap();
+        let entry = walk.iter()
+            .find(|e| e.entry.kind == EntryKind::File && e.entry.size > 0 && e.entry.size < 1_000_000)
+            .expect("Should find a small file in the test image");
+
+        let data = vol.read_file(&entry.path).unwrap();
+        assert_eq!(
+            vol.hash_file(&entry.path, HashAlgo::Sha256).unwrap(),
+            sha2::Sha256::digest(&data).to_vec()
+        );
+        assert_eq!(
+            vol.hash_file(&entry.path, HashAlgo::Sha1).unwrap(),
+            sha1::Sha1::digest(&data).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_offset_reader() {
+        let data: Vec<u8> = (0..100u8).collect();
+        let mut reader = OffsetReader::new(std::io::Cursor::new(&data), 40).unwrap();
+
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [40, 41, 42, 43]);
+
+        assert_eq!(reader.seek(SeekFrom::Start(10)).unwrap(), 10);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [50, 51, 52, 53]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(-2)).unwrap(), 12);
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 59);
+        assert!(reader.seek(SeekFrom::Current(-100)).is_err());
+    }
+
+    #[test]
+    fn test_open_checked_truncated() {
+        // Bare NX superblock claiming 16 blocks of 4 KiB
+        let mut block = vec![0u8; 4096];
+        block[24..28].copy_from_slice(&0x01u32.to_le_bytes()); // type = NX_SUPERBLOCK
+        block[32..36].copy_from_slice(&superblock::NX_MAGIC.to_le_bytes());
+        block[36..40].copy_from_slice(&4096u32.to_le_bytes());
+        block[40..48].copy_from_slice(&16u64.to_le_bytes());
+        let checksum = fletcher::fletcher64(&block[8..]);
+        block[0..8].copy_from_slice(&checksum.to_le_bytes());
+
+        let mut image = block;
+        image.resize(4 * 4096, 0);
+        assert!(matches!(
+            ApfsVolume::open_checked(std::io::Cursor::new(&image)),
+            Err(ApfsError::Truncated { expected: 65536, actual: 16384 })
+        ));
+
+        // Full length passes the size check and fails later, on the empty container
+        image.resize(16 * 4096, 0);
+        let err = ApfsVolume::open_checked(std::io::Cursor::new(&image)).err().unwrap();
+        assert!(!matches!(err, ApfsError::Truncated { .. }));
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_open_checked() {
+        let image = std::fs::read("../tests/appfs.raw").unwrap();
+        assert!(ApfsVolume::open_checked(std::io::Cursor::new(&image)).is_ok());
+
+        let truncated = &image[..image.len() - 4096];
+        match ApfsVolume::open_checked(std::io::Cursor::new(truncated)) {
+            Err(ApfsError::Truncated { expected, actual }) => {
+                assert_eq!(expected, image.len() as u64);
+                assert_eq!(actual, truncated.len() as u64);
+            }
+            other => panic!("expected Truncated, got {:?}", other.err()),
+        }
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_open_at_offset() {
+        let mut image = vec![0xAAu8; 1024 * 1024];
+        image.extend(std::fs::read("../tests/appfs.raw").unwrap());
+
+        let mut embedded = ApfsVolume::open_at(std::io::Cursor::new(image), 1024 * 1024).unwrap();
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut direct = ApfsVolume::open(BufReader::new(file)).unwrap();
+
+        let names = |entries: Vec<DirEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.name).collect()
+        };
+        assert_eq!(
+            names(embedded.list_directory("/").unwrap()),
+            names(direct.list_directory("/").unwrap())
+        );
+
+        let walk = direct.walk().unwrap();
+        let entry = walk.iter()
+            .find(|e| e.entry.kind == EntryKind::File && e.entry.size > 0 && e.entry.size < 1_000_000)
+            .expect("Should find a small file in the test image");
+        assert_eq!(
+            embedded.read_file(&entry.path).unwrap(),
+            direct.read_file(&entry.path).unwrap()
+        );
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_into_inner_reopen() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut volume = ApfsVolume::open(BufReader::new(file)).unwrap();
+        let first = volume.list_directory("/").unwrap();
+
+        let reader = volume.into_inner();
+        let mut reopened = ApfsVolume::open(reader).unwrap();
+        let second = reopened.list_directory("/").unwrap();
+
+        assert_eq!(
+            first.into_iter().map(|e| e.name).collect::<Vec<_>>(),
+            second.into_iter().map(|e| e.name).collect::<Vec<_>>()
+        );
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_btree_stats() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut vol = ApfsVolume::open(BufReader::new(file)).unwrap();
+
+        let stats = vol.btree_stats().unwrap();
+        assert!(stats.node_count > 0);
+        assert!(stats.leaf_records > 0);
+        assert!(stats.longest_key > 0 && stats.longest_val > 0);
+
+        // Descend the leftmost spine; the number of levels visited is the depth
+        let mut block = vol.catalog_root_block;
+        let mut levels = 0u16;
+        loop {
+            let data = object::read_block(&mut vol.reader, block, vol.block_size).unwrap();
+            let node = btree::BTreeNode::parse(&data).unwrap();
+            levels += 1;
+            if node.node_header.is_leaf() {
+                break;
+            }
+            let child = node.child_oid(0).unwrap();
+            block = match vol.catalog_omap_root {
+                Some(omap_root) => omap::omap_lookup(&mut vol.reader, omap_root, vol.block_size, child).unwrap(),
+                None => child,
+            };
+        }
+        assert_eq!(stats.depth, levels);
+    }
+
+    #[test]
+    fn test_volume_info_from_superblock() {
+        let mut block = vec![0u8; 4096];
+        block[0x20..0x24].copy_from_slice(&superblock::APSB_MAGIC.to_le_bytes());
+        for (offset, count) in [(0xB8, 120u64), (0xC0, 30), (0xC8, 4), (0xD0, 2), (0xD8, 3)] {
+            block[offset..offset + 8].copy_from_slice(&count.to_le_bytes());
+        }
+        block[0x2C0..0x2C4].copy_from_slice(b"Data");
+
+        let sb = superblock::ApfsSuperblock::parse(&block).unwrap();
+        let info = VolumeInfo::from_superblock(&sb, 4096);
+        assert_eq!(info.name, "Data");
+        assert_eq!(info.num_files, 120);
+        assert_eq!(info.num_directories, 30);
+        assert_eq!(info.num_symlinks, 4);
+        assert_eq!(info.num_other_fsobjects, 2);
+        assert_eq!(info.num_snapshots, 3);
+    }
+
+    #[test]
+    fn test_read_link() {
+        let mut vol = ApfsVolume::open(std::io::Cursor::new(sample_container())).unwrap();
+        assert_eq!(vol.read_link("/link").unwrap(), "hello.txt");
+        assert!(matches!(vol.read_link("/hello.txt"), Err(ApfsError::NotASymlink(p)) if p == "/hello.txt"));
+        assert!(matches!(vol.read_link("/docs"), Err(ApfsError::NotASymlink(_))));
+        assert!(matches!(vol.read_link("/missing"), Err(ApfsError::FileNotFound(_))));
+
+        // A symlink inode without its target xattr
+        let root = catalog::ROOT_DIR_RECORD;
+        let records = vec![
+            inode_record(root, catalog::ROOT_DIR_PARENT, 0o40755, 0),
+            drec_record(root, "dangling", 16, catalog::DT_LNK),
+            inode_record(16, root, 0o120755, 0),
+        ];
+        let mut vol = ApfsVolume::open(std::io::Cursor::new(catalog_container(records, &[]))).unwrap();
+        assert!(matches!(vol.read_link("/dangling"), Err(ApfsError::CorruptedData(_))));
+    }
+
+    #[test]
+    fn test_walk_with_progress() {
+        use std::sync::atomic::AtomicBool;
+
+        let mut vol = ApfsVolume::open(std::io::Cursor::new(sample_container())).unwrap();
+        let mut calls = Vec::new();
+        let walk = vol.walk_with_progress(|found| calls.push(found), None).unwrap();
+        assert_eq!(walk.len(), vol.walk().unwrap().len());
+        // The root and `docs` are listed
+        assert_eq!(calls, [walk.len() - 1, walk.len()]);
+
+        // Cancel once the root is listed, before descending into `docs`
+        let cancel = AtomicBool::new(false);
+        let result = vol.walk_with_progress(|_| cancel.store(true, Ordering::Relaxed), Some(&cancel));
+        assert!(matches!(result, Err(ApfsError::Cancelled)));
+    }
+
+    #[test]
+    fn test_export_subtree_synthetic() {
+        let mut vol = ApfsVolume::open(std::io::Cursor::new(sample_container())).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let root = dest.path().join("export");
+        let report = vol.export_subtree("/", &root).unwrap();
+
+        assert_eq!(std::fs::read(root.join("hello.txt")).unwrap(), b"Hello, world!");
+        assert_eq!(std::fs::read(root.join("docs/a.txt")).unwrap(), b"abc");
+        // Symlinks are skipped on platforms without them
+        let symlinks = if cfg!(unix) { 1 } else { 0 };
+        let expected = ExportReport { directories: 2, files: 2, symlinks, bytes: 16, skipped: 2 - symlinks };
+        assert_eq!(report, expected);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::read_link(root.join("link")).unwrap(), Path::new("hello.txt"));
+            let mode = |path: &str| std::fs::metadata(root.join(path)).unwrap().permissions().mode() & 0o7777;
+            assert_eq!(mode("docs"), 0o750);
+            assert_eq!(mode("docs/a.txt"), 0o600);
+            assert_eq!(mode("hello.txt"), 0o644);
+        }
+
+        // A file is written to `dest` itself
+        let single = dest.path().join("single.txt");
+        let report = vol.export_subtree("/docs/a.txt", &single).unwrap();
+        assert_eq!(report, ExportReport { files: 1, bytes: 3, ..Default::default() });
+        assert_eq!(std::fs::read(single).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_locate_physical_catalog_root() {
+        let inode_key = ((catalog::J_TYPE_INODE as u64) << 60 | catalog::ROOT_DIR_RECORD).to_le_bytes().to_vec();
+        let mut inode_val = vec![0u8; 92];
+        inode_val[80..82].copy_from_slice(&catalog::INODE_DIR_TYPE.to_le_bytes());
+
+        // Block 1: physically-addressed index root whose child OID is block 2
+        let mut image = vec![0u8; 4096];
+        image.extend(build_catalog_node(1, Some(btree::BTREE_PHYSICAL), &[
+            (inode_key.clone(), 2u64.to_le_bytes().to_vec()),
+        ]));
+        image.extend(build_catalog_node(0, None, &[(inode_key, inode_val)]));
+
+        let mut sb_block = vec![0u8; 4096];
+        sb_block[0x20..0x24].copy_from_slice(&superblock::APSB_MAGIC.to_le_bytes());
+        sb_block[0x74..0x78].copy_from_slice(&(object::OBJ_PHYSICAL | object::OBJECT_TYPE_BTREE).to_le_bytes());
+        sb_block[0x88..0x90].copy_from_slice(&1u64.to_le_bytes());
+        let sb = superblock::ApfsSuperblock::parse(&sb_block).unwrap();
+
+        // The volume OMAP block is bogus; touching it would fail the lookup
+        let mut reader = std::io::Cursor::new(image);
+        let (root, omap_root) = locate_catalog_root(&mut reader, &sb, 0xDEAD, 4096).unwrap();
+        assert_eq!((root, omap_root), (1, None));
+
+        let inode = catalog::lookup_inode(&mut reader, root, omap_root, 4096, catalog::ROOT_DIR_RECORD).unwrap();
+        assert_eq!(inode.mode & 0o170000, catalog::INODE_DIR_TYPE);
+    }
+
+    #[test]
+    fn test_open_skips_unmountable_volume() {
+        let inode_key = ((catalog::J_TYPE_INODE as u64) << 60 | catalog::ROOT_DIR_RECORD).to_le_bytes().to_vec();
+        let mut inode_val = vec![0u8; 92];
+        inode_val[80..82].copy_from_slice(&catalog::INODE_DIR_TYPE.to_le_bytes());
+
+        // Block 1: container OMAP, 2: its tree, 3: a volume whose catalog root
+        // is block 7 (zeroed, so unparsable), 4: a good volume, 5: volume
+        // OMAP (unused by physical catalogs), 6: the good catalog root
+        let mut omap_phys = vec![0u8; 4096];
+        omap_phys[48..56].copy_from_slice(&2u64.to_le_bytes());
+        let mut image = build_container(&[1026, 1027], 8);
+        image.extend(omap_phys);
+        image.extend(build_omap_node(&[(1026, 3), (1027, 4), (1028, 7)]));
+        image.extend(build_volume("Preboot", 7));
+        image.extend(build_volume("Data", 6));
+        image.extend(vec![0u8; 4096]);
+        image.extend(build_catalog_node(0, Some(btree::BTREE_PHYSICAL), &[(inode_key, inode_val)]));
+        image.extend(vec![0u8; 4096]);
+
+        let mut vol = ApfsVolume::open(std::io::Cursor::new(&image)).unwrap();
+        assert_eq!(vol.volume_info().name, "Data");
+        assert_eq!(vol.stat("/").unwrap().kind, EntryKind::Directory);
+
+        // With no mountable volume, the first volume's error comes back
+        image[..4096].copy_from_slice(&build_container(&[1026, 1028], 8));
+        assert!(ApfsVolume::open(std::io::Cursor::new(&image)).is_err());
+        image[..4096].copy_from_slice(&build_container(&[0, 0], 8));
+        assert!(matches!(ApfsVolume::open(std::io::Cursor::new(&image)), Err(ApfsError::NoVolume)));
+    }
+
+    #[test]
+    fn test_lookup_dstream_refcount() {
+        let key = |oid: u64, j_type: u8| ((j_type as u64) << 60 | oid).to_le_bytes().to_vec();
+        let mut image = vec![0u8; 4096];
+        image.extend(build_catalog_node(0, Some(btree::BTREE_PHYSICAL), &[
+            (key(5, catalog::J_TYPE_INODE), vec![0u8; 92]),
+            (key(5, catalog::J_TYPE_DSTREAM_ID), 2u32.to_le_bytes().to_vec()),
+            (key(9, catalog::J_TYPE_DSTREAM_ID), 1u32.to_le_bytes().to_vec()),
+            (key(12, catalog::J_TYPE_DSTREAM_ID), vec![1]),
+        ]));
+        let mut reader = std::io::Cursor::new(image);
+
+        let refcount = |reader: &mut std::io::Cursor<Vec<u8>>, id| {
+            catalog::lookup_dstream_refcount(reader, 1, None, 4096, id)
+        };
+        assert_eq!(refcount(&mut reader, 5).unwrap(), 2);
+        assert_eq!(refcount(&mut reader, 9).unwrap(), 1);
+        assert!(matches!(refcount(&mut reader, 7), Err(ApfsError::FileNotFound(_))));
+        assert!(matches!(refcount(&mut reader, 12), Err(ApfsError::CorruptedData(_))));
+    }
+
+    #[test]
+    fn test_quarantine_info_parse() {
+        let info = QuarantineInfo::parse(b"0083;65a1b2c3;Safari;1F2E3D4C-0000-4000-8000-123456789ABC").unwrap();
+        assert_eq!(info.flags, 0x83);
+        assert_eq!(info.timestamp, Some(0x65a1b2c3));
+        assert_eq!(info.agent, "Safari");
+        assert_eq!(info.event_id.as_deref(), Some("1F2E3D4C-0000-4000-8000-123456789ABC"));
+
+        let info = QuarantineInfo::parse(b"0181;65a1b2c3;curl;\0").unwrap();
+        assert_eq!(info.agent, "curl");
+        assert_eq!(info.event_id, None);
+
+        assert!(QuarantineInfo::parse(b"zz;0;x;").is_err());
+        assert!(QuarantineInfo::parse(&[0xFF, 0xFE]).is_err());
+    }
+
+    #[test]
+    fn test_finder_info_parse() {
+        let mut data = [0u8; 32];
+        data[0..4].copy_from_slice(b"APPL");
+        data[4..8].copy_from_slice(b"ttxt");
+        data[8..10].copy_from_slice(&(FinderInfo::IS_INVISIBLE | 0x0400).to_be_bytes());
+        data[10..12].copy_from_slice(&(-20i16).to_be_bytes());
+        data[12..14].copy_from_slice(&300i16.to_be_bytes());
+        data[24..26].copy_from_slice(&0x0004u16.to_be_bytes());
+
+        let info = FinderInfo::parse(&data).unwrap();
+        assert_eq!(&info.file_type, b"APPL");
+        assert_eq!(&info.creator, b"ttxt");
+        assert!(info.is_invisible());
+        assert_eq!(info.location, (-20, 300));
+        assert_eq!(info.extended_flags, 0x0004);
+        assert!(FinderInfo::parse(&data[..16]).is_err());
+    }
+
+    /// Requires ../tests/appfs.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_xattrs_on_fixture() {
+        let file = std::fs::File::open("../tests/appfs.raw").unwrap();
+        let mut vol = ApfsVolume::open(BufReader::new(file)).unwrap();
+
+        for entry in vol.walk().unwrap() {
+            let names = vol.list_xattrs(&entry.path).unwrap();
+            for name in &names {
+                assert!(vol.read_xattr(&entry.path, name).unwrap().is_some());
+            }
+            let quarantine = vol.quarantine_info(&entry.path).unwrap();
+            assert_eq!(quarantine.is_some(), names.iter().any(|n| n == XATTR_QUARANTINE));
+            let finder = vol.finder_info(&entry.path).unwrap();
+            assert_eq!(finder.is_some(), names.iter().any(|n| n == XATTR_FINDER_INFO));
+        }
+        assert_eq!(vol.read_xattr("/", "com.example.missing").unwrap(), None);
+    }
 }
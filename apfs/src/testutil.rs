@@ -0,0 +1,213 @@
+//! Synthetic APFS containers for tests
+//!
+//! Built with the `test-util` feature so that crates layered on top of
+//! `apfs` can exercise it without fixture images.
+
+use crate::{btree, catalog, fletcher, object, superblock, XATTR_SYMLINK};
+
+/// Variable-size key/value B-tree node; the root carries `bt_flags`.
+pub fn build_catalog_node(level: u16, root_flags: Option<u32>, entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut block = vec![0u8; 4096];
+    block[0x18..0x1C].copy_from_slice(&object::OBJECT_TYPE_BTREE_NODE.to_le_bytes());
+
+    let mut flags = if level == 0 { btree::BTNODE_LEAF } else { 0 };
+    if root_flags.is_some() {
+        flags |= btree::BTNODE_ROOT;
+    }
+    let toc_len = (entries.len() * 8) as u16;
+    let hdr = object::ObjectHeader::SIZE;
+    block[hdr..hdr + 2].copy_from_slice(&flags.to_le_bytes());
+    block[hdr + 2..hdr + 4].copy_from_slice(&level.to_le_bytes());
+    block[hdr + 4..hdr + 8].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+    block[hdr + 10..hdr + 12].copy_from_slice(&toc_len.to_le_bytes());
+
+    let toc_start = hdr + btree::BTreeNodeHeader::SIZE;
+    let key_area = toc_start + toc_len as usize;
+    let val_end = block.len() - if root_flags.is_some() { btree::BTreeInfo::SIZE } else { 0 };
+
+    let (mut key_off, mut val_off) = (0usize, 0usize);
+    for (i, (key, val)) in entries.iter().enumerate() {
+        val_off += val.len();
+        let toc = toc_start + i * 8;
+        block[toc..toc + 2].copy_from_slice(&(key_off as u16).to_le_bytes());
+        block[toc + 2..toc + 4].copy_from_slice(&(key.len() as u16).to_le_bytes());
+        block[toc + 4..toc + 6].copy_from_slice(&(val_off as u16).to_le_bytes());
+        block[toc + 6..toc + 8].copy_from_slice(&(val.len() as u16).to_le_bytes());
+        block[key_area + key_off..key_area + key_off + key.len()].copy_from_slice(key);
+        block[val_end - val_off..val_end - val_off + val.len()].copy_from_slice(val);
+        key_off += key.len();
+    }
+
+    if let Some(bt_flags) = root_flags {
+        let info = block.len() - btree::BTreeInfo::SIZE;
+        block[info..info + 4].copy_from_slice(&bt_flags.to_le_bytes());
+        block[info + 4..info + 8].copy_from_slice(&4096u32.to_le_bytes());
+    }
+    block
+}
+
+/// Fixed-size OMAP leaf mapping virtual OIDs to physical blocks
+pub fn build_omap_node(entries: &[(u64, u64)]) -> Vec<u8> {
+    let mut block = vec![0u8; 4096];
+    block[0x18..0x1C].copy_from_slice(&object::OBJECT_TYPE_BTREE_NODE.to_le_bytes());
+    let flags = btree::BTNODE_ROOT | btree::BTNODE_LEAF | btree::BTNODE_FIXED_KV_SIZE;
+    let toc_len = (entries.len() * 4) as u16;
+    let hdr = object::ObjectHeader::SIZE;
+    block[hdr..hdr + 2].copy_from_slice(&flags.to_le_bytes());
+    block[hdr + 4..hdr + 8].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+    block[hdr + 10..hdr + 12].copy_from_slice(&toc_len.to_le_bytes());
+
+    let toc_start = hdr + btree::BTreeNodeHeader::SIZE;
+    let key_area = toc_start + toc_len as usize;
+    let val_end = block.len() - btree::BTreeInfo::SIZE;
+    for (i, (oid, paddr)) in entries.iter().enumerate() {
+        let (key_off, val_off) = (i * 16, (i + 1) * 16);
+        let toc = toc_start + i * 4;
+        block[toc..toc + 2].copy_from_slice(&(key_off as u16).to_le_bytes());
+        block[toc + 2..toc + 4].copy_from_slice(&(val_off as u16).to_le_bytes());
+        block[key_area + key_off..key_area + key_off + 8].copy_from_slice(&oid.to_le_bytes());
+        block[key_area + key_off + 8..key_area + key_off + 16].copy_from_slice(&1u64.to_le_bytes());
+        block[val_end - val_off + 8..val_end - val_off + 16].copy_from_slice(&paddr.to_le_bytes());
+    }
+    let info = block.len() - btree::BTreeInfo::SIZE;
+    block[info..info + 4].copy_from_slice(&btree::BTREE_PHYSICAL.to_le_bytes());
+    block[info + 4..info + 8].copy_from_slice(&4096u32.to_le_bytes());
+    block[info + 8..info + 12].copy_from_slice(&16u32.to_le_bytes());
+    block[info + 12..info + 16].copy_from_slice(&16u32.to_le_bytes());
+    block
+}
+
+/// Volume superblock with a physical catalog root at `root_block`
+pub fn build_volume(name: &str, root_block: u64) -> Vec<u8> {
+    let mut block = vec![0u8; 4096];
+    block[0x20..0x24].copy_from_slice(&superblock::APSB_MAGIC.to_le_bytes());
+    block[0x74..0x78].copy_from_slice(&(object::OBJ_PHYSICAL | object::OBJECT_TYPE_BTREE).to_le_bytes());
+    block[0x80..0x88].copy_from_slice(&5u64.to_le_bytes());
+    block[0x88..0x90].copy_from_slice(&root_block.to_le_bytes());
+    block[0x2C0..0x2C0 + name.len()].copy_from_slice(name.as_bytes());
+    block
+}
+
+/// Container superblock of `block_count` blocks whose OMAP is at block 1
+pub fn build_container(fs_oids: &[u64], block_count: u64) -> Vec<u8> {
+    let mut block = vec![0u8; 4096];
+    block[24..28].copy_from_slice(&0x01u32.to_le_bytes()); // type = NX_SUPERBLOCK
+    block[32..36].copy_from_slice(&superblock::NX_MAGIC.to_le_bytes());
+    block[36..40].copy_from_slice(&4096u32.to_le_bytes());
+    block[40..48].copy_from_slice(&block_count.to_le_bytes());
+    block[160..168].copy_from_slice(&1u64.to_le_bytes()); // omap_oid
+    block[180..184].copy_from_slice(&(fs_oids.len() as u32).to_le_bytes());
+    for (i, oid) in fs_oids.iter().enumerate() {
+        block[184 + i * 8..192 + i * 8].copy_from_slice(&oid.to_le_bytes());
+    }
+    let checksum = fletcher::fletcher64(&block[8..]);
+    block[0..8].copy_from_slice(&checksum.to_le_bytes());
+    block
+}
+
+/// Catalog key of `oid` with record type `j_type`
+pub fn catalog_key(oid: u64, j_type: u8) -> Vec<u8> {
+    ((j_type as u64) << 60 | oid).to_le_bytes().to_vec()
+}
+
+/// Inode record with `mode` and a `size` given as `uncompressed_size`;
+/// the data stream ID is the inode's own OID
+pub fn inode_record(oid: u64, parent: u64, mode: u16, size: u64) -> (Vec<u8>, Vec<u8>) {
+    let mut val = vec![0u8; 92];
+    val[0..8].copy_from_slice(&parent.to_le_bytes());
+    val[8..16].copy_from_slice(&oid.to_le_bytes());
+    val[56..60].copy_from_slice(&1i32.to_le_bytes());
+    val[80..82].copy_from_slice(&mode.to_le_bytes());
+    val[84..92].copy_from_slice(&size.to_le_bytes());
+    (catalog_key(oid, catalog::J_TYPE_INODE), val)
+}
+
+/// Directory record for `name` under `parent`, pointing at `oid`
+pub fn drec_record(parent: u64, name: &str, oid: u64, file_type: u16) -> (Vec<u8>, Vec<u8>) {
+    let mut key = catalog_key(parent, catalog::J_TYPE_DIR_REC);
+    key.extend_from_slice(&(name.len() as u32 + 1).to_le_bytes());
+    key.extend_from_slice(name.as_bytes());
+    key.push(0);
+    let mut val = oid.to_le_bytes().to_vec();
+    val.extend_from_slice(&[0u8; 8]);
+    val.extend_from_slice(&file_type.to_le_bytes());
+    (key, val)
+}
+
+/// Single-block extent at logical offset 0 of data stream `id`
+pub fn extent_record(id: u64, phys_block: u64) -> (Vec<u8>, Vec<u8>) {
+    let mut key = catalog_key(id, catalog::J_TYPE_FILE_EXTENT);
+    key.extend_from_slice(&0u64.to_le_bytes());
+    let mut val = 4096u64.to_le_bytes().to_vec();
+    val.extend_from_slice(&phys_block.to_le_bytes());
+    val.extend_from_slice(&0u64.to_le_bytes());
+    (key, val)
+}
+
+/// Embedded extended attribute `name` of `oid`
+pub fn xattr_record(oid: u64, name: &str, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut key = catalog_key(oid, catalog::J_TYPE_XATTR);
+    key.extend_from_slice(&(name.len() as u16 + 1).to_le_bytes());
+    key.extend_from_slice(name.as_bytes());
+    key.push(0);
+    let mut val = catalog::XATTR_DATA_EMBEDDED.to_le_bytes().to_vec();
+    val.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    val.extend_from_slice(data);
+    (key, val)
+}
+
+/// First block after the metadata written by [`catalog_container`]
+pub const DATA_BLOCK: u64 = 7;
+
+/// Container with one volume, "Data", whose physical catalog is a single
+/// leaf holding `records`, followed by one block per `data` entry from
+/// [`DATA_BLOCK`]
+pub fn catalog_container(mut records: Vec<(Vec<u8>, Vec<u8>)>, data: &[&[u8]]) -> Vec<u8> {
+    // Catalog keys sort by OID, then record type
+    records.sort_by_key(|(key, _)| {
+        let mut oid_and_type = [0u8; 8];
+        oid_and_type.copy_from_slice(&key[..8]);
+        let oid_and_type = u64::from_le_bytes(oid_and_type);
+        (oid_and_type & 0x0FFF_FFFF_FFFF_FFFF, oid_and_type >> 60)
+    });
+
+    // Block 1: container OMAP, 2: its tree, 3: the volume, 4: unused,
+    // 5: volume OMAP (unused by physical catalogs), 6: the catalog root
+    let mut omap_phys = vec![0u8; 4096];
+    omap_phys[48..56].copy_from_slice(&2u64.to_le_bytes());
+    let mut image = build_container(&[1026], DATA_BLOCK + data.len() as u64);
+    image.extend(omap_phys);
+    image.extend(build_omap_node(&[(1026, 3)]));
+    image.extend(build_volume("Data", 6));
+    image.extend(vec![0u8; 4096 * 2]);
+    image.extend(build_catalog_node(0, Some(btree::BTREE_PHYSICAL), &records));
+    for content in data {
+        let mut block = content.to_vec();
+        block.resize(4096, 0);
+        image.extend(block);
+    }
+    image
+}
+
+/// Root directory holding `docs/a.txt`, `hello.txt`, a symlink to it, and
+/// an entry whose name is not a host path component
+pub fn sample_container() -> Vec<u8> {
+    let root = catalog::ROOT_DIR_RECORD;
+    let records = vec![
+        inode_record(root, catalog::ROOT_DIR_PARENT, 0o40755, 0),
+        drec_record(root, "docs", 16, catalog::DT_DIR),
+        drec_record(root, "hello.txt", 17, catalog::DT_REG),
+        drec_record(root, "link", 18, catalog::DT_LNK),
+        drec_record(root, "..", 19, catalog::DT_REG),
+        inode_record(16, root, 0o40750, 0),
+        drec_record(16, "a.txt", 20, catalog::DT_REG),
+        inode_record(17, root, 0o100644, 13),
+        extent_record(17, DATA_BLOCK),
+        inode_record(18, root, 0o120755, 0),
+        xattr_record(18, XATTR_SYMLINK, b"hello.txt\0"),
+        inode_record(19, root, 0o100644, 0),
+        inode_record(20, 16, 0o100600, 3),
+        extent_record(20, DATA_BLOCK + 1),
+    ];
+    catalog_container(records, &[b"Hello, world!", b"abc"])
+}
@@ -61,6 +61,8 @@ fn info(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::erro
     kv_highlight("Files", &format_commas(vi.num_files));
     kv_highlight("Directories", &format_commas(vi.num_directories));
     kv("Symlinks", &format_commas(vi.num_symlinks));
+    kv("Other objects", &format_commas(vi.num_other_fsobjects));
+    kv("Snapshots", &format_commas(vi.num_snapshots));
     println!();
 
     Ok(())
@@ -88,8 +90,8 @@ fn ls(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::error:
     header(&format!("{dmg_path}:{path}"));
     println!();
     println!(
-        "  {DIM}{:<5} {:>12}  {}{RESET}",
-        "Kind", "Size", "Name"
+        "  {DIM}{:<5} {:>12}  Name{RESET}",
+        "Kind", "Size"
     );
     println!("  {DIM}{}{RESET}", "-".repeat(56));
 
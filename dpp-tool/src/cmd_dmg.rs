@@ -42,9 +42,9 @@ fn info(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let dmg_path = &args[0];
-    let archive = udif::DmgArchive::open(dmg_path)?;
-    let stats = archive.stats();
-    let comp_info = archive.compression_info();
+    let mut archive = udif::DmgArchive::open(dmg_path)?;
+    let stats = archive.stats()?;
+    let comp_info = archive.compression_info()?;
 
     header(&format!("DMG: {dmg_path}"));
 
@@ -86,7 +86,7 @@ fn ls(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
 
     header(&format!("Partitions: {dmg_path}"));
     println!();
-    println!("  {DIM}{:>4}  {:>12}  {:>12}  {:>12}  {:>7}  {}{RESET}", "ID", "Sectors", "Size", "Compressed", "Ratio", "Name");
+    println!("  {DIM}{:>4}  {:>12}  {:>12}  {:>12}  {:>7}  Name{RESET}", "ID", "Sectors", "Size", "Compressed", "Ratio");
     println!("  {DIM}{}{RESET}", "-".repeat(72));
 
     for p in &partitions {
@@ -107,7 +107,7 @@ fn ls(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
             format_size(p.size),
             format_size(p.compressed_size),
             ratio,
-            p.name
+            p.label()
         );
     }
 
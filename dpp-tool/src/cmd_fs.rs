@@ -113,8 +113,8 @@ fn ls(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::error:
     header(&format!("{dmg_path}:{path}"));
     println!();
     println!(
-        "  {DIM}{:<5} {:>12}  {}{RESET}",
-        "Kind", "Size", "Name"
+        "  {DIM}{:<5} {:>12}  Name{RESET}",
+        "Kind", "Size"
     );
     println!("  {DIM}{}{RESET}", "-".repeat(56));
 
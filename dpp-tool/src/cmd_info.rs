@@ -17,8 +17,8 @@ pub(crate) fn run(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn
     let mut pipeline = open_pipeline(dmg_path)?;
     let partitions = pipeline.partitions();
     let archive_stats = {
-        let archive = udif::DmgArchive::open(dmg_path)?;
-        (archive.stats(), archive.compression_info())
+        let mut archive = udif::DmgArchive::open(dmg_path)?;
+        (archive.stats()?, archive.compression_info()?)
     };
     let (stats, comp_info) = archive_stats;
 
@@ -45,7 +45,7 @@ pub(crate) fn run(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn
 
     // Partition table
     println!();
-    println!("  {DIM}{:>4}  {:>12}  {:>12}  {:>7}  {}{RESET}", "ID", "Sectors", "Size", "Ratio", "Name");
+    println!("  {DIM}{:>4}  {:>12}  {:>12}  {:>7}  Name{RESET}", "ID", "Sectors", "Size", "Ratio");
     println!("  {DIM}{}{RESET}", "-".repeat(58));
     for p in &partitions {
         let ratio = if p.size > 0 {
@@ -64,7 +64,7 @@ pub(crate) fn run(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn
             p.sectors,
             format_size(p.size),
             ratio,
-            p.name
+            p.label()
         );
     }
 
@@ -1,4 +1,3 @@
-use std::collections::BTreeMap;
 use std::io::{self, Cursor, Write};
 use std::process;
 use std::time::Instant;
@@ -32,11 +31,15 @@ fn print_usage() {
 {BOLD}dpp-tool payload{RESET} — Component payload (PBZX/CPIO) commands
 
 {BOLD}COMMANDS:{RESET}
-    {GREEN}info{RESET}   <dmg> <pkg-path> <component>                            Payload stats
+    {GREEN}info{RESET}   <dmg> <pkg-path> <component> [--raw]                    Payload stats
     {GREEN}ls{RESET}     <dmg> <pkg-path> <component> [path]                     List files
     {GREEN}tree{RESET}   <dmg> <pkg-path> <component> [path]                     Browse file tree
     {GREEN}find{RESET}   <dmg> <pkg-path> <component> [-name pat] [-type f|d|l]  Find files
     {GREEN}cat{RESET}    <dmg> <pkg-path> <component> <file>                     Extract file to stdout
+    {GREEN}cat{RESET}    <dmg> <pkg-path> <component> --raw                      Dump the raw Payload to stdout
+
+{BOLD}OPTIONS:{RESET}
+    {GREEN}--raw{RESET}  Use the Payload as stored in the package, without PBZX decompression
 "#
     );
 }
@@ -78,6 +81,52 @@ fn basename_of(p: &str) -> &str {
     }
 }
 
+/// Remove a `--raw` flag from the arguments, reporting whether it was present.
+fn take_raw_flag(args: &[String]) -> (bool, Vec<String>) {
+    let raw = args.iter().any(|a| a == "--raw");
+    let rest = args.iter().filter(|a| *a != "--raw").cloned().collect();
+    (raw, rest)
+}
+
+/// Best-effort identification of a payload's format from its leading bytes.
+fn payload_format(data: &[u8]) -> &'static str {
+    if data.starts_with(b"pbzx") {
+        "PBZX"
+    } else if data.starts_with(&[0x1F, 0x8B]) {
+        "gzip"
+    } else if data.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        "XZ"
+    } else if data.starts_with(b"BZh") {
+        "bzip2"
+    } else if data.starts_with(b"070707") {
+        "CPIO (odc)"
+    } else if data.starts_with(b"070701") {
+        "CPIO (newc)"
+    } else if data.starts_with(b"070702") {
+        "CPIO (crc)"
+    } else {
+        "unknown"
+    }
+}
+
+/// Read the raw Payload for a component, skipping PBZX decompression.
+fn open_raw_payload(
+    dmg_path: &str,
+    pkg_path: &str,
+    component: &str,
+    mode: dpp::ExtractMode,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut pipeline = open_pipeline(dmg_path)?;
+    let mut fs = open_filesystem(&mut pipeline, mode)?;
+
+    spinner_msg(&format!("Opening {pkg_path}"));
+    let t = Instant::now();
+    let mut pkg = fs.open_pkg(pkg_path)?;
+    spinner_done(&format!(" ({})", format_duration(t.elapsed())));
+
+    Ok(pkg.raw_payload(component)?)
+}
+
 /// Open the PBZX payload for a component and return the parsed Archive.
 fn open_archive(
     dmg_path: &str,
@@ -109,11 +158,27 @@ fn open_archive(
 // ── info ────────────────────────────────────────────────────────────────
 
 fn info(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::error::Error>> {
+    let (raw, args) = take_raw_flag(args);
     if args.len() < 3 {
-        eprintln!("Usage: dpp-tool payload info <dmg> <pkg-path> <component>");
+        eprintln!("Usage: dpp-tool payload info <dmg> <pkg-path> <component> [--raw]");
         process::exit(1);
     }
 
+    if raw {
+        let payload = open_raw_payload(&args[0], &args[1], &args[2], mode)?;
+        let head: Vec<String> = payload.iter().take(16).map(|b| format!("{b:02x}")).collect();
+
+        header(&format!("Payload: {}", args[2]));
+
+        section("Raw Payload");
+        kv("Size", &format_size(payload.len() as u64));
+        kv_highlight("Format", payload_format(&payload));
+        kv("First bytes", &head.join(" "));
+        println!();
+
+        return Ok(());
+    }
+
     let archive = open_archive(&args[0], &args[1], &args[2], mode)?;
     let entries = archive.list()?;
 
@@ -177,8 +242,8 @@ fn ls(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::error:
     header(&format!("Payload: {} — {display_dir}", args[2]));
     println!();
     println!(
-        "  {DIM}{:<5} {:>12}  {}{RESET}",
-        "Kind", "Size", "Name"
+        "  {DIM}{:<5} {:>12}  Name{RESET}",
+        "Kind", "Size"
     );
     println!("  {DIM}{}{RESET}", "-".repeat(56));
 
@@ -219,57 +284,33 @@ fn tree(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::erro
     let base = normalize_user_path(base_arg);
 
     let archive = open_archive(&args[0], &args[1], &args[2], mode)?;
-    let entries = archive.list()?;
-
-    // Build parent → children map
-    let mut children_map: BTreeMap<String, Vec<&pbzx::FileEntry>> = BTreeMap::new();
-    for entry in &entries {
-        let np = normalize_cpio(&entry.path);
-        if np.is_empty() { continue; }
-        let parent = parent_of(&np).to_string();
-        children_map.entry(parent).or_default().push(entry);
-    }
-
-    // Sort children in each bucket: dirs first, then by name
-    for bucket in children_map.values_mut() {
-        bucket.sort_by(|a, b| {
-            b.is_dir.cmp(&a.is_dir).then_with(|| {
-                basename_of(&normalize_cpio(&a.path)).cmp(basename_of(&normalize_cpio(&b.path)))
-            })
-        });
-    }
+    let root = archive.tree()?;
+    let Some(start) = root.find(&base) else {
+        return Err(format!("not found in payload: {base_arg}").into());
+    };
 
     let display_base = if base.is_empty() { "/" } else { base_arg };
     header(&format!("Tree: {} — {display_base}", args[2]));
     println!();
 
-    print_tree(&children_map, &base, "", 0, 3);
+    print_tree(start, "", 0, 3);
     println!();
 
     Ok(())
 }
 
-fn print_tree(
-    children_map: &BTreeMap<String, Vec<&pbzx::FileEntry>>,
-    dir: &str,
-    prefix: &str,
-    depth: usize,
-    max_depth: usize,
-) {
+fn print_tree(node: &pbzx::TreeNode, prefix: &str, depth: usize, max_depth: usize) {
     if depth > max_depth {
         println!("  {prefix}{DIM}{TEE} ...{RESET}");
         return;
     }
 
-    let entries = match children_map.get(dir) {
-        Some(v) => v,
-        None => return,
-    };
+    // Dirs first, then by name (children are already sorted by name)
+    let mut children: Vec<&pbzx::TreeNode> = node.children.iter().collect();
+    children.sort_by_key(|child| !child.is_dir());
 
-    for (i, entry) in entries.iter().enumerate() {
-        let np = normalize_cpio(&entry.path);
-        let name = basename_of(&np);
-        let is_last = i == entries.len() - 1;
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i == children.len() - 1;
         let connector = if is_last { ELBOW } else { TEE };
         let child_prefix = if is_last {
             format!("{prefix}    ")
@@ -277,20 +318,28 @@ fn print_tree(
             format!("{prefix}{PIPE}   ")
         };
 
-        let (color, _) = entry_style(entry);
-        let size_str = if !entry.is_dir {
-            format!("  {DIM}{}{RESET}", format_size(entry.size))
+        let color = match child.kind {
+            pbzx::TreeNodeKind::Directory => BLUE,
+            pbzx::TreeNodeKind::Symlink => CYAN,
+            pbzx::TreeNodeKind::File => WHITE,
+        };
+        let size_str = if !child.is_dir() {
+            format!("  {DIM}{}{RESET}", format_size(child.size))
         } else {
             String::new()
         };
-        let suffix = symlink_suffix(entry);
+        let suffix = match &child.link_target {
+            Some(target) => format!(" {DIM}-> {target}{RESET}"),
+            None => String::new(),
+        };
+        let name = &child.name;
 
         println!(
             "  {prefix}{DIM}{connector}{RESET} {color}{BOLD}{name}{RESET}{suffix}{size_str}",
         );
 
-        if entry.is_dir {
-            print_tree(children_map, &np, &child_prefix, depth + 1, max_depth);
+        if child.is_dir() {
+            print_tree(child, &child_prefix, depth + 1, max_depth);
         }
     }
 }
@@ -351,9 +400,9 @@ fn find(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::erro
 
             if let Some(tf) = type_filter {
                 match tf {
-                    "f" => if e.is_dir || e.is_symlink { return false; }
-                    "d" => if !e.is_dir { return false; }
-                    "l" => if !e.is_symlink { return false; }
+                    "f" if e.is_dir || e.is_symlink => return false,
+                    "d" if !e.is_dir => return false,
+                    "l" if !e.is_symlink => return false,
                     _ => {}
                 }
             }
@@ -395,8 +444,15 @@ fn find(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::erro
 // ── cat ─────────────────────────────────────────────────────────────────
 
 fn cat(args: &[String], mode: dpp::ExtractMode) -> Result<(), Box<dyn std::error::Error>> {
-    if args.len() < 4 {
+    let (raw, args) = take_raw_flag(args);
+    if raw && args.len() == 3 {
+        let payload = open_raw_payload(&args[0], &args[1], &args[2], mode)?;
+        io::stdout().lock().write_all(&payload)?;
+        return Ok(());
+    }
+    if raw || args.len() < 4 {
         eprintln!("Usage: dpp-tool payload cat <dmg> <pkg-path> <component> <file>");
+        eprintln!("       dpp-tool payload cat <dmg> <pkg-path> <component> --raw");
         process::exit(1);
     }
 
@@ -39,11 +39,12 @@
 //! dpp-tool pkg find <dmg> <pkg-path> [-name p] [-type f|d|l]  Find entries
 //! dpp-tool pkg cat <dmg> <pkg-path> <file>               XAR entry to stdout
 //!
-//! dpp-tool payload info <dmg> <pkg> <comp>               Payload (PBZX/CPIO) stats
+//! dpp-tool payload info <dmg> <pkg> <comp> [--raw]       Payload (PBZX/CPIO) stats
 //! dpp-tool payload ls <dmg> <pkg> <comp> [path]          List payload files
 //! dpp-tool payload tree <dmg> <pkg> <comp> [path]        Browse payload tree
 //! dpp-tool payload find <dmg> <pkg> <comp> [-name p] [-type f|d|l]  Find payload files
 //! dpp-tool payload cat <dmg> <pkg> <comp> <file>         Extract payload file to stdout
+//! dpp-tool payload cat <dmg> <pkg> <comp> --raw          Dump the raw Payload to stdout
 //! ```
 
 mod style;
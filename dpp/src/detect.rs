@@ -0,0 +1,92 @@
+//! Identify Apple file formats by their magic numbers
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::error::Result;
+
+/// Kind of file recognised by [`detect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// UDIF disk image (`koly` trailer 512 bytes from the end)
+    Dmg,
+    /// PBZX stream, e.g. a package payload (`pbzx`)
+    Pbzx,
+    /// XAR archive, e.g. a flat `.pkg` (`xar!`)
+    Xar,
+    /// CPIO archive in newc, crc, odc or old binary format
+    Cpio,
+    /// Raw HFS+ or HFSX volume (`H+`/`HX` at offset 1024)
+    HfsPlus,
+    /// Raw APFS container (`NXSB` in block 0)
+    Apfs,
+    /// None of the above
+    Unknown,
+}
+
+/// Size of the koly trailer at the end of a DMG
+const KOLY_SIZE: u64 = 512;
+
+/// Offset of the HFS+ volume header signature
+const HFS_SIGNATURE_OFFSET: usize = 1024;
+
+/// Offset of the container superblock magic, after the 32-byte object header
+const NX_MAGIC_OFFSET: usize = 32;
+
+/// Identify a file by sniffing its magic numbers
+///
+/// The koly trailer is checked first, since a DMG's data fork may itself
+/// start with a volume. Files too short to hold a given magic are simply
+/// not that kind; only I/O errors fail.
+pub fn detect<P: AsRef<Path>>(path: P) -> Result<FileKind> {
+    let mut reader = BufReader::new(File::open(path)?);
+    detect_reader(&mut reader)
+}
+
+/// [`detect`] over any seekable reader
+fn detect_reader<R: Read + Seek>(reader: &mut R) -> Result<FileKind> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    if len >= KOLY_SIZE {
+        reader.seek(SeekFrom::End(-(KOLY_SIZE as i64)))?;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic == udif::format::KOLY_MAGIC {
+            return Ok(FileKind::Dmg);
+        }
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut head = Vec::new();
+    reader.by_ref().take(HFS_SIGNATURE_OFFSET as u64 + 2).read_to_end(&mut head)?;
+    Ok(classify_head(&head))
+}
+
+/// Classify a file from its first bytes
+fn classify_head(head: &[u8]) -> FileKind {
+    use pbzx::format::{CPIO_MAGIC_BIN, CPIO_MAGIC_CRC, CPIO_MAGIC_NEWC, CPIO_MAGIC_ODC, PBZX_MAGIC};
+
+    let be_u16 = |at: usize| head.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let be_u32 = |at: usize| head.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let le_u32 = |at: usize| head.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    if head.starts_with(&PBZX_MAGIC) {
+        FileKind::Pbzx
+    } else if be_u32(0) == Some(xara::header::XAR_MAGIC) {
+        FileKind::Xar
+    } else if [CPIO_MAGIC_NEWC, CPIO_MAGIC_CRC, CPIO_MAGIC_ODC].iter().any(|m| head.starts_with(&m[..])) {
+        FileKind::Cpio
+    } else if matches!(
+        be_u16(HFS_SIGNATURE_OFFSET),
+        Some(hfsplus::volume::HFS_PLUS_SIGNATURE | hfsplus::volume::HFSX_SIGNATURE)
+    ) {
+        FileKind::HfsPlus
+    } else if le_u32(NX_MAGIC_OFFSET) == Some(apfs::superblock::NX_MAGIC) {
+        FileKind::Apfs
+    } else if be_u16(0).is_some_and(|m| m == CPIO_MAGIC_BIN || m.swap_bytes() == CPIO_MAGIC_BIN) {
+        // Only two bytes of magic, so checked after the volume formats
+        FileKind::Cpio
+    } else {
+        FileKind::Unknown
+    }
+}
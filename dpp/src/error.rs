@@ -1,5 +1,9 @@
 use thiserror::Error;
 
+/// Errors from the dpp pipeline.
+///
+/// Errors from the underlying crates convert via `From` (so `?` works on their
+/// results) and remain reachable through [`std::error::Error::source`].
 #[derive(Error, Debug)]
 pub enum DppError {
     #[error("I/O error: {0}")]
@@ -31,6 +35,12 @@ pub enum DppError {
 
     #[error("no filesystem partition found in DMG")]
     NoFilesystemPartition,
+
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("partition '{partition}' uses unsupported {method} compression")]
+    UnsupportedCompression { partition: String, method: String },
 }
 
 pub type Result<T> = std::result::Result<T, DppError>;
@@ -1,6 +1,8 @@
+pub mod detect;
 pub mod error;
 pub mod pipeline;
 
+pub use detect::{detect, FileKind};
 pub use error::{DppError, Result};
 pub use pipeline::{
     DmgPipeline, ExtractMode, HfsHandle, ApfsHandle,
@@ -19,6 +21,21 @@ pub use pbzx;
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_error_source_chain() {
+        use std::error::Error;
+
+        let err: DppError = pbzx::PbzxError::InvalidMagic(*b"nope").into();
+        let source = err.source().expect("wrapped error should be the source");
+        let inner = source.downcast_ref::<pbzx::PbzxError>().unwrap();
+        assert!(matches!(inner, pbzx::PbzxError::InvalidMagic(m) if m == b"nope"));
+
+        let err: DppError = apfs::ApfsError::InvalidChecksum.into();
+        assert!(err.source().unwrap().is::<apfs::ApfsError>());
+
+        assert!(DppError::NoHfsPartition.source().is_none());
+    }
+
     #[test]
     fn test_extract_mode_default() {
         assert!(matches!(ExtractMode::default(), ExtractMode::TempFile));
@@ -46,6 +63,8 @@ mod tests {
             },
             data_fork_extents: 3,
             resource_fork_size: 512,
+            data_fork_blocks: 1,
+            resource_fork_blocks: 1,
         };
 
         let unified = FsFileStat::from(&hfs_stat);
@@ -78,6 +97,8 @@ mod tests {
             },
             data_fork_extents: 0,
             resource_fork_size: 0,
+            data_fork_blocks: 0,
+            resource_fork_blocks: 0,
         };
 
         let unified = FsFileStat::from(&hfs_stat);
@@ -96,6 +117,8 @@ mod tests {
             gid: 80,
             mode: 0o120755,
             nlink: 2,
+            is_clone: false,
+            dstream_id: 7,
         };
 
         let unified = FsFileStat::from(&apfs_stat);
@@ -127,4 +150,362 @@ mod tests {
         let err = DppError::FileNotFound("test.pkg".to_string());
         assert_eq!(err.to_string(), "file not found: test.pkg");
     }
+
+    #[test]
+    fn test_unsupported_compression_names_partition() {
+        use base64::Engine;
+        use std::io::Cursor;
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = udif::DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("Apple_HFS", &[0x5Au8; 4096]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // Rewrite the first block run's type to ADC inside the base64 mish blob,
+        // keeping the line layout so every offset in the koly stays valid
+        let find = |from: usize, needle: &[u8]| {
+            from + dmg_buf[from..]
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .unwrap()
+        };
+        let start = find(0, b"<data>") + b"<data>".len();
+        let end = find(start, b"</data>");
+        let encoded: Vec<u8> = dmg_buf[start..end]
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut mish = engine.decode(&encoded).unwrap();
+        assert_eq!(&mish[..4], b"mish");
+        mish[204..208].copy_from_slice(&0x8000_0004u32.to_be_bytes());
+
+        let mut reencoded = engine.encode(&mish).into_bytes().into_iter();
+        for byte in &mut dmg_buf[start..end] {
+            if !byte.is_ascii_whitespace() {
+                *byte = reencoded.next().unwrap();
+            }
+        }
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dmg_path = temp_dir.path().join("adc.dmg");
+        std::fs::write(&dmg_path, &dmg_buf).unwrap();
+
+        let mut pipeline = DmgPipeline::open(&dmg_path).unwrap();
+        match pipeline.open_hfs() {
+            Err(DppError::UnsupportedCompression { partition, method }) => {
+                assert_eq!(partition, "Apple_HFS");
+                assert_eq!(method, "ADC");
+            }
+            Err(e) => panic!("expected UnsupportedCompression, got {}", e),
+            Ok(_) => panic!("expected UnsupportedCompression, got a volume"),
+        }
+    }
+
+    /// Requires ../tests/kdk.raw and ../tests/appfs.raw fixtures.
+    /// Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_read_link_unified() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (raw, partition, fs_type) in [
+            ("../tests/kdk.raw", "disk image (Apple_HFSX : 1)", FsType::HfsPlus),
+            ("../tests/appfs.raw", "disk image (Apple_APFS : 1)", FsType::Apfs),
+        ] {
+            let data = std::fs::read(raw).unwrap();
+            let dmg_path = temp_dir.path().join("fs.dmg");
+            {
+                let file = std::fs::File::create(&dmg_path).unwrap();
+                let mut writer = udif::DmgWriter::new(std::io::BufWriter::new(file));
+                writer.add_partition(partition, &data).unwrap();
+                writer.finish().unwrap();
+            }
+
+            let mut pipeline = DmgPipeline::open(&dmg_path).unwrap();
+            let mut fs = pipeline.open_filesystem_with_mode(ExtractMode::InMemory).unwrap();
+            assert_eq!(fs.fs_type(), fs_type);
+
+            let entries = fs.walk().unwrap();
+            let symlinks: Vec<_> = entries
+                .iter()
+                .filter(|e| e.entry.kind == FsEntryKind::Symlink)
+                .collect();
+            assert!(!symlinks.is_empty(), "{} has no symlinks", raw);
+            for link in symlinks {
+                let target = fs.read_link(&link.path).unwrap();
+                assert!(!target.is_empty() && !target.contains('\0'), "{} -> {:?}", link.path, target);
+            }
+
+            let file = entries.iter().find(|e| e.entry.kind == FsEntryKind::File).unwrap();
+            assert!(fs.read_link(&file.path).is_err());
+        }
+    }
+
+    /// Requires ../tests/kdk.raw and ../tests/appfs.raw fixtures.
+    /// Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_read_file_cancelled() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (raw, partition) in [
+            ("../tests/kdk.raw", "disk image (Apple_HFSX : 1)"),
+            ("../tests/appfs.raw", "disk image (Apple_APFS : 1)"),
+        ] {
+            let data = std::fs::read(raw).unwrap();
+            let dmg_path = temp_dir.path().join("fs.dmg");
+            {
+                let file = std::fs::File::create(&dmg_path).unwrap();
+                let mut writer = udif::DmgWriter::new(std::io::BufWriter::new(file));
+                writer.add_partition(partition, &data).unwrap();
+                writer.finish().unwrap();
+            }
+
+            let mut pipeline = DmgPipeline::open(&dmg_path).unwrap();
+            let mut fs = pipeline.open_filesystem_with_mode(ExtractMode::InMemory).unwrap();
+            let block_size = fs.volume_info().block_size as u64;
+            let large = fs
+                .walk()
+                .unwrap()
+                .into_iter()
+                .find(|e| e.entry.kind == FsEntryKind::File && e.entry.size > 4 * block_size)
+                .expect("no multi-block file");
+
+            // Cancel from the progress callback once the first block is out
+            let cancel = AtomicBool::new(false);
+            let mut out = Vec::new();
+            let result = fs.read_file_to_with_progress(
+                &large.path,
+                &mut out,
+                |_, _| cancel.store(true, Ordering::Relaxed),
+                Some(&cancel),
+            );
+            assert!(matches!(result, Err(DppError::Cancelled)), "{}: {:?}", raw, result);
+            assert!((out.len() as u64) < large.entry.size);
+        }
+    }
+
+    #[test]
+    fn test_stat_cache_lru() {
+        use crate::pipeline::{StatCache, STAT_CACHE_CAPACITY};
+
+        let mut cache = StatCache::default();
+        for i in 0..STAT_CACHE_CAPACITY {
+            cache.insert(&format!("/f{}", i), i);
+        }
+        // Touching the oldest entry saves it from the next eviction
+        assert_eq!(cache.get("/f0"), Some(0));
+        cache.insert("/new", 100);
+        assert!(cache.contains("/f0"));
+        assert!(!cache.contains("/f1"));
+        assert!(cache.contains("/new"));
+
+        let mut lookups = 0;
+        let mut stat = |cache: &mut StatCache<usize>| {
+            cache.get_or_try_insert("/new", || {
+                lookups += 1;
+                Ok::<_, ()>(7)
+            })
+        };
+        assert_eq!(stat(&mut cache), Ok(100));
+        cache.clear();
+        assert_eq!(stat(&mut cache), Ok(7));
+        assert_eq!(stat(&mut cache), Ok(7));
+        assert_eq!(lookups, 1);
+
+        // Failed lookups are not cached
+        assert_eq!(cache.get_or_try_insert("/missing", || Err(())), Err(()));
+        assert!(!cache.contains("/missing"));
+    }
+
+    /// Requires ../tests/kdk.raw and ../tests/appfs.raw fixtures.
+    /// Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_stat_cache_skips_reads() {
+        use crate::pipeline::StatCache;
+        use std::cell::Cell;
+        use std::io::{Cursor, Read, Seek, SeekFrom};
+        use std::rc::Rc;
+
+        /// Reader that counts `read` calls
+        struct CountingReader {
+            inner: Cursor<Vec<u8>>,
+            reads: Rc<Cell<usize>>,
+        }
+
+        impl Read for CountingReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.reads.set(self.reads.get() + 1);
+                self.inner.read(buf)
+            }
+        }
+
+        impl Seek for CountingReader {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        let reads = Rc::new(Cell::new(0));
+        let reader = |raw: &str| CountingReader {
+            inner: Cursor::new(std::fs::read(raw).unwrap()),
+            reads: reads.clone(),
+        };
+        let reads_during = |f: &mut dyn FnMut()| {
+            let before = reads.get();
+            f();
+            reads.get() - before
+        };
+
+        let mut hfs = hfsplus::HfsVolume::open(reader("../tests/kdk.raw")).unwrap();
+        let path = hfs.walk().unwrap().into_iter().map(|e| e.path).max_by_key(|p| p.len()).unwrap();
+        let mut cache = StatCache::default();
+        let first = reads_during(&mut || {
+            cache.get_or_try_insert(&path, || hfs.stat(&path)).unwrap();
+        });
+        let second = reads_during(&mut || {
+            cache.get_or_try_insert(&path, || hfs.stat(&path)).unwrap();
+        });
+        assert!(first > 0 && second < first, "{}: {} then {} reads", path, first, second);
+
+        let mut apfs = apfs::ApfsVolume::open(reader("../tests/appfs.raw")).unwrap();
+        let path = apfs.walk().unwrap().into_iter().map(|e| e.path).max_by_key(|p| p.len()).unwrap();
+        let mut cache = StatCache::default();
+        let first = reads_during(&mut || {
+            cache.get_or_try_insert(&path, || apfs.stat(&path)).unwrap();
+        });
+        let second = reads_during(&mut || {
+            cache.get_or_try_insert(&path, || apfs.stat(&path)).unwrap();
+        });
+        assert!(first > 0 && second < first, "{}: {} then {} reads", path, first, second);
+    }
+
+    #[test]
+    fn test_handle_cache_synthetic() {
+        use crate::pipeline::{ApfsHandle, ApfsHandleInner, HfsHandle, HfsHandleInner, StatCache};
+        use std::io::Cursor;
+
+        // Renaming the catalog entry of `hello.txt` on disk leaves only a cached
+        // lookup able to find it
+        let rename = |image: &mut Vec<u8>, from: &[u8], to: &[u8]| {
+            let at = image.windows(from.len()).position(|w| w == from).unwrap();
+            image[at..at + to.len()].copy_from_slice(to);
+        };
+
+        let mut image = hfsplus::testutil::sample_volume();
+        let volume = |image: Vec<u8>| HfsHandleInner::Memory(hfsplus::HfsVolume::open(Cursor::new(image)).unwrap());
+        let mut hfs = HfsHandle { inner: volume(image.clone()), stat_cache: StatCache::default() };
+        assert_eq!(hfs.stat("/hello.txt").unwrap().size, 13);
+        assert!(hfs.exists("/link").unwrap());
+        hfs.stat("/link").unwrap();
+        rename(&mut image, &[0, b'h', 0, b'e'], &[0, b'x']);
+        hfs.inner = volume(image);
+
+        assert_eq!(hfs.read_file("/hello.txt").unwrap(), b"Hello, world!");
+        assert_eq!(hfs.read_link("/link").unwrap(), "hello.txt");
+        assert!(matches!(hfs.read_link("/hello.txt"), Err(DppError::Hfs(hfsplus::HfsPlusError::NotASymlink(_)))));
+        hfs.clear_cache();
+        assert!(hfs.read_file("/hello.txt").is_err());
+
+        let mut image = apfs::testutil::sample_container();
+        let volume = |image: Vec<u8>| ApfsHandleInner::Memory(apfs::ApfsVolume::open(Cursor::new(image)).unwrap());
+        let mut fs = ApfsHandle { inner: volume(image.clone()), stat_cache: StatCache::default() };
+        assert_eq!(fs.stat("/hello.txt").unwrap().size, 13);
+        assert_eq!(fs.stat("/link").unwrap().kind, apfs::EntryKind::Symlink);
+        rename(&mut image, b"hello.txt\0", b"x");
+        fs.inner = volume(image);
+
+        assert_eq!(fs.read_file("/hello.txt").unwrap(), b"Hello, world!");
+        assert_eq!(fs.read_link("/link").unwrap(), "hello.txt");
+        assert!(matches!(fs.read_link("/hello.txt"), Err(DppError::Apfs(apfs::ApfsError::NotASymlink(_)))));
+        fs.clear_cache();
+        assert!(fs.read_file("/hello.txt").is_err());
+    }
+
+    #[test]
+    fn test_walk_cancel_synthetic() {
+        use crate::pipeline::{ApfsHandle, ApfsHandleInner, HfsHandle, HfsHandleInner, StatCache};
+        use std::io::Cursor;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let hfs = hfsplus::HfsVolume::open(Cursor::new(hfsplus::testutil::sample_volume())).unwrap();
+        let apfs = apfs::ApfsVolume::open(Cursor::new(apfs::testutil::sample_container())).unwrap();
+        let handles = [
+            FilesystemHandle::Hfs(HfsHandle {
+                inner: HfsHandleInner::Memory(hfs),
+                stat_cache: StatCache::default(),
+            }),
+            FilesystemHandle::Apfs(ApfsHandle {
+                inner: ApfsHandleInner::Memory(apfs),
+                stat_cache: StatCache::default(),
+            }),
+        ];
+        for mut fs in handles {
+            let cancel = AtomicBool::new(false);
+            let walk = fs.walk_with_progress(|_| {}, Some(&cancel)).unwrap();
+            assert_eq!(walk.len(), fs.walk().unwrap().len());
+
+            let result = fs.walk_with_progress(|_| cancel.store(true, Ordering::Relaxed), Some(&cancel));
+            assert!(matches!(result, Err(DppError::Cancelled)), "{:?}", result.map(|w| w.len()));
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        use pbzx::format::CpioFormat;
+        use std::io::Cursor;
+
+        let cpio = |format| {
+            let mut builder = pbzx::CpioBuilder::with_format(format);
+            builder.add_file("file.txt", b"contents", 0o644);
+            builder.finish()
+        };
+        let volume = |at: usize, magic: &[u8]| {
+            let mut data = vec![0u8; 4096];
+            data[at..at + magic.len()].copy_from_slice(magic);
+            data
+        };
+
+        let mut dmg = Vec::new();
+        {
+            let mut writer = udif::DmgWriter::new(Cursor::new(&mut dmg));
+            // A volume inside the data fork must not hide the koly trailer
+            writer.add_partition("Apple_HFS", &volume(1024, b"H+")).unwrap();
+            writer.finish().unwrap();
+        }
+        let mut payload = pbzx::PbzxWriter::new(Vec::new()).compression_level(0);
+        payload.write_cpio(&cpio(CpioFormat::Newc)).unwrap();
+        let payload = payload.finish().unwrap();
+        let mut xar = b"xar!\x00\x1c\x00\x01".to_vec();
+        xar.resize(64, 0);
+
+        let samples: Vec<(&str, Vec<u8>, FileKind)> = vec![
+            ("image.dmg", dmg, FileKind::Dmg),
+            ("Payload", payload, FileKind::Pbzx),
+            ("installer.pkg", xar, FileKind::Xar),
+            ("newc.cpio", cpio(CpioFormat::Newc), FileKind::Cpio),
+            ("crc.cpio", cpio(CpioFormat::Crc), FileKind::Cpio),
+            ("odc.cpio", cpio(CpioFormat::Odc), FileKind::Cpio),
+            ("bin-le.cpio", cpio(CpioFormat::Bin { big_endian: false }), FileKind::Cpio),
+            ("bin-be.cpio", cpio(CpioFormat::Bin { big_endian: true }), FileKind::Cpio),
+            ("hfs.raw", volume(1024, b"H+"), FileKind::HfsPlus),
+            ("hfsx.raw", volume(1024, b"HX"), FileKind::HfsPlus),
+            ("apfs.raw", volume(32, b"NXSB"), FileKind::Apfs),
+            ("text.txt", b"just some text".to_vec(), FileKind::Unknown),
+            ("empty", Vec::new(), FileKind::Unknown),
+        ];
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (name, data, kind) in samples {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, data).unwrap();
+            assert_eq!(detect(&path).unwrap(), kind, "{}", name);
+        }
+        assert!(matches!(detect(temp_dir.path().join("missing")), Err(DppError::Io(_))));
+    }
 }
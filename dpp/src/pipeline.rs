@@ -1,23 +1,20 @@
+use std::collections::VecDeque;
 use std::io::{BufReader, BufWriter, Cursor, Seek};
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
 
-use crate::error::Result;
+use crate::error::{DppError, Result};
 
 /// Extraction mode for partition data
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ExtractMode {
     /// Stream to temp file on disk (low memory). Default.
+    #[default]
     TempFile,
     /// Buffer entire partition in memory. Fast for small DMGs.
     InMemory,
 }
 
-impl Default for ExtractMode {
-    fn default() -> Self {
-        ExtractMode::TempFile
-    }
-}
-
 /// Main pipeline entry point: DMG → HFS+/APFS → PKG → PBZX
 pub struct DmgPipeline {
     archive: udif::DmgArchive,
@@ -47,6 +44,7 @@ impl DmgPipeline {
             .archive
             .hfs_partition_id()
             .map_err(|_| crate::error::DppError::NoHfsPartition)?;
+        self.check_partition_supported(partition_id)?;
 
         match mode {
             ExtractMode::TempFile => {
@@ -62,6 +60,7 @@ impl DmgPipeline {
                 let volume = hfsplus::HfsVolume::open(reader)?;
                 Ok(HfsHandle {
                     inner: HfsHandleInner::File(volume),
+                    stat_cache: StatCache::default(),
                 })
             }
             ExtractMode::InMemory => {
@@ -72,6 +71,7 @@ impl DmgPipeline {
                 let volume = hfsplus::HfsVolume::open(cursor)?;
                 Ok(HfsHandle {
                     inner: HfsHandleInner::Memory(volume),
+                    stat_cache: StatCache::default(),
                 })
             }
         }
@@ -86,6 +86,7 @@ impl DmgPipeline {
     /// Extract APFS with explicit mode
     pub fn open_apfs_with_mode(&mut self, mode: ExtractMode) -> Result<ApfsHandle> {
         let partition_id = self.apfs_partition_id()?;
+        self.check_partition_supported(partition_id)?;
 
         match mode {
             ExtractMode::TempFile => {
@@ -100,6 +101,7 @@ impl DmgPipeline {
                 let volume = apfs::ApfsVolume::open(reader)?;
                 Ok(ApfsHandle {
                     inner: ApfsHandleInner::File(volume),
+                    stat_cache: StatCache::default(),
                 })
             }
             ExtractMode::InMemory => {
@@ -110,6 +112,7 @@ impl DmgPipeline {
                 let volume = apfs::ApfsVolume::open(cursor)?;
                 Ok(ApfsHandle {
                     inner: ApfsHandleInner::Memory(volume),
+                    stat_cache: StatCache::default(),
                 })
             }
         }
@@ -144,6 +147,29 @@ impl DmgPipeline {
         Err(crate::error::DppError::NoFilesystemPartition)
     }
 
+    /// Fail early, naming the partition, if it uses a block compression
+    /// that cannot be extracted (currently ADC).
+    fn check_partition_supported(&mut self, partition_id: i32) -> Result<()> {
+        let adc_blocks = self
+            .archive
+            .partition_compression_info(partition_id)?
+            .map_or(0, |info| info.adc_blocks);
+        if adc_blocks == 0 {
+            return Ok(());
+        }
+
+        let partition = self
+            .archive
+            .partitions()
+            .into_iter()
+            .find(|p| p.id == partition_id)
+            .map_or_else(|| format!("#{}", partition_id), |p| p.name);
+        Err(crate::error::DppError::UnsupportedCompression {
+            partition,
+            method: "ADC".into(),
+        })
+    }
+
     /// Find the partition ID of the APFS partition.
     fn apfs_partition_id(&self) -> Result<i32> {
         let partitions = self.archive.partitions();
@@ -156,15 +182,84 @@ impl DmgPipeline {
     }
 }
 
+// ── Stat Cache ──────────────────────────────────────────────────────────
+
+/// Number of paths a handle keeps resolved records for
+pub(crate) const STAT_CACHE_CAPACITY: usize = 64;
+
+/// Small LRU of path → resolved catalog record, most recently used first
+///
+/// Interactive tools tend to `stat` a path and then read it, so each lookup
+/// would otherwise walk the catalog B-tree from the root again.
+pub(crate) struct StatCache<T> {
+    entries: VecDeque<(String, T)>,
+}
+
+impl<T> Default for StatCache<T> {
+    fn default() -> Self {
+        StatCache { entries: VecDeque::with_capacity(STAT_CACHE_CAPACITY) }
+    }
+}
+
+impl<T: Clone> StatCache<T> {
+    /// Cached record for `path`, marking it most recently used
+    pub(crate) fn get(&mut self, path: &str) -> Option<T> {
+        let index = self.entries.iter().position(|(p, _)| p == path)?;
+        let entry = self.entries.remove(index)?;
+        let value = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(value)
+    }
+
+    /// Remember `value` for `path`, evicting the least recently used entry when full
+    pub(crate) fn insert(&mut self, path: &str, value: T) {
+        if let Some(index) = self.entries.iter().position(|(p, _)| p == path) {
+            self.entries.remove(index);
+        } else if self.entries.len() >= STAT_CACHE_CAPACITY {
+            self.entries.pop_back();
+        }
+        self.entries.push_front((path.to_string(), value));
+    }
+
+    /// Cached record for `path`, or the result of `lookup` (cached on success)
+    pub(crate) fn get_or_try_insert<E>(
+        &mut self,
+        path: &str,
+        lookup: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        if let Some(value) = self.get(path) {
+            return Ok(value);
+        }
+        let value = lookup()?;
+        self.insert(path, value.clone());
+        Ok(value)
+    }
+
+    /// Whether `path` has a cached record, without touching its recency
+    pub(crate) fn contains(&self, path: &str) -> bool {
+        self.entries.iter().any(|(p, _)| p == path)
+    }
+
+    /// Drop every cached entry
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
 // ── HFS+ Handle ─────────────────────────────────────────────────────────
 
 /// Handle to an opened HFS+ volume.
 /// Type-erased over the underlying reader (temp file vs in-memory).
+///
+/// Resolved catalog records are kept in a small per-handle LRU keyed by
+/// path and shared by `stat`, `read_file*` and `read_link`;
+/// see [`HfsHandle::clear_cache`].
 pub struct HfsHandle {
-    inner: HfsHandleInner,
+    pub(crate) inner: HfsHandleInner,
+    pub(crate) stat_cache: StatCache<hfsplus::catalog::CatalogRecord>,
 }
 
-enum HfsHandleInner {
+pub(crate) enum HfsHandleInner {
     File(hfsplus::HfsVolume<BufReader<std::fs::File>>),
     Memory(hfsplus::HfsVolume<Cursor<Vec<u8>>>),
 }
@@ -187,7 +282,9 @@ impl HfsHandle {
 
     /// Read a file into memory
     pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>> {
-        Ok(dispatch!(self, read_file, path)?)
+        let mut buf = Vec::new();
+        self.read_file_to(path, &mut buf)?;
+        Ok(buf)
     }
 
     /// Stream a file to a writer (low memory)
@@ -196,12 +293,55 @@ impl HfsHandle {
         path: &str,
         writer: &mut W,
     ) -> Result<u64> {
-        Ok(dispatch!(self, read_file_to, path, writer)?)
+        let record = self.lookup(path)?;
+        Ok(dispatch!(self, read_record_to, path, &record, writer)?)
+    }
+
+    /// Stream a file to a writer, calling `progress(bytes_written, total_size)` per block
+    ///
+    /// Raising `cancel` stops the copy at the next block with [`DppError::Cancelled`].
+    pub fn read_file_to_with_progress<W: std::io::Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let record = self.lookup(path)?;
+        dispatch!(self, read_record_to_with_progress, path, &record, &mut *writer, progress, cancel).map_err(|e| {
+            match e {
+                hfsplus::HfsPlusError::Cancelled => DppError::Cancelled,
+                e => e.into(),
+            }
+        })
     }
 
     /// Get file metadata
+    ///
+    /// Repeated lookups of the same path are answered from the handle's cache.
     pub fn stat(&mut self, path: &str) -> Result<hfsplus::FileStat> {
-        Ok(dispatch!(self, stat, path)?)
+        let record = self.lookup(path)?;
+        Ok(hfsplus::FileStat::from_record(&record)?)
+    }
+
+    /// Catalog record of `path`, resolved once and then answered from the cache
+    fn lookup(&mut self, path: &str) -> Result<hfsplus::catalog::CatalogRecord> {
+        let Self { inner, stat_cache } = self;
+        Ok(stat_cache.get_or_try_insert(path, || match inner {
+            HfsHandleInner::File(vol) => vol.lookup_record(path),
+            HfsHandleInner::Memory(vol) => vol.lookup_record(path),
+        })?)
+    }
+
+    /// Forget all cached path lookups
+    pub fn clear_cache(&mut self) {
+        self.stat_cache.clear();
+    }
+
+    /// Read the target of a symbolic link
+    pub fn read_link(&mut self, path: &str) -> Result<String> {
+        let record = self.lookup(path)?;
+        Ok(dispatch!(self, read_link_record, path, &record)?)
     }
 
     /// Walk all files
@@ -209,14 +349,31 @@ impl HfsHandle {
         Ok(dispatch!(self, walk)?)
     }
 
+    /// Walk all files, calling `progress(entries_found)` per directory
+    ///
+    /// Raising `cancel` stops the walk at the next directory with [`DppError::Cancelled`].
+    pub fn walk_with_progress<F: FnMut(usize)>(
+        &mut self,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Vec<hfsplus::WalkEntry>> {
+        dispatch!(self, walk_with_progress, progress, cancel).map_err(|e| match e {
+            hfsplus::HfsPlusError::Cancelled => DppError::Cancelled,
+            e => e.into(),
+        })
+    }
+
     /// Check if a path exists
     pub fn exists(&mut self, path: &str) -> Result<bool> {
+        if self.stat_cache.contains(path) {
+            return Ok(true);
+        }
         Ok(dispatch!(self, exists, path)?)
     }
 
     /// Open a .pkg file found on the HFS+ volume (reads into memory)
     pub fn open_pkg(&mut self, pkg_path: &str) -> Result<xara::PkgReader<Cursor<Vec<u8>>>> {
-        let data = dispatch!(self, read_file, pkg_path)?;
+        let data = self.read_file(pkg_path)?;
         let cursor = Cursor::new(data);
         let pkg = xara::PkgReader::open(cursor)?;
         Ok(pkg)
@@ -230,7 +387,7 @@ impl HfsHandle {
         let mut tmp = tempfile::tempfile()?;
         {
             let mut writer = BufWriter::new(&mut tmp);
-            dispatch!(self, read_file_to, pkg_path, &mut writer)?;
+            self.read_file_to(pkg_path, &mut writer)?;
         }
         tmp.seek(std::io::SeekFrom::Start(0))?;
         let reader = BufReader::new(tmp);
@@ -251,11 +408,16 @@ impl HfsHandle {
 
 /// Handle to an opened APFS volume.
 /// Type-erased over the underlying reader (temp file vs in-memory).
+///
+/// Resolved inodes are kept in a small per-handle LRU keyed by path and
+/// shared by `stat`, `read_file*` and `read_link`;
+/// see [`ApfsHandle::clear_cache`].
 pub struct ApfsHandle {
-    inner: ApfsHandleInner,
+    pub(crate) inner: ApfsHandleInner,
+    pub(crate) stat_cache: StatCache<(u64, apfs::catalog::InodeVal)>,
 }
 
-enum ApfsHandleInner {
+pub(crate) enum ApfsHandleInner {
     File(apfs::ApfsVolume<BufReader<std::fs::File>>),
     Memory(apfs::ApfsVolume<Cursor<Vec<u8>>>),
 }
@@ -277,7 +439,9 @@ impl ApfsHandle {
 
     /// Read a file into memory
     pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>> {
-        Ok(dispatch_apfs!(self, read_file, path)?)
+        let mut buf = Vec::new();
+        self.read_file_to(path, &mut buf)?;
+        Ok(buf)
     }
 
     /// Stream a file to a writer (low memory)
@@ -286,12 +450,53 @@ impl ApfsHandle {
         path: &str,
         writer: &mut W,
     ) -> Result<u64> {
-        Ok(dispatch_apfs!(self, read_file_to, path, writer)?)
+        let (_oid, inode) = self.lookup(path)?;
+        Ok(dispatch_apfs!(self, read_inode_to, &inode, writer)?)
+    }
+
+    /// Stream a file to a writer, calling `progress(bytes_written, total_size)` per block
+    ///
+    /// Raising `cancel` stops the copy at the next block with [`DppError::Cancelled`].
+    pub fn read_file_to_with_progress<W: std::io::Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let (_oid, inode) = self.lookup(path)?;
+        dispatch_apfs!(self, read_inode_to_with_progress, &inode, writer, progress, cancel).map_err(|e| match e {
+            apfs::ApfsError::Cancelled => DppError::Cancelled,
+            e => e.into(),
+        })
     }
 
     /// Get file metadata
+    ///
+    /// Repeated lookups of the same path are answered from the handle's cache.
     pub fn stat(&mut self, path: &str) -> Result<apfs::FileStat> {
-        Ok(dispatch_apfs!(self, stat, path)?)
+        let (oid, inode) = self.lookup(path)?;
+        Ok(apfs::FileStat::from_inode(oid, &inode))
+    }
+
+    /// Inode OID and record of `path`, resolved once and then answered from the cache
+    fn lookup(&mut self, path: &str) -> Result<(u64, apfs::catalog::InodeVal)> {
+        let Self { inner, stat_cache } = self;
+        Ok(stat_cache.get_or_try_insert(path, || match inner {
+            ApfsHandleInner::File(vol) => vol.lookup_inode(path),
+            ApfsHandleInner::Memory(vol) => vol.lookup_inode(path),
+        })?)
+    }
+
+    /// Forget all cached path lookups
+    pub fn clear_cache(&mut self) {
+        self.stat_cache.clear();
+    }
+
+    /// Read the target of a symbolic link
+    pub fn read_link(&mut self, path: &str) -> Result<String> {
+        let (oid, inode) = self.lookup(path)?;
+        Ok(dispatch_apfs!(self, read_link_inode, path, oid, &inode)?)
     }
 
     /// Walk all files
@@ -299,8 +504,25 @@ impl ApfsHandle {
         Ok(dispatch_apfs!(self, walk)?)
     }
 
+    /// Walk all files, calling `progress(entries_found)` per directory
+    ///
+    /// Raising `cancel` stops the walk at the next directory with [`DppError::Cancelled`].
+    pub fn walk_with_progress<F: FnMut(usize)>(
+        &mut self,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Vec<apfs::WalkEntry>> {
+        dispatch_apfs!(self, walk_with_progress, progress, cancel).map_err(|e| match e {
+            apfs::ApfsError::Cancelled => DppError::Cancelled,
+            e => e.into(),
+        })
+    }
+
     /// Check if a path exists
     pub fn exists(&mut self, path: &str) -> Result<bool> {
+        if self.stat_cache.contains(path) {
+            return Ok(true);
+        }
         Ok(dispatch_apfs!(self, exists, path)?)
     }
 
@@ -314,7 +536,7 @@ impl ApfsHandle {
 
     /// Open a .pkg file found on the APFS volume (reads into memory)
     pub fn open_pkg(&mut self, pkg_path: &str) -> Result<xara::PkgReader<Cursor<Vec<u8>>>> {
-        let data = dispatch_apfs!(self, read_file, pkg_path)?;
+        let data = self.read_file(pkg_path)?;
         let cursor = Cursor::new(data);
         let pkg = xara::PkgReader::open(cursor)?;
         Ok(pkg)
@@ -328,7 +550,7 @@ impl ApfsHandle {
         let mut tmp = tempfile::tempfile()?;
         {
             let mut writer = BufWriter::new(&mut tmp);
-            dispatch_apfs!(self, read_file_to, pkg_path, &mut writer)?;
+            self.read_file_to(pkg_path, &mut writer)?;
         }
         tmp.seek(std::io::SeekFrom::Start(0))?;
         let reader = BufReader::new(tmp);
@@ -523,6 +745,7 @@ pub struct FsVolumeInfo {
 
 /// Unified handle to either an HFS+ or APFS volume.
 /// Returned by `DmgPipeline::open_filesystem()`.
+#[allow(clippy::large_enum_variant)]
 pub enum FilesystemHandle {
     Hfs(HfsHandle),
     Apfs(ApfsHandle),
@@ -545,6 +768,22 @@ impl FilesystemHandle {
         }
     }
 
+    /// Read the target of a symbolic link
+    pub fn read_link(&mut self, path: &str) -> Result<String> {
+        match self {
+            FilesystemHandle::Hfs(h) => h.read_link(path),
+            FilesystemHandle::Apfs(h) => h.read_link(path),
+        }
+    }
+
+    /// Forget all cached path lookups
+    pub fn clear_cache(&mut self) {
+        match self {
+            FilesystemHandle::Hfs(h) => h.clear_cache(),
+            FilesystemHandle::Apfs(h) => h.clear_cache(),
+        }
+    }
+
     /// Get unified volume information
     pub fn volume_info(&self) -> FsVolumeInfo {
         match self {
@@ -617,6 +856,22 @@ impl FilesystemHandle {
         }
     }
 
+    /// Stream a file to a writer, calling `progress(bytes_written, total_size)` per block
+    ///
+    /// Raising `cancel` stops the copy at the next block with [`DppError::Cancelled`].
+    pub fn read_file_to_with_progress<W: std::io::Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        match self {
+            FilesystemHandle::Hfs(h) => h.read_file_to_with_progress(path, writer, progress, cancel),
+            FilesystemHandle::Apfs(h) => h.read_file_to_with_progress(path, writer, progress, cancel),
+        }
+    }
+
     /// Walk all files, returning unified entries
     pub fn walk(&mut self) -> Result<Vec<FsWalkEntry>> {
         match self {
@@ -625,6 +880,24 @@ impl FilesystemHandle {
         }
     }
 
+    /// Walk all files, calling `progress(entries_found)` per directory
+    ///
+    /// Raising `cancel` stops the walk at the next directory with [`DppError::Cancelled`].
+    pub fn walk_with_progress<F: FnMut(usize)>(
+        &mut self,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Vec<FsWalkEntry>> {
+        match self {
+            FilesystemHandle::Hfs(h) => {
+                Ok(h.walk_with_progress(progress, cancel)?.iter().map(FsWalkEntry::from).collect())
+            }
+            FilesystemHandle::Apfs(h) => {
+                Ok(h.walk_with_progress(progress, cancel)?.iter().map(FsWalkEntry::from).collect())
+            }
+        }
+    }
+
     /// Check if a path exists
     pub fn exists(&mut self, path: &str) -> Result<bool> {
         match self {
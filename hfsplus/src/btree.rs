@@ -10,6 +10,10 @@ pub const NODE_KIND_INDEX: u8 = 0x00;
 pub const NODE_KIND_HEADER: u8 = 0x01;
 pub const NODE_KIND_MAP: u8 = 0x02;
 
+/// Catalog key comparison types (keyCompareType in the B-tree header)
+pub const KEY_COMPARE_CASE_FOLDING: u32 = 0xCF; // kHFSCaseFolding
+pub const KEY_COMPARE_BINARY: u32 = 0xBC;       // kHFSBinaryCompare
+
 /// B-tree header record (from the header node, record 0)
 #[derive(Debug, Clone)]
 pub struct BTreeHeaderRecord {
@@ -104,6 +108,55 @@ pub fn read_btree_header<R: Read + Seek>(
     })
 }
 
+/// List counts in a B-tree header record that contradict each other.
+///
+/// None of these stop the tree from being searched, so callers decide whether
+/// they are errors or warnings. `name` identifies the tree in the messages.
+pub fn check_btree_header(header: &BTreeHeaderRecord, name: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    if header.free_nodes > header.total_nodes {
+        issues.push(format!(
+            "{} B-tree has {} free nodes but only {} total",
+            name, header.free_nodes, header.total_nodes
+        ));
+    }
+    for (field, node) in [
+        ("root", header.root_node),
+        ("first leaf", header.first_leaf_node),
+        ("last leaf", header.last_leaf_node),
+    ] {
+        if node >= header.total_nodes && node != 0 {
+            issues.push(format!(
+                "{} B-tree {} node {} is past the last node {}",
+                name, field, node, header.total_nodes
+            ));
+        }
+    }
+    if (header.tree_depth == 0) != (header.root_node == 0) {
+        issues.push(format!(
+            "{} B-tree depth {} disagrees with root node {}",
+            name, header.tree_depth, header.root_node
+        ));
+    }
+    issues
+}
+
+/// Validate the catalog B-tree's `key_compare_type` against the volume signature.
+///
+/// HFS+ volumes always use case-folding comparison; the field is reserved
+/// there and readers ignore it, so it is not checked. HFSX volumes must
+/// declare either case-folding or binary comparison; any other value means
+/// the header is corrupt or was mis-parsed.
+pub fn validate_key_compare_type(header: &BTreeHeaderRecord, is_hfsx: bool) -> Result<()> {
+    if !is_hfsx || matches!(header.key_compare_type, KEY_COMPARE_CASE_FOLDING | KEY_COMPARE_BINARY) {
+        return Ok(());
+    }
+    Err(HfsPlusError::InvalidBTree(format!(
+        "catalog key compare type 0x{:02X} is not valid for an HFSX volume",
+        header.key_compare_type
+    )))
+}
+
 /// Read raw bytes for a node. We read `read_size` bytes at the node's offset.
 /// If `read_size` is less than the actual node size, we read what we can
 /// (used for initial header read where we don't know node_size yet).
@@ -388,5 +441,64 @@ mod tests {
         assert!(catalog_header.node_size > 0);
         assert!(catalog_header.root_node > 0);
         assert!(catalog_header.leaf_records > 0);
+        validate_key_compare_type(&catalog_header, vol.is_hfsx).unwrap();
+    }
+
+    fn header_with_compare_type(key_compare_type: u32) -> BTreeHeaderRecord {
+        BTreeHeaderRecord {
+            tree_depth: 1,
+            root_node: 1,
+            leaf_records: 0,
+            first_leaf_node: 1,
+            last_leaf_node: 1,
+            node_size: 4096,
+            max_key_length: 516,
+            total_nodes: 2,
+            free_nodes: 0,
+            key_compare_type,
+            fork: ForkData::default(),
+            block_size: 4096,
+        }
+    }
+
+    #[test]
+    fn test_validate_key_compare_type() {
+        // HFS+: reserved, so any value is accepted
+        for compare_type in [0, KEY_COMPARE_CASE_FOLDING, KEY_COMPARE_BINARY, 0x42] {
+            assert!(validate_key_compare_type(&header_with_compare_type(compare_type), false).is_ok());
+        }
+
+        // HFSX: case-folding or binary
+        assert!(validate_key_compare_type(&header_with_compare_type(KEY_COMPARE_CASE_FOLDING), true).is_ok());
+        assert!(validate_key_compare_type(&header_with_compare_type(KEY_COMPARE_BINARY), true).is_ok());
+    }
+
+    #[test]
+    fn test_validate_key_compare_type_inconsistent() {
+        // HFSX must declare a compare type
+        let result = validate_key_compare_type(&header_with_compare_type(0), true);
+        assert!(matches!(result, Err(HfsPlusError::InvalidBTree(_))));
+
+        let result = validate_key_compare_type(&header_with_compare_type(0x42), true);
+        assert!(matches!(result, Err(HfsPlusError::InvalidBTree(_))));
+    }
+
+    #[test]
+    fn test_compute_fork_offset_block_sizes() {
+        use crate::volume::ExtentDescriptor;
+
+        let mut fork = ForkData::default();
+        fork.extents[0] = ExtentDescriptor { start_block: 10, block_count: 3 };
+        fork.extents[1] = ExtentDescriptor { start_block: 100, block_count: 2 };
+
+        for block_size in [512u32, 1024, 4096, 65536] {
+            let bs = block_size as u64;
+            assert_eq!(compute_fork_offset(&fork, block_size, 0).unwrap(), 10 * bs);
+            assert_eq!(compute_fork_offset(&fork, block_size, bs + 7).unwrap(), 11 * bs + 7);
+            // First byte past the first extent lands at the start of the second
+            assert_eq!(compute_fork_offset(&fork, block_size, 3 * bs).unwrap(), 100 * bs);
+            assert_eq!(compute_fork_offset(&fork, block_size, 5 * bs - 1).unwrap(), 102 * bs - 1);
+            assert!(compute_fork_offset(&fork, block_size, 5 * bs).is_err());
+        }
     }
 }
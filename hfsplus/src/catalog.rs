@@ -3,6 +3,7 @@ use std::io::{Cursor, Read, Seek};
 
 use crate::btree::{self, BTreeHeaderRecord};
 use crate::error::{HfsPlusError, Result};
+use crate::extents::{FORK_TYPE_DATA, FORK_TYPE_RESOURCE};
 use crate::unicode;
 use crate::volume::{ExtentDescriptor, ForkData, VolumeHeader};
 use crate::{DirEntry, EntryKind};
@@ -23,6 +24,13 @@ pub const RECORD_TYPE_FILE: u16 = 0x0002;
 pub const RECORD_TYPE_FOLDER_THREAD: u16 = 0x0003;
 pub const RECORD_TYPE_FILE_THREAD: u16 = 0x0004;
 
+/// Finder type and creator of a file hard link (`hlnk` / `hfs+`)
+pub const HARD_LINK_FILE_TYPE: [u8; 4] = *b"hlnk";
+pub const HARD_LINK_CREATOR: [u8; 4] = *b"hfs+";
+
+/// Hidden root-level folder that holds hard link targets (`iNode<N>` files)
+pub const PRIVATE_DATA_FOLDER: &str = "\0\0\0\0HFS+ Private Data";
+
 /// BSD permissions
 #[derive(Debug, Clone)]
 pub struct HfsPlusBsdInfo {
@@ -44,11 +52,41 @@ pub struct CatalogFile {
     pub access_date: u32,
     pub backup_date: u32,
     pub permissions: HfsPlusBsdInfo,
+    /// Finder file type (e.g. `hlnk` for a hard link)
+    pub file_type: [u8; 4],
+    /// Finder creator code (e.g. `hfs+` for a hard link)
+    pub creator: [u8; 4],
     pub data_fork: ForkData,
     pub resource_fork: ForkData,
     pub text_encoding: u32,
 }
 
+impl CatalogFile {
+    /// Check whether this record is a hard link to an `iNode<N>` file
+    pub fn is_hard_link(&self) -> bool {
+        self.file_type == HARD_LINK_FILE_TYPE && self.creator == HARD_LINK_CREATOR
+    }
+
+    /// Name of the hard link target in the private data folder
+    ///
+    /// For hard links, the BSD `special` field holds the indirect node number.
+    pub fn indirect_node_name(&self) -> String {
+        format!("iNode{}", self.permissions.special)
+    }
+
+    /// The fork holding this file's content, with its fork type
+    ///
+    /// This is the data fork, unless it is empty and the resource fork is
+    /// not, as with classic Mac files whose content lives only there.
+    pub fn best_fork(&self) -> (&ForkData, u8) {
+        if self.data_fork.logical_size == 0 && self.resource_fork.logical_size > 0 {
+            (&self.resource_fork, FORK_TYPE_RESOURCE)
+        } else {
+            (&self.data_fork, FORK_TYPE_DATA)
+        }
+    }
+}
+
 /// Catalog folder record
 #[derive(Debug, Clone)]
 pub struct CatalogFolder {
@@ -110,7 +148,7 @@ fn parse_catalog_key(data: &[u8]) -> Result<(CatalogKey, usize)> {
     // Record data starts after key_length + 2 bytes for the key_length field itself
     let record_offset = 2 + key_length;
     // Ensure even alignment
-    let record_offset = if record_offset % 2 != 0 { record_offset + 1 } else { record_offset };
+    let record_offset = if !record_offset.is_multiple_of(2) { record_offset + 1 } else { record_offset };
 
     Ok((
         CatalogKey {
@@ -203,8 +241,13 @@ fn parse_catalog_record(data: &[u8]) -> Result<CatalogRecord> {
             let access_date = cursor.read_u32::<BigEndian>()?;
             let backup_date = cursor.read_u32::<BigEndian>()?;
             let permissions = parse_bsd_info(&mut cursor)?;
-            // Skip user info (16 bytes) and finder info (16 bytes)
-            let mut _skip = [0u8; 32];
+            // User info starts with the Finder type and creator; skip the
+            // rest of it (8 bytes) and the finder info (16 bytes)
+            let mut file_type = [0u8; 4];
+            cursor.read_exact(&mut file_type)?;
+            let mut creator = [0u8; 4];
+            cursor.read_exact(&mut creator)?;
+            let mut _skip = [0u8; 24];
             cursor.read_exact(&mut _skip)?;
             let text_encoding = cursor.read_u32::<BigEndian>()?;
             let _reserved2 = cursor.read_u32::<BigEndian>()?;
@@ -219,6 +262,8 @@ fn parse_catalog_record(data: &[u8]) -> Result<CatalogRecord> {
                 access_date,
                 backup_date,
                 permissions,
+                file_type,
+                creator,
                 data_fork,
                 resource_fork,
                 text_encoding,
@@ -526,6 +571,39 @@ pub fn resolve_path<R: Read + Seek>(
     unreachable!()
 }
 
+/// Follow a file hard link to its indirect node in the private data folder
+///
+/// Records that are not hard links are returned unchanged.
+pub fn resolve_hard_link<R: Read + Seek>(
+    reader: &mut R,
+    vol: &VolumeHeader,
+    btree_header: &BTreeHeaderRecord,
+    file: CatalogFile,
+) -> Result<CatalogFile> {
+    if !file.is_hard_link() {
+        return Ok(file);
+    }
+
+    let private_id =
+        match lookup_catalog(reader, vol, btree_header, CNID_ROOT_FOLDER, PRIVATE_DATA_FOLDER)? {
+            Some(CatalogRecord::Folder(f)) => f.folder_id,
+            _ => {
+                return Err(HfsPlusError::CorruptedData(
+                    "hard link without a private data folder".into(),
+                ))
+            }
+        };
+
+    let node_name = file.indirect_node_name();
+    match lookup_catalog(reader, vol, btree_header, private_id, &node_name)? {
+        Some(CatalogRecord::File(target)) => Ok(target),
+        _ => Err(HfsPlusError::CorruptedData(format!(
+            "hard link target {} not found",
+            node_name
+        ))),
+    }
+}
+
 /// Look up the root folder by finding it in the catalog
 fn lookup_root_folder<R: Read + Seek>(
     reader: &mut R,
@@ -581,4 +659,57 @@ mod tests {
         let path = format!("/{}", first.name);
         let (_record, _name) = resolve_path(&mut reader, &vol, &catalog_header, &path).unwrap();
     }
+
+    #[test]
+    fn test_parse_hard_link_record() {
+        // HFSPlusCatalogFile is 248 bytes; only the fields under test are set
+        let mut data = vec![0u8; 248];
+        data[0..2].copy_from_slice(&RECORD_TYPE_FILE.to_be_bytes());
+        data[8..12].copy_from_slice(&42u32.to_be_bytes());
+        data[44..48].copy_from_slice(&1234u32.to_be_bytes());
+        data[48..52].copy_from_slice(&HARD_LINK_FILE_TYPE);
+        data[52..56].copy_from_slice(&HARD_LINK_CREATOR);
+
+        let file = match parse_catalog_record(&data).unwrap() {
+            CatalogRecord::File(f) => f,
+            other => panic!("expected file record, got {:?}", other),
+        };
+        assert_eq!(file.file_id, 42);
+        assert!(file.is_hard_link());
+        assert_eq!(file.indirect_node_name(), "iNode1234");
+
+        data[48..52].copy_from_slice(b"TEXT");
+        match parse_catalog_record(&data).unwrap() {
+            CatalogRecord::File(f) => assert!(!f.is_hard_link()),
+            other => panic!("expected file record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_best_fork_resource_only() {
+        // Data fork at offset 88 is left empty; the resource fork at 168
+        // holds 5 bytes in block 1
+        let mut data = vec![0u8; 248];
+        data[0..2].copy_from_slice(&RECORD_TYPE_FILE.to_be_bytes());
+        data[168..176].copy_from_slice(&5u64.to_be_bytes());
+        data[184..188].copy_from_slice(&1u32.to_be_bytes());
+        data[188..192].copy_from_slice(&1u32.to_be_bytes());
+
+        let mut file = match parse_catalog_record(&data).unwrap() {
+            CatalogRecord::File(f) => f,
+            other => panic!("expected file record, got {:?}", other),
+        };
+        let (fork, fork_type) = file.best_fork();
+        assert_eq!(fork_type, FORK_TYPE_RESOURCE);
+
+        let mut disk = vec![0u8; 512 * 2];
+        disk[512..517].copy_from_slice(b"rsrc!");
+        let mut cursor = Cursor::new(disk);
+        let mut content = Vec::new();
+        crate::extents::ForkReader::new(&mut cursor, fork, 512).read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"rsrc!");
+
+        file.data_fork.logical_size = 3;
+        assert_eq!(file.best_fork().1, FORK_TYPE_DATA);
+    }
 }
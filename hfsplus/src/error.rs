@@ -8,6 +8,9 @@ pub enum HfsPlusError {
     #[error("invalid HFS+ signature: 0x{0:04X} (expected 0x482B or 0x4858)")]
     InvalidSignature(u16),
 
+    #[error("invalid volume: {0}")]
+    InvalidVolume(String),
+
     #[error("invalid B-tree: {0}")]
     InvalidBTree(String),
 
@@ -17,9 +20,18 @@ pub enum HfsPlusError {
     #[error("not a directory: {0}")]
     NotADirectory(String),
 
+    #[error("not a symbolic link: {0}")]
+    NotASymlink(String),
+
     #[error("corrupted data: {0}")]
     CorruptedData(String),
 
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("directory tree deeper than {max_depth} levels at {path}")]
+    TooDeep { path: String, max_depth: usize },
+
     #[error("unsupported version: {0}")]
     UnsupportedVersion(u16),
 }
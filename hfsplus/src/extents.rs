@@ -1,9 +1,13 @@
 use byteorder::{BigEndian, ReadBytesExt};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::btree::{self, BTreeHeaderRecord};
 use crate::error::{HfsPlusError, Result};
 use crate::volume::{ExtentDescriptor, ForkData, VolumeHeader};
+use crate::HashAlgo;
 
 /// A reader that presents a file's data fork as a contiguous `Read + Seek` stream.
 /// Translates logical file offsets to physical disk offsets through the extent map.
@@ -121,6 +125,95 @@ impl<R: Read + Seek> Seek for ForkReader<'_, R> {
     }
 }
 
+/// Writer adapter that reports `(bytes_written, total_size)` after each write.
+///
+/// When `cancel` is set, each write first checks the flag and fails once it
+/// is raised; [`finish`](Self::finish) turns that failure into `Cancelled`.
+pub(crate) struct ProgressWriter<'a, W, F> {
+    inner: W,
+    written: u64,
+    total: u64,
+    progress: F,
+    cancel: Option<&'a AtomicBool>,
+    cancelled: bool,
+}
+
+impl<'a, W: Write, F: FnMut(u64, u64)> ProgressWriter<'a, W, F> {
+    pub(crate) fn new(inner: W, total: u64, progress: F, cancel: Option<&'a AtomicBool>) -> Self {
+        ProgressWriter {
+            inner,
+            written: 0,
+            total,
+            progress,
+            cancel,
+            cancelled: false,
+        }
+    }
+
+    /// Replace the result of a read that wrote through this adapter with
+    /// `Cancelled` if it stopped because of the cancel flag
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        if self.cancelled {
+            return Err(HfsPlusError::Cancelled);
+        }
+        result
+    }
+}
+
+impl<W: Write, F: FnMut(u64, u64)> Write for ProgressWriter<'_, W, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            self.cancelled = true;
+            return Err(std::io::Error::other("operation cancelled"));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        (self.progress)(self.written, self.total);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer adapter that feeds everything written into a digest.
+pub(crate) enum HashWriter {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl HashWriter {
+    pub(crate) fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha1 => HashWriter::Sha1(Sha1::new()),
+            HashAlgo::Sha256 => HashWriter::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Consume the hasher and return the digest bytes.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            HashWriter::Sha1(h) => h.finalize().to_vec(),
+            HashWriter::Sha256(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            HashWriter::Sha1(h) => h.update(buf),
+            HashWriter::Sha256(h) => h.update(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 /// Fork types
 pub const FORK_TYPE_DATA: u8 = 0x00;
 pub const FORK_TYPE_RESOURCE: u8 = 0xFF;
@@ -134,6 +227,22 @@ pub fn read_fork_data<R: Read + Seek, W: Write>(
     fork: &ForkData,
     file_id: u32,
     writer: &mut W,
+) -> Result<u64> {
+    read_fork_data_of_type(reader, vol, extents_btree, fork, file_id, FORK_TYPE_DATA, writer)
+}
+
+/// Read either fork of a file, writing its contents to `writer`
+///
+/// `fork_type` (`FORK_TYPE_DATA` or `FORK_TYPE_RESOURCE`) selects which
+/// overflow extents are looked up once the inline extents run out.
+pub fn read_fork_data_of_type<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    vol: &VolumeHeader,
+    extents_btree: &BTreeHeaderRecord,
+    fork: &ForkData,
+    file_id: u32,
+    fork_type: u8,
+    writer: &mut W,
 ) -> Result<u64> {
     let block_size = vol.block_size as u64;
     let total_bytes = fork.logical_size;
@@ -175,7 +284,7 @@ pub fn read_fork_data<R: Read + Seek, W: Write>(
             reader,
             extents_btree,
             file_id,
-            FORK_TYPE_DATA,
+            fork_type,
             start_block,
         )?;
 
@@ -204,7 +313,7 @@ fn read_extent<R: Read + Seek, W: Write>(
     extent: &ExtentDescriptor,
     block_size: u64,
     remaining: u64,
-    buf: &mut Vec<u8>,
+    buf: &mut [u8],
     writer: &mut W,
 ) -> Result<u64> {
     let mut written = 0u64;
@@ -291,6 +400,70 @@ fn lookup_overflow_extents<R: Read + Seek>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_progress_writer_reports_running_total() {
+        let mut calls = Vec::new();
+        let mut out = Vec::new();
+        {
+            let mut writer = ProgressWriter::new(&mut out, 10, |done, total| calls.push((done, total)), None);
+            writer.write_all(b"abcd").unwrap();
+            writer.write_all(b"efghij").unwrap();
+        }
+        assert_eq!(out, b"abcdefghij");
+        assert_eq!(calls, vec![(4, 10), (10, 10)]);
+    }
+
+    #[test]
+    fn test_progress_writer_cancel() {
+        let cancel = AtomicBool::new(false);
+        let mut out = Vec::new();
+        let mut writer = ProgressWriter::new(&mut out, 10, |_, _| cancel.store(true, Ordering::Relaxed), Some(&cancel));
+        writer.write_all(b"abcd").unwrap();
+        let result = writer.write_all(b"efghij").map_err(HfsPlusError::from);
+        assert!(matches!(writer.finish(result), Err(HfsPlusError::Cancelled)));
+        assert_eq!(out, b"abcd");
+    }
+
+    #[test]
+    fn test_fork_reader_512_byte_blocks() {
+        // Blocks 2-3 hold 'a'..., block 6 holds 'b'...; the fork is 1200 bytes
+        let mut disk = vec![0u8; 512 * 8];
+        disk[1024..2048].fill(b'a');
+        disk[3072..3584].fill(b'b');
+
+        let mut fork = ForkData { logical_size: 1200, ..ForkData::default() };
+        fork.extents[0] = ExtentDescriptor { start_block: 2, block_count: 2 };
+        fork.extents[1] = ExtentDescriptor { start_block: 6, block_count: 1 };
+
+        let mut cursor = Cursor::new(disk);
+        let mut reader = ForkReader::new(&mut cursor, &fork, 512);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data.len(), 1200);
+        assert!(data[..1024].iter().all(|&b| b == b'a'));
+        assert!(data[1024..].iter().all(|&b| b == b'b'));
+
+        reader.seek(SeekFrom::Start(1020)).unwrap();
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"aaaabbbb");
+    }
+
+    #[test]
+    fn test_hash_writer_streams() {
+        let mut writer = HashWriter::new(HashAlgo::Sha256);
+        writer.write_all(b"a").unwrap();
+        writer.write_all(b"bc").unwrap();
+        assert_eq!(writer.finish(), Sha256::digest(b"abc").to_vec());
+
+        let mut writer = HashWriter::new(HashAlgo::Sha1);
+        writer.write_all(b"abc").unwrap();
+        let digest = writer.finish();
+        assert_eq!(digest.len(), 20);
+        assert_eq!(&digest[..4], &[0xa9, 0x99, 0x3e, 0x36]);
+    }
+
     /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
     #[test]
     #[ignore]
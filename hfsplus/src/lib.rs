@@ -4,11 +4,19 @@ pub mod btree;
 pub mod catalog;
 pub mod extents;
 pub mod unicode;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod testutil;
 
 pub use error::{HfsPlusError, Result};
 pub use volume::VolumeHeader;
 
-use std::io::{Read, Seek, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Maximum number of parent links followed when reconstructing a path
+const MAX_PATH_DEPTH: usize = 1024;
 
 /// Entry kind in the filesystem
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,6 +26,13 @@ pub enum EntryKind {
     Symlink,
 }
 
+/// Digest algorithm for `hash_file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgo {
+    Sha1,
+    Sha256,
+}
+
 /// A directory entry returned by list_directory
 #[derive(Debug, Clone)]
 pub struct DirEntry {
@@ -54,6 +69,66 @@ pub struct FileStat {
     pub permissions: HfsPermissions,
     pub data_fork_extents: u32,
     pub resource_fork_size: u64,
+    /// Allocation blocks of the data fork (`totalBlocks`)
+    pub data_fork_blocks: u32,
+    /// Allocation blocks of the resource fork
+    pub resource_fork_blocks: u32,
+}
+
+impl FileStat {
+    /// Metadata of a file or folder record, as returned by
+    /// [`HfsVolume::lookup_record`]
+    pub fn from_record(record: &catalog::CatalogRecord) -> Result<Self> {
+        match record {
+            catalog::CatalogRecord::File(f) => Ok(FileStat {
+                cnid: f.file_id,
+                kind: EntryKind::File,
+                size: f.data_fork.logical_size,
+                create_date: f.create_date,
+                modify_date: f.content_mod_date,
+                permissions: HfsPermissions {
+                    owner_id: f.permissions.owner_id,
+                    group_id: f.permissions.group_id,
+                    mode: f.permissions.file_mode,
+                },
+                data_fork_extents: f.data_fork.extents.iter().filter(|e| e.block_count > 0).count() as u32,
+                resource_fork_size: f.resource_fork.logical_size,
+                data_fork_blocks: f.data_fork.total_blocks,
+                resource_fork_blocks: f.resource_fork.total_blocks,
+            }),
+            catalog::CatalogRecord::Folder(f) => Ok(FileStat {
+                cnid: f.folder_id,
+                kind: EntryKind::Directory,
+                size: 0,
+                create_date: f.create_date,
+                modify_date: f.content_mod_date,
+                permissions: HfsPermissions {
+                    owner_id: f.permissions.owner_id,
+                    group_id: f.permissions.group_id,
+                    mode: f.permissions.file_mode,
+                },
+                data_fork_extents: 0,
+                resource_fork_size: 0,
+                data_fork_blocks: 0,
+                resource_fork_blocks: 0,
+            }),
+            _ => Err(HfsPlusError::CorruptedData("unexpected thread record".into())),
+        }
+    }
+
+    /// Logical size of the data and resource forks together
+    ///
+    /// Extended attributes live in the attributes B-tree and are not counted.
+    pub fn total_logical_size(&self) -> u64 {
+        self.size + self.resource_fork_size
+    }
+
+    /// Allocation blocks used by both forks, as `du` would count them
+    ///
+    /// Multiply by [`HfsVolume::block_size`] for bytes on disk.
+    pub fn total_allocated_blocks(&self) -> u64 {
+        self.data_fork_blocks as u64 + self.resource_fork_blocks as u64
+    }
 }
 
 /// Entry from walk() — includes full path
@@ -63,18 +138,117 @@ pub struct WalkEntry {
     pub entry: DirEntry,
 }
 
+/// Counts returned by [`HfsVolume::export_subtree`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Directories created, including the top one
+    pub directories: u64,
+    /// Regular files written
+    pub files: u64,
+    /// Symlinks created
+    pub symlinks: u64,
+    /// Bytes of file data written
+    pub bytes: u64,
+    /// Entries not exported: names that are not valid host path components,
+    /// special files, and symlinks on platforms without them
+    pub skipped: u64,
+}
+
+/// Options for opening an HFS+/HFSX volume
+#[derive(Debug, Clone, Default)]
+pub struct HfsVolumeOptions {
+    /// Fail on header inconsistencies that do not prevent reading (e.g. more
+    /// free B-tree nodes than total nodes). When `false` (the default) they
+    /// are recorded as warnings instead, see [`HfsVolume::warnings`].
+    pub strict: bool,
+}
+
+/// Shape of the catalog B-tree, from its header record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BTreeStats {
+    /// Node size in bytes
+    pub node_size: u16,
+    /// Nodes in use (total minus free)
+    pub node_count: u32,
+    /// Number of records in leaf nodes
+    pub leaf_records: u32,
+    /// Number of levels, 0 for an empty tree
+    pub depth: u16,
+}
+
+/// Reader adapter that shifts all positions by a fixed base offset
+///
+/// Position 0 of the adapter is byte `base` of the inner reader, so a volume
+/// embedded in a larger image (e.g. a whole-disk image) can be parsed in place.
+pub struct OffsetReader<R> {
+    inner: R,
+    base: u64,
+}
+
+impl<R: Seek> OffsetReader<R> {
+    /// Wrap `inner`, placing position 0 at `base` and seeking there
+    pub fn new(mut inner: R, base: u64) -> std::io::Result<Self> {
+        inner.seek(SeekFrom::Start(base))?;
+        Ok(OffsetReader { inner, base })
+    }
+
+    /// Base offset within the inner reader
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Unwrap the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for OffsetReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => SeekFrom::Start(self.base.checked_add(n).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek offset overflow")
+            })?),
+            other => other,
+        };
+        let absolute = self.inner.seek(pos)?;
+        absolute.checked_sub(self.base).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before volume start")
+        })
+    }
+}
+
 /// High-level HFS+/HFSX volume reader
 pub struct HfsVolume<R: Read + Seek> {
     reader: R,
     pub(crate) header: VolumeHeader,
     pub(crate) catalog_btree_header: btree::BTreeHeaderRecord,
     pub(crate) extents_btree_header: btree::BTreeHeaderRecord,
+    warnings: Vec<String>,
 }
 
 impl<R: Read + Seek> HfsVolume<R> {
-    /// Open and validate an HFS+/HFSX volume
-    pub fn open(mut reader: R) -> Result<Self> {
+    /// Open and validate an HFS+/HFSX volume with default (lenient) options
+    pub fn open(reader: R) -> Result<Self> {
+        Self::open_with_options(reader, HfsVolumeOptions::default())
+    }
+
+    /// Open and validate an HFS+/HFSX volume with custom options
+    pub fn open_with_options(mut reader: R, options: HfsVolumeOptions) -> Result<Self> {
         let header = volume::VolumeHeader::parse(&mut reader)?;
+        let mut warnings = Vec::new();
+        for issue in header.check_consistency() {
+            if options.strict {
+                return Err(HfsPlusError::InvalidVolume(issue));
+            }
+            warnings.push(issue);
+        }
 
         // Read catalog B-tree header
         let catalog_btree_header = btree::read_btree_header(
@@ -82,6 +256,7 @@ impl<R: Read + Seek> HfsVolume<R> {
             &header.catalog_file,
             header.block_size,
         )?;
+        btree::validate_key_compare_type(&catalog_btree_header, header.is_hfsx)?;
 
         // Read extents overflow B-tree header
         let extents_btree_header = btree::read_btree_header(
@@ -90,19 +265,84 @@ impl<R: Read + Seek> HfsVolume<R> {
             header.block_size,
         )?;
 
+        let btree_issues = btree::check_btree_header(&catalog_btree_header, "catalog")
+            .into_iter()
+            .chain(btree::check_btree_header(&extents_btree_header, "extents"));
+        for issue in btree_issues {
+            if options.strict {
+                return Err(HfsPlusError::InvalidBTree(issue));
+            }
+            warnings.push(issue);
+        }
+
         Ok(HfsVolume {
             reader,
             header,
             catalog_btree_header,
             extents_btree_header,
+            warnings,
         })
     }
 
+    /// Header inconsistencies tolerated by a non-strict open
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Consume the volume and return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
     /// Access the parsed volume header
     pub fn volume_header(&self) -> &VolumeHeader {
         &self.header
     }
 
+    /// Allocation block size in bytes
+    pub fn block_size(&self) -> u32 {
+        self.header.block_size
+    }
+
+    /// Node, leaf-record and depth statistics for the catalog B-tree
+    pub fn btree_stats(&self) -> BTreeStats {
+        let h = &self.catalog_btree_header;
+        BTreeStats {
+            node_size: h.node_size,
+            node_count: h.total_nodes.saturating_sub(h.free_nodes),
+            leaf_records: h.leaf_records,
+            depth: h.tree_depth,
+        }
+    }
+
+    /// Read one of the special files described by the volume header
+    ///
+    /// `cnid` is one of `catalog::CNID_EXTENTS_FILE`, `CNID_CATALOG_FILE`,
+    /// `CNID_ALLOCATION_FILE`, `CNID_STARTUP_FILE` or `CNID_ATTRIBUTES_FILE`.
+    /// Returns the raw fork bytes, including any extents that overflowed
+    /// into the extents B-tree; the B-tree files start with their header node.
+    pub fn read_system_file(&mut self, cnid: u32) -> Result<Vec<u8>> {
+        let fork = match cnid {
+            catalog::CNID_EXTENTS_FILE => &self.header.extents_file,
+            catalog::CNID_CATALOG_FILE => &self.header.catalog_file,
+            catalog::CNID_ALLOCATION_FILE => &self.header.allocation_file,
+            catalog::CNID_STARTUP_FILE => &self.header.startup_file,
+            catalog::CNID_ATTRIBUTES_FILE => &self.header.attributes_file,
+            _ => return Err(HfsPlusError::FileNotFound(format!("system file CNID {}", cnid))),
+        };
+
+        let mut data = Vec::new();
+        extents::read_fork_data(
+            &mut self.reader,
+            &self.header,
+            &self.extents_btree_header,
+            fork,
+            cnid,
+            &mut data,
+        )?;
+        Ok(data)
+    }
+
     /// List entries in a directory by path
     pub fn list_directory(&mut self, path: &str) -> Result<Vec<DirEntry>> {
         let cnid = self.resolve_path_to_cnid(path)?;
@@ -121,10 +361,39 @@ impl<R: Read + Seek> HfsVolume<R> {
         Ok(buf)
     }
 
+    /// Read a file's content, falling back to its resource fork
+    ///
+    /// Like [`read_file`](Self::read_file), but when the data fork is empty
+    /// and the resource fork is not (classic Mac files, AppleDouble
+    /// companions), the resource fork is read instead.
+    pub fn read_best_fork(&mut self, path: &str) -> Result<Vec<u8>> {
+        let file_record = self.resolve_path_to_file(path)?;
+        let (fork, fork_type) = file_record.best_fork();
+        let mut buf = Vec::new();
+        extents::read_fork_data_of_type(
+            &mut self.reader,
+            &self.header,
+            &self.extents_btree_header,
+            fork,
+            file_record.file_id,
+            fork_type,
+            &mut buf,
+        )?;
+        Ok(buf)
+    }
+
     /// Open a file for streaming Read+Seek access without loading it into memory.
     /// Returns a ForkReader that translates logical file offsets to physical disk offsets.
     pub fn open_file(&mut self, path: &str) -> Result<extents::ForkReader<'_, R>> {
-        let file_record = self.resolve_path_to_file(path)?;
+        let record = self.lookup_record(path)?;
+        self.open_record(path, &record)
+    }
+
+    /// [`open_file`](Self::open_file) for a record from [`lookup_record`](Self::lookup_record)
+    ///
+    /// `path` is only used in error messages.
+    pub fn open_record(&mut self, path: &str, record: &catalog::CatalogRecord) -> Result<extents::ForkReader<'_, R>> {
+        let file_record = record_file(path, record)?;
         Ok(extents::ForkReader::new(
             &mut self.reader,
             &file_record.data_fork,
@@ -133,8 +402,21 @@ impl<R: Read + Seek> HfsVolume<R> {
     }
 
     /// Stream a file to a writer
-    pub fn read_file_to<W: Write>(&mut self, path: &str, mut writer: W) -> Result<u64> {
-        let file_record = self.resolve_path_to_file(path)?;
+    pub fn read_file_to<W: Write>(&mut self, path: &str, writer: W) -> Result<u64> {
+        let record = self.lookup_record(path)?;
+        self.read_record_to(path, &record, writer)
+    }
+
+    /// [`read_file_to`](Self::read_file_to) for a record from [`lookup_record`](Self::lookup_record)
+    ///
+    /// `path` is only used in error messages.
+    pub fn read_record_to<W: Write>(
+        &mut self,
+        path: &str,
+        record: &catalog::CatalogRecord,
+        mut writer: W,
+    ) -> Result<u64> {
+        let file_record = record_file(path, record)?;
         extents::read_fork_data(
             &mut self.reader,
             &self.header,
@@ -145,47 +427,230 @@ impl<R: Read + Seek> HfsVolume<R> {
         )
     }
 
+    /// Stream a file to a writer, reporting progress
+    ///
+    /// `progress` is called after each block is written with
+    /// `(bytes_written, total_size)`. If `cancel` is given and becomes `true`
+    /// (e.g. from another thread), the copy stops before the next block and
+    /// fails with `Cancelled`.
+    pub fn read_file_to_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        writer: W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let record = self.lookup_record(path)?;
+        self.read_record_to_with_progress(path, &record, writer, progress, cancel)
+    }
+
+    /// [`read_file_to_with_progress`](Self::read_file_to_with_progress) for a
+    /// record from [`lookup_record`](Self::lookup_record)
+    ///
+    /// `path` is only used in error messages.
+    pub fn read_record_to_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        path: &str,
+        record: &catalog::CatalogRecord,
+        writer: W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let file_record = record_file(path, record)?;
+        let mut writer =
+            extents::ProgressWriter::new(writer, file_record.data_fork.logical_size, progress, cancel);
+        let result = extents::read_fork_data(
+            &mut self.reader,
+            &self.header,
+            &self.extents_btree_header,
+            &file_record.data_fork,
+            file_record.file_id,
+            &mut writer,
+        );
+        writer.finish(result)
+    }
+
+    /// Compute the digest of a file's contents
+    ///
+    /// The file is streamed through the hasher block by block, so it is
+    /// never held in memory.
+    pub fn hash_file(&mut self, path: &str, algo: HashAlgo) -> Result<Vec<u8>> {
+        let mut hasher = extents::HashWriter::new(algo);
+        self.read_file_to(path, &mut hasher)?;
+        Ok(hasher.finish())
+    }
+
     /// Get metadata for a file or directory
     pub fn stat(&mut self, path: &str) -> Result<FileStat> {
-        let (record, _name) = self.resolve_path_to_record(path)?;
-        match record {
-            catalog::CatalogRecord::File(f) => Ok(FileStat {
-                cnid: f.file_id,
-                kind: EntryKind::File,
-                size: f.data_fork.logical_size,
-                create_date: f.create_date,
-                modify_date: f.content_mod_date,
-                permissions: HfsPermissions {
-                    owner_id: f.permissions.owner_id,
-                    group_id: f.permissions.group_id,
-                    mode: f.permissions.file_mode,
-                },
-                data_fork_extents: f.data_fork.extents.iter().filter(|e| e.block_count > 0).count() as u32,
-                resource_fork_size: f.resource_fork.logical_size,
-            }),
-            catalog::CatalogRecord::Folder(f) => Ok(FileStat {
-                cnid: f.folder_id,
-                kind: EntryKind::Directory,
-                size: 0,
-                create_date: f.create_date,
-                modify_date: f.content_mod_date,
-                permissions: HfsPermissions {
-                    owner_id: f.permissions.owner_id,
-                    group_id: f.permissions.group_id,
-                    mode: f.permissions.file_mode,
-                },
-                data_fork_extents: 0,
-                resource_fork_size: 0,
-            }),
-            _ => Err(HfsPlusError::CorruptedData("unexpected thread record".into())),
-        }
+        let record = self.lookup_record(path)?;
+        FileStat::from_record(&record)
     }
 
-    /// Recursive walk of all entries
+    /// Read the target of a symbolic link
+    ///
+    /// HFS+ stores the target path as the link's data fork. Fails with
+    /// `NotASymlink` if `path` is not a symlink.
+    pub fn read_link(&mut self, path: &str) -> Result<String> {
+        let record = self.lookup_record(path)?;
+        self.read_link_record(path, &record)
+    }
+
+    /// [`read_link`](Self::read_link) for a record from [`lookup_record`](Self::lookup_record)
+    ///
+    /// `path` is only used in error messages.
+    pub fn read_link_record(&mut self, path: &str, record: &catalog::CatalogRecord) -> Result<String> {
+        let file = match record {
+            catalog::CatalogRecord::File(f) if f.permissions.file_mode & 0o170000 == 0o120000 => f,
+            _ => return Err(HfsPlusError::NotASymlink(path.to_string())),
+        };
+
+        let mut target = Vec::new();
+        extents::read_fork_data(
+            &mut self.reader,
+            &self.header,
+            &self.extents_btree_header,
+            &file.data_fork,
+            file.file_id,
+            &mut target,
+        )?;
+        String::from_utf8(target)
+            .map_err(|_| HfsPlusError::CorruptedData(format!("symlink target of {} is not UTF-8", path)))
+    }
+
+    /// Walk all entries depth-first
+    ///
+    /// Fails with `TooDeep` if entries are nested more than 1024 levels deep.
     pub fn walk(&mut self) -> Result<Vec<WalkEntry>> {
-        let mut entries = Vec::new();
-        self.walk_recursive(catalog::CNID_ROOT_FOLDER, "", &mut entries)?;
-        Ok(entries)
+        self.walk_with_max_depth(MAX_PATH_DEPTH)
+    }
+
+    /// Walk all entries, descending at most `max_depth` directory levels
+    ///
+    /// Entries directly under the root are at depth 1. Fails with `TooDeep`
+    /// if a directory at depth `max_depth` is not empty. The walk uses an
+    /// explicit stack, so deep trees cannot overflow the call stack.
+    pub fn walk_with_max_depth(&mut self, max_depth: usize) -> Result<Vec<WalkEntry>> {
+        let reader = &mut self.reader;
+        let header = &self.header;
+        let catalog_btree_header = &self.catalog_btree_header;
+        walk_tree(catalog::CNID_ROOT_FOLDER, max_depth, |parent| {
+            catalog::list_directory(reader, header, catalog_btree_header, parent)
+        })
+    }
+
+    /// Walk all entries, reporting progress
+    ///
+    /// `progress` is called after each directory is listed with the number of
+    /// entries found so far. If `cancel` is given and becomes `true`, the walk
+    /// stops before listing the next directory and fails with `Cancelled`.
+    pub fn walk_with_progress<F: FnMut(usize)>(
+        &mut self,
+        mut progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<Vec<WalkEntry>> {
+        let reader = &mut self.reader;
+        let header = &self.header;
+        let catalog_btree_header = &self.catalog_btree_header;
+        let mut found = 0;
+        walk_tree(catalog::CNID_ROOT_FOLDER, MAX_PATH_DEPTH, |parent| {
+            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                return Err(HfsPlusError::Cancelled);
+            }
+            let children = catalog::list_directory(reader, header, catalog_btree_header, parent)?;
+            found += children.len();
+            progress(found);
+            Ok(children)
+        })
+    }
+
+    /// Walk all entries, collecting per-directory errors instead of failing
+    ///
+    /// A directory that cannot be listed (or is nested deeper than the
+    /// default limit) is reported with its path and its contents skipped;
+    /// everything else is still walked.
+    pub fn walk_lenient(&mut self) -> (Vec<WalkEntry>, Vec<(String, HfsPlusError)>) {
+        let reader = &mut self.reader;
+        let header = &self.header;
+        let catalog_btree_header = &self.catalog_btree_header;
+        let mut errors = Vec::new();
+        // `on_error` never aborts, so the walk itself cannot fail
+        let entries = walk_tree_with(
+            catalog::CNID_ROOT_FOLDER,
+            MAX_PATH_DEPTH,
+            |parent| catalog::list_directory(reader, header, catalog_btree_header, parent),
+            |path, e| {
+                errors.push((path, e));
+                Ok(())
+            },
+        )
+        .unwrap_or_default();
+        (entries, errors)
+    }
+
+    /// Recreate the subtree at `src_path` on the host, at `dest`
+    ///
+    /// A directory becomes `dest` with its contents below it; a file or
+    /// symlink is written to `dest` itself. Files are streamed with
+    /// [`read_file_to`](Self::read_file_to), and permission bits are applied
+    /// to directories only after their contents are written. Fails with
+    /// `TooDeep` if directories nest more than 1024 levels below `src_path`.
+    pub fn export_subtree<P: AsRef<Path>>(&mut self, src_path: &str, dest: P) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+        let mut directories = Vec::new();
+        let mut stack = vec![(src_path.to_string(), dest.as_ref().to_path_buf(), 0usize)];
+
+        while let Some((path, host_path, depth)) = stack.pop() {
+            let stat = self.stat(&path)?;
+            let mode = stat.permissions.mode as u32;
+            match (stat.kind, mode & S_IFMT) {
+                (EntryKind::Directory, _) => {
+                    std::fs::create_dir_all(&host_path)?;
+                    report.directories += 1;
+
+                    let children = self.list_directory(&path)?;
+                    if !children.is_empty() && depth >= MAX_PATH_DEPTH {
+                        return Err(HfsPlusError::TooDeep { path, max_depth: MAX_PATH_DEPTH });
+                    }
+                    for child in children.into_iter().rev() {
+                        if !is_host_component(&child.name) {
+                            report.skipped += 1;
+                            continue;
+                        }
+                        let child_path = format!("{}/{}", path.trim_end_matches('/'), child.name);
+                        stack.push((child_path, host_path.join(&child.name), depth + 1));
+                    }
+                    directories.push((host_path, mode));
+                }
+                (_, S_IFLNK) => {
+                    #[cfg(unix)]
+                    {
+                        let target = self.read_link(&path)?;
+                        let _ = std::fs::remove_file(&host_path);
+                        std::os::unix::fs::symlink(target, &host_path)?;
+                        report.symlinks += 1;
+                    }
+                    #[cfg(not(unix))]
+                    {
+                        report.skipped += 1;
+                    }
+                }
+                // Files without BSD info have no type bits
+                (_, 0) | (_, S_IFREG) => {
+                    let mut out = std::io::BufWriter::new(std::fs::File::create(&host_path)?);
+                    report.bytes += self.read_file_to(&path, &mut out)?;
+                    out.flush()?;
+                    set_host_mode(&host_path, mode)?;
+                    report.files += 1;
+                }
+                _ => report.skipped += 1,
+            }
+        }
+
+        // Deepest first, so a read-only parent does not block its children
+        for (host_path, mode) in directories.iter().rev() {
+            set_host_mode(host_path, *mode)?;
+        }
+        Ok(report)
     }
 
     /// Check if a path exists
@@ -197,6 +662,58 @@ impl<R: Read + Seek> HfsVolume<R> {
         }
     }
 
+    /// Reconstruct the absolute path of a file or folder from its CNID.
+    ///
+    /// Follows thread records (keyed by `(cnid, "")`) up to the root folder.
+    /// Returns `/` for the root. Fails with `CorruptedData` if the chain does
+    /// not reach the root within a fixed number of hops.
+    pub fn path_for_cnid(&mut self, cnid: u32) -> Result<String> {
+        let mut components = Vec::new();
+        let mut current = cnid;
+
+        while current != catalog::CNID_ROOT_FOLDER {
+            if components.len() >= MAX_PATH_DEPTH {
+                return Err(HfsPlusError::CorruptedData(format!(
+                    "parent chain of CNID {} exceeds {} links",
+                    cnid, MAX_PATH_DEPTH
+                )));
+            }
+
+            let record = catalog::lookup_catalog(
+                &mut self.reader,
+                &self.header,
+                &self.catalog_btree_header,
+                current,
+                "",
+            )?;
+            let thread = match record {
+                Some(catalog::CatalogRecord::FolderThread(t))
+                | Some(catalog::CatalogRecord::FileThread(t)) => t,
+                _ => {
+                    return Err(HfsPlusError::FileNotFound(format!(
+                        "thread record for CNID {}",
+                        current
+                    )))
+                }
+            };
+
+            components.push(thread.node_name);
+            current = thread.parent_id;
+        }
+
+        components.reverse();
+        Ok(format!("/{}", components.join("/")))
+    }
+
+    /// Resolve a path to its catalog file or folder record
+    ///
+    /// File hard links are followed to their indirect node. The record can be
+    /// kept and passed to the `*_record` methods to skip resolving the path again.
+    pub fn lookup_record(&mut self, path: &str) -> Result<catalog::CatalogRecord> {
+        let (record, _name) = self.resolve_path_to_record(path)?;
+        Ok(record)
+    }
+
     // --- Internal helpers ---
 
     fn resolve_path_to_cnid(&mut self, path: &str) -> Result<u32> {
@@ -209,56 +726,605 @@ impl<R: Read + Seek> HfsVolume<R> {
     }
 
     fn resolve_path_to_file(&mut self, path: &str) -> Result<catalog::CatalogFile> {
-        let (record, _name) = self.resolve_path_to_record(path)?;
-        match record {
-            catalog::CatalogRecord::File(f) => Ok(f),
-            catalog::CatalogRecord::Folder(_) => Err(HfsPlusError::NotADirectory(path.to_string())),
-            _ => Err(HfsPlusError::CorruptedData("unexpected thread record".into())),
-        }
+        let record = self.lookup_record(path)?;
+        record_file(path, &record).cloned()
     }
 
+    /// Resolve a path, following file hard links to their indirect node
     fn resolve_path_to_record(&mut self, path: &str) -> Result<(catalog::CatalogRecord, String)> {
-        catalog::resolve_path(
+        let (record, name) = catalog::resolve_path(
             &mut self.reader,
             &self.header,
             &self.catalog_btree_header,
             path,
-        )
+        )?;
+        match record {
+            catalog::CatalogRecord::File(f) if f.is_hard_link() => {
+                let target = catalog::resolve_hard_link(
+                    &mut self.reader,
+                    &self.header,
+                    &self.catalog_btree_header,
+                    f,
+                )?;
+                Ok((catalog::CatalogRecord::File(target), name))
+            }
+            record => Ok((record, name)),
+        }
     }
+}
 
-    fn walk_recursive(
-        &mut self,
-        parent_cnid: u32,
-        parent_path: &str,
-        entries: &mut Vec<WalkEntry>,
-    ) -> Result<()> {
-        let dir_entries = catalog::list_directory(
-            &mut self.reader,
-            &self.header,
-            &self.catalog_btree_header,
-            parent_cnid,
-        )?;
+/// The file behind `record`, failing for folders and thread records
+fn record_file<'a>(path: &str, record: &'a catalog::CatalogRecord) -> Result<&'a catalog::CatalogFile> {
+    match record {
+        catalog::CatalogRecord::File(f) => Ok(f),
+        catalog::CatalogRecord::Folder(_) => Err(HfsPlusError::NotADirectory(path.to_string())),
+        _ => Err(HfsPlusError::CorruptedData("unexpected thread record".into())),
+    }
+}
+
+impl<R: Read + Seek> HfsVolume<OffsetReader<R>> {
+    /// Open a volume that starts `offset` bytes into `reader`
+    ///
+    /// All seeks are relative to `offset`, so a partition inside a disk
+    /// image can be opened without copying it out first.
+    pub fn open_at(reader: R, offset: u64) -> Result<Self> {
+        Self::open(OffsetReader::new(reader, offset)?)
+    }
+}
+
+/// File type mask of a BSD mode
+const S_IFMT: u32 = 0o170000;
+/// Regular file type bits
+const S_IFREG: u32 = 0o100000;
+/// Symbolic link type bits
+const S_IFLNK: u32 = 0o120000;
+
+/// Whether a catalog name can be used as a single host path component
+fn is_host_component(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\0'])
+}
+
+/// Apply the permission bits of `mode` to an exported host path
+///
+/// Modes without type bits carry no BSD info and are left alone.
+fn set_host_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    if mode & S_IFMT != 0 {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+/// Depth-first walk from `root`, listing directories with `list`
+///
+/// Entries are returned in the same pre-order as a recursive walk. Pending
+/// directories are kept on a heap-allocated stack.
+fn walk_tree<F>(root: u32, max_depth: usize, list: F) -> Result<Vec<WalkEntry>>
+where
+    F: FnMut(u32) -> Result<Vec<DirEntry>>,
+{
+    walk_tree_with(root, max_depth, list, |_, e| Err(e))
+}
+
+/// [`walk_tree`] with a handler for directories that fail to list
+///
+/// `on_error` receives the directory path and error. Returning `Ok` skips
+/// that directory's contents and continues the walk; returning `Err` aborts.
+fn walk_tree_with<F, E>(root: u32, max_depth: usize, mut list: F, mut on_error: E) -> Result<Vec<WalkEntry>>
+where
+    F: FnMut(u32) -> Result<Vec<DirEntry>>,
+    E: FnMut(String, HfsPlusError) -> Result<()>,
+{
+    let mut entries = Vec::new();
+    let root_children = match list(root) {
+        Ok(children) => children,
+        Err(e) => {
+            on_error("/".to_string(), e)?;
+            Vec::new()
+        }
+    };
+    let mut stack = vec![(root_children.into_iter(), String::new())];
+
+    while let Some((children, parent_path)) = stack.last_mut() {
+        let Some(entry) = children.next() else {
+            stack.pop();
+            continue;
+        };
+
+        let full_path = format!("{}/{}", parent_path, entry.name);
+        let subdir = (entry.kind == EntryKind::Directory).then_some(entry.cnid);
 
-        for entry in dir_entries {
-            let full_path = if parent_path.is_empty() {
-                format!("/{}", entry.name)
-            } else {
-                format!("{}/{}", parent_path, entry.name)
+        entries.push(WalkEntry {
+            path: full_path.clone(),
+            entry,
+        });
+
+        if let Some(cnid) = subdir {
+            let children = match list(cnid) {
+                Ok(children) => children,
+                Err(e) => {
+                    on_error(full_path, e)?;
+                    continue;
+                }
             };
+            if !children.is_empty() {
+                if stack.len() >= max_depth {
+                    on_error(
+                        full_path.clone(),
+                        HfsPlusError::TooDeep {
+                            path: full_path,
+                            max_depth,
+                        },
+                    )?;
+                    continue;
+                }
+                stack.push((children.into_iter(), full_path));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::*;
+    use std::io::{BufReader, Cursor};
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_path_for_cnid() {
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut vol = HfsVolume::open(BufReader::new(file)).unwrap();
+        assert_eq!(vol.path_for_cnid(catalog::CNID_ROOT_FOLDER).unwrap(), "/");
+
+        let walk = vol.walk().unwrap();
+        let deepest = walk.iter()
+            .max_by_key(|e| e.path.matches('/').count())
+            .expect("Walk should return entries");
+
+        assert_eq!(vol.path_for_cnid(deepest.entry.cnid).unwrap(), deepest.path);
+    }
 
-            let is_dir = entry.kind == EntryKind::Directory;
-            let cnid = entry.cnid;
+    fn dir(name: &str, id: u32) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            cnid: id,
+            kind: EntryKind::Directory,
+            size: 0,
+            create_date: 0,
+            modify_date: 0,
+        }
+    }
 
-            entries.push(WalkEntry {
-                path: full_path.clone(),
-                entry,
-            });
+    #[test]
+    fn test_walk_tree_preorder() {
+        // 1 -> {a(2) -> {c(4)}, b(3)}
+        let walk = walk_tree(1, 8, |parent| {
+            Ok(match parent {
+                1 => vec![dir("a", 2), dir("b", 3)],
+                2 => vec![dir("c", 4)],
+                _ => Vec::new(),
+            })
+        })
+        .unwrap();
 
-            if is_dir {
-                self.walk_recursive(cnid, &full_path, entries)?;
+        let paths: Vec<_> = walk.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/a/c", "/b"]);
+    }
+
+    #[test]
+    fn test_walk_tree_deep() {
+        // A single chain of directories, far deeper than a recursive walk could handle
+        const DEPTH: u32 = 5000;
+        let chain = |parent: u32| -> Result<Vec<DirEntry>> {
+            Ok(if parent < DEPTH { vec![dir("d", parent + 1)] } else { Vec::new() })
+        };
+
+        let walk = walk_tree(0, usize::MAX, chain).unwrap();
+        assert_eq!(walk.len(), DEPTH as usize);
+        assert_eq!(walk.last().unwrap().path.len(), 2 * DEPTH as usize);
+
+        // The chain is DEPTH levels deep, so exactly DEPTH is allowed
+        assert!(walk_tree(0, DEPTH as usize, chain).is_ok());
+        match walk_tree(0, 100, chain) {
+            Err(HfsPlusError::TooDeep { path, max_depth }) => {
+                assert_eq!(max_depth, 100);
+                assert_eq!(path, "/d".repeat(100));
+            }
+            other => panic!("expected TooDeep, got {:?}", other.map(|w| w.len())),
+        }
+    }
+
+    #[test]
+    fn test_walk_tree_lenient() {
+        // 1 -> {a(2) -> {c(4)}, bad(3) -> <error>, b(5) -> {d(6)}}
+        let list = |parent: u32| -> Result<Vec<DirEntry>> {
+            match parent {
+                1 => Ok(vec![dir("a", 2), dir("bad", 3), dir("b", 5)]),
+                2 => Ok(vec![dir("c", 4)]),
+                3 => Err(HfsPlusError::CorruptedData("bad node".into())),
+                5 => Ok(vec![dir("d", 6)]),
+                _ => Ok(Vec::new()),
+            }
+        };
+
+        assert!(walk_tree(1, 8, list).is_err());
+
+        let mut errors = Vec::new();
+        let walk = walk_tree_with(1, 8, list, |path, e| {
+            errors.push((path, e));
+            Ok(())
+        })
+        .unwrap();
+
+        let paths: Vec<_> = walk.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/a/c", "/bad", "/b", "/b/d"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "/bad");
+        assert!(matches!(errors[0].1, HfsPlusError::CorruptedData(_)));
+
+        // Too-deep directories are reported the same way
+        let mut errors = Vec::new();
+        let walk = walk_tree_with(1, 1, list, |path, e| {
+            errors.push((path, e));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(walk.len(), 3);
+        let paths: Vec<_> = errors.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, ["/a", "/bad", "/b"]);
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_hash_file() {
+        use sha2::Digest;
+
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut vol = HfsVolume::open(BufReader::new(file)).unwrap();
+
+        let walk = vol.walk().unwrap();
+        let entry = walk.iter()
+            .find(|e| e.entry.kind == EntryKind::File && e.entry.size > 0 && e.entry.size < 1_000_000)
+            .expect("Should find a small file in the test image");
+
+        let data = vol.read_file(&entry.path).unwrap();
+        assert_eq!(
+            vol.hash_file(&entry.path, HashAlgo::Sha256).unwrap(),
+            sha2::Sha256::digest(&data).to_vec()
+        );
+        assert_eq!(
+            vol.hash_file(&entry.path, HashAlgo::Sha1).unwrap(),
+            sha1::Sha1::digest(&data).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_offset_reader() {
+        let data: Vec<u8> = (0..100u8).collect();
+        let mut reader = OffsetReader::new(std::io::Cursor::new(&data), 40).unwrap();
+
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [40, 41, 42, 43]);
+
+        assert_eq!(reader.seek(SeekFrom::Start(10)).unwrap(), 10);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [50, 51, 52, 53]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(-2)).unwrap(), 12);
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 59);
+        assert!(reader.seek(SeekFrom::Current(-100)).is_err());
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_open_at_offset() {
+        let mut image = vec![0xAAu8; 1024 * 1024];
+        image.extend(std::fs::read("../tests/kdk.raw").unwrap());
+
+        let mut embedded = HfsVolume::open_at(std::io::Cursor::new(image), 1024 * 1024).unwrap();
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut direct = HfsVolume::open(BufReader::new(file)).unwrap();
+
+        let names = |entries: Vec<DirEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.name).collect()
+        };
+        assert_eq!(
+            names(embedded.list_directory("/").unwrap()),
+            names(direct.list_directory("/").unwrap())
+        );
+
+        let walk = direct.walk().unwrap();
+        let entry = walk.iter()
+            .find(|e| e.entry.kind == EntryKind::File && e.entry.size > 0 && e.entry.size < 1_000_000)
+            .expect("Should find a small file in the test image");
+        assert_eq!(
+            embedded.read_file(&entry.path).unwrap(),
+            direct.read_file(&entry.path).unwrap()
+        );
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_into_inner_reopen() {
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut volume = HfsVolume::open(BufReader::new(file)).unwrap();
+        let first = volume.list_directory("/").unwrap();
+
+        let reader = volume.into_inner();
+        let mut reopened = HfsVolume::open(reader).unwrap();
+        let second = reopened.list_directory("/").unwrap();
+
+        assert_eq!(
+            first.into_iter().map(|e| e.name).collect::<Vec<_>>(),
+            second.into_iter().map(|e| e.name).collect::<Vec<_>>()
+        );
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_export_subtree() {
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut vol = HfsVolume::open(BufReader::new(file)).unwrap();
+
+        // Export the directory holding the first symlink
+        let walk = vol.walk().unwrap();
+        let link = walk
+            .iter()
+            .find(|e| e.entry.kind == EntryKind::Symlink)
+            .expect("Should find a symlink in the test image");
+        let src = link.path.rsplit_once('/').unwrap().0.to_string();
+
+        let dest = tempfile::tempdir().unwrap();
+        let root = dest.path().join("export");
+        let report = vol.export_subtree(&src, &root).unwrap();
+        assert_eq!(report.skipped, 0);
+
+        let prefix = format!("{}/", src);
+        let mut expected = ExportReport { directories: 1, ..Default::default() };
+        for entry in walk.iter().filter(|e| e.path.starts_with(&prefix)) {
+            let host = root.join(&entry.path[prefix.len()..]);
+            match entry.entry.kind {
+                EntryKind::Directory => {
+                    assert!(host.is_dir(), "{}", entry.path);
+                    expected.directories += 1;
+                }
+                EntryKind::File => {
+                    let content = vol.read_file(&entry.path).unwrap();
+                    assert_eq!(std::fs::read(&host).unwrap(), content, "{}", entry.path);
+                    expected.files += 1;
+                    expected.bytes += content.len() as u64;
+                }
+                EntryKind::Symlink => {
+                    let target = std::fs::read_link(&host).unwrap();
+                    assert_eq!(target.to_str().unwrap(), vol.read_link(&entry.path).unwrap());
+                    expected.symlinks += 1;
+                }
             }
         }
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn test_is_host_component() {
+        assert!(is_host_component("Info.plist"));
+        assert!(is_host_component(".hidden"));
+        for name in ["", ".", "..", "a/b", "\0\0\0\0HFS+ Private Data"] {
+            assert!(!is_host_component(name), "{:?}", name);
+        }
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_read_hard_link() {
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut vol = HfsVolume::open(BufReader::new(file)).unwrap();
+
+        let walk = vol.walk().unwrap();
+        let mut found = false;
+        for entry in walk.iter().filter(|e| e.entry.kind == EntryKind::File) {
+            let (record, _) = catalog::resolve_path(
+                &mut vol.reader,
+                &vol.header,
+                &vol.catalog_btree_header,
+                &entry.path,
+            )
+            .unwrap();
+            let link = match record {
+                catalog::CatalogRecord::File(f) if f.is_hard_link() => f,
+                _ => continue,
+            };
+
+            let target = format!(
+                "/{}/{}",
+                catalog::PRIVATE_DATA_FOLDER,
+                link.indirect_node_name()
+            );
+            let content = vol.read_file(&entry.path).unwrap();
+            assert_eq!(content, vol.read_file(&target).unwrap());
+            assert_eq!(vol.stat(&entry.path).unwrap().size, content.len() as u64);
+            found = true;
+            break;
+        }
+        assert!(found, "Should find a hard link in the test image");
+    }
+
+    /// Requires ../tests/kdk.raw fixture. Run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_btree_stats() {
+        let file = std::fs::File::open("../tests/kdk.raw").unwrap();
+        let mut vol = HfsVolume::open(BufReader::new(file)).unwrap();
+
+        let stats = vol.btree_stats();
+        assert_eq!(stats.depth, vol.catalog_btree_header.tree_depth);
+        assert!(stats.depth > 0);
+        assert!(stats.node_count > 0);
+        assert!(stats.leaf_records > 0);
+
+        // The root node sits at the top level, so its height is the tree depth
+        let root = btree::read_node(
+            &mut vol.reader,
+            &vol.catalog_btree_header,
+            vol.catalog_btree_header.root_node,
+        )
+        .unwrap();
+        assert_eq!(root.descriptor.height as u16, stats.depth);
+    }
+
+    #[test]
+    fn test_walk_with_progress() {
+        use std::sync::atomic::AtomicBool;
+
+        let mut vol = HfsVolume::open(std::io::Cursor::new(sample_volume())).unwrap();
+        let mut calls = Vec::new();
+        let walk = vol.walk_with_progress(|found| calls.push(found), None).unwrap();
+        assert_eq!(walk.len(), vol.walk().unwrap().len());
+        // The root and `docs` are listed
+        assert_eq!(calls, [walk.len() - 1, walk.len()]);
+
+        // Cancel once the root is listed, before descending into `docs`
+        let cancel = AtomicBool::new(false);
+        let result = vol.walk_with_progress(|_| cancel.store(true, Ordering::Relaxed), Some(&cancel));
+        assert!(matches!(result, Err(HfsPlusError::Cancelled)));
+    }
+
+    #[test]
+    fn test_read_best_fork() {
+        let root = catalog::CNID_ROOT_FOLDER;
+        let records = [
+            (catalog::CNID_ROOT_PARENT, "Vol", folder_record(root, 0o40755)),
+            (root, "both", file_record(16, 0o100644, Some((DATA_BLOCK, 4)), Some((DATA_BLOCK + 1, 5)))),
+            (root, "classic", file_record(17, 0o100644, None, Some((DATA_BLOCK + 1, 5)))),
+        ];
+        let image = catalog_volume(&records, &[b"data", b"rsrc!"]);
+        let mut vol = HfsVolume::open(Cursor::new(image)).unwrap();
+
+        // Content only in the resource fork, as with classic Mac files
+        assert_eq!(vol.read_file("/classic").unwrap(), b"");
+        assert_eq!(vol.read_best_fork("/classic").unwrap(), b"rsrc!");
+        // A non-empty data fork wins
+        assert_eq!(vol.read_best_fork("/both").unwrap(), b"data");
+        assert!(matches!(vol.read_best_fork("/missing"), Err(HfsPlusError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_read_link() {
+        let mut vol = HfsVolume::open(Cursor::new(sample_volume())).unwrap();
+        assert_eq!(vol.read_link("/link").unwrap(), "hello.txt");
+        assert!(matches!(vol.read_link("/hello.txt"), Err(HfsPlusError::NotASymlink(p)) if p == "/hello.txt"));
+        assert!(matches!(vol.read_link("/docs"), Err(HfsPlusError::NotASymlink(_))));
+        assert!(matches!(vol.read_link("/missing"), Err(HfsPlusError::FileNotFound(_))));
+
+        // The target is the data fork, which must be UTF-8
+        let root = catalog::CNID_ROOT_FOLDER;
+        let records = [
+            (catalog::CNID_ROOT_PARENT, "Vol", folder_record(root, 0o40755)),
+            (root, "bad", file_record(16, 0o120755, Some((DATA_BLOCK, 2)), None)),
+        ];
+        let mut vol = HfsVolume::open(Cursor::new(catalog_volume(&records, &[b"\xff\xfe"]))).unwrap();
+        assert!(matches!(vol.read_link("/bad"), Err(HfsPlusError::CorruptedData(_))));
+    }
+
+    #[test]
+    fn test_export_subtree_synthetic() {
+        let mut vol = HfsVolume::open(Cursor::new(sample_volume())).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let root = dest.path().join("export");
+        let report = vol.export_subtree("/", &root).unwrap();
+
+        assert_eq!(std::fs::read(root.join("hello.txt")).unwrap(), b"Hello, world!");
+        assert_eq!(std::fs::read(root.join("docs/a.txt")).unwrap(), b"abc");
+        // Symlinks are skipped on platforms without them
+        let symlinks = if cfg!(unix) { 1 } else { 0 };
+        let expected = ExportReport { directories: 2, files: 2, symlinks, bytes: 16, skipped: 2 - symlinks };
+        assert_eq!(report, expected);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::read_link(root.join("link")).unwrap(), Path::new("hello.txt"));
+            let mode = |path: &str| std::fs::metadata(root.join(path)).unwrap().permissions().mode() & 0o7777;
+            assert_eq!(mode("docs"), 0o750);
+            assert_eq!(mode("docs/a.txt"), 0o600);
+            assert_eq!(mode("hello.txt"), 0o644);
+        }
+
+        // A file is written to `dest` itself
+        let single = dest.path().join("single.txt");
+        let report = vol.export_subtree("/docs/a.txt", &single).unwrap();
+        assert_eq!(report, ExportReport { files: 1, bytes: 3, ..Default::default() });
+        assert_eq!(std::fs::read(single).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_open_with_options_lenient() {
+        let consistent = HfsVolume::open(Cursor::new(minimal_volume(0))).unwrap();
+        assert!(consistent.warnings().is_empty());
+
+        let image = minimal_volume(7);
+        let options = HfsVolumeOptions { strict: true };
+        assert!(matches!(
+            HfsVolume::open_with_options(Cursor::new(image.clone()), options),
+            Err(HfsPlusError::InvalidBTree(_))
+        ));
+
+        let vol = HfsVolume::open(Cursor::new(image)).unwrap();
+        assert_eq!(vol.warnings().len(), 1);
+        assert!(vol.warnings()[0].contains("7 free nodes"), "{}", vol.warnings()[0]);
+        assert_eq!(vol.btree_stats().node_count, 0);
+    }
+
+    #[test]
+    fn test_file_stat_totals() {
+        let stat = FileStat {
+            cnid: 20,
+            kind: EntryKind::File,
+            size: 5000,
+            create_date: 0,
+            modify_date: 0,
+            permissions: HfsPermissions { owner_id: 0, group_id: 0, mode: 0o100644 },
+            data_fork_extents: 1,
+            resource_fork_size: 300,
+            data_fork_blocks: 2,
+            resource_fork_blocks: 1,
+        };
+        assert_eq!(stat.total_logical_size(), 5300);
+        assert_eq!(stat.total_allocated_blocks(), 3);
+    }
+
+    #[test]
+    fn test_block_size() {
+        let vol = HfsVolume::open(Cursor::new(minimal_volume(0))).unwrap();
+        assert_eq!(vol.block_size(), 4096);
+        assert_eq!(vol.block_size(), vol.volume_header().block_size);
+    }
+
+    #[test]
+    fn test_read_system_file() {
+        let mut vol = HfsVolume::open(Cursor::new(minimal_volume(0))).unwrap();
+
+        let catalog_file = vol.read_system_file(catalog::CNID_CATALOG_FILE).unwrap();
+        assert_eq!(catalog_file.len() as u64, vol.volume_header().catalog_file.logical_size);
+        // Node 0 is the header node: kind at descriptor offset 8
+        assert_eq!(catalog_file[8], btree::NODE_KIND_HEADER);
 
-        Ok(())
+        assert!(vol.read_system_file(catalog::CNID_STARTUP_FILE).unwrap().is_empty());
+        assert!(matches!(
+            vol.read_system_file(catalog::CNID_ROOT_FOLDER),
+            Err(HfsPlusError::FileNotFound(_))
+        ));
     }
 }
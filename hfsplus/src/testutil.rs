@@ -0,0 +1,134 @@
+//! Synthetic HFS+ images for tests
+//!
+//! Built with the `test-util` feature so that crates layered on top of
+//! `hfsplus` can exercise it without fixture images.
+
+use crate::{btree, catalog, unicode, volume};
+
+pub const BLOCK: usize = 4096;
+/// First allocation block after the metadata written by [`catalog_volume`]
+pub const DATA_BLOCK: u32 = 4;
+
+/// Catalog folder record with BSD `mode`
+pub fn folder_record(id: u32, mode: u16) -> Vec<u8> {
+    let mut body = vec![0u8; 88];
+    body[0..2].copy_from_slice(&catalog::RECORD_TYPE_FOLDER.to_be_bytes());
+    body[8..12].copy_from_slice(&id.to_be_bytes());
+    body[42..44].copy_from_slice(&mode.to_be_bytes());
+    body
+}
+
+/// Catalog file record with BSD `mode`; each fork is `(start_block, size)`
+/// and fits in a single allocation block
+pub fn file_record(id: u32, mode: u16, data: Option<(u32, u64)>, rsrc: Option<(u32, u64)>) -> Vec<u8> {
+    let mut body = vec![0u8; 248];
+    body[0..2].copy_from_slice(&catalog::RECORD_TYPE_FILE.to_be_bytes());
+    body[8..12].copy_from_slice(&id.to_be_bytes());
+    body[42..44].copy_from_slice(&mode.to_be_bytes());
+    for (offset, fork) in [(88, data), (168, rsrc)] {
+        if let Some((start_block, size)) = fork {
+            let fork = &mut body[offset..offset + 80];
+            fork[0..8].copy_from_slice(&size.to_be_bytes());
+            fork[12..16].copy_from_slice(&1u32.to_be_bytes());
+            fork[16..20].copy_from_slice(&start_block.to_be_bytes());
+            fork[20..24].copy_from_slice(&1u32.to_be_bytes());
+        }
+    }
+    body
+}
+
+/// HFS+ image: volume header in block 0, a two-node catalog (header and
+/// one leaf holding `records` as `(parent CNID, name, record)`) in blocks
+/// 1-2, the extents B-tree header in block 3, then one block per `data`
+/// entry from [`DATA_BLOCK`]
+pub fn catalog_volume(records: &[(u32, &str, Vec<u8>)], data: &[&[u8]]) -> Vec<u8> {
+    let total_blocks = DATA_BLOCK as usize + data.len();
+    let mut image = vec![0u8; total_blocks * BLOCK];
+    let vh = &mut image[1024..1536];
+    vh[0..2].copy_from_slice(&volume::HFS_PLUS_SIGNATURE.to_be_bytes());
+    vh[2..4].copy_from_slice(&volume::HFS_PLUS_VERSION.to_be_bytes());
+    vh[40..44].copy_from_slice(&(BLOCK as u32).to_be_bytes());
+    vh[44..48].copy_from_slice(&(total_blocks as u32).to_be_bytes());
+    // Fork data: logical size, clump size, total blocks, first extent
+    for (fork_offset, start_block, blocks) in [(192, 3u32, 1u32), (272, 1, 2)] {
+        let fork = &mut vh[fork_offset..fork_offset + 80];
+        fork[0..8].copy_from_slice(&(blocks as u64 * BLOCK as u64).to_be_bytes());
+        fork[12..16].copy_from_slice(&blocks.to_be_bytes());
+        fork[16..20].copy_from_slice(&start_block.to_be_bytes());
+        fork[20..24].copy_from_slice(&blocks.to_be_bytes());
+    }
+
+    let leaf_records = records.len() as u32;
+    for (block, total_nodes, leaf_records) in [(1, 2u32, leaf_records), (3, 1, 0)] {
+        let node = &mut image[block * BLOCK..(block + 1) * BLOCK];
+        node[8] = btree::NODE_KIND_HEADER;
+        node[10..12].copy_from_slice(&3u16.to_be_bytes()); // num_records
+        let record = &mut node[14..];
+        if leaf_records > 0 {
+            record[0..2].copy_from_slice(&1u16.to_be_bytes()); // tree_depth
+            for field in [2, 10, 14] {
+                // root, first leaf and last leaf are all node 1
+                record[field..field + 4].copy_from_slice(&1u32.to_be_bytes());
+            }
+            record[6..10].copy_from_slice(&leaf_records.to_be_bytes());
+        }
+        record[18..20].copy_from_slice(&(BLOCK as u16).to_be_bytes()); // node_size
+        record[22..26].copy_from_slice(&total_nodes.to_be_bytes());
+        record[37] = btree::KEY_COMPARE_CASE_FOLDING as u8;
+    }
+
+    // Leaf records are ordered by parent, then case-folded name
+    let mut sorted: Vec<_> = records.iter().collect();
+    sorted.sort_by(|a, b| {
+        a.0.cmp(&b.0).then_with(|| {
+            unicode::compare_case_insensitive(&unicode::string_to_utf16(a.1), &unicode::string_to_utf16(b.1))
+        })
+    });
+    let leaf = &mut image[2 * BLOCK..3 * BLOCK];
+    leaf[8] = btree::NODE_KIND_LEAF;
+    leaf[9] = 1; // height
+    leaf[10..12].copy_from_slice(&(sorted.len() as u16).to_be_bytes());
+    let mut offset = 14;
+    for (i, (parent, name, body)) in sorted.iter().enumerate() {
+        let name = unicode::string_to_utf16(name);
+        let mut record = Vec::new();
+        record.extend_from_slice(&(6 + 2 * name.len() as u16).to_be_bytes()); // key_length
+        record.extend_from_slice(&parent.to_be_bytes());
+        record.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        record.extend(name.iter().flat_map(|c| c.to_be_bytes()));
+        record.extend_from_slice(body);
+        leaf[BLOCK - 2 * (i + 1)..BLOCK - 2 * i].copy_from_slice(&(offset as u16).to_be_bytes());
+        leaf[offset..offset + record.len()].copy_from_slice(&record);
+        offset += record.len();
+    }
+    let free = BLOCK - 2 * (sorted.len() + 1);
+    leaf[free..free + 2].copy_from_slice(&(offset as u16).to_be_bytes());
+
+    for (i, content) in data.iter().enumerate() {
+        let start = (DATA_BLOCK as usize + i) * BLOCK;
+        image[start..start + content.len()].copy_from_slice(content);
+    }
+    image
+}
+
+/// Volume with an empty catalog whose header claims `catalog_free_nodes`
+pub fn minimal_volume(catalog_free_nodes: u32) -> Vec<u8> {
+    let mut image = catalog_volume(&[], &[]);
+    image[BLOCK + 40..BLOCK + 44].copy_from_slice(&catalog_free_nodes.to_be_bytes());
+    image
+}
+
+/// Catalog with a root folder holding `docs/a.txt`, `hello.txt`, a
+/// symlink to it, and an entry whose name is not a host path component
+pub fn sample_volume() -> Vec<u8> {
+    let root = catalog::CNID_ROOT_FOLDER;
+    let records = [
+        (catalog::CNID_ROOT_PARENT, "Vol", folder_record(root, 0o40755)),
+        (root, "docs", folder_record(16, 0o40750)),
+        (root, "hello.txt", file_record(17, 0o100644, Some((DATA_BLOCK, 13)), None)),
+        (root, "link", file_record(18, 0o120755, Some((DATA_BLOCK + 1, 9)), None)),
+        (root, "..", file_record(19, 0o100644, None, None)),
+        (16, "a.txt", file_record(20, 0o100600, Some((DATA_BLOCK + 2, 3)), None)),
+    ];
+    catalog_volume(&records, &[b"Hello, world!", b"hello.txt", b"abc"])
+}
@@ -1,8 +1,8 @@
-/// HFS+ Unicode comparison utilities.
-///
-/// HFSX (case-sensitive) uses binary comparison of UTF-16BE values.
-/// HFS+ (case-insensitive) uses Apple's FastUnicodeCompare with a
-/// case-folding table defined in Apple TN1150.
+//! HFS+ Unicode comparison utilities.
+//!
+//! HFSX (case-sensitive) uses binary comparison of UTF-16BE values.
+//! HFS+ (case-insensitive) uses Apple's FastUnicodeCompare with a
+//! case-folding table defined in Apple TN1150.
 
 /// Compare two HFS+ Unicode names using binary comparison (HFSX / case-sensitive).
 pub fn compare_binary(a: &[u16], b: &[u16]) -> std::cmp::Ordering {
@@ -20,6 +20,7 @@ pub fn compare_binary(a: &[u16], b: &[u16]) -> std::cmp::Ordering {
 /// Maps Unicode code points to their case-folded equivalents.
 /// Only entries that differ from identity are listed.
 static CASE_FOLD: &[(u16, u16)] = &[
+    (0x0000, 0xFFFF), // NUL sorts after everything else
     (0x0041, 0x0061), // A -> a
     (0x0042, 0x0062), // B -> b
     (0x0043, 0x0063), // C -> c
@@ -210,6 +211,12 @@ mod tests {
         let a = string_to_utf16("Hello");
         let b = string_to_utf16("hello");
         assert_eq!(compare_case_insensitive(&a, &b), std::cmp::Ordering::Equal);
+
+        // The hard link directory "\0\0\0\0HFS+ Private Data" sorts last
+        let a = string_to_utf16("\0\0\0\0HFS+ Private Data");
+        let b = string_to_utf16("zzz");
+        assert_eq!(compare_case_insensitive(&a, &b), std::cmp::Ordering::Greater);
+        assert_eq!(compare_binary(&a, &b), std::cmp::Ordering::Less);
     }
 
     #[test]
@@ -16,6 +16,15 @@ pub const HFSX_SIGNATURE: u16 = 0x4858;
 pub const HFS_PLUS_VERSION: u16 = 4;
 pub const HFSX_VERSION: u16 = 5;
 
+/// Smallest and largest allocation block sizes accepted on open
+pub const MIN_BLOCK_SIZE: u32 = 512;
+pub const MAX_BLOCK_SIZE: u32 = 65536;
+
+/// Volume attribute bits (`kHFSVolume*Bit` in hfs_format.h)
+pub const VOLUME_HARDWARE_LOCK: u32 = 1 << 7;
+pub const VOLUME_JOURNALED: u32 = 1 << 13;
+pub const VOLUME_SOFTWARE_LOCK: u32 = 1 << 15;
+
 /// An extent descriptor: contiguous range of allocation blocks
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ExtentDescriptor {
@@ -24,7 +33,7 @@ pub struct ExtentDescriptor {
 }
 
 /// Fork data: describes a data or resource fork
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ForkData {
     pub logical_size: u64,
     pub clump_size: u32,
@@ -32,17 +41,6 @@ pub struct ForkData {
     pub extents: [ExtentDescriptor; 8],
 }
 
-impl Default for ForkData {
-    fn default() -> Self {
-        ForkData {
-            logical_size: 0,
-            clump_size: 0,
-            total_blocks: 0,
-            extents: [ExtentDescriptor::default(); 8],
-        }
-    }
-}
-
 /// The HFS+ Volume Header (512 bytes at offset 1024)
 #[derive(Debug, Clone)]
 pub struct VolumeHeader {
@@ -127,6 +125,12 @@ impl VolumeHeader {
         let file_count = reader.read_u32::<BigEndian>()?;
         let folder_count = reader.read_u32::<BigEndian>()?;
         let block_size = reader.read_u32::<BigEndian>()?;
+        if !block_size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+            return Err(HfsPlusError::InvalidVolume(format!(
+                "block size {} is not a power of two in {}..={}",
+                block_size, MIN_BLOCK_SIZE, MAX_BLOCK_SIZE
+            )));
+        }
         let total_blocks = reader.read_u32::<BigEndian>()?;
         let free_blocks = reader.read_u32::<BigEndian>()?;
         let next_allocation = reader.read_u32::<BigEndian>()?;
@@ -177,6 +181,44 @@ impl VolumeHeader {
             is_hfsx,
         })
     }
+
+    /// Signature of the implementation that last mounted the volume for writing
+    ///
+    /// e.g. `b"10.0"` for Mac OS X, `b"HFSJ"` for a journaled Mac OS X mount,
+    /// `b"fsck"` after `fsck_hfs`, or `b"H+Lx"` for Linux.
+    pub fn last_mounted_version(&self) -> [u8; 4] {
+        self.last_mounted_version.to_be_bytes()
+    }
+
+    /// Raw volume attribute bits
+    pub fn attributes(&self) -> u32 {
+        self.attributes
+    }
+
+    /// Whether the volume has a journal enabled
+    pub fn is_journaled(&self) -> bool {
+        self.attributes & VOLUME_JOURNALED != 0
+    }
+
+    /// Whether the volume is hardware- or software-locked against writes
+    pub fn is_read_only(&self) -> bool {
+        self.attributes & (VOLUME_HARDWARE_LOCK | VOLUME_SOFTWARE_LOCK) != 0
+    }
+
+    /// List counts in the header that contradict each other.
+    ///
+    /// None of these stop the volume from being read, so callers decide
+    /// whether they are errors or warnings.
+    pub fn check_consistency(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.free_blocks > self.total_blocks {
+            issues.push(format!(
+                "{} free blocks but only {} total",
+                self.free_blocks, self.total_blocks
+            ));
+        }
+        issues
+    }
 }
 
 #[cfg(test)]
@@ -199,5 +241,58 @@ mod tests {
         assert!(header.file_count > 0);
         assert!(header.folder_count > 0);
         assert!(header.catalog_file.logical_size > 0);
+
+        let version = header.last_mounted_version();
+        assert!(
+            [b"10.0", b"HFSJ", b"fsck", b"H+Lx", b"8.10"].contains(&&version),
+            "unexpected last mounted version {:?}",
+            String::from_utf8_lossy(&version)
+        );
+        assert_eq!(header.is_journaled(), header.attributes() & VOLUME_JOURNALED != 0);
+    }
+
+    /// Minimal HFS+ volume header with the given block size
+    fn header_bytes(block_size: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 2048];
+        data[1024..1026].copy_from_slice(&HFS_PLUS_SIGNATURE.to_be_bytes());
+        data[1026..1028].copy_from_slice(&HFS_PLUS_VERSION.to_be_bytes());
+        data[1064..1068].copy_from_slice(&block_size.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_attributes_and_last_mounted_version() {
+        let mut data = header_bytes(4096);
+        data[1028..1032].copy_from_slice(&(VOLUME_JOURNALED | VOLUME_SOFTWARE_LOCK).to_be_bytes());
+        data[1032..1036].copy_from_slice(b"HFSJ");
+        let header = VolumeHeader::parse(&mut std::io::Cursor::new(data)).unwrap();
+
+        assert_eq!(&header.last_mounted_version(), b"HFSJ");
+        assert!(header.is_journaled());
+        assert!(header.is_read_only());
+
+        let header = VolumeHeader::parse(&mut std::io::Cursor::new(header_bytes(4096))).unwrap();
+        assert_eq!(header.attributes(), 0);
+        assert!(!header.is_journaled());
+        assert!(!header.is_read_only());
+    }
+
+    #[test]
+    fn test_block_size_validation() {
+        for block_size in [512, 1024, 2048, 4096, 8192, 65536] {
+            let header = VolumeHeader::parse(&mut std::io::Cursor::new(header_bytes(block_size)))
+                .unwrap_or_else(|e| panic!("block size {}: {}", block_size, e));
+            assert_eq!(header.block_size, block_size);
+            assert!(!header.is_hfsx);
+        }
+
+        for block_size in [0, 256, 3000, 4097, 131072] {
+            let result = VolumeHeader::parse(&mut std::io::Cursor::new(header_bytes(block_size)));
+            assert!(
+                matches!(result, Err(HfsPlusError::InvalidVolume(_))),
+                "block size {} should be rejected",
+                block_size
+            );
+        }
     }
 }
@@ -8,6 +8,7 @@
 //! - newc (070701): SVR4 portable format with no CRC
 //! - crc (070702): SVR4 portable format with CRC
 //! - odc (070707): POSIX.1 portable format
+//! - bin (16-bit 070707): old binary format, either byte order
 //!
 //! # Example
 //!
@@ -24,16 +25,98 @@
 //! }
 //! ```
 
+use std::collections::HashMap;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use crate::error::{PbzxError, Result};
-use crate::format::{CpioFormat, CpioHeader, FileEntry, CPIO_MAGIC_CRC, CPIO_MAGIC_NEWC, CPIO_MAGIC_ODC};
+use crate::format::{CpioFormat, CpioHeader, FileEntry};
+use crate::writer::CpioBuilder;
 
 /// A reader for CPIO archives.
 pub struct CpioReader<R> {
     reader: R,
     position: u64,
+    multi: bool,
+    /// Magic of a concatenated archive, already consumed by `next_archive`
+    pending_magic: Option<[u8; 6]>,
+    /// Format of the header `read_header` returned last
+    last_format: Option<CpioFormat>,
+}
+
+/// Options for [`CpioReader::extract_all_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    exclude: Vec<String>,
+    sparse: bool,
+    preserve_order: bool,
+    create_device_nodes: bool,
+}
+
+/// Granularity at which [`ExtractOptions::sparse`] looks for zero runs.
+const SPARSE_BLOCK_SIZE: usize = 4096;
+
+impl ExtractOptions {
+    /// Create options that extract every entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip entries whose path matches any of these globs.
+    ///
+    /// Patterns are matched against the full relative path (without a
+    /// leading `./` or `/`). `*` and `?` do not cross `/`, `**` matches any
+    /// number of path components, and a pattern ending in `/**` also matches
+    /// the directory itself, so `**/*.dSYM/**` drops whole `.dSYM` bundles.
+    pub fn exclude(mut self, patterns: Vec<String>) -> Self {
+        self.exclude = patterns;
+        self
+    }
+
+    /// Write files sparsely, seeking past all-zero blocks instead of
+    /// writing them.
+    ///
+    /// On filesystems with sparse file support the skipped regions become
+    /// holes, so zero-filled files take little disk space; elsewhere the
+    /// result is an ordinary file with the same contents.
+    pub fn sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Create entries strictly in archive order.
+    ///
+    /// Parent directories are not created on demand: an entry that comes
+    /// before its parent's directory entry fails with `InvalidCpio`. Each
+    /// explicit directory entry's mode is also applied once extraction
+    /// finishes (deepest last-listed first), along with its mtime, so later
+    /// children cannot trip over a read-only mode.
+    pub fn preserve_order(mut self, preserve_order: bool) -> Self {
+        self.preserve_order = preserve_order;
+        self
+    }
+
+    /// Recreate character and block device entries with `mknod`.
+    ///
+    /// Unix only, and normally requires root; without the privilege
+    /// extraction fails with the `mknod` error. When off (the default), or
+    /// on other platforms, device entries are skipped like other special
+    /// files.
+    pub fn create_device_nodes(mut self, create_device_nodes: bool) -> Self {
+        self.create_device_nodes = create_device_nodes;
+        self
+    }
+
+    /// Check whether `path` is excluded by these options.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.exclude.iter().any(|pattern| {
+            glob_match(pattern.as_bytes(), path.as_bytes())
+                || pattern
+                    .strip_suffix("/**")
+                    .is_some_and(|dir| glob_match(dir.as_bytes(), path.as_bytes()))
+        })
+    }
 }
 
 impl<R: Read> CpioReader<R> {
@@ -42,9 +125,24 @@ impl<R: Read> CpioReader<R> {
         Self {
             reader,
             position: 0,
+            multi: false,
+            pending_magic: None,
+            last_format: None,
         }
     }
 
+    /// Continue past `TRAILER!!!` into concatenated archives.
+    ///
+    /// Some payloads are several CPIO archives back to back, each with its
+    /// own trailer. When enabled, zero padding after a trailer is skipped
+    /// and, if another CPIO magic follows, reading carries on with that
+    /// archive (in whatever format it uses); anything else ends the scan.
+    /// Off by default, so only the first archive is read.
+    pub fn lenient_multi(mut self, multi: bool) -> Self {
+        self.multi = multi;
+        self
+    }
+
     /// Get an iterator over all entries in the archive.
     pub fn entries(&mut self) -> Result<CpioEntries<'_, R>> {
         Ok(CpioEntries {
@@ -53,31 +151,22 @@ impl<R: Read> CpioReader<R> {
         })
     }
 
-    /// Detect CPIO format from magic bytes.
-    fn detect_format(magic: &[u8; 6]) -> Option<CpioFormat> {
-        if magic == CPIO_MAGIC_NEWC {
-            Some(CpioFormat::Newc)
-        } else if magic == CPIO_MAGIC_CRC {
-            Some(CpioFormat::Crc)
-        } else if magic == CPIO_MAGIC_ODC {
-            Some(CpioFormat::Odc)
-        } else {
-            None
-        }
-    }
-
     /// Read and parse a CPIO header at the current position.
     fn read_header(&mut self) -> Result<Option<CpioHeader>> {
         let mut magic = [0u8; 6];
-        match self.reader.read_exact(&mut magic) {
-            Ok(()) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-            Err(e) => return Err(e.into()),
+        if let Some(pending) = self.pending_magic.take() {
+            magic = pending;
+        } else {
+            match self.reader.read_exact(&mut magic) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
         }
         self.position += 6;
 
         // Detect format
-        let format = Self::detect_format(&magic).ok_or_else(|| {
+        let format = CpioFormat::from_magic(&magic).ok_or_else(|| {
             PbzxError::InvalidCpio(format!(
                 "Invalid CPIO magic at offset {}: {:?}",
                 self.position - 6,
@@ -85,187 +174,116 @@ impl<R: Read> CpioReader<R> {
             ))
         })?;
 
-        match format {
-            CpioFormat::Newc | CpioFormat::Crc => self.read_newc_header(),
-            CpioFormat::Odc => self.read_odc_header(),
-        }
-    }
-
-    /// Read newc/crc format header (after magic has been read).
-    fn read_newc_header(&mut self) -> Result<Option<CpioHeader>> {
-        // Read the rest of the fixed header (104 bytes after magic)
-        let mut header_buf = [0u8; 104];
-        self.reader.read_exact(&mut header_buf)?;
-        self.position += 104;
-
-        // Parse hex fields (8 chars each)
-        let parse_hex = |start: usize, len: usize| -> Result<u32> {
-            let s = std::str::from_utf8(&header_buf[start..start + len])
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid UTF-8 in header: {}", e)))?;
-            u32::from_str_radix(s, 16)
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid hex value '{}': {}", s, e)))
+        // Read the rest of the fixed header
+        let mut header_buf = vec![0u8; format.header_size()];
+        header_buf[..6].copy_from_slice(&magic);
+        self.reader.read_exact(&mut header_buf[6..])?;
+        self.position += header_buf.len() as u64 - 6;
+
+        let mut header = match format {
+            CpioFormat::Newc | CpioFormat::Crc => CpioHeader::parse_newc_fields(&header_buf[6..])?,
+            CpioFormat::Odc => CpioHeader::parse_odc_fields(&header_buf[6..])?,
+            CpioFormat::Bin { big_endian } => {
+                CpioHeader::parse_bin_fields(&header_buf[2..], big_endian)?
+            }
         };
 
-        let ino = parse_hex(0, 8)?;
-        let mode = parse_hex(8, 8)?;
-        let uid = parse_hex(16, 8)?;
-        let gid = parse_hex(24, 8)?;
-        let nlink = parse_hex(32, 8)?;
-        let mtime = parse_hex(40, 8)?;
-        let filesize = parse_hex(48, 8)?;
-        let devmajor = parse_hex(56, 8)?;
-        let devminor = parse_hex(64, 8)?;
-        let rdevmajor = parse_hex(72, 8)?;
-        let rdevminor = parse_hex(80, 8)?;
-        let namesize = parse_hex(88, 8)?;
-        let check = parse_hex(96, 8)?;
-
         // Read filename
-        let mut name_buf = vec![0u8; namesize as usize];
+        let mut name_buf = vec![0u8; header.namesize as usize];
         self.reader.read_exact(&mut name_buf)?;
-        self.position += namesize as u64;
-
-        // Remove null terminator if present
-        if name_buf.last() == Some(&0) {
-            name_buf.pop();
-        }
+        self.position += header.namesize as u64;
+        header.name = CpioHeader::parse_name(name_buf)?;
 
-        let name = String::from_utf8(name_buf)
-            .map_err(|e| PbzxError::InvalidCpio(format!("Invalid filename: {}", e)))?;
-
-        // Align to 4-byte boundary (header is 110 bytes + namesize)
-        let header_total = 110 + namesize as u64;
-        let padding = (4 - (header_total % 4)) % 4;
+        // Align newc/crc to 4-byte and binary to 2-byte boundary (fixed
+        // header + namesize). ODC format has no padding requirement.
+        let padding = format.padding(format.header_size() as u64 + header.namesize as u64);
         if padding > 0 {
             let mut pad = vec![0u8; padding as usize];
             self.reader.read_exact(&mut pad)?;
             self.position += padding;
         }
 
-        Ok(Some(CpioHeader {
-            ino,
-            mode,
-            uid,
-            gid,
-            nlink,
-            mtime,
-            filesize,
-            devmajor,
-            devminor,
-            rdevmajor,
-            rdevminor,
-            namesize,
-            check,
-            name,
-        }))
-    }
-
-    /// Read odc format header (after magic has been read).
-    ///
-    /// ODC format structure (76 bytes total including magic):
-    /// - 6 bytes: magic "070707" (already read)
-    /// - 6 bytes: dev (octal)
-    /// - 6 bytes: ino (octal)
-    /// - 6 bytes: mode (octal)
-    /// - 6 bytes: uid (octal)
-    /// - 6 bytes: gid (octal)
-    /// - 6 bytes: nlink (octal)
-    /// - 6 bytes: rdev (octal)
-    /// - 11 bytes: mtime (octal)
-    /// - 6 bytes: namesize (octal)
-    /// - 11 bytes: filesize (octal)
-    fn read_odc_header(&mut self) -> Result<Option<CpioHeader>> {
-        // Read the rest of the fixed header (70 bytes after magic)
-        let mut header_buf = [0u8; 70];
-        self.reader.read_exact(&mut header_buf)?;
-        self.position += 70;
-
-        // Parse octal fields
-        let parse_octal = |start: usize, len: usize| -> Result<u32> {
-            let s = std::str::from_utf8(&header_buf[start..start + len])
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid UTF-8 in header: {}", e)))?;
-            u32::from_str_radix(s.trim(), 8)
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid octal value '{}': {}", s, e)))
-        };
-
-        let parse_octal_u64 = |start: usize, len: usize| -> Result<u64> {
-            let s = std::str::from_utf8(&header_buf[start..start + len])
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid UTF-8 in header: {}", e)))?;
-            u64::from_str_radix(s.trim(), 8)
-                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid octal value '{}': {}", s, e)))
-        };
-
-        let dev = parse_octal(0, 6)?;
-        let ino = parse_octal(6, 6)?;
-        let mode = parse_octal(12, 6)?;
-        let uid = parse_octal(18, 6)?;
-        let gid = parse_octal(24, 6)?;
-        let nlink = parse_octal(30, 6)?;
-        let rdev = parse_octal(36, 6)?;
-        let mtime = parse_octal_u64(42, 11)? as u32;
-        let namesize = parse_octal(53, 6)?;
-        let filesize = parse_octal_u64(59, 11)? as u32;
-
-        // Read filename
-        let mut name_buf = vec![0u8; namesize as usize];
-        self.reader.read_exact(&mut name_buf)?;
-        self.position += namesize as u64;
+        self.last_format = Some(format);
+        Ok(Some(header))
+    }
 
-        // Remove null terminator if present
-        if name_buf.last() == Some(&0) {
-            name_buf.pop();
+    /// After a trailer, find the header of a concatenated archive.
+    ///
+    /// Returns `None` unless [`lenient_multi`](Self::lenient_multi) is set
+    /// and a CPIO magic follows the trailer's zero padding. The magic is
+    /// kept for the next `read_header` call.
+    fn next_archive(&mut self) -> Result<Option<CpioFormat>> {
+        if !self.multi {
+            return Ok(None);
         }
 
-        let name = String::from_utf8(name_buf)
-            .map_err(|e| PbzxError::InvalidCpio(format!("Invalid filename: {}", e)))?;
+        let mut magic = [0u8; 6];
+        loop {
+            match self.reader.read_exact(&mut magic[..1]) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+            if magic[0] != 0 {
+                break;
+            }
+            self.position += 1;
+        }
+        match self.reader.read_exact(&mut magic[1..]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
 
-        // ODC format has no padding requirement
+        let format = CpioFormat::from_magic(&magic);
+        if format.is_some() {
+            self.pending_magic = Some(magic);
+        }
+        Ok(format)
+    }
 
-        Ok(Some(CpioHeader {
-            ino,
-            mode,
-            uid,
-            gid,
-            nlink,
-            mtime,
-            filesize,
-            devmajor: dev >> 8,
-            devminor: dev & 0xff,
-            rdevmajor: rdev >> 8,
-            rdevminor: rdev & 0xff,
-            namesize,
-            check: 0,
-            name,
-        }))
+    /// Move past a `TRAILER!!!` entry.
+    ///
+    /// Returns `true`, with `format` switched to the next archive's, when a
+    /// concatenated archive follows (see [`next_archive`](Self::next_archive));
+    /// `false` when the scan should stop.
+    fn advance_after_trailer(&mut self, format: &mut CpioFormat) -> Result<bool> {
+        match self.next_archive()? {
+            Some(next) => {
+                *format = next;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
-    /// Skip the file data for the current entry (newc format with padding).
-    fn skip_data_newc(&mut self, size: u64) -> Result<()> {
-        // Read and discard the data
-        let mut remaining = size;
-        let mut buf = [0u8; 8192];
+    /// Skip an entry's file data and the format's padding after it.
+    fn skip_data(&mut self, format: CpioFormat, size: u64) -> Result<()> {
+        self.discard(size + format.padding(size))
+    }
 
-        while remaining > 0 {
-            let to_read = std::cmp::min(remaining, buf.len() as u64) as usize;
-            self.reader.read_exact(&mut buf[..to_read])?;
-            remaining -= to_read as u64;
-        }
+    /// Read an entry's file data, then skip the format's padding after it.
+    fn read_data(&mut self, format: CpioFormat, size: u64) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; size as usize];
+        self.reader.read_exact(&mut data)?;
         self.position += size;
+        self.discard(format.padding(size))?;
+        Ok(data)
+    }
 
-        // Align to 4-byte boundary
-        let padding = (4 - (size % 4)) % 4;
-        if padding > 0 {
-            let mut pad = vec![0u8; padding as usize];
-            self.reader.read_exact(&mut pad)?;
-            self.position += padding;
+    /// Copy an entry's file data to `out`, then skip the format's padding.
+    fn copy_data<W: Write>(&mut self, format: CpioFormat, size: u64, out: &mut W) -> Result<()> {
+        let copied = std::io::copy(&mut (&mut self.reader).take(size), out)?;
+        if copied < size {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
         }
-
-        Ok(())
+        self.position += size;
+        self.discard(format.padding(size))
     }
 
-    /// Skip the file data for the current entry (odc format, no padding).
-    fn skip_data_odc(&mut self, size: u64) -> Result<()> {
-        let mut remaining = size;
+    /// Read and throw away `len` bytes.
+    fn discard(&mut self, len: u64) -> Result<()> {
+        let mut remaining = len;
         let mut buf = [0u8; 8192];
 
         while remaining > 0 {
@@ -273,41 +291,18 @@ impl<R: Read> CpioReader<R> {
             self.reader.read_exact(&mut buf[..to_read])?;
             remaining -= to_read as u64;
         }
-        self.position += size;
+        self.position += len;
 
         Ok(())
     }
 
-    /// Read the file data for an entry (newc format with padding).
-    fn read_data_newc(&mut self, size: u64) -> Result<Vec<u8>> {
-        let mut data = vec![0u8; size as usize];
-        self.reader.read_exact(&mut data)?;
-        self.position += size;
-
-        // Align to 4-byte boundary
-        let padding = (4 - (size % 4)) % 4;
-        if padding > 0 {
-            let mut pad = vec![0u8; padding as usize];
-            self.reader.read_exact(&mut pad)?;
-            self.position += padding;
-        }
-
-        Ok(data)
-    }
-
-    /// Read the file data for an entry (odc format, no padding).
-    fn read_data_odc(&mut self, size: u64) -> Result<Vec<u8>> {
-        let mut data = vec![0u8; size as usize];
-        self.reader.read_exact(&mut data)?;
-        self.position += size;
-        Ok(data)
-    }
-
     /// Internal: Detect format at current position without consuming.
     fn peek_format(&mut self) -> Result<Option<CpioFormat>>
     where
         R: Seek,
     {
+        // Every scan starts here; drop a magic left over from an earlier one
+        self.pending_magic = None;
         let mut magic = [0u8; 6];
         match self.reader.read_exact(&mut magic) {
             Ok(()) => {}
@@ -316,44 +311,62 @@ impl<R: Read> CpioReader<R> {
         }
         // Seek back
         self.reader.seek(SeekFrom::Current(-6))?;
-        Ok(Self::detect_format(&magic))
+        Ok(CpioFormat::from_magic(&magic))
     }
 }
 
 impl<R: Read + Seek> CpioReader<R> {
     /// List all files in the archive.
+    ///
+    /// Fails with `InvalidCpio` if the archive ends before its `TRAILER!!!`
+    /// entry; use [`list_lenient`](Self::list_lenient) to keep the entries
+    /// read up to that point.
     pub fn list(&mut self) -> Result<Vec<FileEntry>> {
-        self.reader.seek(SeekFrom::Start(0))?;
-        self.position = 0;
+        let mut entries = Vec::new();
+        self.list_into(&mut entries).map_err(truncation_error)?;
+        Ok(entries)
+    }
 
+    /// List files, returning the entries parsed before any error.
+    ///
+    /// For a truncated or trailer-less archive this yields every complete
+    /// entry together with the `InvalidCpio` error [`list`](Self::list)
+    /// would return. The error is `None` when the archive is intact.
+    pub fn list_lenient(&mut self) -> (Vec<FileEntry>, Option<PbzxError>) {
         let mut entries = Vec::new();
+        let error = self.list_into(&mut entries).map_err(truncation_error).err();
+        (entries, error)
+    }
+
+    /// Parse entries into `entries` up to the trailer.
+    fn list_into(&mut self, entries: &mut Vec<FileEntry>) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
 
         // Detect format from first header
-        let format = match self.peek_format()? {
+        let mut format = match self.peek_format()? {
             Some(f) => f,
-            None => return Ok(entries),
+            None => return Ok(()),
         };
 
-        while let Some(header) = self.read_header()? {
+        loop {
+            let header = self.read_header()?.ok_or_else(missing_trailer)?;
             if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
                 break;
             }
 
             // Read symlink target if applicable
             let link_target = if header.is_symlink() && header.filesize > 0 {
-                let data = match format {
-                    CpioFormat::Odc => self.read_data_odc(header.filesize as u64)?,
-                    _ => self.read_data_newc(header.filesize as u64)?,
-                };
+                let data = self.read_data(format, header.filesize as u64)?;
                 Some(
                     String::from_utf8(data)
                         .map_err(|e| PbzxError::InvalidCpio(format!("Invalid symlink target: {}", e)))?,
                 )
             } else {
-                match format {
-                    CpioFormat::Odc => self.skip_data_odc(header.filesize as u64)?,
-                    _ => self.skip_data_newc(header.filesize as u64)?,
-                }
+                self.skip_data(format, header.filesize as u64)?;
                 None
             };
 
@@ -367,9 +380,40 @@ impl<R: Read + Seek> CpioReader<R> {
                 is_dir: header.is_directory(),
                 is_symlink: header.is_symlink(),
                 link_target,
+                checksum: (format == CpioFormat::Crc).then_some(header.check),
+                devmajor: header.devmajor,
+                devminor: header.devminor,
+                rdevmajor: header.rdevmajor,
+                rdevminor: header.rdevminor,
             });
         }
 
+        Ok(())
+    }
+
+    /// Read every header with its data, up to the trailer.
+    fn read_raw_entries(&mut self) -> Result<Vec<(CpioHeader, Vec<u8>)>> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+
+        let mut format = match self.peek_format()? {
+            Some(f) => f,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut entries = Vec::new();
+        loop {
+            let header = self.read_header()?.ok_or_else(missing_trailer)?;
+            if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
+                break;
+            }
+            let size = header.filesize as u64;
+            let data = self.read_data(format, size)?;
+            entries.push((header, data));
+        }
         Ok(entries)
     }
 
@@ -379,13 +423,16 @@ impl<R: Read + Seek> CpioReader<R> {
         self.position = 0;
 
         // Detect format
-        let format = match self.peek_format()? {
+        let mut format = match self.peek_format()? {
             Some(f) => f,
             None => return Err(PbzxError::FileNotFound(path.to_string())),
         };
 
         while let Some(header) = self.read_header()? {
             if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
                 break;
             }
 
@@ -396,63 +443,212 @@ impl<R: Read + Seek> CpioReader<R> {
                         path
                     )));
                 }
-                return match format {
-                    CpioFormat::Odc => self.read_data_odc(header.filesize as u64),
-                    _ => self.read_data_newc(header.filesize as u64),
-                };
+                return self.read_data(format, header.filesize as u64);
             }
 
-            match format {
-                CpioFormat::Odc => self.skip_data_odc(header.filesize as u64)?,
-                _ => self.skip_data_newc(header.filesize as u64)?,
-            }
+            self.skip_data(format, header.filesize as u64)?;
         }
 
         Err(PbzxError::FileNotFound(path.to_string()))
     }
 
+    /// Stream a single file to `dest` without buffering it in memory.
+    ///
+    /// The host file is created (or truncated) and, on Unix, given the
+    /// entry's permission bits.
+    pub fn extract_file_to_path<P: AsRef<Path>>(&mut self, archive_path: &str, dest: P) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+
+        let mut format = match self.peek_format()? {
+            Some(f) => f,
+            None => return Err(PbzxError::FileNotFound(archive_path.to_string())),
+        };
+
+        while let Some(header) = self.read_header()? {
+            if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
+                break;
+            }
+
+            if header.name == archive_path {
+                if header.is_directory() {
+                    return Err(PbzxError::InvalidPath(format!(
+                        "'{}' is a directory",
+                        archive_path
+                    )));
+                }
+                let dest = dest.as_ref();
+                let mut file = std::io::BufWriter::new(std::fs::File::create(dest)?);
+                self.copy_data(format, header.filesize as u64, &mut file)?;
+                file.flush()?;
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let perms = std::fs::Permissions::from_mode(header.mode & 0o7777);
+                    std::fs::set_permissions(dest, perms)?;
+                }
+                return Ok(());
+            }
+
+            self.skip_data(format, header.filesize as u64)?;
+        }
+
+        Err(PbzxError::FileNotFound(archive_path.to_string()))
+    }
+
+    /// Check whether an entry with the given path exists.
+    ///
+    /// Only headers are parsed; file data is skipped, and scanning stops at
+    /// the first match.
+    pub fn contains(&mut self, path: &str) -> Result<bool> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+
+        let mut format = match self.peek_format()? {
+            Some(f) => f,
+            None => return Ok(false),
+        };
+
+        while let Some(header) = self.read_header()? {
+            if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
+                break;
+            }
+
+            if header.name == path {
+                return Ok(true);
+            }
+
+            self.skip_data(format, header.filesize as u64)?;
+        }
+
+        Ok(false)
+    }
+
+    /// Read every entry header, in archive order, without the file data.
+    ///
+    /// Unlike [`list`](Self::list), this keeps all raw header fields (inode,
+    /// link count, device numbers, checksum). The trailer is not included.
+    pub fn headers(&mut self) -> Result<Vec<CpioHeader>> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+
+        let mut format = match self.peek_format()? {
+            Some(f) => f,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut headers = Vec::new();
+
+        while let Some(header) = self.read_header()? {
+            if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
+                break;
+            }
+
+            self.skip_data(format, header.filesize as u64)?;
+            headers.push(header);
+        }
+
+        Ok(headers)
+    }
+
     /// Extract all files to a directory.
+    ///
+    /// On Unix, explicit directory entries get their archived mtime once
+    /// every entry is written, since writing children would bump it.
     pub fn extract_all<P: AsRef<Path>>(&mut self, dest: P) -> Result<Vec<PathBuf>> {
-        let dest = dest.as_ref();
+        self.extract_all_with_options(dest, &ExtractOptions::default())
+    }
+
+    /// Extract files to a directory, skipping entries excluded by `options`.
+    ///
+    /// Excluded entries are not written and their data is skipped. Fails
+    /// with `InvalidCpio` if the archive ends before its `TRAILER!!!` entry;
+    /// files extracted up to that point are left in place.
+    pub fn extract_all_with_options<P: AsRef<Path>>(
+        &mut self,
+        dest: P,
+        options: &ExtractOptions,
+    ) -> Result<Vec<PathBuf>> {
+        let mut extracted = Vec::new();
+        self.extract_into(dest.as_ref(), options, &mut extracted)
+            .map_err(truncation_error)?;
+        Ok(extracted)
+    }
+
+    /// Extract entries up to the trailer, recording written paths in `extracted`.
+    fn extract_into(
+        &mut self,
+        dest: &Path,
+        options: &ExtractOptions,
+        extracted: &mut Vec<PathBuf>,
+    ) -> Result<()> {
         std::fs::create_dir_all(dest)?;
 
         self.reader.seek(SeekFrom::Start(0))?;
         self.position = 0;
 
         // Detect format
-        let format = match self.peek_format()? {
+        let mut format = match self.peek_format()? {
             Some(f) => f,
-            None => return Ok(Vec::new()),
+            None => return Ok(()),
         };
 
-        let mut extracted = Vec::new();
+        // Explicit directory entries whose metadata is applied at the end:
+        // (path, mode if it should be set, mtime)
+        let mut directories = Vec::new();
 
-        while let Some(header) = self.read_header()? {
+        loop {
+            let header = self.read_header()?.ok_or_else(missing_trailer)?;
             if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
                 break;
             }
 
+            if options.is_excluded(&header.name) {
+                self.skip_data(format, header.filesize as u64)?;
+                continue;
+            }
+
             // Sanitize path to prevent directory traversal
             let clean_path = sanitize_path(&header.name)?;
             let full_path = dest.join(&clean_path);
 
             // Create parent directories as needed
             if let Some(parent) = full_path.parent() {
-                std::fs::create_dir_all(parent)?;
+                if !options.preserve_order {
+                    std::fs::create_dir_all(parent)?;
+                } else if !parent.is_dir() {
+                    return Err(PbzxError::InvalidCpio(format!(
+                        "{} precedes its parent directory entry",
+                        header.name
+                    )));
+                }
             }
 
             if header.is_directory() {
-                std::fs::create_dir_all(&full_path)?;
-                match format {
-                    CpioFormat::Odc => self.skip_data_odc(header.filesize as u64)?,
-                    _ => self.skip_data_newc(header.filesize as u64)?,
+                if !options.preserve_order {
+                    std::fs::create_dir_all(&full_path)?;
+                } else if !full_path.is_dir() {
+                    std::fs::create_dir(&full_path)?;
                 }
+                let mode = options.preserve_order.then_some(header.mode);
+                directories.push((full_path.clone(), mode, header.mtime));
+                self.skip_data(format, header.filesize as u64)?;
             } else if header.is_symlink() {
                 let target = if header.filesize > 0 {
-                    let data = match format {
-                        CpioFormat::Odc => self.read_data_odc(header.filesize as u64)?,
-                        _ => self.read_data_newc(header.filesize as u64)?,
-                    };
+                    let data = self.read_data(format, header.filesize as u64)?;
                     String::from_utf8(data)
                         .map_err(|e| PbzxError::InvalidCpio(format!("Invalid symlink: {}", e)))?
                 } else {
@@ -472,12 +668,13 @@ impl<R: Read + Seek> CpioReader<R> {
                     file.write_all(target.as_bytes())?;
                 }
             } else if header.is_file() {
-                let data = match format {
-                    CpioFormat::Odc => self.read_data_odc(header.filesize as u64)?,
-                    _ => self.read_data_newc(header.filesize as u64)?,
-                };
+                let data = self.read_data(format, header.filesize as u64)?;
                 let mut file = std::fs::File::create(&full_path)?;
-                file.write_all(&data)?;
+                if options.sparse {
+                    write_sparse(&mut file, &data)?;
+                } else {
+                    file.write_all(&data)?;
+                }
 
                 // Set permissions on Unix
                 #[cfg(unix)]
@@ -486,19 +683,95 @@ impl<R: Read + Seek> CpioReader<R> {
                     let perms = std::fs::Permissions::from_mode(header.mode & 0o7777);
                     std::fs::set_permissions(&full_path, perms)?;
                 }
+            } else if cfg!(unix) && options.create_device_nodes && header.is_device() {
+                self.skip_data(format, header.filesize as u64)?;
+                #[cfg(unix)]
+                {
+                    let _ = std::fs::remove_file(&full_path);
+                    make_device_node(&full_path, header.mode, header.rdevmajor, header.rdevminor)?;
+                }
             } else {
                 // Skip special files (devices, fifos, etc.)
-                match format {
-                    CpioFormat::Odc => self.skip_data_odc(header.filesize as u64)?,
-                    _ => self.skip_data_newc(header.filesize as u64)?,
-                }
+                self.skip_data(format, header.filesize as u64)?;
                 continue;
             }
 
             extracted.push(full_path);
         }
 
-        Ok(extracted)
+        // Set mtimes before modes: a directory without read permission
+        // cannot be opened to change its times
+        #[cfg(unix)]
+        for (path, mode, mtime) in directories.iter().rev() {
+            use std::os::unix::fs::PermissionsExt;
+            let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(*mtime as u64);
+            std::fs::File::open(path)?.set_modified(mtime)?;
+            if let Some(mode) = mode {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recreate the directory tree with empty placeholder files.
+    ///
+    /// Directories are created and every regular file becomes a zero-byte
+    /// file with its archived permissions; file data is skipped without being
+    /// read into memory. Symlinks and special files are ignored.
+    ///
+    /// Returns the number of directories and files created.
+    pub fn extract_tree_structure<P: AsRef<Path>>(&mut self, dest: P) -> Result<usize> {
+        let dest = dest.as_ref();
+        std::fs::create_dir_all(dest)?;
+
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+
+        let mut format = match self.peek_format()? {
+            Some(f) => f,
+            None => return Ok(0),
+        };
+
+        let mut created = 0;
+
+        while let Some(header) = self.read_header()? {
+            if header.is_trailer() {
+                if self.advance_after_trailer(&mut format)? {
+                    continue;
+                }
+                break;
+            }
+
+            self.skip_data(format, header.filesize as u64)?;
+
+            if !header.is_directory() && !header.is_file() {
+                continue;
+            }
+
+            let clean_path = sanitize_path(&header.name)?;
+            let full_path = dest.join(&clean_path);
+
+            if header.is_directory() {
+                std::fs::create_dir_all(&full_path)?;
+            } else {
+                if let Some(parent) = full_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::File::create(&full_path)?;
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let perms = std::fs::Permissions::from_mode(header.mode & 0o7777);
+                    std::fs::set_permissions(&full_path, perms)?;
+                }
+            }
+
+            created += 1;
+        }
+
+        Ok(created)
     }
 }
 
@@ -519,14 +792,23 @@ impl<'a, R: Read> Iterator for CpioEntries<'a, R> {
         match self.reader.read_header() {
             Ok(Some(header)) => {
                 if header.is_trailer() {
-                    self.finished = true;
-                    return None;
+                    return match self.reader.next_archive() {
+                        Ok(Some(_)) => self.next(),
+                        Ok(None) => {
+                            self.finished = true;
+                            None
+                        }
+                        Err(e) => {
+                            self.finished = true;
+                            Some(Err(e))
+                        }
+                    };
                 }
 
-                // For streaming iterator, we read data with odc-style (no padding)
-                // This is a simplification - in practice you'd need to track format
                 let data = if header.filesize > 0 {
-                    match self.reader.read_data_odc(header.filesize as u64) {
+                    let size = header.filesize as u64;
+                    let format = self.reader.last_format.unwrap_or(CpioFormat::Odc);
+                    match self.reader.read_data(format, size) {
                         Ok(d) => Some(d),
                         Err(e) => return Some(Err(e)),
                     }
@@ -543,6 +825,10 @@ impl<'a, R: Read> Iterator for CpioEntries<'a, R> {
                     gid: header.gid,
                     is_dir: header.is_directory(),
                     is_symlink: header.is_symlink(),
+                    devmajor: header.devmajor,
+                    devminor: header.devminor,
+                    rdevmajor: header.rdevmajor,
+                    rdevminor: header.rdevminor,
                     data,
                 }))
             }
@@ -577,6 +863,14 @@ pub struct CpioEntry {
     pub is_dir: bool,
     /// Whether this is a symlink
     pub is_symlink: bool,
+    /// Major number of the device holding the file
+    pub devmajor: u32,
+    /// Minor number of the device holding the file
+    pub devminor: u32,
+    /// Major number of the device a device node refers to
+    pub rdevmajor: u32,
+    /// Minor number of the device a device node refers to
+    pub rdevminor: u32,
     /// File data (or symlink target if is_symlink)
     pub data: Option<Vec<u8>>,
 }
@@ -588,30 +882,188 @@ impl CpioEntry {
     }
 }
 
+/// Re-pack a CPIO archive in another header format.
+///
+/// Every entry keeps its name, mode, ownership, mtime, device numbers and
+/// data. Regular files sharing an inode are re-emitted as hard links of
+/// the first path seen, carrying the data wherever the source stored it
+/// (newc puts it on the last link, odc on each one). Inode numbers are
+/// renumbered and crc checksums recomputed for the target format.
+///
+/// # Example
+///
+/// ```no_run
+/// use pbzx::cpio::convert;
+/// use pbzx::format::CpioFormat;
+///
+/// let odc = std::fs::read("payload.cpio").unwrap();
+/// let newc = convert(&odc, CpioFormat::Newc).unwrap();
+/// ```
+pub fn convert(input: &[u8], to: CpioFormat) -> Result<Vec<u8>> {
+    let entries = CpioReader::new(std::io::Cursor::new(input))
+        .read_raw_entries()
+        .map_err(truncation_error)?;
+
+    let link_key = |header: &CpioHeader| {
+        (header.is_file() && header.nlink > 1)
+            .then_some((header.devmajor, header.devminor, header.ino))
+    };
+
+    // For each hard-linked inode, the entry holding the most data
+    let mut link_data: HashMap<(u32, u32, u32), usize> = HashMap::new();
+    for (i, (header, data)) in entries.iter().enumerate() {
+        if let Some(key) = link_key(header) {
+            let best = link_data.entry(key).or_insert(i);
+            if data.len() > entries[*best].1.len() {
+                *best = i;
+            }
+        }
+    }
+
+    let mut builder = CpioBuilder::with_format(to);
+    let mut first_links: HashMap<(u32, u32, u32), &str> = HashMap::new();
+    for (header, data) in &entries {
+        match link_key(header) {
+            Some(key) => match first_links.get(&key) {
+                Some(target) => builder.add_hardlink(&header.name, target)?,
+                None => {
+                    builder.add_header(header, &entries[link_data[&key]].1);
+                    first_links.insert(key, &header.name);
+                }
+            },
+            None => builder.add_header(header, data),
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// Error for an archive that ends before its `TRAILER!!!` entry.
+fn missing_trailer() -> PbzxError {
+    PbzxError::InvalidCpio("missing trailer / truncated archive".into())
+}
+
+/// Report running out of data mid-entry as a truncated archive rather than
+/// a bare `UnexpectedEof`.
+fn truncation_error(e: PbzxError) -> PbzxError {
+    match e {
+        PbzxError::Io(ref io) if io.kind() == std::io::ErrorKind::UnexpectedEof => missing_trailer(),
+        e => e,
+    }
+}
+
+/// Create a character or block device node at `path`.
+#[cfg(unix)]
+fn make_device_node(path: &Path, mode: u32, rdevmajor: u32, rdevminor: u32) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| PbzxError::InvalidPath(format!("{} contains a NUL byte", path.display())))?;
+    let dev = libc::makedev(rdevmajor as _, rdevminor as _);
+    // SAFETY: `c_path` is a valid NUL-terminated string that outlives the call
+    let rc = unsafe { libc::mknod(c_path.as_ptr(), mode as libc::mode_t, dev) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Write `data` to a freshly created file, leaving all-zero blocks as holes.
+fn write_sparse(file: &mut std::fs::File, data: &[u8]) -> Result<()> {
+    for block in data.chunks(SPARSE_BLOCK_SIZE) {
+        if block.iter().all(|&b| b == 0) {
+            file.seek(SeekFrom::Current(block.len() as i64))?;
+        } else {
+            file.write_all(block)?;
+        }
+    }
+    // A trailing hole is only a seek; set the length to materialize it
+    file.set_len(data.len() as u64)?;
+    Ok(())
+}
+
 /// Sanitize a path to prevent directory traversal attacks.
+///
+/// `/` is the only separator in a stored path. On Windows each component is
+/// additionally escaped (see [`windows_component`]) so that backslashes and
+/// reserved device names cannot redirect the write.
 fn sanitize_path(path: &str) -> Result<PathBuf> {
     let path = path.trim_start_matches('/');
 
-    // Check for path traversal
-    for component in Path::new(path).components() {
+    let mut clean = PathBuf::new();
+    for component in path.split('/') {
         match component {
-            std::path::Component::ParentDir => {
+            "" | "." => {}
+            ".." => {
                 return Err(PbzxError::InvalidPath(format!(
                     "Path traversal detected: {}",
                     path
                 )));
             }
-            std::path::Component::Normal(_) | std::path::Component::CurDir => {}
-            _ => {
-                return Err(PbzxError::InvalidPath(format!(
-                    "Invalid path component: {}",
-                    path
-                )));
-            }
+            #[cfg(windows)]
+            name => clean.push(windows_component(name)),
+            #[cfg(not(windows))]
+            name => clean.push(name),
         }
     }
 
-    Ok(PathBuf::from(path))
+    Ok(clean)
+}
+
+/// Windows device names that cannot be used as file names, with or without
+/// an extension.
+#[cfg(windows)]
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make one path component safe to create on Windows.
+///
+/// Characters Windows treats as separators or rejects (`\`, `:`, `<`, `>`,
+/// `"`, `|`, `?`, `*`) become `_`, and a reserved device name gets `_`
+/// appended to its stem (`CON` -> `CON_`, `nul.txt` -> `nul_.txt`).
+#[cfg(windows)]
+fn windows_component(name: &str) -> String {
+    let mut escaped: String = name
+        .chars()
+        .map(|c| match c {
+            '\\' | ':' | '<' | '>' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let stem_len = escaped.find('.').unwrap_or(escaped.len());
+    let stem = escaped[..stem_len].trim_end_matches(' ');
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        escaped.insert(stem_len, '_');
+    }
+    escaped
+}
+
+/// Match a path against a glob: `*`/`?` stay within one component, `**`
+/// spans any number of components (including none).
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, path)
+                || path
+                    .iter()
+                    .position(|&c| c == b'/')
+                    .is_some_and(|slash| glob_match(pattern, &path[slash + 1..]))
+        }
+        [b'*', b'*'] => true,
+        [b'*', rest @ ..] => {
+            let component_end = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=component_end).any(|skip| glob_match(rest, &path[skip..]))
+        }
+        [b'?', rest @ ..] => matches!(path, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [p, rest @ ..] => matches!(path, [c, tail @ ..] if c == p && glob_match(rest, tail)),
+    }
 }
 
 #[cfg(test)]
@@ -625,4 +1077,513 @@ mod tests {
         assert!(sanitize_path("../traversal").is_err());
         assert!(sanitize_path("path/../traversal").is_err());
     }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_sanitize_path_windows() {
+        assert_eq!(sanitize_path("dir/CON").unwrap(), Path::new("dir").join("CON_"));
+        assert_eq!(sanitize_path("nul.txt").unwrap(), PathBuf::from("nul_.txt"));
+        assert_eq!(sanitize_path("Com1.tar.gz").unwrap(), PathBuf::from("Com1_.tar.gz"));
+        assert_eq!(sanitize_path("console").unwrap(), PathBuf::from("console"));
+
+        // A backslash is part of the name, never a separator
+        let clean = sanitize_path("a\\..\\..\\evil").unwrap();
+        assert_eq!(clean, PathBuf::from("a_.._.._evil"));
+        assert_eq!(clean.components().count(), 1);
+        assert_eq!(sanitize_path("C:/x").unwrap(), Path::new("C_").join("x"));
+    }
+
+    /// Append a binary-format entry, padding name and data to 2 bytes.
+    fn push_bin_entry(out: &mut Vec<u8>, big_endian: bool, name: &str, mode: u16, data: &[u8]) {
+        let namesize = name.len() as u16 + 1;
+        let size = data.len() as u32;
+        let fields = [
+            crate::format::CPIO_MAGIC_BIN, 0, 1, mode, 501, 20, 1, 0,
+            0x6000, 0x0000, namesize, (size >> 16) as u16, size as u16,
+        ];
+        for value in fields {
+            if big_endian {
+                out.extend_from_slice(&value.to_be_bytes());
+            } else {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        if !namesize.is_multiple_of(2) {
+            out.push(0);
+        }
+        out.extend_from_slice(data);
+        if !data.len().is_multiple_of(2) {
+            out.push(0);
+        }
+    }
+
+    #[test]
+    fn test_bin_format_archive() {
+        for big_endian in [false, true] {
+            let mut archive = Vec::new();
+            push_bin_entry(&mut archive, big_endian, "etc", 0o040755, b"");
+            push_bin_entry(&mut archive, big_endian, "etc/motd", 0o100644, b"hello");
+            push_bin_entry(&mut archive, big_endian, "etc/hosts", 0o100644, b"127.0.0.1");
+            push_bin_entry(&mut archive, big_endian, "TRAILER!!!", 0, b"");
+
+            let mut reader = CpioReader::new(std::io::Cursor::new(&archive));
+            let entries = reader.list().unwrap();
+            let names: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(names, ["etc", "etc/motd", "etc/hosts"]);
+            assert!(entries[0].is_dir);
+            assert_eq!(entries[1].size, 5);
+            assert_eq!(entries[1].uid, 501);
+            assert_eq!(entries[1].mtime, 0x6000_0000);
+
+            assert_eq!(reader.extract_file("etc/motd").unwrap(), b"hello");
+            assert_eq!(reader.extract_file("etc/hosts").unwrap(), b"127.0.0.1");
+            assert!(reader.contains("etc/hosts").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_list_crc_checksum() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_file("file.txt", b"abc", 0o644);
+        let newc = builder.finish();
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&newc));
+        assert_eq!(reader.list().unwrap()[0].checksum, None);
+
+        // Turn the first header into a crc header; its check field is the byte sum
+        let sum: u32 = b"abc".iter().map(|&b| b as u32).sum();
+        let mut crc = newc.clone();
+        crc[..6].copy_from_slice(crate::format::CPIO_MAGIC_CRC);
+        crc[102..110].copy_from_slice(format!("{:08X}", sum).as_bytes());
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&crc));
+        let entries = reader.list().unwrap();
+        assert_eq!(entries[0].path, "file.txt");
+        assert_eq!(entries[0].checksum, Some(sum));
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_directory("usr", 0o755);
+        builder.add_file("usr/a.txt", b"first", 0o644);
+        builder.add_file("usr/b.txt", b"second", 0o600);
+        let mut cpio_data = builder.finish();
+
+        // Give the directory a link count of 2 (newc nlink field at offset 38)
+        cpio_data[38..46].copy_from_slice(b"00000002");
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let headers = reader.headers().unwrap();
+        let names: Vec<_> = headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["usr", "usr/a.txt", "usr/b.txt"]);
+        assert_eq!(headers.iter().map(|h| h.ino).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(headers.iter().map(|h| h.nlink).collect::<Vec<_>>(), [2, 1, 1]);
+        assert_eq!(headers[2].filesize, 6);
+        assert_eq!(headers[2].mode & 0o7777, 0o600);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_directory("usr", 0o755);
+        builder.add_file("usr/bin/tool", b"binary", 0o755);
+        let cpio_data = builder.finish();
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        assert!(reader.contains("usr/bin/tool").unwrap());
+        assert!(reader.contains("usr").unwrap());
+        assert!(!reader.contains("usr/bin/missing").unwrap());
+        assert!(!reader.contains("TRAILER!!!").unwrap());
+    }
+
+    #[test]
+    fn test_extract_tree_structure() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_directory("usr", 0o755);
+        builder.add_file("usr/bin/tool", b"binary contents", 0o755);
+        builder.add_file("usr/share/readme.txt", b"read me", 0o644);
+        builder.add_symlink("usr/bin/alias", "tool", 0o755);
+        let cpio_data = builder.finish();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        assert_eq!(reader.extract_tree_structure(dest.path()).unwrap(), 3);
+
+        assert!(dest.path().join("usr").is_dir());
+        assert!(dest.path().join("usr/share").is_dir());
+        for file in ["usr/bin/tool", "usr/share/readme.txt"] {
+            let meta = std::fs::metadata(dest.path().join(file)).unwrap();
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 0);
+        }
+        assert!(!dest.path().join("usr/bin/alias").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dest.path().join("usr/bin/tool"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let m = |p: &str, t: &str| glob_match(p.as_bytes(), t.as_bytes());
+        assert!(m("*.txt", "a.txt"));
+        assert!(!m("*.txt", "dir/a.txt"));
+        assert!(m("**/*.txt", "a.txt"));
+        assert!(m("**/*.txt", "dir/sub/a.txt"));
+        assert!(m("usr/**", "usr/bin/tool"));
+        assert!(!m("usr/**", "usr"));
+        assert!(m("usr/?in/*", "usr/bin/tool"));
+        assert!(!m("usr/?in", "usr/bin/tool"));
+    }
+
+    #[test]
+    fn test_extract_all_with_exclude() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_directory("./App", 0o755);
+        builder.add_file("./App/main", b"binary", 0o755);
+        builder.add_directory("./App/main.dSYM", 0o755);
+        builder.add_file("./App/main.dSYM/Contents/Info.plist", b"plist", 0o644);
+        builder.add_file("./App/notes.txt", b"notes", 0o644);
+        let cpio_data = builder.finish();
+
+        let options = ExtractOptions::new()
+            .exclude(vec!["**/*.dSYM/**".to_string(), "**/*.txt".to_string()]);
+        assert!(options.is_excluded("./App/main.dSYM"));
+        assert!(!options.is_excluded("./App/main"));
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let extracted = reader.extract_all_with_options(dest.path(), &options).unwrap();
+
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(std::fs::read(dest.path().join("App/main")).unwrap(), b"binary");
+        assert!(!dest.path().join("App/main.dSYM").exists());
+        assert!(!dest.path().join("App/notes.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_sparse() {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut data = vec![0u8; 4 * 1024 * 1024];
+        data[SPARSE_BLOCK_SIZE * 100..SPARSE_BLOCK_SIZE * 100 + 5].copy_from_slice(b"hello");
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_file("./disk.img", &data, 0o644);
+        let cpio_data = builder.finish();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        reader
+            .extract_all_with_options(dest.path(), &ExtractOptions::new().sparse(true))
+            .unwrap();
+
+        let path = dest.path().join("disk.img");
+        let meta = std::fs::metadata(&path).unwrap();
+        assert_eq!(meta.len(), data.len() as u64);
+        assert!(meta.blocks() * 512 < meta.len(), "{} blocks allocated", meta.blocks());
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn test_device_entry_fields() {
+        let null = CpioHeader {
+            ino: 7,
+            mode: 0o020666,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            mtime: 0,
+            filesize: 0,
+            devmajor: 8,
+            devminor: 1,
+            rdevmajor: 1,
+            rdevminor: 3,
+            namesize: 0,
+            check: 0,
+            name: "./dev/null".to_string(),
+        };
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_header(&null, &[]);
+        let cpio_data = builder.finish();
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let entries = reader.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!((entry.devmajor, entry.devminor), (8, 1));
+        assert_eq!((entry.rdevmajor, entry.rdevminor), (1, 3));
+        assert!(reader.headers().unwrap()[0].is_device());
+
+        let mut streaming = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let entry = streaming.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!((entry.rdevmajor, entry.rdevminor), (1, 3));
+
+        // Without the option the device is skipped
+        let dest = tempfile::tempdir().unwrap();
+        assert!(reader.extract_all(dest.path()).unwrap().is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+            let options = ExtractOptions::new().create_device_nodes(true);
+            match reader.extract_all_with_options(dest.path(), &options) {
+                Ok(extracted) => {
+                    assert_eq!(extracted.len(), 1);
+                    let meta = std::fs::symlink_metadata(dest.path().join("dev/null")).unwrap();
+                    assert!(meta.file_type().is_char_device());
+                    assert_eq!(meta.rdev(), libc::makedev(1, 3) as u64);
+                }
+                // Not running as root
+                Err(PbzxError::Io(e)) if e.kind() == std::io::ErrorKind::PermissionDenied => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_file_to_path() {
+        let big: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+        for format in [CpioFormat::Newc, CpioFormat::Odc] {
+            let mut builder = crate::CpioBuilder::with_format(format);
+            builder.add_file("./small", b"abc", 0o600);
+            builder.add_file("./bin/tool", &big, 0o755);
+            let cpio_data = builder.finish();
+
+            let dest = tempfile::tempdir().unwrap();
+            let out = dest.path().join("tool");
+            let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+            reader.extract_file_to_path("./bin/tool", &out).unwrap();
+            assert_eq!(std::fs::read(&out).unwrap(), big);
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_eq!(std::fs::metadata(&out).unwrap().permissions().mode() & 0o7777, 0o755);
+            }
+
+            assert!(matches!(
+                reader.extract_file_to_path("./missing", dest.path().join("missing")),
+                Err(PbzxError::FileNotFound(_))
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_all_sets_directory_mtime() {
+        let dir = |name: &str, mtime: u32| CpioHeader {
+            ino: 0,
+            mode: 0o040755,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            mtime,
+            filesize: 0,
+            devmajor: 0,
+            devminor: 0,
+            rdevmajor: 0,
+            rdevminor: 0,
+            namesize: 0,
+            check: 0,
+            name: name.to_string(),
+        };
+        // Children listed before and after their directory entries
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_file("./usr/share/doc/README", b"docs", 0o644);
+        builder.add_header(&dir("./usr", 1_400_000_000), &[]);
+        builder.add_header(&dir("./usr/share", 1_500_000_000), &[]);
+        builder.add_file("./usr/share/LICENSE", b"MIT", 0o644);
+        let cpio_data = builder.finish();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        reader.extract_all(dest.path()).unwrap();
+
+        let mtime = |path: &str| {
+            let meta = std::fs::metadata(dest.path().join(path)).unwrap();
+            meta.modified().unwrap().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+        };
+        assert_eq!(mtime("usr"), 1_400_000_000);
+        assert_eq!(mtime("usr/share"), 1_500_000_000);
+        // Implicit parents are left alone
+        assert!(mtime("usr/share/doc") > 1_500_000_000);
+        assert_eq!(std::fs::read(dest.path().join("usr/share/LICENSE")).unwrap(), b"MIT");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_preserve_order() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = |name: &str, mode: u32, mtime: u32| CpioHeader {
+            ino: 0,
+            mode: 0o040000 | mode,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            mtime,
+            filesize: 0,
+            devmajor: 0,
+            devminor: 0,
+            rdevmajor: 0,
+            rdevminor: 0,
+            namesize: 0,
+            check: 0,
+            name: name.to_string(),
+        };
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_header(&dir("./etc", 0o750, 1_600_000_000), &[]);
+        builder.add_header(&dir("./etc/ssh", 0o700, 1_500_000_000), &[]);
+        builder.add_file("./etc/ssh/sshd_config", b"Port 22", 0o644);
+        builder.add_file("./etc/hosts", b"127.0.0.1", 0o644);
+        let cpio_data = builder.finish();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let options = ExtractOptions::new().preserve_order(true);
+        assert_eq!(reader.extract_all_with_options(dest.path(), &options).unwrap().len(), 4);
+
+        for (path, mode, mtime) in [("etc", 0o750, 1_600_000_000), ("etc/ssh", 0o700, 1_500_000_000)] {
+            let meta = std::fs::metadata(dest.path().join(path)).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o7777, mode, "{}", path);
+            let modified = meta.modified().unwrap().duration_since(std::time::UNIX_EPOCH).unwrap();
+            assert_eq!(modified.as_secs(), mtime, "{}", path);
+        }
+        assert_eq!(std::fs::read(dest.path().join("etc/hosts")).unwrap(), b"127.0.0.1");
+
+        // A file listed before its directory is rejected rather than reordered
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_file("./var/log", b"", 0o644);
+        builder.add_directory("./var", 0o755);
+        let out_of_order = builder.finish();
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = CpioReader::new(std::io::Cursor::new(&out_of_order));
+        assert!(matches!(
+            reader.extract_all_with_options(dest.path(), &options),
+            Err(PbzxError::InvalidCpio(msg)) if msg.contains("./var/log")
+        ));
+        let mut reader = CpioReader::new(std::io::Cursor::new(&out_of_order));
+        assert!(reader.extract_all(dest.path()).is_ok());
+    }
+
+    #[test]
+    fn test_missing_trailer() {
+        let mut builder = crate::CpioBuilder::new();
+        builder.add_directory("./etc", 0o755);
+        builder.add_file("./etc/motd", b"welcome", 0o644);
+        let cpio_data = builder.finish();
+        // Drop the trailer entry: 110-byte header + "TRAILER!!!\0", padded to 124
+        let no_trailer = &cpio_data[..cpio_data.len() - 124];
+        // Cut off in the middle of the second entry's data
+        let cut = &cpio_data[..cpio_data.len() - 124 - 4];
+
+        for data in [no_trailer, cut] {
+            let mut reader = CpioReader::new(std::io::Cursor::new(data));
+            assert!(matches!(
+                reader.list(),
+                Err(PbzxError::InvalidCpio(msg)) if msg.contains("missing trailer")
+            ));
+
+            let dest = tempfile::tempdir().unwrap();
+            assert!(matches!(reader.extract_all(dest.path()), Err(PbzxError::InvalidCpio(_))));
+        }
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(no_trailer));
+        let (entries, error) = reader.list_lenient();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].path, "./etc/motd");
+        assert!(matches!(error, Some(PbzxError::InvalidCpio(_))));
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(cut));
+        let (entries, error) = reader.list_lenient();
+        assert_eq!(entries.len(), 1);
+        assert!(error.is_some());
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&cpio_data));
+        let (entries, error) = reader.list_lenient();
+        assert_eq!(entries.len(), 2);
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn test_convert_odc_to_newc() {
+        let mut builder = crate::CpioBuilder::with_format(CpioFormat::Odc);
+        builder.add_directory("./usr", 0o755);
+        builder.add_file("./usr/tool", b"#!/bin/sh\necho hi\n", 0o755);
+        builder.add_symlink("./usr/alias", "tool", 0o777);
+        builder.add_hardlink("./usr/tool2", "./usr/tool").unwrap();
+        builder.add_file("./usr/empty", b"", 0o600);
+        let odc = builder.finish();
+        assert_eq!(&odc[..6], crate::format::CPIO_MAGIC_ODC);
+        assert!(matches!(
+            crate::CpioBuilder::new().add_hardlink("./a", "./missing"),
+            Err(PbzxError::FileNotFound(_))
+        ));
+
+        let newc = convert(&odc, CpioFormat::Newc).unwrap();
+        assert_eq!(&newc[..6], crate::format::CPIO_MAGIC_NEWC);
+
+        let summarize = |data: &[u8]| {
+            let mut reader = CpioReader::new(std::io::Cursor::new(data));
+            reader
+                .list()
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.path, e.mode, e.size, e.link_target))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summarize(&odc), summarize(&newc));
+
+        // The hard link still shares the file's inode and link count
+        let mut reader = CpioReader::new(std::io::Cursor::new(&newc));
+        let headers = reader.headers().unwrap();
+        assert_eq!(headers[1].ino, headers[3].ino);
+        assert_eq!((headers[1].nlink, headers[3].nlink), (2, 2));
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&newc));
+        assert_eq!(reader.extract_file("./usr/tool").unwrap(), b"#!/bin/sh\necho hi\n");
+
+        // And back again, byte for byte
+        assert_eq!(convert(&newc, CpioFormat::Odc).unwrap(), odc);
+    }
+
+    #[test]
+    fn test_lenient_multi() {
+        let mut first = crate::CpioBuilder::new();
+        first.add_directory("usr", 0o755);
+        first.add_file("usr/one", b"first archive", 0o644);
+        let mut data = first.finish();
+        // Trailers are commonly padded out to a 512-byte block
+        data.resize(data.len().next_multiple_of(512), 0);
+
+        let mut second = crate::CpioBuilder::with_format(CpioFormat::Odc);
+        second.add_file("usr/two", b"second archive", 0o600);
+        data.extend(second.finish());
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&data));
+        let paths: Vec<String> = reader.list().unwrap().into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, ["usr", "usr/one"]);
+
+        let mut reader = CpioReader::new(std::io::Cursor::new(&data)).lenient_multi(true);
+        let paths: Vec<String> = reader.list().unwrap().into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, ["usr", "usr/one", "usr/two"]);
+        assert_eq!(reader.extract_file("usr/two").unwrap(), b"second archive");
+        assert!(reader.contains("usr/one").unwrap());
+
+        // Trailing garbage after the last trailer is ignored
+        data.extend_from_slice(b"not a cpio header");
+        let mut reader = CpioReader::new(std::io::Cursor::new(&data)).lenient_multi(true);
+        assert_eq!(reader.headers().unwrap().len(), 3);
+    }
 }
@@ -1,6 +1,6 @@
 //! Error types for PBZX operations.
 
-use std::io;
+use alloc::string::String;
 use thiserror::Error;
 
 /// Errors that can occur during PBZX operations.
@@ -11,8 +11,9 @@ pub enum PbzxError {
     InvalidMagic([u8; 4]),
 
     /// I/O error during read/write operations
+    #[cfg(feature = "std")]
     #[error("I/O error: {0}")]
-    Io(#[from] io::Error),
+    Io(#[from] std::io::Error),
 
     /// XZ/LZMA decompression error
     #[error("Decompression error: {0}")]
@@ -48,4 +49,4 @@ pub enum PbzxError {
 }
 
 /// Result type alias for PBZX operations.
-pub type Result<T> = std::result::Result<T, PbzxError>;
+pub type Result<T> = core::result::Result<T, PbzxError>;
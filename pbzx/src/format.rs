@@ -31,6 +31,14 @@
 //! When compressed_size == uncompressed_size, the data is stored uncompressed.
 //!
 //! The decompressed output is typically a CPIO archive containing the payload files.
+//!
+//! Everything in this module only needs `alloc`, so it is available without
+//! the `std` feature.
+
+use alloc::{format, string::String, vec::Vec};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::error::{PbzxError, Result};
 
 /// PBZX magic bytes: "pbzx"
 pub const PBZX_MAGIC: [u8; 4] = [0x70, 0x62, 0x7a, 0x78];
@@ -38,6 +46,9 @@ pub const PBZX_MAGIC: [u8; 4] = [0x70, 0x62, 0x7a, 0x78];
 /// XZ magic bytes for validation
 pub const XZ_MAGIC: [u8; 6] = [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
 
+/// Size of an LZMA-alone header (properties + dictionary size + uncompressed size)
+pub const LZMA_ALONE_HEADER_SIZE: usize = 13;
+
 /// Size of the PBZX header (magic + flags)
 pub const HEADER_SIZE: usize = 12;
 
@@ -53,6 +64,10 @@ pub const CPIO_MAGIC_CRC: &[u8; 6] = b"070702";
 /// CPIO magic for "odc" format (ASCII, POSIX.1 portable)
 pub const CPIO_MAGIC_ODC: &[u8; 6] = b"070707";
 
+/// CPIO magic for the old binary format, stored as a 16-bit word in the
+/// byte order of the machine that wrote the archive
+pub const CPIO_MAGIC_BIN: u16 = 0o070707;
+
 /// CPIO trailer filename
 pub const CPIO_TRAILER: &str = "TRAILER!!!";
 
@@ -65,6 +80,57 @@ pub enum CpioFormat {
     Crc,
     /// POSIX.1 odc format (070707)
     Odc,
+    /// Old binary format (16-bit magic 0o070707)
+    Bin {
+        /// Whether the 16-bit fields are big-endian
+        big_endian: bool,
+    },
+}
+
+impl CpioFormat {
+    /// Detect the CPIO format from the 6 magic bytes.
+    ///
+    /// The binary format only uses the first 2 bytes; their order tells the
+    /// endianness of the rest of the header.
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&CPIO_MAGIC_BIN.to_le_bytes()) {
+            Some(CpioFormat::Bin { big_endian: false })
+        } else if magic.starts_with(&CPIO_MAGIC_BIN.to_be_bytes()) {
+            Some(CpioFormat::Bin { big_endian: true })
+        } else if magic == CPIO_MAGIC_NEWC {
+            Some(CpioFormat::Newc)
+        } else if magic == CPIO_MAGIC_CRC {
+            Some(CpioFormat::Crc)
+        } else if magic == CPIO_MAGIC_ODC {
+            Some(CpioFormat::Odc)
+        } else {
+            None
+        }
+    }
+
+    /// Size of the fixed header (including magic) for this format.
+    pub fn header_size(&self) -> usize {
+        match self {
+            CpioFormat::Newc | CpioFormat::Crc => CpioHeader::HEADER_SIZE,
+            CpioFormat::Odc => CpioHeader::ODC_HEADER_SIZE,
+            CpioFormat::Bin { .. } => CpioHeader::BIN_HEADER_SIZE,
+        }
+    }
+
+    /// Alignment of the filename and file data for this format.
+    pub(crate) fn alignment(&self) -> u64 {
+        match self {
+            CpioFormat::Newc | CpioFormat::Crc => 4,
+            CpioFormat::Odc => 1,
+            CpioFormat::Bin { .. } => 2,
+        }
+    }
+
+    /// Number of padding bytes needed after `len` bytes to reach alignment.
+    pub(crate) fn padding(&self, len: u64) -> u64 {
+        let align = self.alignment();
+        (align - (len % align)) % align
+    }
 }
 
 /// PBZX file header.
@@ -77,6 +143,50 @@ pub struct PbzxHeader {
 }
 
 impl PbzxHeader {
+    /// Parse a PBZX header from the first 12 bytes of an archive.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(PbzxError::UnexpectedEof(data.len() as u64));
+        }
+
+        let mut magic = [0u8; 4];
+        magic.copy_from_slice(&data[0..4]);
+        if magic != PBZX_MAGIC {
+            return Err(PbzxError::InvalidMagic(magic));
+        }
+
+        Ok(PbzxHeader {
+            magic,
+            flags: BigEndian::read_u64(&data[4..12]),
+        })
+    }
+
+    /// Fuzzing entry point: parse the header at the start of `data`, then
+    /// walk the chunk headers that follow it.
+    ///
+    /// Chunk payloads are skipped, not decompressed. The walk stops at an
+    /// end marker or at the end of `data`. A chunk whose payload runs past
+    /// the end yields [`PbzxError::UnexpectedEof`]. Never panics.
+    #[cfg(feature = "fuzzing")]
+    pub fn try_parse(data: &[u8]) -> Result<(Self, Vec<ChunkHeader>)> {
+        let header = Self::from_bytes(data)?;
+        let mut chunks = Vec::new();
+        let mut offset = HEADER_SIZE;
+        while offset < data.len() {
+            let chunk = ChunkHeader::from_bytes(&data[offset..])?;
+            if chunk.is_end_marker() {
+                break;
+            }
+            offset = usize::try_from(chunk.compressed_size)
+                .ok()
+                .and_then(|size| (offset + CHUNK_HEADER_SIZE).checked_add(size))
+                .filter(|&end| end <= data.len())
+                .ok_or(PbzxError::UnexpectedEof(data.len() as u64))?;
+            chunks.push(chunk);
+        }
+        Ok((header, chunks))
+    }
+
     /// Check if the header has valid magic bytes.
     pub fn is_valid(&self) -> bool {
         self.magic == PBZX_MAGIC
@@ -93,7 +203,23 @@ pub struct ChunkHeader {
 }
 
 impl ChunkHeader {
+    /// Parse a chunk header from 16 bytes.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < CHUNK_HEADER_SIZE {
+            return Err(PbzxError::UnexpectedEof(data.len() as u64));
+        }
+
+        Ok(ChunkHeader {
+            uncompressed_size: BigEndian::read_u64(&data[0..8]),
+            compressed_size: BigEndian::read_u64(&data[8..16]),
+        })
+    }
+
     /// Check if this chunk's data is stored without compression.
+    ///
+    /// Apple writes a chunk raw when XZ would not shrink it, recording the
+    /// same value in both size fields (the header's chunk size for a full
+    /// chunk). Such chunks carry no XZ magic.
     pub fn is_uncompressed(&self) -> bool {
         self.compressed_size == self.uncompressed_size
     }
@@ -104,6 +230,54 @@ impl ChunkHeader {
     }
 }
 
+/// Compression container used by a chunk's payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkCompression {
+    /// XZ container (`FD 37 7A 58 5A 00`)
+    Xz,
+    /// Raw LZMA-alone stream (legacy `.lzma`)
+    LzmaAlone,
+}
+
+impl ChunkCompression {
+    /// Detect the compression container from the first bytes of a chunk.
+    ///
+    /// LZMA-alone has no magic, so it is recognised by a plausible header:
+    /// a properties byte below 225, a dictionary size of the form `2^n` or
+    /// `2^n + 2^(n-1)` (or `u32::MAX`), and an uncompressed size that is
+    /// either unknown (`u64::MAX`) or below 256 GiB. These are the same
+    /// checks liblzma applies.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&XZ_MAGIC) {
+            return Some(ChunkCompression::Xz);
+        }
+
+        if data.len() < LZMA_ALONE_HEADER_SIZE || data[0] >= 9 * 5 * 5 {
+            return None;
+        }
+
+        let dict_size = LittleEndian::read_u32(&data[1..5]);
+        if dict_size != u32::MAX {
+            let mut d = dict_size.wrapping_sub(1);
+            d |= d >> 2;
+            d |= d >> 3;
+            d |= d >> 4;
+            d |= d >> 8;
+            d |= d >> 16;
+            if d.wrapping_add(1) != dict_size {
+                return None;
+            }
+        }
+
+        let uncompressed_size = LittleEndian::read_u64(&data[5..13]);
+        if uncompressed_size != u64::MAX && uncompressed_size >= 1 << 38 {
+            return None;
+        }
+
+        Some(ChunkCompression::LzmaAlone)
+    }
+}
+
 /// CPIO entry header (newc format).
 #[derive(Debug, Clone)]
 pub struct CpioHeader {
@@ -141,6 +315,225 @@ impl CpioHeader {
     /// Size of the fixed portion of a CPIO newc header (in bytes).
     pub const HEADER_SIZE: usize = 110;
 
+    /// Size of the fixed portion of a CPIO odc header (in bytes).
+    pub const ODC_HEADER_SIZE: usize = 76;
+
+    /// Size of a CPIO binary header (in bytes).
+    pub const BIN_HEADER_SIZE: usize = 26;
+
+    /// Parse a complete CPIO header (magic, fixed fields and filename) from a byte slice.
+    ///
+    /// Returns the header and the number of bytes it occupies, including the
+    /// padding that aligns newc/crc entries to 4 bytes and binary entries to
+    /// 2 bytes. The entry's file data
+    /// starts at that offset.
+    pub fn from_bytes(data: &[u8]) -> Result<(Self, usize)> {
+        if data.len() < 6 {
+            return Err(PbzxError::InvalidCpio("header too short".into()));
+        }
+        let format = CpioFormat::from_magic(&data[..6]).ok_or_else(|| {
+            PbzxError::InvalidCpio(format!(
+                "Invalid CPIO magic: {:?}",
+                String::from_utf8_lossy(&data[..6])
+            ))
+        })?;
+
+        let fixed_size = format.header_size();
+        if data.len() < fixed_size {
+            return Err(PbzxError::InvalidCpio(format!(
+                "header too short: {} bytes, need {}",
+                data.len(),
+                fixed_size
+            )));
+        }
+
+        let mut header = match format {
+            CpioFormat::Newc | CpioFormat::Crc => Self::parse_newc_fields(&data[6..fixed_size])?,
+            CpioFormat::Odc => Self::parse_odc_fields(&data[6..fixed_size])?,
+            CpioFormat::Bin { big_endian } => Self::parse_bin_fields(&data[2..fixed_size], big_endian)?,
+        };
+
+        let name_needed = fixed_size as u64 + header.namesize as u64;
+        if (data.len() as u64) < name_needed {
+            return Err(PbzxError::InvalidCpio(format!(
+                "filename extends past end of data ({} > {})",
+                name_needed,
+                data.len()
+            )));
+        }
+        let name_end = name_needed as usize;
+        header.name = Self::parse_name(data[fixed_size..name_end].to_vec())?;
+
+        let total = name_end + format.padding(name_end as u64) as usize;
+
+        Ok((header, total))
+    }
+
+    /// Fuzzing entry point: parse every header of an in-memory CPIO
+    /// archive, up to and including the trailer.
+    ///
+    /// File data is skipped. Never panics; running off the end of `data`
+    /// before the trailer is an error.
+    #[cfg(feature = "fuzzing")]
+    pub fn try_parse(data: &[u8]) -> Result<Vec<Self>> {
+        let mut headers = Vec::new();
+        let mut offset = 0usize;
+        loop {
+            let rest = &data[offset..];
+            let format = rest.get(..6).and_then(CpioFormat::from_magic);
+            let (header, len) = Self::from_bytes(rest)?;
+            let trailer = header.is_trailer();
+            let filesize = header.filesize as u64;
+            headers.push(header);
+            if trailer {
+                return Ok(headers);
+            }
+            let padding = format.map_or(0, |f| f.padding(filesize));
+            offset = usize::try_from(len as u64 + filesize + padding)
+                .ok()
+                .and_then(|entry| offset.checked_add(entry))
+                .filter(|&end| end <= data.len())
+                .ok_or_else(|| PbzxError::InvalidCpio("file data extends past end of data".into()))?;
+        }
+    }
+
+    /// Parse the 104 bytes of hex fields that follow a newc/crc magic.
+    /// The filename is left empty.
+    pub(crate) fn parse_newc_fields(buf: &[u8]) -> Result<Self> {
+        // Parse hex fields (8 chars each)
+        let parse_hex = |start: usize| -> Result<u32> {
+            let field = buf
+                .get(start..start + 8)
+                .ok_or_else(|| PbzxError::InvalidCpio("newc header too short".into()))?;
+            let s = core::str::from_utf8(field)
+                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid UTF-8 in header: {}", e)))?;
+            u32::from_str_radix(s, 16)
+                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid hex value '{}': {}", s, e)))
+        };
+
+        Ok(CpioHeader {
+            ino: parse_hex(0)?,
+            mode: parse_hex(8)?,
+            uid: parse_hex(16)?,
+            gid: parse_hex(24)?,
+            nlink: parse_hex(32)?,
+            mtime: parse_hex(40)?,
+            filesize: parse_hex(48)?,
+            devmajor: parse_hex(56)?,
+            devminor: parse_hex(64)?,
+            rdevmajor: parse_hex(72)?,
+            rdevminor: parse_hex(80)?,
+            namesize: parse_hex(88)?,
+            check: parse_hex(96)?,
+            name: String::new(),
+        })
+    }
+
+    /// Parse the 70 bytes of octal fields that follow an odc magic.
+    /// The filename is left empty.
+    ///
+    /// ODC format structure (76 bytes total including magic):
+    /// - 6 bytes: magic "070707"
+    /// - 6 bytes: dev (octal)
+    /// - 6 bytes: ino (octal)
+    /// - 6 bytes: mode (octal)
+    /// - 6 bytes: uid (octal)
+    /// - 6 bytes: gid (octal)
+    /// - 6 bytes: nlink (octal)
+    /// - 6 bytes: rdev (octal)
+    /// - 11 bytes: mtime (octal)
+    /// - 6 bytes: namesize (octal)
+    /// - 11 bytes: filesize (octal)
+    pub(crate) fn parse_odc_fields(buf: &[u8]) -> Result<Self> {
+        let parse_octal_u64 = |start: usize, len: usize| -> Result<u64> {
+            let field = buf
+                .get(start..start + len)
+                .ok_or_else(|| PbzxError::InvalidCpio("odc header too short".into()))?;
+            let s = core::str::from_utf8(field)
+                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid UTF-8 in header: {}", e)))?;
+            u64::from_str_radix(s.trim(), 8)
+                .map_err(|e| PbzxError::InvalidCpio(format!("Invalid octal value '{}': {}", s, e)))
+        };
+        let parse_octal = |start: usize, len: usize| -> Result<u32> {
+            parse_octal_u64(start, len).map(|v| v as u32)
+        };
+
+        let dev = parse_octal(0, 6)?;
+        let rdev = parse_octal(36, 6)?;
+
+        Ok(CpioHeader {
+            ino: parse_octal(6, 6)?,
+            mode: parse_octal(12, 6)?,
+            uid: parse_octal(18, 6)?,
+            gid: parse_octal(24, 6)?,
+            nlink: parse_octal(30, 6)?,
+            mtime: parse_octal_u64(42, 11)? as u32,
+            filesize: parse_octal_u64(59, 11)? as u32,
+            devmajor: dev >> 8,
+            devminor: dev & 0xff,
+            rdevmajor: rdev >> 8,
+            rdevminor: rdev & 0xff,
+            namesize: parse_octal(53, 6)?,
+            check: 0,
+            name: String::new(),
+        })
+    }
+
+    /// Parse the 24 bytes of 16-bit fields that follow a binary magic.
+    /// The filename is left empty.
+    ///
+    /// Binary format structure (26 bytes total including magic):
+    /// - 2 bytes: magic 0o070707
+    /// - 2 bytes each: dev, ino, mode, uid, gid, nlink, rdev
+    /// - 4 bytes: mtime (two 16-bit words, most significant first)
+    /// - 2 bytes: namesize
+    /// - 4 bytes: filesize (two 16-bit words, most significant first)
+    pub(crate) fn parse_bin_fields(buf: &[u8], big_endian: bool) -> Result<Self> {
+        if buf.len() < Self::BIN_HEADER_SIZE - 2 {
+            return Err(PbzxError::InvalidCpio("binary header too short".into()));
+        }
+        let word = |start: usize| -> u32 {
+            let bytes = &buf[start..start + 2];
+            if big_endian {
+                BigEndian::read_u16(bytes) as u32
+            } else {
+                LittleEndian::read_u16(bytes) as u32
+            }
+        };
+        let long = |start: usize| -> u32 { (word(start) << 16) | word(start + 2) };
+
+        let dev = word(0);
+        let rdev = word(12);
+
+        Ok(CpioHeader {
+            ino: word(2),
+            mode: word(4),
+            uid: word(6),
+            gid: word(8),
+            nlink: word(10),
+            mtime: long(14),
+            filesize: long(20),
+            devmajor: dev >> 8,
+            devminor: dev & 0xff,
+            rdevmajor: rdev >> 8,
+            rdevminor: rdev & 0xff,
+            namesize: word(18),
+            check: 0,
+            name: String::new(),
+        })
+    }
+
+    /// Decode a NUL-terminated filename.
+    pub(crate) fn parse_name(mut name_buf: Vec<u8>) -> Result<String> {
+        // Remove null terminator if present
+        if name_buf.last() == Some(&0) {
+            name_buf.pop();
+        }
+
+        String::from_utf8(name_buf)
+            .map_err(|e| PbzxError::InvalidCpio(format!("Invalid filename: {}", e)))
+    }
+
     /// Check if this entry is a regular file.
     pub fn is_file(&self) -> bool {
         (self.mode & 0o170000) == 0o100000
@@ -156,6 +549,11 @@ impl CpioHeader {
         (self.mode & 0o170000) == 0o120000
     }
 
+    /// Check if this entry is a character or block device.
+    pub fn is_device(&self) -> bool {
+        matches!(self.mode & 0o170000, 0o020000 | 0o060000)
+    }
+
     /// Check if this is the trailer entry.
     pub fn is_trailer(&self) -> bool {
         self.name == CPIO_TRAILER
@@ -202,6 +600,16 @@ pub struct FileEntry {
     pub is_symlink: bool,
     /// Symlink target (if is_symlink)
     pub link_target: Option<String>,
+    /// Per-file checksum from the header (crc format only)
+    pub checksum: Option<u32>,
+    /// Major number of the device holding the file
+    pub devmajor: u32,
+    /// Minor number of the device holding the file
+    pub devminor: u32,
+    /// Major number of the device a device node refers to
+    pub rdevmajor: u32,
+    /// Minor number of the device a device node refers to
+    pub rdevminor: u32,
 }
 
 impl FileEntry {
@@ -229,3 +637,190 @@ impl FileEntry {
         s
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_pbzx_header_from_bytes() {
+        let mut data = vec![];
+        data.extend_from_slice(&PBZX_MAGIC);
+        data.extend_from_slice(&0x0100_0000u64.to_be_bytes());
+
+        let header = PbzxHeader::from_bytes(&data).unwrap();
+        assert!(header.is_valid());
+        assert_eq!(header.flags, 0x0100_0000);
+
+        data[0] = b'x';
+        assert!(matches!(PbzxHeader::from_bytes(&data), Err(PbzxError::InvalidMagic(_))));
+        assert!(matches!(PbzxHeader::from_bytes(&data[..4]), Err(PbzxError::UnexpectedEof(4))));
+        for len in 0..data.len() {
+            assert!(PbzxHeader::from_bytes(&data[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_chunk_header_from_bytes() {
+        let mut data = vec![];
+        data.extend_from_slice(&100u64.to_be_bytes());
+        data.extend_from_slice(&40u64.to_be_bytes());
+
+        let chunk = ChunkHeader::from_bytes(&data).unwrap();
+        assert_eq!(chunk.uncompressed_size, 100);
+        assert_eq!(chunk.compressed_size, 40);
+        assert!(!chunk.is_uncompressed());
+        for len in 0..data.len() {
+            assert!(ChunkHeader::from_bytes(&data[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_cpio_header_from_bytes_newc() {
+        // "hello.txt\0" is 10 bytes; 110 + 10 = 120, already 4-byte aligned
+        let mut data = vec![];
+        data.extend_from_slice(CPIO_MAGIC_NEWC);
+        for value in [1u32, 0o100644, 501, 20, 1, 0, 5, 0, 0, 0, 0, 10, 0] {
+            data.extend_from_slice(format!("{:08X}", value).as_bytes());
+        }
+        data.extend_from_slice(b"hello.txt\0");
+        data.extend_from_slice(b"hello");
+
+        let (header, len) = CpioHeader::from_bytes(&data).unwrap();
+        assert_eq!(len, 120);
+        assert_eq!(header.name, "hello.txt");
+        assert_eq!(header.filesize, 5);
+        assert_eq!(header.uid, 501);
+        assert!(header.is_file());
+        assert_eq!(&data[len..len + header.filesize as usize], b"hello");
+        for cut in 0..len {
+            assert!(CpioHeader::from_bytes(&data[..cut]).is_err(), "{}", cut);
+        }
+    }
+
+    #[test]
+    fn test_cpio_header_from_bytes_odc() {
+        let mut data = vec![];
+        data.extend_from_slice(CPIO_MAGIC_ODC);
+        // dev, ino, mode, uid, gid, nlink, rdev (6 octal digits each)
+        data.extend_from_slice(b"000000000001040755000000000000000002000000");
+        // mtime (11), namesize (6), filesize (11)
+        data.extend_from_slice(b"00000000000000004");
+        data.extend_from_slice(b"00000000000");
+        data.extend_from_slice(b"dir\0");
+
+        let (header, len) = CpioHeader::from_bytes(&data).unwrap();
+        assert_eq!(len, CpioHeader::ODC_HEADER_SIZE + 4);
+        assert_eq!(header.name, "dir");
+        assert!(header.is_directory());
+        assert_eq!(header.nlink, 2);
+        for cut in 0..len {
+            assert!(CpioHeader::from_bytes(&data[..cut]).is_err(), "{}", cut);
+        }
+    }
+
+    #[test]
+    fn test_cpio_header_from_bytes_bin() {
+        // Big-endian header; "ab\0" is 3 bytes, so one byte of padding follows
+        let mut data = vec![];
+        for value in [CPIO_MAGIC_BIN, 0, 7, 0o100644, 501, 20, 1, 0, 0x0001, 0x0002, 3, 0, 4] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend_from_slice(b"ab\0\0");
+        data.extend_from_slice(b"data");
+
+        assert_eq!(
+            CpioFormat::from_magic(&data[..6]),
+            Some(CpioFormat::Bin { big_endian: true })
+        );
+        let (header, len) = CpioHeader::from_bytes(&data).unwrap();
+        assert_eq!(len, CpioHeader::BIN_HEADER_SIZE + 4);
+        assert_eq!(header.name, "ab");
+        assert_eq!(header.ino, 7);
+        assert_eq!(header.uid, 501);
+        assert_eq!(header.mtime, 0x0001_0002);
+        assert_eq!(header.filesize, 4);
+        assert!(header.is_file());
+        assert_eq!(&data[len..], b"data");
+        for cut in 0..CpioHeader::BIN_HEADER_SIZE + 3 {
+            assert!(CpioHeader::from_bytes(&data[..cut]).is_err(), "{}", cut);
+        }
+    }
+
+    #[test]
+    fn test_cpio_header_from_bytes_rejects_bad_input() {
+        assert!(matches!(CpioHeader::from_bytes(b"123456"), Err(PbzxError::InvalidCpio(_))));
+        assert!(matches!(CpioHeader::from_bytes(CPIO_MAGIC_NEWC), Err(PbzxError::InvalidCpio(_))));
+
+        // Hex fields that hold a valid number but sit in a short buffer
+        assert!(CpioHeader::parse_newc_fields(b"00000001").is_err());
+        assert!(CpioHeader::parse_odc_fields(b"000001").is_err());
+    }
+
+    #[cfg(feature = "fuzzing")]
+    #[test]
+    fn test_try_parse() {
+        let mut pbzx = vec![];
+        pbzx.extend_from_slice(&PBZX_MAGIC);
+        pbzx.extend_from_slice(&0x1000u64.to_be_bytes());
+        for (size, payload) in [(4u64, &b"abcd"[..]), (0, &b""[..])] {
+            pbzx.extend_from_slice(&size.to_be_bytes());
+            pbzx.extend_from_slice(&size.to_be_bytes());
+            pbzx.extend_from_slice(payload);
+        }
+        let (header, chunks) = PbzxHeader::try_parse(&pbzx).unwrap();
+        assert_eq!(header.flags, 0x1000);
+        assert_eq!(chunks.len(), 1);
+        assert!(matches!(PbzxHeader::try_parse(&pbzx[..30]), Err(PbzxError::UnexpectedEof(_))));
+        for len in 0..pbzx.len() {
+            let _ = PbzxHeader::try_parse(&pbzx[..len]);
+        }
+
+        let mut cpio = vec![];
+        for (name, body) in [("a", &b"hello"[..]), (CPIO_TRAILER, &b""[..])] {
+            let namesize = name.len() as u32 + 1;
+            cpio.extend_from_slice(CPIO_MAGIC_NEWC);
+            for value in [1u32, 0o100644, 0, 0, 1, 0, body.len() as u32, 0, 0, 0, 0, namesize, 0] {
+                cpio.extend_from_slice(format!("{:08X}", value).as_bytes());
+            }
+            cpio.extend_from_slice(name.as_bytes());
+            cpio.push(0);
+            cpio.resize(cpio.len().next_multiple_of(4), 0);
+            cpio.extend_from_slice(body);
+            cpio.resize(cpio.len().next_multiple_of(4), 0);
+        }
+        let headers = CpioHeader::try_parse(&cpio).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0].name, "a");
+        assert!(headers[1].is_trailer());
+        assert!(CpioHeader::try_parse(&cpio[..120]).is_err());
+        for len in 0..cpio.len() {
+            let _ = CpioHeader::try_parse(&cpio[..len]);
+        }
+    }
+
+    #[test]
+    fn test_chunk_compression_detect() {
+        let mut xz = XZ_MAGIC.to_vec();
+        xz.extend_from_slice(&[0u8; 16]);
+        assert_eq!(ChunkCompression::detect(&xz), Some(ChunkCompression::Xz));
+
+        // lc=3 lp=0 pb=2, 8 MiB dictionary, unknown size
+        let mut lzma = vec![0x5d];
+        lzma.extend_from_slice(&(8u32 << 20).to_le_bytes());
+        lzma.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(ChunkCompression::detect(&lzma), Some(ChunkCompression::LzmaAlone));
+
+        // 3 * 2^20 is also a valid dictionary size
+        lzma[1..5].copy_from_slice(&(3u32 << 20).to_le_bytes());
+        assert_eq!(ChunkCompression::detect(&lzma), Some(ChunkCompression::LzmaAlone));
+
+        // Dictionary size 5 MiB is not produced by any encoder
+        lzma[1..5].copy_from_slice(&(5u32 << 20).to_le_bytes());
+        assert_eq!(ChunkCompression::detect(&lzma), None);
+
+        assert_eq!(ChunkCompression::detect(b"pbzx garbage data"), None);
+        assert_eq!(ChunkCompression::detect(&[0x5d, 0x00]), None);
+    }
+}
@@ -11,6 +11,10 @@
 //! - **`list`** (default): List files in PBZX archives
 //! - **`extract`** (default): Extract files from PBZX archives
 //! - **`pack`** (default): Create new PBZX archives
+//! - **`std`** (default): Readers, writers and the high-level [`Archive`] API.
+//!   Without it the crate is `no_std` + `alloc` and only exposes the byte-slice
+//!   parsers in [`format`] (`PbzxHeader::from_bytes`, `ChunkHeader::from_bytes`,
+//!   `CpioHeader::from_bytes`)
 //!
 //! ## Quick Start
 //!
@@ -81,21 +85,36 @@
 //! The concatenated decompressed chunks form a CPIO archive containing
 //! the actual payload files.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod cpio;
 pub mod error;
 pub mod format;
+#[cfg(feature = "std")]
 pub mod reader;
+#[cfg(feature = "std")]
 pub mod writer;
 
 // Re-exports for convenience
-pub use cpio::{CpioEntry, CpioReader};
+#[cfg(feature = "std")]
+pub use cpio::{CpioEntry, CpioReader, ExtractOptions};
 pub use error::{PbzxError, Result};
-pub use format::{ChunkHeader, CpioHeader, FileEntry, PbzxHeader};
-pub use reader::{is_pbzx, open, ChunkInfo, PbzxReader};
-pub use writer::{CpioBuilder, PbzxWriter};
-
+pub use format::{ChunkCompression, ChunkHeader, CpioHeader, FileEntry, PbzxHeader};
+#[cfg(feature = "std")]
+pub use reader::{is_pbzx, open, ChunkFailure, ChunkInfo, PbzxReader, VerifyReport};
+#[cfg(feature = "std")]
+pub use writer::{CpioBuilder, PbzxOptions, PbzxWriter, XzOptions};
+#[cfg(feature = "pack")]
+pub use writer::pack_directory;
+
+#[cfg(feature = "std")]
 use std::fs::File;
+#[cfg(feature = "std")]
 use std::io::{BufReader, Cursor, Read};
+#[cfg(feature = "std")]
 use std::path::Path;
 
 /// High-level interface for working with PBZX archives.
@@ -119,10 +138,12 @@ use std::path::Path;
 /// // Extract everything
 /// archive.extract_all("output").unwrap();
 /// ```
+#[cfg(feature = "std")]
 pub struct Archive {
     cpio_data: Vec<u8>,
 }
 
+#[cfg(feature = "std")]
 impl Archive {
     /// Open a PBZX archive from a file path.
     ///
@@ -171,6 +192,42 @@ impl Archive {
         cpio.list()
     }
 
+    /// List files, keeping the entries parsed before a truncation or other
+    /// error (see [`CpioReader::list_lenient`]).
+    #[cfg(feature = "list")]
+    pub fn list_lenient(&self) -> (Vec<FileEntry>, Option<PbzxError>) {
+        let cursor = Cursor::new(&self.cpio_data);
+        let mut cpio = CpioReader::new(cursor);
+        cpio.list_lenient()
+    }
+
+    /// Build a nested directory tree from the archive entries.
+    ///
+    /// The returned root has an empty name. Leading `./` and `/` are
+    /// stripped from paths, directories missing from the archive are created
+    /// implicitly, and children are sorted by name.
+    #[cfg(feature = "list")]
+    pub fn tree(&self) -> Result<TreeNode> {
+        let mut root = TreeBuilder::default();
+        for entry in self.list()? {
+            let path = entry.path.trim_start_matches("./").trim_start_matches('/');
+            let mut node = &mut root;
+            for component in path.split('/').filter(|c| !c.is_empty() && *c != ".") {
+                node = node.children.entry(component.to_string()).or_default();
+            }
+            node.entry = Some(entry);
+        }
+        Ok(root.build(String::new()))
+    }
+
+    /// Check whether the archive contains an entry with the given path.
+    #[cfg(feature = "list")]
+    pub fn contains(&self, path: &str) -> Result<bool> {
+        let cursor = Cursor::new(&self.cpio_data);
+        let mut cpio = CpioReader::new(cursor);
+        cpio.contains(path)
+    }
+
     /// Extract a single file by path.
     #[cfg(feature = "extract")]
     pub fn extract_file(&self, path: &str) -> Result<Vec<u8>> {
@@ -179,6 +236,26 @@ impl Archive {
         cpio.extract_file(path)
     }
 
+    /// Stream a single file to `dest` on the host filesystem.
+    #[cfg(feature = "extract")]
+    pub fn extract_file_to_path<P: AsRef<Path>>(&self, archive_path: &str, dest: P) -> Result<()> {
+        let cursor = Cursor::new(&self.cpio_data);
+        let mut cpio = CpioReader::new(cursor);
+        cpio.extract_file_to_path(archive_path, dest)
+    }
+
+    /// Extract a single file by path, or `None` if it is not in the archive.
+    ///
+    /// Errors other than a missing file are still returned.
+    #[cfg(feature = "extract")]
+    pub fn extract_file_if_exists(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.extract_file(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(PbzxError::FileNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Extract all files to a directory.
     #[cfg(feature = "extract")]
     pub fn extract_all<P: AsRef<Path>>(&self, dest: P) -> Result<Vec<std::path::PathBuf>> {
@@ -187,6 +264,37 @@ impl Archive {
         cpio.extract_all(dest)
     }
 
+    /// Extract files to a directory, skipping entries excluded by `options`.
+    #[cfg(feature = "extract")]
+    pub fn extract_all_with_options<P: AsRef<Path>>(
+        &self,
+        dest: P,
+        options: &ExtractOptions,
+    ) -> Result<Vec<std::path::PathBuf>> {
+        let cursor = Cursor::new(&self.cpio_data);
+        let mut cpio = CpioReader::new(cursor);
+        cpio.extract_all_with_options(dest, options)
+    }
+
+    /// Extract every regular file into memory, keyed by archive path.
+    ///
+    /// Directories, symlinks and device nodes are left out. Paths are kept
+    /// exactly as stored (including any leading `./`); if a path appears
+    /// twice, the later entry wins, as it would on disk.
+    #[cfg(feature = "extract")]
+    pub fn extract_all_to_memory(&self) -> Result<std::collections::BTreeMap<String, Vec<u8>>> {
+        let cursor = Cursor::new(&self.cpio_data);
+        let mut cpio = CpioReader::new(cursor);
+        let mut files = std::collections::BTreeMap::new();
+        for entry in cpio.entries()? {
+            let entry = entry?;
+            if entry.mode & 0o170000 == 0o100000 {
+                files.insert(entry.path, entry.data.unwrap_or_default());
+            }
+        }
+        Ok(files)
+    }
+
     /// Get all entries with their data.
     ///
     /// Note: This loads all file data into memory. For large archives,
@@ -213,7 +321,81 @@ impl Archive {
     }
 }
 
+/// Kind of a [`TreeNode`].
+#[cfg(feature = "list")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeNodeKind {
+    /// A directory, listed in the archive or implied by a child's path
+    Directory,
+    /// A regular file (or other non-directory, non-symlink entry)
+    File,
+    /// A symbolic link
+    Symlink,
+}
+
+/// A node in the nested view returned by [`Archive::tree`].
+#[cfg(feature = "list")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+    /// Last path component (empty for the root)
+    pub name: String,
+    /// What the node is
+    pub kind: TreeNodeKind,
+    /// File size in bytes (0 for directories)
+    pub size: u64,
+    /// Symlink target (if a symlink)
+    pub link_target: Option<String>,
+    /// Child nodes, sorted by name
+    pub children: Vec<TreeNode>,
+}
+
+#[cfg(feature = "list")]
+impl TreeNode {
+    /// Find a descendant by relative path (`""` or `"."` is this node).
+    pub fn find(&self, path: &str) -> Option<&TreeNode> {
+        path.split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .try_fold(self, |node, component| {
+                node.children.iter().find(|child| child.name == component)
+            })
+    }
+
+    /// Whether this node is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.kind == TreeNodeKind::Directory
+    }
+}
+
+/// Mutable intermediate form used while building a [`TreeNode`].
+#[cfg(feature = "list")]
+#[derive(Default)]
+struct TreeBuilder {
+    entry: Option<FileEntry>,
+    children: std::collections::BTreeMap<String, TreeBuilder>,
+}
+
+#[cfg(feature = "list")]
+impl TreeBuilder {
+    fn build(self, name: String) -> TreeNode {
+        let (kind, size, link_target) = match self.entry {
+            Some(entry) if entry.is_symlink => (TreeNodeKind::Symlink, entry.size, entry.link_target),
+            Some(entry) if !entry.is_dir && self.children.is_empty() => {
+                (TreeNodeKind::File, entry.size, None)
+            }
+            _ => (TreeNodeKind::Directory, 0, None),
+        };
+        TreeNode {
+            name,
+            kind,
+            size,
+            link_target,
+            children: self.children.into_iter().map(|(name, child)| child.build(name)).collect(),
+        }
+    }
+}
+
 /// Statistics about a PBZX archive.
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub struct ArchiveStats {
     /// Number of chunks in the archive
@@ -230,6 +412,7 @@ pub struct ArchiveStats {
     pub total_file_size: u64,
 }
 
+#[cfg(feature = "std")]
 impl ArchiveStats {
     /// Calculate the overall compression ratio.
     pub fn compression_ratio(&self) -> f64 {
@@ -247,6 +430,7 @@ impl ArchiveStats {
 }
 
 /// Get statistics about a PBZX archive.
+#[cfg(feature = "std")]
 pub fn stats<P: AsRef<Path>>(path: P) -> Result<ArchiveStats> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -284,7 +468,7 @@ pub fn stats<P: AsRef<Path>>(path: P) -> Result<ArchiveStats> {
     })
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -313,6 +497,75 @@ mod tests {
         assert_eq!(entries[2].path, "subdir/nested.txt");
     }
 
+    #[test]
+    fn test_tree() {
+        let mut builder = CpioBuilder::new();
+        builder.add_directory("./usr", 0o755);
+        builder.add_directory("./usr/bin", 0o755);
+        builder.add_file("./usr/bin/tool", b"binary", 0o755);
+        builder.add_symlink("./usr/bin/alias", "tool", 0o755);
+        // Parent directories not listed in the archive are implied
+        builder.add_file("./Library/Frameworks/Foo.framework/Foo", b"0123456789", 0o644);
+        builder.add_file("./README", b"hi", 0o644);
+        let archive = Archive::from_cpio(&builder.finish()).unwrap();
+
+        let tree = archive.tree().unwrap();
+        assert_eq!(tree.name, "");
+        assert!(tree.is_dir());
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Library", "README", "usr"]);
+
+        let bin = tree.find("usr/bin").unwrap();
+        assert_eq!(bin.kind, TreeNodeKind::Directory);
+        assert_eq!(bin.children.len(), 2);
+        assert_eq!(bin.children[0].name, "alias");
+        assert_eq!(bin.children[0].kind, TreeNodeKind::Symlink);
+        assert_eq!(bin.children[0].link_target.as_deref(), Some("tool"));
+        assert_eq!(bin.children[1].kind, TreeNodeKind::File);
+        assert_eq!(bin.children[1].size, 6);
+
+        let framework = tree.find("Library/Frameworks/Foo.framework").unwrap();
+        assert!(framework.is_dir());
+        assert_eq!(framework.children[0].size, 10);
+        assert_eq!(tree.find("README").unwrap().kind, TreeNodeKind::File);
+        assert!(tree.find("usr/lib").is_none());
+    }
+
+    #[test]
+    fn test_extract_file_if_exists() {
+        let mut builder = CpioBuilder::new();
+        builder.add_directory("etc", 0o755);
+        builder.add_file("etc/motd", b"welcome", 0o644);
+        let archive = Archive::from_cpio(&builder.finish()).unwrap();
+
+        assert_eq!(
+            archive.extract_file_if_exists("etc/motd").unwrap(),
+            Some(b"welcome".to_vec())
+        );
+        assert_eq!(archive.extract_file_if_exists("etc/missing").unwrap(), None);
+        // Directories are not a "missing file" and still error
+        assert!(archive.extract_file_if_exists("etc").is_err());
+    }
+
+    #[cfg(feature = "extract")]
+    #[test]
+    fn test_extract_all_to_memory() {
+        let mut builder = CpioBuilder::new();
+        builder.add_directory("./etc", 0o755);
+        builder.add_file("./etc/motd", b"welcome", 0o644);
+        builder.add_file("./etc/empty", b"", 0o600);
+        builder.add_symlink("./etc/link", "motd", 0o755);
+        builder.add_file("./bin/tool", &[0u8, 1, 2, 255], 0o755);
+        let archive = Archive::from_cpio(&builder.finish()).unwrap();
+
+        let files = archive.extract_all_to_memory().unwrap();
+        let paths: Vec<&str> = files.keys().map(String::as_str).collect();
+        assert_eq!(paths, ["./bin/tool", "./etc/empty", "./etc/motd"]);
+        assert_eq!(files["./etc/motd"], b"welcome");
+        assert!(files["./etc/empty"].is_empty());
+        assert_eq!(files["./bin/tool"], [0u8, 1, 2, 255]);
+    }
+
     #[test]
     fn test_pbzx_roundtrip() {
         // Create CPIO content
@@ -334,4 +587,82 @@ mod tests {
         // Verify content
         assert_eq!(decompressed, cpio_data);
     }
+
+    #[test]
+    fn test_pbzx_lzma_alone_chunk() {
+        use std::io::Write;
+        use xz2::stream::{LzmaOptions, Stream};
+        use xz2::write::XzEncoder;
+
+        let mut builder = CpioBuilder::new();
+        builder.add_file("legacy.txt", b"Compressed as LZMA-alone", 0o644);
+        let cpio_data = builder.finish();
+
+        let options = LzmaOptions::new_preset(6).unwrap();
+        let stream = Stream::new_lzma_encoder(&options).unwrap();
+        let mut encoder = XzEncoder::new_stream(Vec::new(), stream);
+        encoder.write_all(&cpio_data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // Assemble a single-chunk PBZX by hand
+        let mut pbzx = Vec::new();
+        pbzx.extend_from_slice(&format::PBZX_MAGIC);
+        pbzx.extend_from_slice(&(16u64 << 20).to_be_bytes());
+        pbzx.extend_from_slice(&(cpio_data.len() as u64).to_be_bytes());
+        pbzx.extend_from_slice(&(compressed.len() as u64).to_be_bytes());
+        pbzx.extend_from_slice(&compressed);
+
+        let mut reader = PbzxReader::new(Cursor::new(&pbzx)).unwrap();
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        // Corrupt the properties byte so neither XZ nor LZMA-alone matches
+        let data_start = format::HEADER_SIZE + format::CHUNK_HEADER_SIZE;
+        pbzx[data_start] = 0xff;
+        let mut reader = PbzxReader::new(Cursor::new(&pbzx)).unwrap();
+        assert!(matches!(
+            reader.decompress(),
+            Err(PbzxError::InvalidChunk { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pbzx_leading_stored_chunk() {
+        use std::io::Write;
+        use xz2::write::XzEncoder;
+
+        // Apple payloads declare a 16 MiB chunk size in the header flags and
+        // store a chunk raw when XZ would not shrink it: both size fields then
+        // equal the declared chunk size and the data has no XZ magic.
+        const CHUNK_SIZE: usize = 16 << 20;
+
+        let content: Vec<u8> = (0..CHUNK_SIZE as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let mut builder = CpioBuilder::new();
+        builder.add_file("random.bin", &content, 0o644);
+        let cpio_data = builder.finish();
+        assert!(cpio_data.len() > CHUNK_SIZE);
+
+        let (stored, rest) = cpio_data.split_at(CHUNK_SIZE);
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(rest).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut pbzx = Vec::new();
+        pbzx.extend_from_slice(&format::PBZX_MAGIC);
+        pbzx.extend_from_slice(&(CHUNK_SIZE as u64).to_be_bytes());
+        pbzx.extend_from_slice(&(CHUNK_SIZE as u64).to_be_bytes());
+        pbzx.extend_from_slice(&(CHUNK_SIZE as u64).to_be_bytes());
+        pbzx.extend_from_slice(stored);
+        pbzx.extend_from_slice(&(rest.len() as u64).to_be_bytes());
+        pbzx.extend_from_slice(&(compressed.len() as u64).to_be_bytes());
+        pbzx.extend_from_slice(&compressed);
+
+        let mut reader = PbzxReader::new(Cursor::new(&pbzx)).unwrap();
+        let chunks = reader.chunk_info().unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(!chunks[0].is_compressed);
+        assert!(chunks[1].is_compressed);
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+    }
 }
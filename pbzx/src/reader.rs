@@ -6,12 +6,13 @@ use std::path::Path;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use xz2::read::XzDecoder;
+use xz2::stream::Stream;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use crate::error::{PbzxError, Result};
-use crate::format::{ChunkHeader, PbzxHeader, CHUNK_HEADER_SIZE, HEADER_SIZE, PBZX_MAGIC};
+use crate::format::{ChunkCompression, ChunkHeader, PbzxHeader, CHUNK_HEADER_SIZE, HEADER_SIZE, PBZX_MAGIC};
 
 /// A reader for PBZX archives.
 ///
@@ -47,10 +48,6 @@ impl<R: Read> PbzxReader<R> {
     pub fn new(mut reader: R) -> Result<Self> {
         let header = Self::read_header(&mut reader)?;
 
-        if !header.is_valid() {
-            return Err(PbzxError::InvalidMagic(header.magic));
-        }
-
         Ok(Self {
             reader,
             header,
@@ -75,12 +72,9 @@ impl<R: Read> PbzxReader<R> {
     }
 
     fn read_header(reader: &mut R) -> Result<PbzxHeader> {
-        let mut magic = [0u8; 4];
-        reader.read_exact(&mut magic)?;
-
-        let flags = reader.read_u64::<BigEndian>()?;
-
-        Ok(PbzxHeader { magic, flags })
+        let mut buf = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut buf)?;
+        PbzxHeader::from_bytes(&buf)
     }
 
     /// Read the next chunk header, if any.
@@ -106,6 +100,17 @@ impl<R: Read> PbzxReader<R> {
         }))
     }
 
+    /// Read a chunk's `size` payload bytes into `buf`, replacing its contents.
+    ///
+    /// `size` comes from the chunk header, so the buffer only grows as data
+    /// actually arrives. Returns `false` if the archive ends first.
+    fn read_chunk_data(&mut self, size: u64, buf: &mut Vec<u8>) -> Result<bool> {
+        buf.clear();
+        let read = self.reader.by_ref().take(size).read_to_end(buf)?;
+        self.current_offset += read as u64;
+        Ok(read as u64 == size)
+    }
+
     /// Decompress the entire PBZX archive to a writer.
     ///
     /// Returns the total number of bytes written.
@@ -116,37 +121,18 @@ impl<R: Read> PbzxReader<R> {
             let chunk_start = self.current_offset;
 
             // Read the compressed chunk data
-            let mut chunk_data = vec![0u8; chunk.compressed_size as usize];
-            self.reader.read_exact(&mut chunk_data)?;
-            self.current_offset += chunk.compressed_size;
+            let mut chunk_data = Vec::new();
+            if !self.read_chunk_data(chunk.compressed_size, &mut chunk_data)? {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
 
             // Decompress or copy directly
             if chunk.is_uncompressed() {
                 writer.write_all(&chunk_data)?;
                 total_written += chunk_data.len() as u64;
             } else {
-                // Decompress using XZ
-                let mut decoder = XzDecoder::new(&chunk_data[..]);
-                let mut decompressed = Vec::with_capacity(chunk.uncompressed_size as usize);
-
-                decoder.read_to_end(&mut decompressed).map_err(|e| {
-                    PbzxError::Decompression(format!(
-                        "Failed to decompress chunk at offset {}: {}",
-                        chunk_start, e
-                    ))
-                })?;
-
-                if decompressed.len() as u64 != chunk.uncompressed_size {
-                    return Err(PbzxError::InvalidChunk {
-                        offset: chunk_start,
-                        message: format!(
-                            "Decompressed size mismatch: expected {}, got {}",
-                            chunk.uncompressed_size,
-                            decompressed.len()
-                        ),
-                    });
-                }
-
+                let decompressed =
+                    decompress_chunk_data(&chunk_data, chunk.uncompressed_size, chunk_start)?;
                 writer.write_all(&decompressed)?;
                 total_written += decompressed.len() as u64;
             }
@@ -156,6 +142,51 @@ impl<R: Read> PbzxReader<R> {
         Ok(total_written)
     }
 
+    /// Check that every chunk decompresses to its declared size.
+    ///
+    /// Decompressed data is counted and discarded; only one chunk's
+    /// compressed bytes are held at a time. Chunks that fail to decode are
+    /// collected in the report and checking continues with the next chunk.
+    /// An archive that ends mid-chunk is reported as a final failure. Other
+    /// I/O errors are returned.
+    pub fn verify(&mut self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let mut scratch = Vec::new();
+
+        loop {
+            let header = match self.read_chunk_header() {
+                Ok(Some(header)) => header,
+                Ok(None) => break,
+                Err(PbzxError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    report.failures.push(truncated_chunk(report.chunks, self.current_offset));
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            let index = report.chunks;
+            let offset = self.current_offset;
+            report.chunks += 1;
+
+            if !self.read_chunk_data(header.compressed_size, &mut scratch)? {
+                report.failures.push(truncated_chunk(index, offset));
+                break;
+            }
+            report.compressed_bytes += header.compressed_size;
+
+            let decoded = if header.is_uncompressed() {
+                Ok(header.uncompressed_size)
+            } else {
+                count_chunk_data(&scratch, header.uncompressed_size, offset)
+            };
+            match decoded {
+                Ok(len) => report.decompressed_bytes += len,
+                Err(error) => report.failures.push(ChunkFailure { index, offset, error }),
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Decompress to a Vec<u8>.
     ///
     /// Note: This loads the entire decompressed content into memory.
@@ -186,9 +217,10 @@ impl<R: Read> PbzxReader<R> {
 
         while let Some(header) = self.read_chunk_header()? {
             let offset = self.current_offset;
-            let mut data = vec![0u8; header.compressed_size as usize];
-            self.reader.read_exact(&mut data)?;
-            self.current_offset += header.compressed_size;
+            let mut data = Vec::new();
+            if !self.read_chunk_data(header.compressed_size, &mut data)? {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
 
             chunks.push(ReadChunk {
                 header,
@@ -225,7 +257,7 @@ impl<R: Read> PbzxReader<R> {
         // Parallel decompress each chunk
         let results: Vec<Result<Vec<u8>>> = chunks
             .into_par_iter()
-            .map(|chunk| decompress_chunk(chunk))
+            .map(decompress_chunk)
             .collect();
 
         // Calculate total size for pre-allocation
@@ -248,29 +280,109 @@ impl<R: Read> PbzxReader<R> {
     }
 }
 
-/// Decompress a single chunk (used by parallel decompression).
-#[cfg(feature = "parallel")]
-fn decompress_chunk(chunk: ReadChunk) -> Result<Vec<u8>> {
-    if chunk.header.is_uncompressed() {
-        return Ok(chunk.data);
+/// Outcome of [`PbzxReader::verify`]
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Number of chunks examined
+    pub chunks: usize,
+    /// Total compressed bytes read
+    pub compressed_bytes: u64,
+    /// Total bytes produced by chunks that decoded successfully
+    pub decompressed_bytes: u64,
+    /// Chunks that failed to decode or had the wrong size
+    pub failures: Vec<ChunkFailure>,
+}
+
+impl VerifyReport {
+    /// True when every chunk decoded to its declared size
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A chunk that failed verification
+#[derive(Debug)]
+pub struct ChunkFailure {
+    /// Chunk index (0-based)
+    pub index: usize,
+    /// Offset of chunk data in the file
+    pub offset: u64,
+    /// Why the chunk failed
+    pub error: PbzxError,
+}
+
+fn truncated_chunk(index: usize, offset: u64) -> ChunkFailure {
+    ChunkFailure {
+        index,
+        offset,
+        error: PbzxError::InvalidChunk {
+            offset,
+            message: "Archive ends inside chunk".to_string(),
+        },
+    }
+}
+
+/// Create a decoder for a compressed chunk's payload.
+///
+/// The container is detected from the leading bytes: XZ streams and raw
+/// LZMA-alone streams are both accepted.
+fn chunk_decoder(data: &[u8], offset: u64) -> Result<XzDecoder<&[u8]>> {
+    let stream = match ChunkCompression::detect(data) {
+        Some(ChunkCompression::Xz) => Stream::new_stream_decoder(u64::MAX, 0),
+        Some(ChunkCompression::LzmaAlone) => Stream::new_lzma_decoder(u64::MAX),
+        None => {
+            return Err(PbzxError::InvalidChunk {
+                offset,
+                message: "Unknown compression stream (expected XZ or LZMA)".to_string(),
+            })
+        }
     }
+    .map_err(|e| PbzxError::Decompression(format!("Failed to create decoder: {}", e)))?;
 
-    let mut decoder = XzDecoder::new(&chunk.data[..]);
-    let mut decompressed = Vec::with_capacity(chunk.header.uncompressed_size as usize);
+    Ok(XzDecoder::new_stream(data, stream))
+}
+
+/// Decode a compressed chunk, discarding the output, and return its length.
+fn count_chunk_data(data: &[u8], uncompressed_size: u64, offset: u64) -> Result<u64> {
+    let mut decoder = chunk_decoder(data, offset)?;
+    let len = std::io::copy(&mut decoder, &mut std::io::sink()).map_err(|e| {
+        PbzxError::Decompression(format!(
+            "Failed to decompress chunk at offset {}: {}",
+            offset, e
+        ))
+    })?;
+
+    if len != uncompressed_size {
+        return Err(PbzxError::InvalidChunk {
+            offset,
+            message: format!(
+                "Decompressed size mismatch: expected {}, got {}",
+                uncompressed_size, len
+            ),
+        });
+    }
+
+    Ok(len)
+}
+
+/// Decompress the payload of a compressed chunk.
+fn decompress_chunk_data(data: &[u8], uncompressed_size: u64, offset: u64) -> Result<Vec<u8>> {
+    let mut decoder = chunk_decoder(data, offset)?;
+    let mut decompressed = Vec::with_capacity(uncompressed_size as usize);
 
     decoder.read_to_end(&mut decompressed).map_err(|e| {
         PbzxError::Decompression(format!(
             "Failed to decompress chunk at offset {}: {}",
-            chunk.offset, e
+            offset, e
         ))
     })?;
 
-    if decompressed.len() as u64 != chunk.header.uncompressed_size {
+    if decompressed.len() as u64 != uncompressed_size {
         return Err(PbzxError::InvalidChunk {
-            offset: chunk.offset,
+            offset,
             message: format!(
                 "Decompressed size mismatch: expected {}, got {}",
-                chunk.header.uncompressed_size,
+                uncompressed_size,
                 decompressed.len()
             ),
         });
@@ -279,6 +391,16 @@ fn decompress_chunk(chunk: ReadChunk) -> Result<Vec<u8>> {
     Ok(decompressed)
 }
 
+/// Decompress a single chunk (used by parallel decompression).
+#[cfg(feature = "parallel")]
+fn decompress_chunk(chunk: ReadChunk) -> Result<Vec<u8>> {
+    if chunk.header.is_uncompressed() {
+        return Ok(chunk.data);
+    }
+
+    decompress_chunk_data(&chunk.data, chunk.header.uncompressed_size, chunk.offset)
+}
+
 impl<R: Read + Seek> PbzxReader<R> {
     /// Reset the reader to the beginning of the chunks.
     pub fn reset(&mut self) -> Result<()> {
@@ -315,6 +437,24 @@ impl<R: Read + Seek> PbzxReader<R> {
         self.reset()?;
         Ok(chunks)
     }
+
+    /// Count the chunks without decompressing or collecting their info.
+    ///
+    /// Equivalent to `chunk_info()?.len()`; the reader is reset afterwards.
+    pub fn chunk_count(&mut self) -> Result<usize> {
+        self.reset()?;
+        let mut count = 0;
+
+        while let Some(header) = self.read_chunk_header()? {
+            self.reader
+                .seek(SeekFrom::Current(header.compressed_size as i64))?;
+            self.current_offset += header.compressed_size;
+            count += 1;
+        }
+
+        self.reset()?;
+        Ok(count)
+    }
 }
 
 /// Information about a single chunk in the archive.
@@ -407,6 +547,83 @@ mod tests {
 
         assert!(matches!(result, Err(PbzxError::InvalidMagic(_))));
     }
+
+    #[test]
+    fn test_chunk_count() {
+        use crate::writer::{CpioBuilder, PbzxWriter};
+
+        let mut cpio_builder = CpioBuilder::new();
+        cpio_builder.add_file("data.bin", &[0x42u8; 4000], 0o644);
+        let cpio_data = cpio_builder.finish();
+
+        let mut pbzx_data = Vec::new();
+        let mut writer = PbzxWriter::new(&mut pbzx_data).chunk_size(512);
+        writer.write_cpio(&cpio_data).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = PbzxReader::new(Cursor::new(&pbzx_data)).unwrap();
+        let count = reader.chunk_count().unwrap();
+        assert!(count > 1);
+        assert_eq!(count, reader.chunk_info().unwrap().len());
+        // The reader is left at the first chunk
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        let mut empty = PbzxReader::new(Cursor::new(create_minimal_pbzx())).unwrap();
+        assert_eq!(empty.chunk_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_verify() {
+        use crate::writer::{CpioBuilder, PbzxWriter};
+
+        let mut cpio_builder = CpioBuilder::new();
+        cpio_builder.add_file("data.bin", &b"verify me ".repeat(400), 0o644);
+        let cpio_data = cpio_builder.finish();
+
+        let mut pbzx_data = Vec::new();
+        let mut writer = PbzxWriter::new(&mut pbzx_data).chunk_size(1024);
+        writer.write_cpio(&cpio_data).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = PbzxReader::new(Cursor::new(&pbzx_data)).unwrap();
+        let chunks = reader.chunk_info().unwrap();
+        assert!(chunks.len() > 2 && chunks.iter().all(|c| c.is_compressed));
+        let report = reader.verify().unwrap();
+        assert!(report.is_ok(), "{:?}", report.failures);
+        assert_eq!(report.chunks, chunks.len());
+        assert_eq!(report.decompressed_bytes, cpio_data.len() as u64);
+
+        // Corrupt the middle of the second chunk; the others still verify
+        let mut corrupted = pbzx_data.clone();
+        let mid = (chunks[1].offset + chunks[1].compressed_size / 2) as usize;
+        for byte in &mut corrupted[mid..mid + 4] {
+            *byte ^= 0xFF;
+        }
+        let report = PbzxReader::new(Cursor::new(&corrupted)).unwrap().verify().unwrap();
+        assert_eq!(report.chunks, chunks.len());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].index, 1);
+        assert_eq!(report.failures[0].offset, chunks[1].offset);
+
+        // Cut the archive off inside the last chunk
+        let last = chunks.last().unwrap();
+        let truncated = &pbzx_data[..(last.offset + 1) as usize];
+        let report = PbzxReader::new(Cursor::new(truncated)).unwrap().verify().unwrap();
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].index, chunks.len() - 1);
+
+        // A header claiming far more data than the archive holds is a
+        // truncated chunk, not an allocation of the claimed size
+        let mut bogus = pbzx_data[..last.offset as usize - 16].to_vec();
+        bogus.extend_from_slice(&last.uncompressed_size.to_be_bytes());
+        bogus.extend_from_slice(&(u64::MAX / 2).to_be_bytes());
+        bogus.extend_from_slice(b"short");
+        let report = PbzxReader::new(Cursor::new(&bogus)).unwrap().verify().unwrap();
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].index, chunks.len() - 1);
+        assert!(matches!(report.failures[0].error, PbzxError::InvalidChunk { .. }));
+        assert!(PbzxReader::new(Cursor::new(&bogus)).unwrap().decompress().is_err());
+    }
 }
 
 #[cfg(test)]
@@ -492,3 +709,4 @@ mod parallel_tests {
         assert_eq!(sequential, output);
     }
 }
+
@@ -3,15 +3,24 @@
 //! This module provides functionality to create new PBZX archives from files
 //! or directories.
 
+use std::collections::HashMap;
+#[cfg(feature = "pack")]
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
+#[cfg(feature = "pack")]
+use std::io::BufWriter;
+use std::io::{Read, Write};
+#[cfg(feature = "pack")]
 use std::path::Path;
 
 use byteorder::{BigEndian, WriteBytesExt};
+use xz2::stream::{Check, Filters, LzmaOptions, Stream};
 use xz2::write::XzEncoder;
 
 use crate::error::{PbzxError, Result};
-use crate::format::PBZX_MAGIC;
+use crate::format::{
+    CpioFormat, CpioHeader, CPIO_MAGIC_BIN, CPIO_MAGIC_CRC, CPIO_MAGIC_NEWC, CPIO_MAGIC_ODC,
+    CPIO_TRAILER, PBZX_MAGIC, XZ_MAGIC,
+};
 
 /// Default chunk size for compression (16 MB).
 pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024 * 1024;
@@ -19,6 +28,54 @@ pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024 * 1024;
 /// XZ compression preset (0-9, higher = better compression but slower).
 pub const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
 
+/// XZ encoder settings beyond the preset level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct XzOptions {
+    /// Use the slower "extreme" variant of the preset.
+    pub extreme: bool,
+    /// LZMA2 dictionary size in bytes; `None` keeps the preset's size.
+    pub dict_size: Option<u32>,
+}
+
+/// Complete [`PbzxWriter`] configuration.
+///
+/// # Example
+///
+/// ```no_run
+/// use pbzx::{PbzxOptions, PbzxWriter};
+/// use std::fs::File;
+///
+/// let options = PbzxOptions {
+///     compression_level: 9,
+///     chunk_size: 8 * 1024 * 1024,
+///     ..Default::default()
+/// };
+/// let writer = PbzxWriter::with_options(File::create("output.pbzx").unwrap(), options);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PbzxOptions {
+    /// XZ preset level (0-9); higher values are clamped to 9.
+    pub compression_level: u32,
+    /// Uncompressed bytes per chunk.
+    pub chunk_size: usize,
+    /// Further XZ encoder settings.
+    pub xz_options: XzOptions,
+    /// Store the first chunk raw instead of XZ-compressing it, so the start
+    /// of the payload can be inspected without decompression.
+    pub store_first_uncompressed: bool,
+}
+
+impl Default for PbzxOptions {
+    fn default() -> Self {
+        Self {
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            xz_options: XzOptions::default(),
+            store_first_uncompressed: false,
+        }
+    }
+}
+
 /// Builder for creating PBZX archives.
 ///
 /// # Example
@@ -38,31 +95,42 @@ pub const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
 /// ```
 pub struct PbzxWriter<W> {
     writer: W,
-    chunk_size: usize,
-    compression_level: u32,
+    options: PbzxOptions,
     flags: u64,
     header_written: bool,
+    chunks_written: u64,
     total_written: u64,
 }
 
 impl<W: Write> PbzxWriter<W> {
     /// Create a new PBZX writer.
     pub fn new(writer: W) -> Self {
+        Self::with_options(writer, PbzxOptions::default())
+    }
+
+    /// Create a new PBZX writer configured by `options`.
+    pub fn with_options(writer: W, mut options: PbzxOptions) -> Self {
+        options.compression_level = options.compression_level.min(9);
         Self {
             writer,
-            chunk_size: DEFAULT_CHUNK_SIZE,
-            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            options,
             flags: 0x0100000000000000, // Default flags (version 1)
             header_written: false,
+            chunks_written: 0,
             total_written: 0,
         }
     }
 
+    /// The options this writer compresses with.
+    pub fn options(&self) -> &PbzxOptions {
+        &self.options
+    }
+
     /// Set the chunk size for compression.
     ///
     /// Larger chunks may compress better but use more memory.
     pub fn chunk_size(mut self, size: usize) -> Self {
-        self.chunk_size = size;
+        self.options.chunk_size = size;
         self
     }
 
@@ -70,7 +138,19 @@ impl<W: Write> PbzxWriter<W> {
     ///
     /// Higher levels produce smaller files but take longer.
     pub fn compression_level(mut self, level: u32) -> Self {
-        self.compression_level = level.min(9);
+        self.options.compression_level = level.min(9);
+        self
+    }
+
+    /// Set further XZ encoder options.
+    pub fn xz_options(mut self, xz_options: XzOptions) -> Self {
+        self.options.xz_options = xz_options;
+        self
+    }
+
+    /// Store the first chunk raw instead of compressing it.
+    pub fn store_first_uncompressed(mut self, store: bool) -> Self {
+        self.options.store_first_uncompressed = store;
         self
     }
 
@@ -94,42 +174,108 @@ impl<W: Write> PbzxWriter<W> {
         Ok(())
     }
 
+    /// Build the XZ stream encoder for one chunk.
+    fn xz_stream(&self) -> Result<Stream> {
+        let xz = &self.options.xz_options;
+        let mut preset = self.options.compression_level;
+        if xz.extreme {
+            preset |= 0x8000_0000; // LZMA_PRESET_EXTREME
+        }
+        let map_err = |e: xz2::stream::Error| PbzxError::Compression(format!("Invalid XZ options: {}", e));
+
+        let Some(dict_size) = xz.dict_size else {
+            return Stream::new_easy_encoder(preset, Check::Crc64).map_err(map_err);
+        };
+        let mut lzma = LzmaOptions::new_preset(preset).map_err(map_err)?;
+        lzma.dict_size(dict_size);
+        let mut filters = Filters::new();
+        filters.lzma2(&lzma);
+        Stream::new_stream_encoder(&filters, Check::Crc64).map_err(map_err)
+    }
+
     /// Write a single chunk of data.
     fn write_chunk(&mut self, data: &[u8]) -> Result<()> {
         let uncompressed_size = data.len() as u64;
 
-        // Compress the data
-        let mut compressed = Vec::new();
-        {
-            let mut encoder = XzEncoder::new(&mut compressed, self.compression_level);
-            encoder.write_all(data).map_err(|e| {
-                PbzxError::Compression(format!("Failed to compress chunk: {}", e))
-            })?;
-            encoder.finish().map_err(|e| {
-                PbzxError::Compression(format!("Failed to finish compression: {}", e))
-            })?;
-        }
+        let stored = self.options.store_first_uncompressed && self.chunks_written == 0;
+        let payload = if stored {
+            // Equal size fields mark the chunk as raw
+            data.to_vec()
+        } else {
+            let mut compressed = Vec::new();
+            {
+                let mut encoder = XzEncoder::new_stream(&mut compressed, self.xz_stream()?);
+                encoder.write_all(data).map_err(|e| {
+                    PbzxError::Compression(format!("Failed to compress chunk: {}", e))
+                })?;
+                encoder.finish().map_err(|e| {
+                    PbzxError::Compression(format!("Failed to finish compression: {}", e))
+                })?;
+            }
+            compressed
+        };
 
-        let compressed_size = compressed.len() as u64;
+        let compressed_size = payload.len() as u64;
 
         // Write chunk header
         self.writer.write_u64::<BigEndian>(uncompressed_size)?;
         self.writer.write_u64::<BigEndian>(compressed_size)?;
         self.total_written += 16;
 
-        // Write compressed data
-        self.writer.write_all(&compressed)?;
+        // Write chunk data
+        self.writer.write_all(&payload)?;
         self.total_written += compressed_size;
+        self.chunks_written += 1;
+
+        Ok(())
+    }
+
+    /// Write an already XZ-compressed chunk verbatim.
+    ///
+    /// `xz_data` must be one complete XZ stream that decompresses to
+    /// `uncompressed_size` bytes; it is copied without being recompressed or
+    /// decoded, so a wrong size is only caught when the archive is read.
+    /// Returns `InvalidChunk`, writing nothing, if `xz_data` lacks the XZ
+    /// magic or is exactly `uncompressed_size` bytes long (equal sizes mark
+    /// a stored chunk).
+    pub fn write_raw_chunk(&mut self, uncompressed_size: u64, xz_data: &[u8]) -> Result<()> {
+        let offset = self.total_written.max(PBZX_MAGIC.len() as u64 + 8);
+        let invalid = |message: &str| PbzxError::InvalidChunk {
+            offset,
+            message: message.into(),
+        };
+        if !xz_data.starts_with(&XZ_MAGIC) {
+            return Err(invalid("raw chunk is not an XZ stream"));
+        }
+        if xz_data.len() as u64 == uncompressed_size {
+            return Err(invalid("raw chunk size equals its uncompressed size"));
+        }
+
+        self.write_header()?;
+        self.writer.write_u64::<BigEndian>(uncompressed_size)?;
+        self.writer.write_u64::<BigEndian>(xz_data.len() as u64)?;
+        self.writer.write_all(xz_data)?;
+        self.total_written += 16 + xz_data.len() as u64;
+        self.chunks_written += 1;
 
         Ok(())
     }
 
     /// Write CPIO data to the archive, splitting into chunks.
+    ///
+    /// `data` must be a complete archive ending with the `TRAILER!!!` entry
+    /// (as produced by [`CpioBuilder::finish`]); otherwise nothing is written
+    /// and `InvalidCpio` is returned. Use
+    /// [`write_from_reader`](Self::write_from_reader) for unchecked data.
     pub fn write_cpio(&mut self, data: &[u8]) -> Result<()> {
+        if !ends_with_trailer(data) {
+            return Err(PbzxError::InvalidCpio("missing trailer".into()));
+        }
+
         self.write_header()?;
 
         // Split data into chunks and compress each
-        for chunk in data.chunks(self.chunk_size) {
+        for chunk in data.chunks(self.options.chunk_size) {
             self.write_chunk(chunk)?;
         }
 
@@ -141,7 +287,7 @@ impl<W: Write> PbzxWriter<W> {
         self.write_header()?;
 
         let mut total_read = 0u64;
-        let mut buffer = vec![0u8; self.chunk_size];
+        let mut buffer = vec![0u8; self.options.chunk_size];
 
         loop {
             let mut bytes_read = 0;
@@ -172,6 +318,8 @@ impl<W: Write> PbzxWriter<W> {
     }
 
     /// Finish writing and return the inner writer.
+    ///
+    /// Writes the header if no data was written, then flushes.
     pub fn finish(mut self) -> Result<W> {
         if !self.header_written {
             self.write_header()?;
@@ -179,12 +327,27 @@ impl<W: Write> PbzxWriter<W> {
         self.writer.flush()?;
         Ok(self.writer)
     }
+
+    /// Return the inner writer without writing the header or flushing.
+    ///
+    /// Use [`finish`](Self::finish) to produce a complete archive.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Check that CPIO data ends with the trailer entry's name, ignoring the
+/// NUL terminator and any zero padding after it.
+fn ends_with_trailer(data: &[u8]) -> bool {
+    let end = data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    data[..end].ends_with(CPIO_TRAILER.as_bytes())
 }
 
 /// CPIO archive builder for creating payloads.
 ///
 /// This creates a CPIO archive in the newc format that can be wrapped
-/// in a PBZX archive.
+/// in a PBZX archive. Use [`with_format`](Self::with_format) to emit
+/// odc, crc or binary headers instead.
 ///
 /// # Example
 ///
@@ -195,23 +358,50 @@ impl<W: Write> PbzxWriter<W> {
 /// builder.add_file("hello.txt", b"Hello, World!", 0o644);
 /// builder.add_directory("subdir", 0o755);
 /// builder.add_file("subdir/file.txt", b"Nested file", 0o644);
+/// builder.add_hardlink("subdir/link.txt", "subdir/file.txt").unwrap();
 ///
 /// let cpio_data = builder.finish();
 /// ```
 pub struct CpioBuilder {
     data: Vec<u8>,
+    format: CpioFormat,
     inode_counter: u32,
+    /// Regular file path -> index into `inodes`, for hard link lookups.
+    files: HashMap<String, usize>,
+    inodes: Vec<LinkedInode>,
+}
+
+/// A regular file inode and the header offsets of every path linking to it.
+struct LinkedInode {
+    header: CpioHeader,
+    offsets: Vec<usize>,
 }
 
 impl CpioBuilder {
     /// Create a new CPIO builder.
     pub fn new() -> Self {
+        Self::with_format(CpioFormat::Newc)
+    }
+
+    /// Create a CPIO builder that writes headers in `format`.
+    ///
+    /// Fields too wide for the format (e.g. a uid above 0o777777 in odc,
+    /// or above 0xFFFF in the binary format) are truncated.
+    pub fn with_format(format: CpioFormat) -> Self {
         Self {
             data: Vec::new(),
+            format,
             inode_counter: 1,
+            files: HashMap::new(),
+            inodes: Vec::new(),
         }
     }
 
+    /// Get the header format this builder writes.
+    pub fn format(&self) -> CpioFormat {
+        self.format
+    }
+
     /// Add a regular file to the archive.
     pub fn add_file(&mut self, path: &str, content: &[u8], mode: u32) {
         self.add_entry(path, content, 0o100000 | (mode & 0o7777), content.len() as u32);
@@ -232,66 +422,199 @@ impl CpioBuilder {
         );
     }
 
+    /// Add a hard link to a regular file already in the archive.
+    ///
+    /// The new entry shares the target's inode number and carries no data;
+    /// the link count of every header for that inode is updated in place.
+    /// Fails with `FileNotFound` if `target` was not added as a regular file.
+    pub fn add_hardlink(&mut self, path: &str, target: &str) -> Result<()> {
+        let index = *self
+            .files
+            .get(target)
+            .ok_or_else(|| PbzxError::FileNotFound(target.to_string()))?;
+
+        let inode = &mut self.inodes[index];
+        inode.header.nlink += 1;
+        let mut header = inode.header.clone();
+        header.name = path.to_string();
+        header.filesize = 0;
+        header.check = 0;
+        let nlink = header.nlink;
+        let offsets = inode.offsets.clone();
+        for offset in offsets {
+            self.patch_nlink(offset, nlink);
+        }
+
+        let offset = self.push_entry(&header, &[]);
+        self.inodes[index].offsets.push(offset);
+        self.files.insert(path.to_string(), index);
+        Ok(())
+    }
+
+    /// Add an entry with explicit metadata.
+    ///
+    /// Mode, ownership, mtime and device numbers are taken from `header`;
+    /// the name comes from `header.name` and the size from `data`. The
+    /// inode number, link count and checksum are assigned by the builder.
+    pub fn add_header(&mut self, header: &CpioHeader, data: &[u8]) {
+        let mut header = header.clone();
+        header.ino = self.next_inode();
+        header.nlink = 1;
+        header.filesize = data.len() as u32;
+        self.push_file_entry(header, data);
+    }
+
     /// Add an entry to the CPIO archive.
     fn add_entry(&mut self, name: &str, data: &[u8], mode: u32, filesize: u32) {
-        let namesize = name.len() + 1; // Include null terminator
+        let header = CpioHeader {
+            ino: self.next_inode(),
+            mode,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            mtime: 0,
+            filesize,
+            devmajor: 0,
+            devminor: 0,
+            rdevmajor: 0,
+            rdevminor: 0,
+            namesize: 0,
+            check: 0,
+            name: name.to_string(),
+        };
+        self.push_file_entry(header, data);
+    }
+
+    fn next_inode(&mut self) -> u32 {
         let inode = self.inode_counter;
         self.inode_counter += 1;
+        inode
+    }
 
-        // Write header in newc format (all fields as 8-char hex)
-        write!(
-            &mut self.data,
-            "070701\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}\
-             {:08X}",
-            inode,     // ino
-            mode,      // mode
-            0,         // uid
-            0,         // gid
-            1,         // nlink
-            0,         // mtime
-            filesize,  // filesize
-            0,         // devmajor
-            0,         // devminor
-            0,         // rdevmajor
-            0,         // rdevminor
-            namesize,  // namesize
-            0,         // check
-        )
-        .unwrap();
-
-        // Write filename with null terminator
-        self.data.extend_from_slice(name.as_bytes());
-        self.data.push(0);
+    /// Write an entry, remembering regular files as hard link targets.
+    fn push_file_entry(&mut self, header: CpioHeader, data: &[u8]) {
+        let offset = self.push_entry(&header, data);
+        if header.is_file() {
+            self.files.insert(header.name.clone(), self.inodes.len());
+            self.inodes.push(LinkedInode {
+                header,
+                offsets: vec![offset],
+            });
+        }
+    }
 
-        // Pad to 4-byte boundary
-        let header_len = 110 + namesize;
-        let padding = (4 - (header_len % 4)) % 4;
-        self.data.extend(std::iter::repeat(0).take(padding));
+    /// Serialize a header, its name and data, returning the header offset.
+    fn push_entry(&mut self, header: &CpioHeader, data: &[u8]) -> usize {
+        let offset = self.data.len();
+        let namesize = header.name.len() as u32 + 1; // Include null terminator
+        let filesize = header.filesize;
+
+        match self.format {
+            CpioFormat::Newc | CpioFormat::Crc => {
+                let (magic, check) = if self.format == CpioFormat::Crc {
+                    let sum = data.iter().fold(0u32, |acc, &b| acc.wrapping_add(b as u32));
+                    (CPIO_MAGIC_CRC, sum)
+                } else {
+                    (CPIO_MAGIC_NEWC, 0)
+                };
+                self.data.extend_from_slice(magic);
+                // All fields as 8-char hex
+                for value in [
+                    header.ino,
+                    header.mode,
+                    header.uid,
+                    header.gid,
+                    header.nlink,
+                    header.mtime,
+                    filesize,
+                    header.devmajor,
+                    header.devminor,
+                    header.rdevmajor,
+                    header.rdevminor,
+                    namesize,
+                    check,
+                ] {
+                    self.data.extend_from_slice(format!("{:08X}", value).as_bytes());
+                }
+            }
+            CpioFormat::Odc => {
+                let dev = (header.devmajor << 8) | (header.devminor & 0xff);
+                let rdev = (header.rdevmajor << 8) | (header.rdevminor & 0xff);
+                self.data.extend_from_slice(CPIO_MAGIC_ODC);
+                for value in [dev, header.ino, header.mode, header.uid, header.gid, header.nlink, rdev] {
+                    self.data.extend_from_slice(format!("{:06o}", value & 0o777777).as_bytes());
+                }
+                self.data.extend_from_slice(format!("{:011o}", header.mtime).as_bytes());
+                self.data.extend_from_slice(format!("{:06o}", namesize & 0o777777).as_bytes());
+                self.data.extend_from_slice(format!("{:011o}", filesize).as_bytes());
+            }
+            CpioFormat::Bin { big_endian } => {
+                let dev = (header.devmajor << 8) | (header.devminor & 0xff);
+                let rdev = (header.rdevmajor << 8) | (header.rdevminor & 0xff);
+                let words = [
+                    CPIO_MAGIC_BIN as u32,
+                    dev,
+                    header.ino,
+                    header.mode,
+                    header.uid,
+                    header.gid,
+                    header.nlink,
+                    rdev,
+                    header.mtime >> 16,
+                    header.mtime,
+                    namesize,
+                    filesize >> 16,
+                    filesize,
+                ];
+                for word in words {
+                    let word = word as u16;
+                    if big_endian {
+                        self.data.extend_from_slice(&word.to_be_bytes());
+                    } else {
+                        self.data.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+            }
+        }
 
-        // Write file data
+        // Write filename with null terminator, padded to the format's alignment
+        self.data.extend_from_slice(header.name.as_bytes());
+        self.data.push(0);
+        let header_len = self.format.header_size() as u64 + namesize as u64;
+        let padding = self.format.padding(header_len);
+        self.data.extend(std::iter::repeat_n(0, padding as usize));
+
+        // Write file data, padded the same way
         self.data.extend_from_slice(data);
+        let data_padding = self.format.padding(data.len() as u64);
+        self.data.extend(std::iter::repeat_n(0, data_padding as usize));
 
-        // Pad data to 4-byte boundary
-        let data_padding = (4 - (data.len() % 4)) % 4;
-        self.data.extend(std::iter::repeat(0).take(data_padding));
+        offset
+    }
+
+    /// Rewrite the link count of the header starting at `offset`.
+    fn patch_nlink(&mut self, offset: usize, nlink: u32) {
+        match self.format {
+            CpioFormat::Newc | CpioFormat::Crc => {
+                let field = format!("{:08X}", nlink);
+                self.data[offset + 38..offset + 46].copy_from_slice(field.as_bytes());
+            }
+            CpioFormat::Odc => {
+                let field = format!("{:06o}", nlink & 0o777777);
+                self.data[offset + 36..offset + 42].copy_from_slice(field.as_bytes());
+            }
+            CpioFormat::Bin { big_endian } => {
+                let word = nlink as u16;
+                let bytes = if big_endian { word.to_be_bytes() } else { word.to_le_bytes() };
+                self.data[offset + 12..offset + 14].copy_from_slice(&bytes);
+            }
+        }
     }
 
     /// Finish the archive and return the CPIO data.
     pub fn finish(mut self) -> Vec<u8> {
         // Add trailer entry
-        self.add_entry("TRAILER!!!", &[], 0, 0);
+        self.add_entry(CPIO_TRAILER, &[], 0, 0);
         self.data
     }
 
@@ -312,16 +635,81 @@ impl Default for CpioBuilder {
     }
 }
 
+#[cfg(feature = "pack")]
+impl CpioBuilder {
+    /// Recursively add the contents of a host directory.
+    ///
+    /// Entries are named `prefix/relative/path`, or just the relative path
+    /// when `prefix` is empty; `src` itself is not added. Each directory's
+    /// children are added in name order, so packing the same tree twice
+    /// produces the same archive. Symlinks are stored as links (Unix only).
+    pub fn add_dir_recursive<P: AsRef<Path>>(&mut self, src: P, prefix: &str) -> Result<()> {
+        let mut entries = std::fs::read_dir(src.as_ref())?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+
+            let archive_path = if prefix.is_empty() {
+                name_str.to_string()
+            } else {
+                format!("{}/{}", prefix, name_str)
+            };
+
+            let metadata = entry.metadata()?;
+
+            if metadata.is_dir() {
+                #[cfg(unix)]
+                let mode = {
+                    use std::os::unix::fs::PermissionsExt;
+                    metadata.permissions().mode() & 0o7777
+                };
+                #[cfg(not(unix))]
+                let mode = 0o755;
+
+                self.add_directory(&archive_path, mode);
+                self.add_dir_recursive(&path, &archive_path)?;
+            } else if metadata.is_file() {
+                let mut file = File::open(&path)?;
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+
+                #[cfg(unix)]
+                let mode = {
+                    use std::os::unix::fs::PermissionsExt;
+                    metadata.permissions().mode() & 0o7777
+                };
+                #[cfg(not(unix))]
+                let mode = 0o644;
+
+                self.add_file(&archive_path, &content, mode);
+            } else if metadata.file_type().is_symlink() {
+                #[cfg(unix)]
+                {
+                    let target = std::fs::read_link(&path)?;
+                    let target_str = target.to_string_lossy();
+                    self.add_symlink(&archive_path, &target_str, 0o777);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Create a PBZX archive from a directory.
 ///
-/// This recursively adds all files and directories to the archive.
+/// This recursively adds all files and directories to the archive (see
+/// [`CpioBuilder::add_dir_recursive`]) and is the inverse of
+/// [`CpioReader::extract_all`](crate::CpioReader::extract_all). Returns the
+/// size of the uncompressed CPIO payload.
 ///
 /// # Example
 ///
 /// ```no_run
-/// use pbzx::writer::pack_directory;
-///
-/// pack_directory("input_dir", "output.pbzx", 6).unwrap();
+/// pbzx::pack_directory("input_dir", "output.pbzx", 6).unwrap();
 /// ```
 #[cfg(feature = "pack")]
 pub fn pack_directory<P: AsRef<Path>, Q: AsRef<Path>>(
@@ -333,7 +721,7 @@ pub fn pack_directory<P: AsRef<Path>, Q: AsRef<Path>>(
     let dest = dest.as_ref();
 
     let mut builder = CpioBuilder::new();
-    add_directory_to_cpio(&mut builder, source, "")?;
+    builder.add_dir_recursive(source, "")?;
 
     let cpio_data = builder.finish();
     let file = File::create(dest)?;
@@ -346,61 +734,6 @@ pub fn pack_directory<P: AsRef<Path>, Q: AsRef<Path>>(
     Ok(cpio_data.len() as u64)
 }
 
-/// Recursively add a directory to a CPIO builder.
-#[cfg(feature = "pack")]
-fn add_directory_to_cpio(builder: &mut CpioBuilder, base: &Path, prefix: &str) -> Result<()> {
-    for entry in std::fs::read_dir(base)? {
-        let entry = entry?;
-        let path = entry.path();
-        let name = entry.file_name();
-        let name_str = name.to_string_lossy();
-
-        let archive_path = if prefix.is_empty() {
-            name_str.to_string()
-        } else {
-            format!("{}/{}", prefix, name_str)
-        };
-
-        let metadata = entry.metadata()?;
-
-        if metadata.is_dir() {
-            #[cfg(unix)]
-            let mode = {
-                use std::os::unix::fs::PermissionsExt;
-                metadata.permissions().mode() & 0o7777
-            };
-            #[cfg(not(unix))]
-            let mode = 0o755;
-
-            builder.add_directory(&archive_path, mode);
-            add_directory_to_cpio(builder, &path, &archive_path)?;
-        } else if metadata.is_file() {
-            let mut file = File::open(&path)?;
-            let mut content = Vec::new();
-            file.read_to_end(&mut content)?;
-
-            #[cfg(unix)]
-            let mode = {
-                use std::os::unix::fs::PermissionsExt;
-                metadata.permissions().mode() & 0o7777
-            };
-            #[cfg(not(unix))]
-            let mode = 0o644;
-
-            builder.add_file(&archive_path, &content, mode);
-        } else if metadata.file_type().is_symlink() {
-            #[cfg(unix)]
-            {
-                let target = std::fs::read_link(&path)?;
-                let target_str = target.to_string_lossy();
-                builder.add_symlink(&archive_path, &target_str, 0o777);
-            }
-        }
-    }
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -418,6 +751,31 @@ mod tests {
         assert!(String::from_utf8_lossy(&data).contains("TRAILER!!!"));
     }
 
+    #[test]
+    #[cfg(all(feature = "pack", feature = "list", feature = "extract"))]
+    fn test_pack_directory() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("b.txt"), b"second").unwrap();
+        std::fs::create_dir(src.path().join("a")).unwrap();
+        std::fs::write(src.path().join("a/inner.bin"), vec![7u8; 5000]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("b.txt", src.path().join("link")).unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        let dest = out.path().join("packed.pbzx");
+        let cpio_len = pack_directory(src.path(), &dest, 6).unwrap();
+        assert!(cpio_len > 0);
+
+        let archive = crate::Archive::open(&dest).unwrap();
+        let paths: Vec<String> = archive.list().unwrap().into_iter().map(|e| e.path).collect();
+        let mut expected = vec!["a", "a/inner.bin", "b.txt"];
+        #[cfg(unix)]
+        expected.push("link");
+        assert_eq!(paths, expected);
+        assert_eq!(archive.extract_file("a/inner.bin").unwrap(), vec![7u8; 5000]);
+        assert_eq!(archive.extract_file("b.txt").unwrap(), b"second");
+    }
+
     #[test]
     fn test_pbzx_writer() {
         let mut output = Vec::new();
@@ -425,11 +783,138 @@ mod tests {
             .chunk_size(1024)
             .compression_level(0);
 
-        let test_data = b"Hello, PBZX World!";
-        writer.write_cpio(test_data).unwrap();
+        let mut builder = CpioBuilder::new();
+        builder.add_file("hello.txt", b"Hello, PBZX World!", 0o644);
+        writer.write_cpio(&builder.finish()).unwrap();
         writer.finish().unwrap();
 
         // Check magic
         assert_eq!(&output[0..4], b"pbzx");
     }
+
+    #[test]
+    fn test_pbzx_writer_returns_inner() {
+        let cpio_data = CpioBuilder::new().finish();
+        let mut writer = PbzxWriter::new(Vec::new()).compression_level(0);
+        writer.write_cpio(&cpio_data).unwrap();
+        let output = writer.finish().unwrap();
+
+        let mut reader = crate::PbzxReader::new(std::io::Cursor::new(&output)).unwrap();
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        // Nothing is written until the first chunk or finish()
+        let writer = PbzxWriter::new(Vec::new());
+        assert!(writer.into_inner().is_empty());
+
+        let mut writer = PbzxWriter::new(Vec::new()).compression_level(0);
+        writer.write_cpio(&cpio_data).unwrap();
+        let total = writer.total_written();
+        assert_eq!(writer.into_inner().len() as u64, total);
+    }
+
+    #[test]
+    fn test_write_raw_chunk() {
+        let mut builder = CpioBuilder::new();
+        builder.add_file("data.bin", &vec![0x42u8; 6000], 0o644);
+        builder.add_file("notes.txt", b"assembled from raw chunks", 0o644);
+        let cpio_data = builder.finish();
+
+        let chunks: Vec<(u64, Vec<u8>)> = cpio_data
+            .chunks(2048)
+            .map(|chunk| {
+                let mut encoder = XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(chunk).unwrap();
+                (chunk.len() as u64, encoder.finish().unwrap())
+            })
+            .collect();
+
+        let mut writer = PbzxWriter::new(Vec::new());
+        for (size, xz_data) in &chunks {
+            writer.write_raw_chunk(*size, xz_data).unwrap();
+        }
+        let total = writer.total_written();
+        let output = writer.finish().unwrap();
+        assert_eq!(output.len() as u64, total);
+
+        let mut reader = crate::PbzxReader::new(std::io::Cursor::new(&output)).unwrap();
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        // Raw chunks can follow compressed ones
+        let mut writer = PbzxWriter::new(Vec::new()).compression_level(0);
+        writer.write_from_reader(&cpio_data[..4096]).unwrap();
+        for (size, xz_data) in &chunks[2..] {
+            writer.write_raw_chunk(*size, xz_data).unwrap();
+        }
+        let output = writer.finish().unwrap();
+        let mut reader = crate::PbzxReader::new(std::io::Cursor::new(&output)).unwrap();
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        let mut writer = PbzxWriter::new(Vec::new());
+        assert!(matches!(
+            writer.write_raw_chunk(4, b"nope"),
+            Err(PbzxError::InvalidChunk { offset: 12, .. })
+        ));
+        let (_, xz_data) = &chunks[0];
+        assert!(matches!(
+            writer.write_raw_chunk(xz_data.len() as u64, xz_data),
+            Err(PbzxError::InvalidChunk { .. })
+        ));
+        assert!(writer.into_inner().is_empty(), "nothing should be written on error");
+    }
+
+    #[test]
+    fn test_write_cpio_requires_trailer() {
+        let mut builder = CpioBuilder::new();
+        builder.add_file("file.txt", b"contents", 0o644);
+        let cpio_data = builder.finish();
+
+        // Drop the trailer entry: 110-byte header + "TRAILER!!!\0", padded to 124
+        let trailer_len = 124;
+        let truncated = &cpio_data[..cpio_data.len() - trailer_len];
+
+        let mut writer = PbzxWriter::new(Vec::new());
+        assert!(matches!(writer.write_cpio(truncated), Err(PbzxError::InvalidCpio(_))));
+        assert!(matches!(writer.write_cpio(b""), Err(PbzxError::InvalidCpio(_))));
+        assert!(writer.into_inner().is_empty(), "nothing should be written on error");
+
+        // Zero padding after the trailer (as in block-aligned odc archives) is fine
+        let mut padded = cpio_data.clone();
+        padded.resize(padded.len() + 512, 0);
+        let mut writer = PbzxWriter::new(Vec::new()).compression_level(0);
+        writer.write_cpio(&padded).unwrap();
+    }
+
+    #[test]
+    fn test_pbzx_writer_with_options() {
+        let mut builder = CpioBuilder::new();
+        builder.add_file("data.bin", &[0x5Au8; 3000], 0o644);
+        builder.add_file("hello.txt", b"Hello, PBZX World!", 0o644);
+        let cpio_data = builder.finish();
+
+        let options = PbzxOptions {
+            compression_level: 12,
+            chunk_size: 1024,
+            xz_options: XzOptions { extreme: true, dict_size: Some(64 * 1024) },
+            store_first_uncompressed: true,
+        };
+        let mut writer = PbzxWriter::with_options(Vec::new(), options.clone());
+        assert_eq!(writer.options().compression_level, 9);
+        writer.write_cpio(&cpio_data).unwrap();
+        let output = writer.finish().unwrap();
+
+        let mut reader = crate::PbzxReader::new(std::io::Cursor::new(&output)).unwrap();
+        let chunks = reader.chunk_info().unwrap();
+        assert_eq!(chunks.len(), cpio_data.len().div_ceil(1024));
+        assert!(!chunks[0].is_compressed);
+        assert!(chunks[1..].iter().all(|c| c.is_compressed));
+        assert_eq!(reader.decompress().unwrap(), cpio_data);
+
+        // The chained setters reach the same configuration
+        let writer = PbzxWriter::new(Vec::new())
+            .compression_level(12)
+            .chunk_size(1024)
+            .xz_options(options.xz_options)
+            .store_first_uncompressed(true);
+        assert_eq!(writer.options(), &PbzxOptions { compression_level: 9, ..options });
+    }
 }
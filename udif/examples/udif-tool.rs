@@ -88,9 +88,10 @@ fn cmd_info(args: &[String]) -> Result<()> {
     }
 
     let path = &args[0];
-    let archive = DmgArchive::open(path)?;
-    let stats = archive.stats();
-    let comp_info = archive.compression_info();
+    let mut archive = DmgArchive::open(path)?;
+    let stats = archive.stats()?;
+    let comp_info = archive.compression_info()?;
+    let format = archive.image_format()?;
     let koly = archive.koly();
 
     println!("DMG Information: {}", path);
@@ -98,6 +99,7 @@ fn cmd_info(args: &[String]) -> Result<()> {
     println!();
     println!("Header:");
     println!("  Version:          {}", stats.version);
+    println!("  Format:           {}", format.name());
     println!("  Sector count:     {}", stats.sector_count);
     println!("  Data fork length: {} bytes", stats.data_fork_length);
     println!("  Segment:          {}/{}", koly.segment_number, koly.segment_count);
@@ -150,7 +152,7 @@ fn cmd_list(args: &[String]) -> Result<()> {
 
     println!("Partitions in {}:", path);
     println!("{}", "=".repeat(80));
-    println!("{:>4}  {:>12}  {:>12}  {:>8}  {}", "ID", "Sectors", "Size", "Ratio", "Name");
+    println!("{:>4}  {:>12}  {:>12}  {:>8}  Name", "ID", "Sectors", "Size", "Ratio");
     println!("{}", "-".repeat(80));
 
     for p in partitions {
@@ -184,6 +186,7 @@ fn cmd_extract(args: &[String]) -> Result<()> {
     println!("Opening {}...", dmg_path);
     let options = DmgReaderOptions {
         verify_checksums: !fast_mode,
+        ..Default::default()
     };
     let mut archive = DmgArchive::open_with_options(dmg_path, options)?;
 
@@ -210,6 +213,7 @@ fn cmd_extract_all(args: &[String]) -> Result<()> {
     println!("Opening {}...", dmg_path);
     let options = DmgReaderOptions {
         verify_checksums: !fast_mode,
+        ..Default::default()
     };
     let mut archive = DmgArchive::open_with_options(dmg_path, options)?;
 
@@ -239,6 +243,7 @@ fn cmd_extract_partition(args: &[String]) -> Result<()> {
     println!("Opening {}...", dmg_path);
     let options = DmgReaderOptions {
         verify_checksums: !fast_mode,
+        ..Default::default()
     };
     let mut archive = DmgArchive::open_with_options(dmg_path, options)?;
 
@@ -255,7 +260,7 @@ fn cmd_extract_partition(args: &[String]) -> Result<()> {
 fn cmd_create(args: &[String]) -> Result<()> {
     if args.len() < 2 {
         eprintln!("Usage: udif-tool create <output.dmg> <input-file> [--compression <method>] [--fast]");
-        eprintln!("  Compression methods: raw, zlib (default), bzip2, lzfse");
+        eprintln!("  Compression methods: raw, zlib (default), bzip2, lzfse, auto");
         process::exit(1);
     }
 
@@ -274,6 +279,7 @@ fn cmd_create(args: &[String]) -> Result<()> {
                         "zlib" => CompressionMethod::Zlib,
                         "bzip2" | "bz2" => CompressionMethod::Bzip2,
                         "lzfse" => CompressionMethod::Lzfse,
+                        "auto" => CompressionMethod::Auto,
                         _ => {
                             eprintln!("Unknown compression method: {}", args[i + 1]);
                             process::exit(1);
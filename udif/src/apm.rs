@@ -0,0 +1,160 @@
+//! Apple Partition Map (APM) parsing
+//!
+//! Older DMGs hold a whole-disk image whose block 0 is a Driver Descriptor
+//! Map (`ER` signature) followed by one `PM` entry per block describing each
+//! partition. [`parse`] reads that map from a decompressed disk.
+//!
+//! Like [`format`](crate::format), this module only needs `alloc`.
+
+use alloc::{format, string::String, vec::Vec};
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::{DppError, Result};
+use crate::PartitionType;
+
+/// Driver Descriptor Map signature "ER" (0x4552)
+pub const DDM_SIGNATURE: u16 = 0x4552;
+
+/// Partition map entry signature "PM" (0x504D)
+pub const PM_SIGNATURE: u16 = 0x504D;
+
+/// Size of a partition map entry in bytes
+const PM_ENTRY_SIZE: usize = 136;
+
+/// One entry of an Apple Partition Map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApmPartition {
+    /// Partition name (e.g. "disk image")
+    pub name: String,
+    /// Partition type (e.g. "Apple_HFS", "Apple_partition_map")
+    pub type_name: String,
+    /// First block of the partition
+    pub start_block: u32,
+    /// Length of the partition in blocks
+    pub block_count: u32,
+    /// Partition status flags (`pmPartStatus`)
+    pub status: u32,
+    /// Block size from the Driver Descriptor Map
+    pub block_size: u32,
+}
+
+impl ApmPartition {
+    /// Byte offset of the partition within the disk
+    pub fn offset(&self) -> u64 {
+        self.start_block as u64 * self.block_size as u64
+    }
+
+    /// Length of the partition in bytes
+    pub fn size(&self) -> u64 {
+        self.block_count as u64 * self.block_size as u64
+    }
+
+    /// Filesystem type derived from the partition type
+    pub fn partition_type(&self) -> PartitionType {
+        PartitionType::from_partition_name(&self.type_name)
+    }
+}
+
+/// Read the Apple Partition Map from a raw decompressed disk
+///
+/// Block 0 must carry the `ER` signature; its block size places the
+/// `PM` entries, which start at block 1. The entry count comes from the
+/// first entry's `pmMapBlkCnt`.
+pub fn parse(disk: &[u8]) -> Result<Vec<ApmPartition>> {
+    if disk.len() < 4 || BigEndian::read_u16(&disk[0..2]) != DDM_SIGNATURE {
+        return Err(DppError::InvalidPartitionMap("missing 'ER' driver descriptor map".into()));
+    }
+    let block_size = BigEndian::read_u16(&disk[2..4]) as u32;
+    if !block_size.is_power_of_two() || block_size < 512 {
+        return Err(DppError::InvalidPartitionMap(format!("invalid block size {}", block_size)));
+    }
+
+    let entry_at = |index: u64| -> Result<&[u8]> {
+        let start = (index + 1)
+            .checked_mul(block_size as u64)
+            .and_then(|s| usize::try_from(s).ok())
+            .filter(|s| s.checked_add(PM_ENTRY_SIZE).is_some_and(|end| end <= disk.len()))
+            .ok_or_else(|| DppError::InvalidPartitionMap(format!("entry {} past end of disk", index)))?;
+        Ok(&disk[start..start + PM_ENTRY_SIZE])
+    };
+
+    let first = entry_at(0)?;
+    let map_entries = BigEndian::read_u32(&first[4..8]);
+
+    let mut partitions = Vec::new();
+    for index in 0..map_entries as u64 {
+        let entry = entry_at(index)?;
+        if BigEndian::read_u16(&entry[0..2]) != PM_SIGNATURE {
+            return Err(DppError::InvalidPartitionMap(format!("entry {} has no 'PM' signature", index)));
+        }
+        partitions.push(ApmPartition {
+            name: c_string(&entry[16..48]),
+            type_name: c_string(&entry[48..80]),
+            start_block: BigEndian::read_u32(&entry[8..12]),
+            block_count: BigEndian::read_u32(&entry[12..16]),
+            status: BigEndian::read_u32(&entry[88..92]),
+            block_size,
+        });
+    }
+    Ok(partitions)
+}
+
+/// NUL-terminated fixed-width string field
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Disk with a DDM and one `PM` entry per `(name, type, start, count)`
+    fn build_disk(partitions: &[(&str, &str, u32, u32)]) -> Vec<u8> {
+        let mut disk = vec![0u8; 512 * 64];
+        disk[0..2].copy_from_slice(&DDM_SIGNATURE.to_be_bytes());
+        disk[2..4].copy_from_slice(&512u16.to_be_bytes());
+        disk[4..8].copy_from_slice(&64u32.to_be_bytes());
+
+        for (i, (name, type_name, start, count)) in partitions.iter().enumerate() {
+            let entry = &mut disk[512 * (i + 1)..512 * (i + 2)];
+            entry[0..2].copy_from_slice(&PM_SIGNATURE.to_be_bytes());
+            entry[4..8].copy_from_slice(&(partitions.len() as u32).to_be_bytes());
+            entry[8..12].copy_from_slice(&start.to_be_bytes());
+            entry[12..16].copy_from_slice(&count.to_be_bytes());
+            entry[16..16 + name.len()].copy_from_slice(name.as_bytes());
+            entry[48..48 + type_name.len()].copy_from_slice(type_name.as_bytes());
+            entry[88..92].copy_from_slice(&0x33u32.to_be_bytes());
+        }
+        disk
+    }
+
+    #[test]
+    fn test_parse_apm() {
+        let disk = build_disk(&[
+            ("Apple", "Apple_partition_map", 1, 3),
+            ("disk image", "Apple_HFS", 4, 56),
+            ("", "Apple_Free", 60, 4),
+        ]);
+
+        let partitions = parse(&disk).unwrap();
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(partitions[0].type_name, "Apple_partition_map");
+        assert_eq!(partitions[1].name, "disk image");
+        assert_eq!(partitions[1].partition_type(), PartitionType::Hfs);
+        assert_eq!(partitions[1].offset(), 4 * 512);
+        assert_eq!(partitions[1].size(), 56 * 512);
+        assert_eq!(partitions[1].status, 0x33);
+        assert_eq!(partitions[2].name, "");
+
+        let mut bad = disk.clone();
+        bad[0] = 0;
+        assert!(matches!(parse(&bad), Err(DppError::InvalidPartitionMap(_))));
+
+        // More entries claimed than the disk holds
+        let mut bad = disk;
+        bad[512 + 4..512 + 8].copy_from_slice(&1000u32.to_be_bytes());
+        assert!(matches!(parse(&bad), Err(DppError::InvalidPartitionMap(_))));
+    }
+}
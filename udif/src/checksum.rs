@@ -7,7 +7,9 @@
 
 use byteorder::{BigEndian, ByteOrder};
 
-/// Checksum type constants
+use crate::format::ChecksumType;
+
+/// Checksum type constants (raw values of [`ChecksumType`])
 pub const CHECKSUM_TYPE_NONE: u32 = 0;
 pub const CHECKSUM_TYPE_CRC32: u32 = 2;
 
@@ -31,7 +33,7 @@ pub fn create_checksum_array(crc: u32) -> [u8; 128] {
 
 /// Check if a checksum is present (non-zero)
 pub fn has_checksum(checksum_type: u32, checksum_array: &[u8; 128]) -> bool {
-    if checksum_type != CHECKSUM_TYPE_CRC32 {
+    if ChecksumType::from(checksum_type) != ChecksumType::Crc32 {
         return false;
     }
     // Check if the checksum value is non-zero
@@ -47,7 +49,7 @@ pub fn verify_crc32(
     data: &[u8],
 ) -> Result<(), (u32, u32)> {
     // If not CRC32 type, skip verification
-    if checksum_type != CHECKSUM_TYPE_CRC32 {
+    if ChecksumType::from(checksum_type) != ChecksumType::Crc32 {
         return Ok(());
     }
 
@@ -68,6 +70,7 @@ pub fn verify_crc32(
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_range_loop)]
 mod tests {
     use super::*;
 
@@ -1,9 +1,10 @@
 //! Error types for DMG operations
 
+use alloc::string::String;
 use thiserror::Error;
 
 /// Result type alias for DPP operations
-pub type Result<T> = std::result::Result<T, DppError>;
+pub type Result<T> = core::result::Result<T, DppError>;
 
 /// Errors that can occur during DMG operations
 #[derive(Error, Debug)]
@@ -13,6 +14,7 @@ pub enum DppError {
     InvalidMagic,
 
     /// I/O error
+    #[cfg(feature = "std")]
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -20,6 +22,24 @@ pub enum DppError {
     #[error("invalid koly header: {0}")]
     InvalidKolyHeader(String),
 
+    /// A structurally implausible field value
+    #[error("corrupted data: {field} {reason}")]
+    CorruptedData {
+        /// Name of the offending field (e.g. `koly.header_size`)
+        field: &'static str,
+        /// What is wrong with its value
+        reason: String,
+    },
+
+    /// The image is shorter than its koly header declares
+    #[error("truncated image: data fork needs at least {expected} bytes, file has {actual}")]
+    Truncated {
+        /// Minimum image length implied by the data fork and koly trailer
+        expected: u64,
+        /// Actual image length
+        actual: u64,
+    },
+
     /// Invalid plist format
     #[error("invalid plist: {0}")]
     InvalidPlist(String),
@@ -28,6 +48,10 @@ pub enum DppError {
     #[error("invalid block map: {0}")]
     InvalidBlockMap(String),
 
+    /// Invalid Apple Partition Map
+    #[error("invalid partition map: {0}")]
+    InvalidPartitionMap(String),
+
     /// Decompression error
     #[error("decompression error: {0}")]
     Decompression(String),
@@ -63,4 +87,8 @@ pub enum DppError {
     /// Checksum mismatch
     #[error("checksum mismatch: expected {expected:#x}, got {actual:#x}")]
     ChecksumMismatch { expected: u32, actual: u32 },
+
+    /// Stopped because the caller raised its cancel flag
+    #[error("operation cancelled")]
+    Cancelled,
 }
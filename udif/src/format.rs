@@ -4,8 +4,13 @@
 //! 1. Data blocks (compressed partition data)
 //! 2. XML plist containing block maps (blkx)
 //! 3. Koly trailer (512 bytes at end of file)
+//!
+//! The structs and their `from_bytes` parsers only need `alloc`, so this module
+//! is available without the `std` feature. Reader/writer helpers are `std`-only.
 
-use byteorder::{BigEndian, ReadBytesExt};
+use alloc::{format, vec::Vec};
+use byteorder::{BigEndian, ByteOrder};
+#[cfg(feature = "std")]
 use std::io::{Read, Seek, SeekFrom};
 
 use crate::error::{DppError, Result};
@@ -19,6 +24,9 @@ pub const MISH_MAGIC: &[u8; 4] = b"mish";
 /// Koly header size in bytes
 pub const KOLY_SIZE: usize = 512;
 
+/// Magic of an embedded code signature SuperBlob (`CSMAGIC_EMBEDDED_SIGNATURE`)
+pub const CODE_SIGNATURE_MAGIC: [u8; 4] = [0xFA, 0xDE, 0x0C, 0xC0];
+
 /// Block chunk types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -65,6 +73,41 @@ impl TryFrom<u32> for BlockType {
     }
 }
 
+/// Checksum algorithm recorded in koly and mish headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumType {
+    /// No checksum
+    None,
+    /// CRC32 (stored big-endian in the first 4 bytes)
+    Crc32,
+    /// SHA-256
+    Sha256,
+    /// Unrecognized checksum type
+    Other(u32),
+}
+
+impl From<u32> for ChecksumType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => ChecksumType::None,
+            2 => ChecksumType::Crc32,
+            3 => ChecksumType::Sha256,
+            other => ChecksumType::Other(other),
+        }
+    }
+}
+
+impl From<ChecksumType> for u32 {
+    fn from(value: ChecksumType) -> Self {
+        match value {
+            ChecksumType::None => 0,
+            ChecksumType::Crc32 => 2,
+            ChecksumType::Sha256 => 3,
+            ChecksumType::Other(other) => other,
+        }
+    }
+}
+
 /// Koly trailer structure (512 bytes at end of DMG)
 #[derive(Debug, Clone)]
 pub struct KolyHeader {
@@ -92,7 +135,7 @@ pub struct KolyHeader {
     pub segment_count: u32,
     /// Segment ID (UUID)
     pub segment_id: [u8; 16],
-    /// Data checksum type (2 = CRC32)
+    /// Data checksum type (see [`ChecksumType`])
     pub data_checksum_type: u32,
     /// Data checksum size
     pub data_checksum_size: u32,
@@ -104,7 +147,7 @@ pub struct KolyHeader {
     pub plist_length: u64,
     /// Reserved (64 bytes)
     pub reserved: [u8; 64],
-    /// Master checksum type
+    /// Master checksum type (see [`ChecksumType`])
     pub master_checksum_type: u32,
     /// Master checksum size
     pub master_checksum_size: u32,
@@ -117,82 +160,162 @@ pub struct KolyHeader {
 }
 
 impl KolyHeader {
-    /// Read koly header from the end of a file
-    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
-        // Seek to 512 bytes before end
-        reader.seek(SeekFrom::End(-(KOLY_SIZE as i64)))?;
+    /// Parse a koly header from its 512 on-disk bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < KOLY_SIZE {
+            return Err(DppError::InvalidKolyHeader(format!(
+                "koly trailer too short: {} bytes",
+                data.len()
+            )));
+        }
 
         let mut magic = [0u8; 4];
-        reader.read_exact(&mut magic)?;
+        magic.copy_from_slice(&data[0..4]);
         if &magic != KOLY_MAGIC {
             return Err(DppError::InvalidMagic);
         }
 
-        let version = reader.read_u32::<BigEndian>()?;
-        let header_size = reader.read_u32::<BigEndian>()?;
-        let flags = reader.read_u32::<BigEndian>()?;
-        let running_data_fork_offset = reader.read_u64::<BigEndian>()?;
-        let data_fork_offset = reader.read_u64::<BigEndian>()?;
-        let data_fork_length = reader.read_u64::<BigEndian>()?;
-        let rsrc_fork_offset = reader.read_u64::<BigEndian>()?;
-        let rsrc_fork_length = reader.read_u64::<BigEndian>()?;
-        let segment_number = reader.read_u32::<BigEndian>()?;
-        let segment_count = reader.read_u32::<BigEndian>()?;
-
         let mut segment_id = [0u8; 16];
-        reader.read_exact(&mut segment_id)?;
-
-        let data_checksum_type = reader.read_u32::<BigEndian>()?;
-        let data_checksum_size = reader.read_u32::<BigEndian>()?;
+        segment_id.copy_from_slice(&data[64..80]);
 
         let mut data_checksum = [0u8; 128];
-        reader.read_exact(&mut data_checksum)?;
-
-        let plist_offset = reader.read_u64::<BigEndian>()?;
-        let plist_length = reader.read_u64::<BigEndian>()?;
+        data_checksum.copy_from_slice(&data[88..216]);
 
         let mut reserved = [0u8; 64];
-        reader.read_exact(&mut reserved)?;
-
-        let master_checksum_type = reader.read_u32::<BigEndian>()?;
-        let master_checksum_size = reader.read_u32::<BigEndian>()?;
+        reserved.copy_from_slice(&data[232..296]);
 
         let mut master_checksum = [0u8; 128];
-        reader.read_exact(&mut master_checksum)?;
-
-        let image_variant = reader.read_u32::<BigEndian>()?;
-        let sector_count = reader.read_u64::<BigEndian>()?;
+        master_checksum.copy_from_slice(&data[304..432]);
 
-        // Skip final reserved bytes (12 bytes to reach 512 total)
+        // Final reserved bytes (444..512) are ignored
 
         Ok(KolyHeader {
             magic,
-            version,
-            header_size,
-            flags,
-            running_data_fork_offset,
-            data_fork_offset,
-            data_fork_length,
-            rsrc_fork_offset,
-            rsrc_fork_length,
-            segment_number,
-            segment_count,
+            version: BigEndian::read_u32(&data[4..8]),
+            header_size: BigEndian::read_u32(&data[8..12]),
+            flags: BigEndian::read_u32(&data[12..16]),
+            running_data_fork_offset: BigEndian::read_u64(&data[16..24]),
+            data_fork_offset: BigEndian::read_u64(&data[24..32]),
+            data_fork_length: BigEndian::read_u64(&data[32..40]),
+            rsrc_fork_offset: BigEndian::read_u64(&data[40..48]),
+            rsrc_fork_length: BigEndian::read_u64(&data[48..56]),
+            segment_number: BigEndian::read_u32(&data[56..60]),
+            segment_count: BigEndian::read_u32(&data[60..64]),
             segment_id,
-            data_checksum_type,
-            data_checksum_size,
+            data_checksum_type: BigEndian::read_u32(&data[80..84]),
+            data_checksum_size: BigEndian::read_u32(&data[84..88]),
             data_checksum,
-            plist_offset,
-            plist_length,
+            plist_offset: BigEndian::read_u64(&data[216..224]),
+            plist_length: BigEndian::read_u64(&data[224..232]),
             reserved,
-            master_checksum_type,
-            master_checksum_size,
+            master_checksum_type: BigEndian::read_u32(&data[296..300]),
+            master_checksum_size: BigEndian::read_u32(&data[300..304]),
             master_checksum,
-            image_variant,
-            sector_count,
+            image_variant: BigEndian::read_u32(&data[432..436]),
+            sector_count: BigEndian::read_u64(&data[436..444]),
         })
     }
 
+    /// Fuzzing entry point: treat `data` as a whole image, parse the koly
+    /// trailer from its last 512 bytes and [`validate`](Self::validate) it
+    ///
+    /// Never panics; every malformed input yields an error.
+    #[cfg(feature = "fuzzing")]
+    pub fn try_parse(data: &[u8]) -> Result<Self> {
+        let start = data.len().checked_sub(KOLY_SIZE).ok_or_else(|| {
+            DppError::InvalidKolyHeader(format!("image too short for a koly trailer: {} bytes", data.len()))
+        })?;
+        let header = Self::from_bytes(&data[start..])?;
+        header.validate(data.len() as u64)?;
+        Ok(header)
+    }
+
+    /// Data fork checksum algorithm
+    pub fn data_checksum_type(&self) -> ChecksumType {
+        ChecksumType::from(self.data_checksum_type)
+    }
+
+    /// Master checksum algorithm
+    pub fn master_checksum_type(&self) -> ChecksumType {
+        ChecksumType::from(self.master_checksum_type)
+    }
+
+    /// Check that the header's fields are plausible for an image of
+    /// `image_len` bytes (koly trailer included)
+    ///
+    /// The data fork and plist must lie before the trailer; a data fork
+    /// running past it yields [`DppError::Truncated`]. The resource
+    /// fork is not checked here; `DmgReader::resource_fork` validates it
+    /// when it is read.
+    pub fn validate(&self, image_len: u64) -> Result<()> {
+        if self.header_size as usize != KOLY_SIZE {
+            return Err(DppError::CorruptedData {
+                field: "koly.header_size",
+                reason: format!("is {}, expected {}", self.header_size, KOLY_SIZE),
+            });
+        }
+        // Checksum sizes are in bits; the on-disk arrays hold 128 bytes
+        let max_bits = 128 * 8;
+        if self.data_checksum_size > max_bits {
+            return Err(DppError::CorruptedData {
+                field: "koly.data_checksum_size",
+                reason: format!("is {} bits, more than the 128-byte checksum field", self.data_checksum_size),
+            });
+        }
+        if self.master_checksum_size > max_bits {
+            return Err(DppError::CorruptedData {
+                field: "koly.master_checksum_size",
+                reason: format!("is {} bits, more than the 128-byte checksum field", self.master_checksum_size),
+            });
+        }
+
+        let trailer_start = image_len.saturating_sub(KOLY_SIZE as u64);
+        // A data fork that would fit in a longer file means the image was cut short
+        if let Some(end) = self.data_fork_offset.checked_add(self.data_fork_length) {
+            if end > trailer_start {
+                return Err(DppError::Truncated {
+                    expected: end.saturating_add(KOLY_SIZE as u64),
+                    actual: image_len,
+                });
+            }
+        }
+
+        let ranges = [
+            ("koly.data_fork_offset", self.data_fork_offset, self.data_fork_length),
+            ("koly.plist_offset", self.plist_offset, self.plist_length),
+        ];
+        for (field, offset, length) in ranges {
+            if offset.checked_add(length).is_none_or(|end| end > trailer_start) {
+                return Err(DppError::CorruptedData {
+                    field,
+                    reason: format!(
+                        "range {}+{} extends past the koly trailer at {}",
+                        offset, length, trailer_start
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Read koly header from the end of a file
+    ///
+    /// The header is checked with [`validate`](Self::validate) against the
+    /// stream length.
+    #[cfg(feature = "std")]
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        // Seek to 512 bytes before end
+        let trailer_start = reader.seek(SeekFrom::End(-(KOLY_SIZE as i64)))?;
+
+        let mut buf = [0u8; KOLY_SIZE];
+        reader.read_exact(&mut buf)?;
+        let koly = Self::from_bytes(&buf)?;
+        koly.validate(trailer_start + KOLY_SIZE as u64)?;
+        Ok(koly)
+    }
+
     /// Write koly header to a writer
+    #[cfg(feature = "std")]
     pub fn write<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
         use byteorder::WriteBytesExt;
 
@@ -252,36 +375,27 @@ impl BlockRun {
             return Err(DppError::InvalidBlockMap("block run too short".into()));
         }
 
-        let mut cursor = std::io::Cursor::new(data);
-        let block_type_raw = cursor.read_u32::<BigEndian>()?;
-        let block_type = BlockType::try_from(block_type_raw)?;
-        let comment = cursor.read_u32::<BigEndian>()?;
-        let sector_number = cursor.read_u64::<BigEndian>()?;
-        let sector_count = cursor.read_u64::<BigEndian>()?;
-        let compressed_offset = cursor.read_u64::<BigEndian>()?;
-        let compressed_length = cursor.read_u64::<BigEndian>()?;
+        let block_type = BlockType::try_from(BigEndian::read_u32(&data[0..4]))?;
 
         Ok(BlockRun {
             block_type,
-            comment,
-            sector_number,
-            sector_count,
-            compressed_offset,
-            compressed_length,
+            comment: BigEndian::read_u32(&data[4..8]),
+            sector_number: BigEndian::read_u64(&data[8..16]),
+            sector_count: BigEndian::read_u64(&data[16..24]),
+            compressed_offset: BigEndian::read_u64(&data[24..32]),
+            compressed_length: BigEndian::read_u64(&data[32..40]),
         })
     }
 
     /// Convert to bytes
     pub fn to_bytes(&self) -> Vec<u8> {
-        use byteorder::WriteBytesExt;
-
         let mut buf = Vec::with_capacity(40);
-        buf.write_u32::<BigEndian>(self.block_type as u32).unwrap();
-        buf.write_u32::<BigEndian>(self.comment).unwrap();
-        buf.write_u64::<BigEndian>(self.sector_number).unwrap();
-        buf.write_u64::<BigEndian>(self.sector_count).unwrap();
-        buf.write_u64::<BigEndian>(self.compressed_offset).unwrap();
-        buf.write_u64::<BigEndian>(self.compressed_length).unwrap();
+        buf.extend_from_slice(&(self.block_type as u32).to_be_bytes());
+        buf.extend_from_slice(&self.comment.to_be_bytes());
+        buf.extend_from_slice(&self.sector_number.to_be_bytes());
+        buf.extend_from_slice(&self.sector_count.to_be_bytes());
+        buf.extend_from_slice(&self.compressed_offset.to_be_bytes());
+        buf.extend_from_slice(&self.compressed_length.to_be_bytes());
         buf
     }
 }
@@ -319,7 +433,7 @@ pub struct MishHeader {
     pub block_descriptor_count: u32,
     /// Reserved
     pub reserved: [u8; 24],
-    /// Checksum type
+    /// Checksum type (see [`ChecksumType`])
     pub checksum_type: u32,
     /// Checksum size
     pub checksum_size: u32,
@@ -334,15 +448,49 @@ pub struct MishHeader {
 impl MishHeader {
     /// Parse mish header from base64-decoded data
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        // Header is 204 bytes + block runs (40 bytes each)
+        let mut header = Self::header_from_bytes(data)?;
+
+        // Parse block runs (40 bytes each). Sized in u64 so a huge count
+        // cannot overflow `usize` on 32-bit targets.
+        let runs_needed = 204 + header.actual_block_count as u64 * 40;
+        if (data.len() as u64) < runs_needed {
+            return Err(DppError::InvalidBlockMap(format!(
+                "mish data truncated: {} block runs need {} bytes, got {}",
+                header.actual_block_count,
+                runs_needed,
+                data.len()
+            )));
+        }
+        let runs_end = runs_needed as usize;
+        header.block_runs = data[204..runs_end]
+            .chunks_exact(40)
+            .map(BlockRun::from_bytes)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(header)
+    }
+
+    /// Sum the compressed lengths in raw mish data without parsing its runs
+    ///
+    /// Runs cut off by truncated data are not counted.
+    #[cfg(feature = "std")]
+    pub(crate) fn compressed_size_from_bytes(data: &[u8]) -> u64 {
+        let count = data.get(200..204).map_or(0, BigEndian::read_u32) as usize;
+        data.get(204..)
+            .unwrap_or_default()
+            .chunks_exact(40)
+            .take(count)
+            .map(|run| BigEndian::read_u64(&run[32..40]))
+            .sum()
+    }
+
+    /// Parse only the fixed 204-byte header, leaving `block_runs` empty
+    pub(crate) fn header_from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < 204 {
             return Err(DppError::InvalidBlockMap("mish data too short".into()));
         }
 
-        let mut cursor = std::io::Cursor::new(data);
-
         let mut magic = [0u8; 4];
-        cursor.read_exact(&mut magic)?;
+        magic.copy_from_slice(&data[0..4]);
         if &magic != MISH_MAGIC {
             return Err(DppError::InvalidBlockMap(format!(
                 "invalid mish magic: {:?}",
@@ -350,33 +498,25 @@ impl MishHeader {
             )));
         }
 
-        let version = cursor.read_u32::<BigEndian>()?;
-        let first_sector = cursor.read_u64::<BigEndian>()?;
-        let sector_count = cursor.read_u64::<BigEndian>()?;
-        let data_offset = cursor.read_u64::<BigEndian>()?;
-        let buffers_needed = cursor.read_u32::<BigEndian>()?;
-        let block_descriptor_count = cursor.read_u32::<BigEndian>()?;
+        let version = BigEndian::read_u32(&data[4..8]);
+        let first_sector = BigEndian::read_u64(&data[8..16]);
+        let sector_count = BigEndian::read_u64(&data[16..24]);
+        let data_offset = BigEndian::read_u64(&data[24..32]);
+        let buffers_needed = BigEndian::read_u32(&data[32..36]);
+        let block_descriptor_count = BigEndian::read_u32(&data[36..40]);
 
         let mut reserved = [0u8; 24];
-        cursor.read_exact(&mut reserved)?;
+        reserved.copy_from_slice(&data[40..64]);
 
-        let checksum_type = cursor.read_u32::<BigEndian>()?;
-        let checksum_size = cursor.read_u32::<BigEndian>()?;
+        let checksum_type = BigEndian::read_u32(&data[64..68]);
+        let checksum_size = BigEndian::read_u32(&data[68..72]);
 
         let mut checksum = [0u8; 128];
-        cursor.read_exact(&mut checksum)?;
+        checksum.copy_from_slice(&data[72..200]);
 
         // Read the actual block count from reserved2 field (at offset 200)
         // The field at offset 36 (block_descriptor_count) often contains the partition index
-        let actual_block_count = cursor.read_u32::<BigEndian>()?;
-
-        // Parse block runs (40 bytes each)
-        let mut block_runs = Vec::with_capacity(actual_block_count as usize);
-        for _ in 0..actual_block_count {
-            let mut run_data = [0u8; 40];
-            cursor.read_exact(&mut run_data)?;
-            block_runs.push(BlockRun::from_bytes(&run_data)?);
-        }
+        let actual_block_count = BigEndian::read_u32(&data[200..204]);
 
         Ok(MishHeader {
             magic,
@@ -391,13 +531,27 @@ impl MishHeader {
             checksum_size,
             checksum,
             actual_block_count,
-            block_runs,
+            block_runs: Vec::new(),
         })
     }
 
-    /// Calculate total uncompressed size in bytes
-    pub fn uncompressed_size(&self) -> u64 {
-        self.sector_count * 512
+    /// Fuzzing entry point over base64-decoded `mish` data
+    ///
+    /// Same as [`from_bytes`](Self::from_bytes), which never panics; exposed
+    /// under this name so fuzz targets can call every format the same way.
+    #[cfg(feature = "fuzzing")]
+    pub fn try_parse(data: &[u8]) -> Result<Self> {
+        Self::from_bytes(data)
+    }
+
+    /// Partition checksum algorithm
+    pub fn checksum_type(&self) -> ChecksumType {
+        ChecksumType::from(self.checksum_type)
+    }
+
+    /// Calculate total uncompressed size in bytes, given the image's sector size
+    pub fn uncompressed_size(&self, sector_size: u64) -> u64 {
+        self.sector_count * sector_size
     }
 
     /// Calculate total compressed size in bytes
@@ -413,16 +567,27 @@ impl MishHeader {
 #[derive(Debug, Clone)]
 pub struct PartitionEntry {
     /// Partition name
-    pub name: String,
+    pub name: alloc::string::String,
+    /// User-facing name from the plist `CFName` key, if present
+    ///
+    /// Often a localized label that differs from `name`, which carries the
+    /// partition type (e.g. `disk image (Apple_HFS : 4)`).
+    pub display_name: Option<alloc::string::String>,
     /// Partition ID
     pub id: i32,
     /// Attributes
     pub attributes: u32,
     /// Block map (mish data)
     pub block_map: MishHeader,
+    /// Checksum declared by the plist `Checksum` key, if present
+    ///
+    /// Holds the same bytes as the leading `checksum_size / 8` bytes of the
+    /// mish checksum; see `DmgReaderOptions::verify_checksums`.
+    pub plist_checksum: Option<Vec<u8>>,
 }
 
 /// Check if data has the koly magic at the end (512 bytes from end)
+#[cfg(feature = "std")]
 pub fn is_dmg<R: Read + Seek>(reader: &mut R) -> bool {
     let pos = reader.stream_position().ok();
     let result = (|| {
@@ -439,3 +604,109 @@ pub fn is_dmg<R: Read + Seek>(reader: &mut R) -> bool {
 
     result.unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_koly_from_bytes() {
+        let mut data = vec![0u8; KOLY_SIZE];
+        data[0..4].copy_from_slice(KOLY_MAGIC);
+        data[4..8].copy_from_slice(&4u32.to_be_bytes());
+        data[8..12].copy_from_slice(&512u32.to_be_bytes());
+        data[32..40].copy_from_slice(&0x1234u64.to_be_bytes()); // data_fork_length
+        data[216..224].copy_from_slice(&0x1234u64.to_be_bytes()); // plist_offset
+        data[224..232].copy_from_slice(&0x99u64.to_be_bytes()); // plist_length
+        data[432..436].copy_from_slice(&1u32.to_be_bytes()); // image_variant
+        data[436..444].copy_from_slice(&8u64.to_be_bytes()); // sector_count
+
+        let koly = KolyHeader::from_bytes(&data).unwrap();
+        assert_eq!(koly.version, 4);
+        assert_eq!(koly.header_size, 512);
+        assert_eq!(koly.data_fork_length, 0x1234);
+        assert_eq!(koly.plist_offset, 0x1234);
+        assert_eq!(koly.plist_length, 0x99);
+        assert_eq!(koly.image_variant, 1);
+        assert_eq!(koly.sector_count, 8);
+        for len in 0..KOLY_SIZE {
+            assert!(KolyHeader::from_bytes(&data[..len]).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn test_koly_from_bytes_rejects_bad_input() {
+        assert!(matches!(
+            KolyHeader::from_bytes(&[0u8; 100]),
+            Err(DppError::InvalidKolyHeader(_))
+        ));
+        assert!(matches!(
+            KolyHeader::from_bytes(&[0u8; KOLY_SIZE]),
+            Err(DppError::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn test_mish_from_bytes_truncated_runs() {
+        // Header claims two block runs but carries none
+        let mut data = vec![0u8; 204];
+        data[0..4].copy_from_slice(MISH_MAGIC);
+        data[200..204].copy_from_slice(&2u32.to_be_bytes());
+
+        assert!(matches!(
+            MishHeader::from_bytes(&data),
+            Err(DppError::InvalidBlockMap(_))
+        ));
+
+        // Every cut of a complete two-run map is rejected
+        let run = BlockRun {
+            block_type: BlockType::ZeroFill,
+            comment: 0,
+            sector_number: 0,
+            sector_count: 8,
+            compressed_offset: 0,
+            compressed_length: 0,
+        };
+        data.extend(run.to_bytes());
+        data.extend(run.to_bytes());
+        assert_eq!(MishHeader::from_bytes(&data).unwrap().block_runs.len(), 2);
+        for len in 0..data.len() {
+            assert!(MishHeader::from_bytes(&data[..len]).is_err(), "{}", len);
+        }
+        for len in 0..40 {
+            assert!(BlockRun::from_bytes(&run.to_bytes()[..len]).is_err());
+        }
+
+        // Largest possible run count must be reported, not overflow
+        data[200..204].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(MishHeader::from_bytes(&data), Err(DppError::InvalidBlockMap(_))));
+    }
+
+    #[cfg(feature = "fuzzing")]
+    #[test]
+    fn test_try_parse() {
+        let mut image = vec![0u8; 1024];
+        let koly = &mut image[512..];
+        koly[0..4].copy_from_slice(KOLY_MAGIC);
+        koly[8..12].copy_from_slice(&512u32.to_be_bytes());
+        koly[32..40].copy_from_slice(&512u64.to_be_bytes()); // data_fork_length
+        assert_eq!(KolyHeader::try_parse(&image).unwrap().data_fork_length, 512);
+        assert!(KolyHeader::try_parse(&image[..100]).is_err());
+        assert!(KolyHeader::try_parse(&image[1..]).is_err());
+        for len in 0..image.len() {
+            let _ = KolyHeader::try_parse(&image[..len]);
+            let _ = MishHeader::try_parse(&image[..len]);
+        }
+    }
+
+    #[test]
+    fn test_checksum_type_mapping() {
+        assert_eq!(ChecksumType::from(0), ChecksumType::None);
+        assert_eq!(ChecksumType::from(2), ChecksumType::Crc32);
+        assert_eq!(ChecksumType::from(3), ChecksumType::Sha256);
+        assert_eq!(ChecksumType::from(0x1234), ChecksumType::Other(0x1234));
+        assert_eq!(u32::from(ChecksumType::Other(0x1234)), 0x1234);
+        assert_eq!(u32::from(ChecksumType::Crc32), 2);
+    }
+}
@@ -38,18 +38,43 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # `no_std`
+//!
+//! With `default-features = false` the crate builds as `no_std` + `alloc` and
+//! exposes only the pure parsers in [`format`] (`KolyHeader::from_bytes`,
+//! `MishHeader::from_bytes`, `BlockRun::from_bytes`) and [`apm::parse`]. Everything that touches
+//! files or decompression requires the `std` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+pub mod apm;
+#[cfg(feature = "std")]
 pub mod checksum;
 pub mod error;
 pub mod format;
+#[cfg(feature = "std")]
+pub mod range;
+#[cfg(feature = "std")]
 pub mod reader;
+#[cfg(feature = "std")]
 pub mod writer;
 
+#[cfg(feature = "std")]
 pub use checksum::{crc32, CHECKSUM_TYPE_CRC32, CHECKSUM_TYPE_NONE};
+pub use apm::ApmPartition;
 pub use error::{DppError, Result};
-pub use format::{BlockType, KolyHeader, MishHeader, PartitionEntry};
-pub use reader::{open, is_dmg, CompressionInfo, DmgReader, DmgReaderOptions, DmgStats};
-pub use writer::{create, create_from_data, create_from_file, CompressionMethod, DmgWriter};
+pub use format::{BlockType, ChecksumType, KolyHeader, MishHeader, PartitionEntry};
+#[cfg(feature = "std")]
+pub use reader::{open, is_dmg, decode_block_run, CompressionInfo, DecodedBlock, DmgReader, DmgReaderOptions, DmgStats, ImageFormat, IoStats, PartitionReader, ChecksumStatus, VerifyReport};
+#[cfg(feature = "async")]
+pub use reader::PartitionStream;
+#[cfg(feature = "std")]
+pub use range::{RangeReader, RangeSource};
+#[cfg(feature = "std")]
+pub use writer::{create, create_from_data, create_from_file, CompressionMethod, DmgWriter, WriteStats};
 
 /// Partition filesystem type detected from the partition name
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -84,23 +109,67 @@ impl PartitionType {
     }
 }
 
+/// Filesystem detected from the superblock at the start of a partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedFs {
+    /// HFS+ volume header (`H+` at offset 1024)
+    Hfs,
+    /// HFSX volume header (`HX` at offset 1024)
+    Hfsx,
+    /// APFS container superblock (`NXSB` at offset 32)
+    Apfs,
+    /// No recognized superblock
+    Unknown,
+}
+
+impl DetectedFs {
+    /// Number of leading partition bytes needed to detect any supported filesystem
+    pub const PROBE_SIZE: usize = 4096;
+
+    /// Classify a partition from its first bytes
+    pub fn from_superblock(data: &[u8]) -> Self {
+        let hfs_signature = data.get(1024..1026);
+        if hfs_signature == Some(b"H+") {
+            DetectedFs::Hfs
+        } else if hfs_signature == Some(b"HX") {
+            DetectedFs::Hfsx
+        } else if data.get(32..36) == Some(b"NXSB") {
+            DetectedFs::Apfs
+        } else {
+            DetectedFs::Unknown
+        }
+    }
+
+    /// Returns `true` if this filesystem can be parsed as HFS+
+    pub fn is_hfs_compatible(&self) -> bool {
+        matches!(self, DetectedFs::Hfs | DetectedFs::Hfsx)
+    }
+}
+
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::BufReader;
+#[cfg(feature = "std")]
+use std::io::{BufReader, Cursor, Read, Seek};
+#[cfg(feature = "std")]
 use std::path::Path;
 
 /// High-level DMG archive interface
-pub struct DmgArchive {
-    reader: DmgReader<BufReader<File>>,
+#[cfg(feature = "std")]
+pub struct DmgArchive<R = BufReader<File>> {
+    reader: DmgReader<R>,
 }
 
 /// Partition information
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub struct PartitionInfo {
     /// Partition name
     pub name: String,
+    /// User-facing name from the plist `CFName` key, if present
+    pub display_name: Option<String>,
     /// Partition ID
     pub id: i32,
-    /// Number of sectors (512 bytes each)
+    /// Number of sectors (`DmgReaderOptions::sector_size` bytes each)
     pub sectors: u64,
     /// Uncompressed size in bytes
     pub size: u64,
@@ -110,6 +179,27 @@ pub struct PartitionInfo {
     pub partition_type: PartitionType,
 }
 
+#[cfg(feature = "std")]
+impl PartitionInfo {
+    pub(crate) fn from_entry(p: &PartitionEntry, sector_size: u64) -> Self {
+        PartitionInfo {
+            name: p.name.clone(),
+            display_name: p.display_name.clone(),
+            id: p.id,
+            sectors: p.block_map.sector_count,
+            size: p.block_map.uncompressed_size(sector_size),
+            compressed_size: p.block_map.compressed_size(),
+            partition_type: PartitionType::from_partition_name(&p.name),
+        }
+    }
+
+    /// Name to show users: `display_name` when the plist has one, else `name`
+    pub fn label(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[cfg(feature = "std")]
 impl DmgArchive {
     /// Open a DMG file with default options (checksum verification enabled)
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
@@ -125,43 +215,100 @@ impl DmgArchive {
         let reader = DmgReader::open_with_options(path, options)?;
         Ok(DmgArchive { reader })
     }
+}
+
+#[cfg(feature = "std")]
+impl DmgArchive<Cursor<Vec<u8>>> {
+    /// Open an in-memory DMG with default options (checksum verification enabled)
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self> {
+        let reader = DmgReader::from_bytes(data)?;
+        Ok(DmgArchive { reader })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek + Clone> DmgArchive<R> {
+    /// Get an independent seekable reader for every partition
+    ///
+    /// See [`DmgReader::partition_readers`].
+    pub fn partition_readers(&mut self) -> Result<Vec<(PartitionInfo, PartitionReader<R>)>> {
+        self.reader.partition_readers()
+    }
+}
 
+#[cfg(feature = "std")]
+impl<R: Read + Seek> DmgArchive<R> {
     /// Get archive statistics
-    pub fn stats(&self) -> DmgStats {
+    pub fn stats(&mut self) -> Result<DmgStats> {
         self.reader.stats()
     }
 
     /// Get compression info
-    pub fn compression_info(&self) -> CompressionInfo {
+    pub fn compression_info(&mut self) -> Result<CompressionInfo> {
         self.reader.compression_info()
     }
 
+    /// Get compression info for a single partition
+    pub fn partition_compression_info(&mut self, id: i32) -> Result<Option<CompressionInfo>> {
+        self.reader.partition_compression_info(id)
+    }
+
+    /// Get the image format (UDZO, UDBZ, ULFO, ...)
+    pub fn image_format(&mut self) -> Result<ImageFormat> {
+        self.reader.image_format()
+    }
+
+    /// Read the resource fork region (holds the code signature of signed images)
+    pub fn resource_fork(&mut self) -> Result<Option<Vec<u8>>> {
+        self.reader.resource_fork()
+    }
+
+    /// Check whether the image carries a code signature
+    pub fn has_signature(&mut self) -> Result<bool> {
+        self.reader.has_signature()
+    }
+
+    /// Get counters for the work done by extraction calls so far
+    pub fn io_stats(&self) -> &IoStats {
+        self.reader.io_stats()
+    }
+
+    /// Parse block maps deferred by `DmgReaderOptions::lazy_partitions`
+    ///
+    /// See [`DmgReader::load_partitions`].
+    pub fn load_partitions(&mut self) -> Result<()> {
+        self.reader.load_partitions()
+    }
+
     /// List all partitions
+    ///
+    /// Does not load deferred block maps; their compressed sizes are summed
+    /// from the raw run tables.
     pub fn partitions(&self) -> Vec<PartitionInfo> {
         self.reader
             .partitions()
             .iter()
-            .map(|p| PartitionInfo {
-                name: p.name.clone(),
-                id: p.id,
-                sectors: p.block_map.sector_count,
-                size: p.block_map.uncompressed_size(),
-                compressed_size: p.block_map.compressed_size(),
-                partition_type: PartitionType::from_partition_name(&p.name),
+            .enumerate()
+            .map(|(index, p)| PartitionInfo {
+                compressed_size: self.reader.partition_compressed_size(index),
+                ..PartitionInfo::from_entry(p, self.reader.sector_size())
             })
             .collect()
     }
 
+    /// List the partitions of one type, in image order
+    ///
+    /// Matching is exact: `PartitionType::Hfs` does not include HFSX
+    /// partitions.
+    pub fn partitions_of_type(&self, t: PartitionType) -> impl Iterator<Item = PartitionInfo> {
+        self.partitions()
+            .into_iter()
+            .filter(move |p| p.partition_type == t)
+    }
+
     /// Get partition by name
     pub fn partition(&self, name: &str) -> Option<PartitionInfo> {
-        self.reader.partition(name).map(|p| PartitionInfo {
-            name: p.name.clone(),
-            id: p.id,
-            sectors: p.block_map.sector_count,
-            size: p.block_map.uncompressed_size(),
-            compressed_size: p.block_map.compressed_size(),
-            partition_type: PartitionType::from_partition_name(&p.name),
-        })
+        self.partitions().into_iter().find(|p| p.name == name)
     }
 
     /// Extract a partition by ID
@@ -197,6 +344,19 @@ impl DmgArchive {
         self.reader.decompress_partition_to(id, writer)
     }
 
+    /// Stream a partition to a writer, reporting progress and checking `cancel`
+    ///
+    /// See [`DmgReader::decompress_partition_to_with_progress`].
+    pub fn extract_partition_to_with_progress<W: std::io::Write, F: FnMut(u64, u64)>(
+        &mut self,
+        id: i32,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&std::sync::atomic::AtomicBool>,
+    ) -> Result<u64> {
+        self.reader.decompress_partition_to_with_progress(id, writer, progress, cancel)
+    }
+
     /// Stream the main HFS+/APFS partition to a writer (low memory usage)
     pub fn extract_main_partition_to<W: std::io::Write>(
         &mut self,
@@ -205,6 +365,13 @@ impl DmgArchive {
         self.reader.decompress_main_partition_to(writer)
     }
 
+    /// Check the image and every partition against their recorded checksums
+    ///
+    /// See [`DmgReader::verify_all`].
+    pub fn verify(&mut self) -> Result<VerifyReport> {
+        self.reader.verify_all()
+    }
+
     /// Get the ID of the main HFS+/APFS partition
     pub fn main_partition_id(&self) -> Result<i32> {
         self.reader.main_partition_id()
@@ -216,6 +383,17 @@ impl DmgArchive {
         self.reader.hfs_partition_id()
     }
 
+    /// Detect a partition's filesystem from its superblock
+    ///
+    /// Only the first [`DetectedFs::PROBE_SIZE`] bytes are decompressed, and
+    /// the partition name is ignored.
+    pub fn detect_partition_fs(&mut self, id: i32) -> Result<DetectedFs> {
+        let head = self
+            .reader
+            .read_partition_head(id, DetectedFs::PROBE_SIZE as u64)?;
+        Ok(DetectedFs::from_superblock(&head))
+    }
+
     /// Extract a partition to a file
     pub fn extract_partition_to_file<P: AsRef<Path>>(&mut self, id: i32, path: P) -> Result<()> {
         let mut file = File::create(path)?;
@@ -237,6 +415,7 @@ impl DmgArchive {
 }
 
 /// Builder for creating DMG files
+#[cfg(feature = "std")]
 pub struct DmgBuilder {
     compression: CompressionMethod,
     compression_level: u32,
@@ -245,12 +424,14 @@ pub struct DmgBuilder {
     skip_checksums: bool,
 }
 
+#[cfg(feature = "std")]
 impl Default for DmgBuilder {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl DmgBuilder {
     /// Create a new DMG builder
     pub fn new() -> Self {
@@ -293,6 +474,18 @@ impl DmgBuilder {
         self
     }
 
+    /// Estimate the size of the image `build` would write
+    ///
+    /// See [`DmgWriter::estimate_output_size`].
+    pub fn estimate_output_size(&self) -> u64 {
+        let writer = DmgWriter::new(Cursor::new(Vec::new()))
+            .compression(self.compression)
+            .compression_level(self.compression_level)
+            .chunk_size(self.chunk_size);
+        let partitions: Vec<&[u8]> = self.partitions.iter().map(|(_, data)| data.as_slice()).collect();
+        writer.estimate_output_size(&partitions)
+    }
+
     /// Build and write the DMG to a file
     pub fn build<P: AsRef<Path>>(self, path: P) -> Result<()> {
         let mut writer = DmgWriter::create(path)?
@@ -310,21 +503,37 @@ impl DmgBuilder {
 }
 
 /// Quick check if a file is a valid DMG
+#[cfg(feature = "std")]
 pub fn check_dmg<P: AsRef<Path>>(path: P) -> bool {
     is_dmg(path)
 }
 
 /// Get statistics about a DMG file
+#[cfg(feature = "std")]
 pub fn stats<P: AsRef<Path>>(path: P) -> Result<DmgStats> {
-    let reader = DmgReader::open(path)?;
-    Ok(reader.stats())
+    let mut reader = DmgReader::open(path)?;
+    reader.stats()
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
+#[allow(clippy::needless_range_loop, clippy::op_ref)]
 mod tests {
     use super::*;
     use std::io::Cursor;
 
+    /// Xorshift64 stream, for partition data that does not compress
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
     #[test]
     fn test_block_type_conversion() {
         assert_eq!(BlockType::try_from(0x00000000).unwrap(), BlockType::ZeroFill);
@@ -715,6 +924,7 @@ mod tests {
             CompressionMethod::Raw,
             CompressionMethod::Zlib,
             CompressionMethod::Bzip2,
+            CompressionMethod::Lzma,
             // LZFSE tested separately due to buffer quirks
         ] {
             let mut dmg_buf = Vec::new();
@@ -786,6 +996,35 @@ mod tests {
         assert_eq!(&decompressed[..], &original[..]);
     }
 
+    #[test]
+    fn test_lzma_dmg_roundtrip() {
+        // Compressible text followed by a zeroed tail and a partial sector
+        let mut original = b"LZMA block run roundtrip data. ".repeat(3000);
+        original.extend(std::iter::repeat_n(0u8, 40_000));
+        original.extend_from_slice(b"tail");
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Lzma)
+                .compression_level(9)
+                .chunk_size(16 * 1024);
+            writer.add_partition("disk", &original).unwrap();
+            writer.finish().unwrap();
+        }
+        assert!(dmg_buf.len() < original.len() / 4);
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        let info = reader.compression_info().unwrap();
+        assert!(info.xz_blocks > 1);
+        assert_eq!(info.zlib_blocks + info.lzfse_blocks + info.bzip2_blocks, 0);
+
+        let extracted = reader.decompress_partition(0).unwrap();
+        assert_eq!(extracted.len(), original.len().div_ceil(512) * 512);
+        assert_eq!(&extracted[..original.len()], &original[..]);
+        assert!(reader.verify_all().unwrap().is_ok());
+    }
+
     // =========================================================================
     // Integration test with real DMG file (requires fixture)
     // =========================================================================
@@ -797,8 +1036,8 @@ mod tests {
     fn test_real_dmg_if_available() {
         let test_dmg = "../tests/Kernel_Debug_Kit_26.3_build_25D5087f.dmg";
 
-        let archive = DmgArchive::open(test_dmg).unwrap();
-        let stats = archive.stats();
+        let mut archive = DmgArchive::open(test_dmg).unwrap();
+        let stats = archive.stats().unwrap();
 
         assert_eq!(stats.version, 4);
         assert!(stats.partition_count > 0);
@@ -838,8 +1077,8 @@ mod tests {
     fn test_xz_dmg_googlechrome() {
         let test_dmg = "../tests/googlechrome.dmg";
 
-        let archive = DmgArchive::open(test_dmg).unwrap();
-        let comp_info = archive.compression_info();
+        let mut archive = DmgArchive::open(test_dmg).unwrap();
+        let comp_info = archive.compression_info().unwrap();
 
         // googlechrome.dmg uses XZ (block type 0x80000008)
         assert!(comp_info.xz_blocks > 0, "Should have XZ blocks");
@@ -878,6 +1117,7 @@ mod tests {
         // Read with checksums disabled
         let options = reader::DmgReaderOptions {
             verify_checksums: false,
+            ..Default::default()
         };
         let mut reader = DmgReader::with_options(Cursor::new(&dmg_buf), options).unwrap();
         let extracted = reader.decompress_partition(0).unwrap();
@@ -927,6 +1167,7 @@ mod tests {
         // Open with custom options
         let options = reader::DmgReaderOptions {
             verify_checksums: false,
+            ..Default::default()
         };
         let mut archive = DmgArchive::open_with_options(&temp_path, options).unwrap();
         let extracted = archive.extract_partition(0).unwrap();
@@ -960,9 +1201,9 @@ mod tests {
 
         // Verify checksums are non-zero in koly header
         let koly = reader.koly();
-        assert_eq!(koly.data_checksum_type, 2); // CRC32
+        assert_eq!(koly.data_checksum_type(), ChecksumType::Crc32);
         assert_ne!(&koly.data_checksum[..4], &[0u8; 4]); // Non-zero checksum
-        assert_eq!(koly.master_checksum_type, 2); // CRC32
+        assert_eq!(koly.master_checksum_type(), ChecksumType::Crc32);
         assert_ne!(&koly.master_checksum[..4], &[0u8; 4]); // Non-zero checksum
     }
 
@@ -993,6 +1234,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_auto_compression() {
+        // One incompressible chunk followed by two compressible ones
+        let mut state = 0x1234_5678u32;
+        let mut original: Vec<u8> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect();
+        original.extend(b"Auto compression picks per chunk. ".repeat(240).iter().take(8192));
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Auto)
+                .chunk_size(4096);
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        let types: Vec<BlockType> = reader.partitions()[0]
+            .block_map
+            .block_runs
+            .iter()
+            .map(|r| r.block_type)
+            .filter(|t| *t != BlockType::End)
+            .collect();
+        assert_eq!(types.len(), 3);
+        assert_eq!(types[0], BlockType::Raw);
+        assert!(types[1..]
+            .iter()
+            .all(|t| matches!(t, BlockType::Zlib | BlockType::Lzfse)));
+
+        let extracted = reader.decompress_partition(0).unwrap();
+        assert_eq!(&extracted[..original.len()], &original[..]);
+    }
+
     #[test]
     fn test_checksum_all_compression_methods() {
         // Test checksum verification with all compression methods
@@ -1003,6 +1283,8 @@ mod tests {
             CompressionMethod::Zlib,
             CompressionMethod::Bzip2,
             CompressionMethod::Lzfse,
+            CompressionMethod::Lzma,
+            CompressionMethod::Auto,
         ] {
             let mut dmg_buf = Vec::new();
             {
@@ -1032,4 +1314,1037 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_partition_by_attribute() {
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("first", &[0x11u8; 512]).unwrap();
+            writer.add_partition("second", &[0x22u8; 512]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // The writer uses 0x0050 for every partition; patch the second one's
+        // plist entry in place (same length, so offsets stay valid)
+        let needle = b"<string>0x0050</string>";
+        let positions: Vec<usize> = dmg_buf
+            .windows(needle.len())
+            .enumerate()
+            .filter(|(_, w)| *w == needle)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(positions.len(), 2);
+        dmg_buf[positions[1]..positions[1] + needle.len()]
+            .copy_from_slice(b"<string>0x0051</string>");
+
+        let reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.partitions()[0].attributes, 0x0050);
+        assert_eq!(reader.partitions()[1].attributes, 0x0051);
+
+        let p = reader.partition_by_attribute(0x0001, 0x0001).unwrap();
+        assert_eq!(p.name, "second");
+        let p = reader.partition_by_attribute(0x0001, 0).unwrap();
+        assert_eq!(p.name, "first");
+        let p = reader.partition_by_attribute(0x0050, 0x0050).unwrap();
+        assert_eq!(p.name, "first");
+        assert!(reader.partition_by_attribute(0x0100, 0x0100).is_none());
+    }
+
+    #[test]
+    fn test_open_from_bytes() {
+        let original = b"In-memory DMG contents".repeat(64);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::from_bytes(&dmg_buf[..]).unwrap();
+        let extracted = reader.decompress_partition(0).unwrap();
+        assert_eq!(&extracted[..original.len()], &original[..]);
+
+        let mut archive = DmgArchive::from_bytes(dmg_buf).unwrap();
+        assert_eq!(archive.partitions().len(), 1);
+        let extracted = archive.extract_partition(0).unwrap();
+        assert_eq!(&extracted[..original.len()], &original[..]);
+    }
+
+    #[test]
+    fn test_io_stats_accumulate() {
+        let original = b"Counting bytes through the reader. ".repeat(200);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Zlib)
+                .chunk_size(2048);
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.io_stats().decompressed_bytes, 0);
+
+        let stats = reader.stats().unwrap();
+        let zlib_blocks = reader.compression_info().unwrap().zlib_blocks;
+        assert!(zlib_blocks > 1);
+
+        let extracted = reader.decompress_partition(0).unwrap();
+        let io = reader.io_stats();
+        assert_eq!(io.compressed_bytes_read, stats.total_compressed);
+        assert_eq!(io.decompressed_bytes, extracted.len() as u64);
+        assert_eq!(io.blocks.zlib_blocks, zlib_blocks);
+
+        // Streaming extraction adds to the same counters
+        let mut sink = Vec::new();
+        reader.decompress_partition_to(0, &mut sink).unwrap();
+        let io = reader.io_stats();
+        assert_eq!(io.compressed_bytes_read, 2 * stats.total_compressed);
+        assert_eq!(io.decompressed_bytes, 2 * extracted.len() as u64);
+        assert_eq!(io.blocks.zlib_blocks, 2 * zlib_blocks);
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(4096);
+            writer.add_partition("first", &b"verify me ".repeat(2000)).unwrap();
+            writer.add_partition("second", &vec![0u8; 10_000]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut archive = DmgArchive::from_bytes(&dmg_buf).unwrap();
+        let report = archive.verify().unwrap();
+        assert!(report.is_ok());
+        assert!(matches!(report.data_fork, ChecksumStatus::Valid));
+        assert!(matches!(report.master, ChecksumStatus::Valid));
+        assert_eq!(report.partitions.len(), 2);
+        assert!(report.partitions.iter().all(|(_, s)| matches!(s, ChecksumStatus::Valid)));
+
+        // Corrupt the first partition's stored data; open without checks
+        dmg_buf[10] ^= 0xFF;
+        let options = DmgReaderOptions { verify_checksums: false, ..Default::default() };
+        let mut reader = DmgReader::with_options(Cursor::new(&dmg_buf), options).unwrap();
+        let report = reader.verify_all().unwrap();
+        assert!(!report.is_ok());
+        assert!(matches!(report.data_fork, ChecksumStatus::Mismatch { .. }));
+        assert!(report.master.is_ok());
+        assert!(!report.partitions[0].1.is_ok());
+        assert!(matches!(report.partitions[1].1, ChecksumStatus::Valid));
+    }
+
+    #[test]
+    fn test_decompress_partition_cancel() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let original: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(4096);
+            writer.add_partition("disk", &original).unwrap();
+            writer.finish().unwrap();
+        }
+        let mut archive = DmgArchive::from_bytes(&dmg_buf).unwrap();
+
+        let mut calls = Vec::new();
+        let mut out = Vec::new();
+        let total = archive
+            .extract_partition_to_with_progress(0, &mut out, |done, total| calls.push((done, total)), None)
+            .unwrap();
+        assert_eq!(&out[..original.len()], &original[..]);
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(calls.last(), Some(&(total, total)));
+
+        // Cancel from the progress callback once the first run is out
+        let cancel = AtomicBool::new(false);
+        let mut out = Vec::new();
+        let result = archive.extract_partition_to_with_progress(
+            0,
+            &mut out,
+            |_, _| cancel.store(true, Ordering::Relaxed),
+            Some(&cancel),
+        );
+        assert!(matches!(result, Err(DppError::Cancelled)), "{:?}", result);
+        assert!(!out.is_empty() && (out.len() as u64) < total);
+
+        #[cfg(feature = "parallel")]
+        {
+            cancel.store(false, Ordering::Relaxed);
+            let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+            let mut out = Vec::new();
+            // One thread keeps windows small enough for several to be read
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+            let result = pool.install(|| {
+                reader.decompress_partition_to_parallel_with_progress(
+                    0,
+                    &mut out,
+                    |_, _| cancel.store(true, Ordering::Relaxed),
+                    Some(&cancel),
+                )
+            });
+            assert!(matches!(result, Err(DppError::Cancelled)), "{:?}", result);
+            assert!(!out.is_empty() && (out.len() as u64) < total);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_decompress_partition_to_parallel() {
+        // Only 16 distinct byte values, so the runs still compress
+        let mut original: Vec<u8> = noise(0x9E37_79B9_7F4A_7C15, 300 * 1024).iter().map(|b| b >> 4).collect();
+        // A zeroed stretch so the map mixes zero-fill and compressed runs
+        original[64 * 1024..160 * 1024].fill(0);
+
+        for method in [CompressionMethod::Zlib, CompressionMethod::Lzfse] {
+            let mut dmg_buf = Vec::new();
+            {
+                let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                    .compression(method)
+                    .chunk_size(4096);
+                writer.add_partition("disk", &original).unwrap();
+                writer.finish().unwrap();
+            }
+
+            let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+            let mut sequential = Vec::new();
+            let seq_len = reader.decompress_partition_to(0, &mut sequential).unwrap();
+            let seq_io = reader.io_stats().clone();
+
+            let mut parallel = Vec::new();
+            let par_len = reader.decompress_partition_to_parallel(0, &mut parallel).unwrap();
+            assert_eq!(par_len, seq_len);
+            assert_eq!(parallel, sequential);
+            assert_eq!(&parallel[..original.len()], &original[..]);
+            assert_eq!(reader.io_stats().decompressed_bytes, 2 * seq_io.decompressed_bytes);
+
+            // From a worker of a single-threaded pool, waiting must not
+            // starve the decode jobs queued behind it
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+            let mut pooled = Vec::new();
+            pool.install(|| reader.decompress_partition_to_parallel(0, &mut pooled)).unwrap();
+            assert_eq!(pooled, sequential);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_partition_stream() {
+        use futures::{executor::block_on, StreamExt};
+
+        // Non-sector-aligned tail, plus a zeroed stretch for zero-fill runs
+        let mut original: Vec<u8> = (0..200 * 1024 + 100).map(|i| (i % 251) as u8).collect();
+        original[32 * 1024..96 * 1024].fill(0);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Zlib)
+                .chunk_size(8192);
+            writer.add_partition("disk", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        let expected = reader.decompress_partition(0).unwrap();
+
+        let chunks: Vec<_> = block_on(reader.partition_stream(0).unwrap().collect());
+        assert!(chunks.len() > 1);
+        let mut streamed = Vec::new();
+        for chunk in chunks {
+            streamed.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(streamed, expected);
+        assert_eq!(&streamed[..original.len()], &original[..]);
+
+        assert!(matches!(reader.partition_stream(99), Err(DppError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_from_range_source() {
+        use std::cell::RefCell;
+
+        /// Byte slice that records every range asked of it
+        struct Recording<'a> {
+            data: &'a [u8],
+            ranges: RefCell<Vec<(u64, usize)>>,
+        }
+
+        impl RangeReader for Recording<'_> {
+            fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+                self.ranges.borrow_mut().push((offset, len));
+                self.data.read_range(offset, len)
+            }
+        }
+
+        let noise = noise(0x2545_F491_4F6C_DD1D, 256 * 1024);
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Zlib)
+                .chunk_size(8192);
+            writer.add_partition("first", &noise).unwrap();
+            writer.add_partition("second", &noise[..64 * 1024]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let source = Recording { data: &dmg_buf, ranges: RefCell::new(Vec::new()) };
+        let mut reader = DmgReader::from_range_source(&source, dmg_buf.len() as u64).unwrap();
+        assert_eq!(reader.list_partitions(), ["first", "second"]);
+        let fetched = |source: &Recording| source.ranges.borrow().iter().map(|&(_, len)| len).sum::<usize>();
+        // Opening touches the koly trailer and plist, not the data fork
+        assert!(fetched(&source) < dmg_buf.len() - noise.len());
+
+        // The head of the second partition needs one block run
+        source.ranges.borrow_mut().clear();
+        let head = reader.read_partition_head(1, 4096).unwrap();
+        assert_eq!(head, &noise[..4096]);
+        assert_eq!(source.ranges.borrow().len(), 1);
+        assert!(fetched(&source) <= 8192 + 64);
+
+        assert_eq!(reader.decompress_partition(0).unwrap(), noise);
+
+        // A source shorter than its declared length surfaces as an I/O error
+        let short = &dmg_buf[..dmg_buf.len() - 1024];
+        let result = DmgReader::from_range_source(short, dmg_buf.len() as u64);
+        assert!(matches!(result, Err(DppError::Io(_))));
+    }
+
+    #[test]
+    fn test_lazy_partitions() {
+        let first: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let second: Vec<u8> = (0..96 * 1024).map(|i| (i % 13) as u8).collect();
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .compression(CompressionMethod::Zlib)
+                .chunk_size(8192);
+            writer.add_partition("first", &first).unwrap();
+            writer.add_partition("second", &second).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut eager = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        // Checksums are still verified on open: they live in the mish header
+        let options = DmgReaderOptions { lazy_partitions: true, ..Default::default() };
+        let mut reader = DmgReader::with_options(Cursor::new(&dmg_buf), options.clone()).unwrap();
+        assert_eq!(reader.list_partitions(), ["first", "second"]);
+        for (lazy, eager) in reader.partitions().iter().zip(eager.partitions()) {
+            assert!(lazy.block_map.block_runs.is_empty());
+            assert_eq!(lazy.block_map.sector_count, eager.block_map.sector_count);
+            assert_eq!(lazy.block_map.checksum, eager.block_map.checksum);
+        }
+
+        let extracted = reader.decompress_partition(1).unwrap();
+        assert_eq!(&extracted[..second.len()], &second[..]);
+        assert!(!reader.partitions()[1].block_map.block_runs.is_empty());
+        assert!(reader.partitions()[0].block_map.block_runs.is_empty());
+
+        // Summaries load the remaining block maps instead of skipping them
+        assert_eq!(reader.compression_info().unwrap(), eager.compression_info().unwrap());
+        assert_eq!(reader.stats().unwrap().total_compressed, eager.stats().unwrap().total_compressed);
+        assert!(!reader.partitions()[0].block_map.block_runs.is_empty());
+
+        let mut reader = DmgReader::with_options(Cursor::new(&dmg_buf), options.clone()).unwrap();
+        assert_eq!(
+            reader.partition_compression_info(0).unwrap(),
+            eager.partition_compression_info(0).unwrap()
+        );
+        assert_eq!(reader.image_format().unwrap(), ImageFormat::Udzo);
+
+        // Listing sums the deferred run tables without loading them
+        let mut lazy = DmgArchive { reader: DmgReader::with_options(Cursor::new(&dmg_buf), options).unwrap() };
+        let eager = DmgArchive { reader: eager };
+        for (info, expected) in lazy.partitions().iter().zip(eager.partitions()) {
+            assert!(info.compressed_size > 0);
+            assert_eq!(info.compressed_size, expected.compressed_size);
+        }
+        assert!(lazy.reader.partitions()[0].block_map.block_runs.is_empty());
+        for ((info, part), expected) in lazy.partition_readers().unwrap().iter().zip(eager.partitions()) {
+            assert_eq!(info.compressed_size, expected.compressed_size);
+            assert_eq!(part.size(), expected.size);
+        }
+    }
+
+    #[test]
+    fn test_comment_roundtrip() {
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).comment("Created by udif <tests> & friends");
+            writer.add_partition("test", &[0x5au8; 1024]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.comment(), Some("Created by udif <tests> & friends"));
+
+        // No comment written
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("test", &[0x5au8; 1024]).unwrap();
+            writer.finish().unwrap();
+        }
+        let reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.comment(), None);
+    }
+
+    #[test]
+    fn test_data_fork_base_offset_roundtrip() {
+        let original = b"Segment of a concatenated image. ".repeat(100);
+        let base = 0x10_0000u64;
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .data_fork_base_offset(base)
+                .chunk_size(4096);
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.koly().running_data_fork_offset, base);
+        let runs = &reader.partitions()[0].block_map.block_runs;
+        assert!(runs
+            .iter()
+            .filter(|r| r.compressed_length > 0)
+            .all(|r| r.compressed_offset >= base));
+
+        let extracted = reader.decompress_partition(0).unwrap();
+        assert_eq!(&extracted[..original.len()], &original[..]);
+
+        let mut streamed = Vec::new();
+        reader.decompress_partition_to(0, &mut streamed).unwrap();
+        assert_eq!(streamed, extracted);
+    }
+
+    #[test]
+    fn test_4k_sector_size() {
+        // Three 4K sectors plus a partial one
+        let original: Vec<u8> = (0..4096 * 3 + 100).map(|i| (i % 253) as u8).collect();
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                .sector_size(4096)
+                .chunk_size(8192);
+            writer.add_partition("first", &[0x11u8; 4096]).unwrap();
+            writer.add_partition("second", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let options = reader::DmgReaderOptions {
+            sector_size: 4096,
+            ..Default::default()
+        };
+        let mut archive = DmgArchive { reader: DmgReader::with_options(Cursor::new(&dmg_buf), options).unwrap() };
+        let sizes: Vec<u64> = archive.partitions().iter().map(|p| p.size).collect();
+        assert_eq!(sizes, [4096, 4096 * 4]);
+        assert_eq!(archive.stats().unwrap().total_uncompressed, 4096 * 5);
+        let mut reader = archive.reader;
+
+        let second = &reader.partitions()[1];
+        assert_eq!(second.block_map.first_sector, 1);
+        assert_eq!(second.block_map.sector_count, 4);
+        assert_eq!(second.block_map.block_runs[1].sector_number, 2);
+
+        let extracted = reader.decompress_partition(1).unwrap();
+        assert_eq!(extracted.len(), 4096 * 4);
+        assert_eq!(&extracted[..original.len()], &original[..]);
+
+        let mut streamed = Vec::new();
+        reader.decompress_partition_to(1, &mut streamed).unwrap();
+        assert_eq!(streamed, extracted);
+
+        let all = reader.decompress_all().unwrap();
+        assert_eq!(all.len(), 4096 * 5);
+        assert_eq!(&all[4096..4096 + original.len()], &original[..]);
+    }
+
+    #[test]
+    fn test_image_format() {
+        let original = b"Image format classification data. ".repeat(200);
+
+        for (method, expected) in [
+            (CompressionMethod::Raw, ImageFormat::Udro),
+            (CompressionMethod::Zlib, ImageFormat::Udzo),
+            (CompressionMethod::Bzip2, ImageFormat::Udbz),
+            (CompressionMethod::Lzfse, ImageFormat::Ulfo),
+            (CompressionMethod::Lzma, ImageFormat::Ulmo),
+        ] {
+            let mut dmg_buf = Vec::new();
+            {
+                let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                    .compression(method);
+                writer.add_partition("test", &original).unwrap();
+                writer.finish().unwrap();
+            }
+
+            let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+            assert_eq!(reader.image_format().unwrap(), expected, "format for {:?}", method);
+        }
+
+        assert_eq!(ImageFormat::Udzo.name(), "UDZO");
+
+        // An unrecognized image variant is not classified
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+        let variant_offset = dmg_buf.len() - format::KOLY_SIZE + 432;
+        dmg_buf[variant_offset..variant_offset + 4].copy_from_slice(&7u32.to_be_bytes());
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.image_format().unwrap(), ImageFormat::Unknown);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_open_mmap() {
+        let original = b"Memory-mapped extraction data. ".repeat(500);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("first", &original[..4000]).unwrap();
+            writer.add_partition("second", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_path = temp_dir.path().join("test.dmg");
+        std::fs::write(&temp_path, &dmg_buf).unwrap();
+
+        let mut mapped = DmgReader::open_mmap(&temp_path).unwrap();
+        let mut buffered = DmgReader::open(&temp_path).unwrap();
+        assert_eq!(mapped.list_partitions(), buffered.list_partitions());
+        for id in [0, 1] {
+            assert_eq!(
+                mapped.decompress_partition(id).unwrap(),
+                buffered.decompress_partition(id).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_partition_fs() {
+        let mut hfs = vec![0u8; 8192];
+        hfs[1024..1026].copy_from_slice(b"H+");
+        let mut hfsx = vec![0u8; 8192];
+        hfsx[1024..1026].copy_from_slice(b"HX");
+        let mut apfs = vec![0u8; 8192];
+        apfs[32..36].copy_from_slice(b"NXSB");
+        let other = b"no superblock here".repeat(400);
+
+        // Names deliberately disagree with the contents
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(4096);
+            writer.add_partition("disk image (Apple_APFS : 1)", &hfs).unwrap();
+            writer.add_partition("disk image (Apple_HFS : 2)", &hfsx).unwrap();
+            writer.add_partition("disk image (Apple_HFS : 3)", &apfs).unwrap();
+            writer.add_partition("disk image (Apple_HFS : 4)", &other).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut archive = DmgArchive::from_bytes(dmg_buf).unwrap();
+        let ids: Vec<i32> = archive.partitions().iter().map(|p| p.id).collect();
+        let detected: Vec<DetectedFs> = ids
+            .iter()
+            .map(|&id| archive.detect_partition_fs(id).unwrap())
+            .collect();
+        assert_eq!(
+            detected,
+            [DetectedFs::Hfs, DetectedFs::Hfsx, DetectedFs::Apfs, DetectedFs::Unknown]
+        );
+        assert_eq!(
+            archive.reader.read_partition_head(ids[0], 100).unwrap(),
+            &hfs[..100]
+        );
+        assert!(archive.detect_partition_fs(99).is_err());
+    }
+
+    #[test]
+    fn test_resource_fork() {
+        let original = b"Signed image contents".repeat(64);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("test", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.resource_fork().unwrap(), None);
+        assert!(!reader.has_signature().unwrap());
+
+        // Insert a signature blob before the koly and point the koly at it
+        let mut koly = format::KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+        dmg_buf.truncate(dmg_buf.len() - format::KOLY_SIZE);
+        let mut signature = format::CODE_SIGNATURE_MAGIC.to_vec();
+        signature.extend_from_slice(b"\x00\x00\x00\x14signature-bytes!");
+        koly.rsrc_fork_offset = dmg_buf.len() as u64;
+        koly.rsrc_fork_length = signature.len() as u64;
+        dmg_buf.extend_from_slice(&signature);
+        koly.write(&mut dmg_buf).unwrap();
+
+        let mut archive = DmgArchive::from_bytes(dmg_buf.clone()).unwrap();
+        assert_eq!(archive.resource_fork().unwrap(), Some(signature.clone()));
+        assert!(archive.has_signature().unwrap());
+        let extracted = archive.extract_partition(0).unwrap();
+        assert_eq!(&extracted[..original.len()], &original[..]);
+
+        // A resource fork running past the end of the image is rejected
+        let mut koly = format::KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+        dmg_buf.truncate(dmg_buf.len() - format::KOLY_SIZE);
+        koly.rsrc_fork_length = 1 << 40;
+        koly.write(&mut dmg_buf).unwrap();
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert!(matches!(
+            reader.resource_fork(),
+            Err(DppError::InvalidKolyHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_partitions_of_type() {
+        let data = b"partition".repeat(100);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            for name in [
+                "Driver Descriptor Map (DDM : 0)",
+                "disk image (Apple_APFS : 1)",
+                "disk image (Apple_HFS : 2)",
+                "backup (Apple_APFS : 3)",
+                "case-sensitive (Apple_HFSX : 4)",
+            ] {
+                writer.add_partition(name, &data).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let archive = DmgArchive::from_bytes(dmg_buf).unwrap();
+        let ids = |t| archive.partitions_of_type(t).map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(PartitionType::Apfs), [1, 3]);
+        assert_eq!(ids(PartitionType::Hfs), [2]);
+        assert_eq!(ids(PartitionType::Hfsx), [4]);
+        assert_eq!(ids(PartitionType::Other), [0]);
+    }
+
+    #[test]
+    fn test_estimate_output_size() {
+        // Compressible text with a zero-filled tail, across several chunks
+        let mut data = Vec::new();
+        for i in 0..20_000 {
+            data.extend_from_slice(format!("line {} of the estimate test input\n", i).as_bytes());
+        }
+        data.resize(data.len() + 256 * 1024, 0);
+
+        for method in [CompressionMethod::Raw, CompressionMethod::Zlib, CompressionMethod::Lzfse] {
+            let builder = DmgBuilder::new()
+                .compression(method)
+                .chunk_size(64 * 1024)
+                .add_partition("test", data.clone());
+            let estimate = builder.estimate_output_size();
+
+            let mut dmg_buf = Vec::new();
+            {
+                let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf))
+                    .compression(method)
+                    .chunk_size(64 * 1024);
+                writer.add_partition("test", &data).unwrap();
+                writer.finish().unwrap();
+            }
+            let actual = dmg_buf.len() as u64;
+
+            assert!(
+                estimate >= actual / 2 && estimate <= actual * 2,
+                "{:?}: estimate {} vs actual {}",
+                method,
+                estimate,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_partition_readers() {
+        use std::io::{Read, Seek, SeekFrom};
+        use std::sync::Arc;
+
+        let first: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut second = vec![0u8; 150_000];
+        second[70_000..80_000].fill(0x5A);
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(32 * 1024);
+            writer.add_partition("first", &first).unwrap();
+            writer.add_partition("second", &second).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let image: Arc<[u8]> = Arc::from(dmg_buf);
+        let mut reader = DmgReader::new(Cursor::new(image)).unwrap();
+        let sequential: Vec<Vec<u8>> = reader
+            .partitions()
+            .to_vec()
+            .iter()
+            .map(|p| reader.decompress_partition(p.id).unwrap())
+            .collect();
+
+        let handles: Vec<_> = reader
+            .partition_readers()
+            .unwrap()
+            .into_iter()
+            .map(|(info, mut part)| {
+                std::thread::spawn(move || {
+                    let mut data = Vec::new();
+                    part.read_to_end(&mut data).unwrap();
+                    assert_eq!(data.len() as u64, info.size);
+                    data
+                })
+            })
+            .collect();
+        let concurrent: Vec<Vec<u8>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(concurrent, sequential);
+
+        // Random access across a block-run boundary
+        let (_, mut part) = reader.partition_readers().unwrap().remove(0);
+        let mut buf = vec![0u8; 1000];
+        part.seek(SeekFrom::Start(32 * 1024 - 500)).unwrap();
+        part.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, &sequential[0][32 * 1024 - 500..32 * 1024 + 500]);
+        assert_eq!(part.seek(SeekFrom::End(0)).unwrap(), part.size());
+        assert_eq!(part.read(&mut buf).unwrap(), 0);
+        assert!(part.seek(SeekFrom::Current(-(part.size() as i64) - 1)).is_err());
+    }
+
+    #[test]
+    fn test_content_defined_chunking() {
+        use std::collections::HashSet;
+
+        // Pseudo-random data so every chunk is distinct
+        let original = noise(0x2545_F491_4F6C_DD1D, 1024 * 1024);
+        let mut edited = original.clone();
+        edited.splice(3000..3000, [0xEEu8; 1024]);
+
+        /// Build an image and return its decoded partition plus the stored
+        /// bytes of every data-carrying block run
+        fn build(data: &[u8], cdc: bool) -> (Vec<u8>, Vec<Vec<u8>>) {
+            let mut dmg_buf = Vec::new();
+            {
+                let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(16 * 1024);
+                if cdc {
+                    writer = writer.content_defined_chunking(4 * 1024, 16 * 1024, 64 * 1024);
+                }
+                writer.add_partition("disk", data).unwrap();
+                writer.finish().unwrap();
+            }
+            let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+            let chunks = reader.partitions()[0]
+                .block_map
+                .block_runs
+                .iter()
+                .filter(|r| r.compressed_length > 0)
+                .map(|r| {
+                    let start = r.compressed_offset as usize;
+                    dmg_buf[start..start + r.compressed_length as usize].to_vec()
+                })
+                .collect();
+            (reader.decompress_partition(0).unwrap(), chunks)
+        }
+
+        let shared = |a: &[Vec<u8>], b: &[Vec<u8>]| {
+            let a: HashSet<&Vec<u8>> = a.iter().collect();
+            b.iter().filter(|c| a.contains(c)).count()
+        };
+
+        let (decoded, cdc_before) = build(&original, true);
+        assert_eq!(&decoded[..original.len()], &original[..]);
+        let (decoded, cdc_after) = build(&edited, true);
+        assert_eq!(&decoded[..edited.len()], &edited[..]);
+        assert!(cdc_after.len() > 8);
+        assert!(
+            shared(&cdc_before, &cdc_after) * 10 >= cdc_after.len() * 8,
+            "only {} of {} chunks unchanged",
+            shared(&cdc_before, &cdc_after),
+            cdc_after.len()
+        );
+
+        // Fixed-size chunks all shift after the insertion
+        let (_, fixed_before) = build(&original, false);
+        let (_, fixed_after) = build(&edited, false);
+        assert!(shared(&fixed_before, &fixed_after) <= 1);
+    }
+
+    #[test]
+    fn test_partition_display_name() {
+        let original = b"display name test data".repeat(100);
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("disk image (Apple_HFS : 4)", &original).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // Rewrite the blkx entry's `CFName`, or drop it when `None`
+        let with_cf_name = |cf_name: Option<&str>| {
+            let mut koly = KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+            let start = koly.plist_offset as usize;
+            let plist = std::str::from_utf8(&dmg_buf[start..start + koly.plist_length as usize]).unwrap();
+            let written = "<key>CFName</key>\n\t\t\t\t<string>disk image (Apple_HFS : 4)</string>\n";
+            assert!(plist.contains(written));
+            let replacement = cf_name.map_or(String::new(), |name| format!("<key>CFName</key><string>{}</string>", name));
+            let plist = plist.replacen(written, &replacement, 1);
+
+            let mut image = dmg_buf[..start].to_vec();
+            image.extend_from_slice(plist.as_bytes());
+            koly.plist_length = plist.len() as u64;
+            koly.write(&mut image).unwrap();
+            image
+        };
+
+        let archive = DmgArchive::from_bytes(with_cf_name(Some("Macintosh HD"))).unwrap();
+        let info = &archive.partitions()[0];
+        assert_eq!(info.name, "disk image (Apple_HFS : 4)");
+        assert_eq!(info.display_name.as_deref(), Some("Macintosh HD"));
+        assert_eq!(info.label(), "Macintosh HD");
+        assert_eq!(info.partition_type, PartitionType::Hfs);
+
+        let reader = DmgReader::new(Cursor::new(with_cf_name(None))).unwrap();
+        let info = PartitionInfo::from_entry(&reader.partitions()[0], 512);
+        assert_eq!(info.display_name, None);
+        assert_eq!(info.label(), "disk image (Apple_HFS : 4)");
+    }
+
+    #[test]
+    fn test_plist_checksum() {
+        use base64::Engine;
+
+        let original = b"plist checksum test data".repeat(100);
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("disk", &original).unwrap();
+            writer.finish().unwrap();
+        }
+        let reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        assert_eq!(reader.partitions()[0].plist_checksum, None);
+        let mish_crc = reader.partitions()[0].block_map.checksum[..4].to_vec();
+
+        // Rewrite the plist with a `Checksum` key in the blkx entry
+        let with_checksum = |checksum: &[u8]| {
+            let mut koly = KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+            let start = koly.plist_offset as usize;
+            let plist = std::str::from_utf8(&dmg_buf[start..start + koly.plist_length as usize]).unwrap();
+            let encoded = base64::engine::general_purpose::STANDARD.encode(checksum);
+            let plist = plist.replacen(
+                "<key>Data</key>",
+                &format!("<key>Checksum</key><data>{}</data><key>Data</key>", encoded),
+                1,
+            );
+
+            let mut image = dmg_buf[..start].to_vec();
+            image.extend_from_slice(plist.as_bytes());
+            koly.plist_length = plist.len() as u64;
+            koly.write(&mut image).unwrap();
+            image
+        };
+
+        let image = with_checksum(&mish_crc);
+        let reader = DmgReader::new(Cursor::new(&image)).unwrap();
+        assert_eq!(reader.partitions()[0].plist_checksum.as_deref(), Some(&mish_crc[..]));
+
+        let image = with_checksum(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert!(matches!(
+            DmgReader::new(Cursor::new(&image)),
+            Err(DppError::InvalidPlist(msg)) if msg.contains("'disk'")
+        ));
+        let options = DmgReaderOptions { verify_checksums: false, ..Default::default() };
+        let mut reader = DmgReader::with_options(Cursor::new(&image), options).unwrap();
+        assert_eq!(&reader.decompress_partition(0).unwrap()[..original.len()], &original[..]);
+    }
+
+    #[test]
+    fn test_decode_block_run_each_type() {
+        use std::io::Write;
+
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("disk", &[0u8; 512]).unwrap();
+            writer.finish().unwrap();
+        }
+        let koly = DmgReader::new(Cursor::new(&dmg_buf)).unwrap().koly().clone();
+        assert_eq!(koly.running_data_fork_offset, 0);
+
+        let sector: Vec<u8> = (0..1024u32).map(|i| (i % 251) as u8).collect();
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&sector).unwrap();
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(&sector).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&sector).unwrap();
+        let mut lzfse_buf = vec![0u8; sector.len() * 2];
+        let lzfse_len = lzfse::encode_buffer(&sector, &mut lzfse_buf).unwrap();
+        lzfse_buf.truncate(lzfse_len);
+
+        let payloads = [
+            (BlockType::Raw, sector.clone()),
+            (BlockType::Raw, sector[..100].to_vec()),
+            (BlockType::Zlib, zlib.finish().unwrap()),
+            (BlockType::Bzip2, bz.finish().unwrap()),
+            (BlockType::Xz, xz.finish().unwrap()),
+            (BlockType::Lzfse, lzfse_buf),
+        ];
+        let mut stream = Vec::new();
+        let mut runs = Vec::new();
+        for (block_type, payload) in payloads {
+            runs.push(format::BlockRun {
+                block_type,
+                comment: 0,
+                sector_number: 0,
+                sector_count: 2,
+                compressed_offset: stream.len() as u64,
+                compressed_length: payload.len() as u64,
+            });
+            stream.extend_from_slice(&payload);
+        }
+        let mut reader = Cursor::new(stream);
+
+        let mut short = sector[..100].to_vec();
+        short.resize(1024, 0);
+        let expected = [sector.clone(), short, sector.clone(), sector.clone(), sector.clone(), sector];
+        for (run, expected) in runs.iter().zip(expected) {
+            let block = decode_block_run(&mut reader, &koly, run, 512).unwrap();
+            assert_eq!(block, DecodedBlock::Data(expected), "{:?}", run.block_type);
+        }
+
+        let run = |block_type, compressed_length| format::BlockRun {
+            block_type,
+            comment: 0,
+            sector_number: 0,
+            sector_count: 2,
+            compressed_offset: 0,
+            compressed_length,
+        };
+        let zeros = decode_block_run(&mut reader, &koly, &run(BlockType::ZeroFill, 0), 512).unwrap();
+        assert_eq!(zeros, DecodedBlock::Zeros(1024));
+        let empty_raw = decode_block_run(&mut reader, &koly, &run(BlockType::Raw, 0), 512).unwrap();
+        assert_eq!(empty_raw, DecodedBlock::Zeros(1024));
+        for marker in [BlockType::Comment, BlockType::End] {
+            let block = decode_block_run(&mut reader, &koly, &run(marker, 0), 512).unwrap();
+            assert!(block.is_empty());
+        }
+        assert!(matches!(
+            decode_block_run(&mut reader, &koly, &run(BlockType::Adc, 10), 512),
+            Err(DppError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_koly_structural_validation() {
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("disk", &[7u8; 4096]).unwrap();
+            writer.finish().unwrap();
+        }
+        let koly = KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+        let body = &dmg_buf[..dmg_buf.len() - format::KOLY_SIZE];
+        let with_koly = |edit: &dyn Fn(&mut KolyHeader)| {
+            let mut koly = koly.clone();
+            edit(&mut koly);
+            let mut image = body.to_vec();
+            koly.write(&mut image).unwrap();
+            image
+        };
+
+        let image = with_koly(&|k| k.header_size = 0);
+        assert!(matches!(
+            DmgReader::new(Cursor::new(&image)),
+            Err(DppError::CorruptedData { field: "koly.header_size", .. })
+        ));
+
+        let image = with_koly(&|k| k.data_checksum_size = 2048);
+        assert!(matches!(
+            DmgReader::new(Cursor::new(&image)),
+            Err(DppError::CorruptedData { field: "koly.data_checksum_size", .. })
+        ));
+
+        let image = with_koly(&|k| k.plist_offset = body.len() as u64);
+        let err = DmgReader::new(Cursor::new(&image)).err().unwrap();
+        assert!(matches!(err, DppError::CorruptedData { field: "koly.plist_offset", .. }));
+        assert!(err.to_string().contains("koly.plist_offset"));
+
+        // An offset + length that overflows is corruption, not truncation
+        let image = with_koly(&|k| {
+            k.data_fork_offset = 1;
+            k.data_fork_length = u64::MAX;
+        });
+        assert!(matches!(
+            DmgReader::new(Cursor::new(&image)),
+            Err(DppError::CorruptedData { field: "koly.data_fork_offset", .. })
+        ));
+    }
+
+    #[test]
+    fn test_finish_with_stats() {
+        let mut disk = vec![0u8; 64 * 1024];
+        disk[..16 * 1024].fill(0x5A);
+        disk[40 * 1024..48 * 1024].copy_from_slice(&[0x11; 8 * 1024]);
+
+        let mut dmg_buf = Vec::new();
+        let stats = {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf)).chunk_size(8 * 1024);
+            writer.add_partition("disk", &disk).unwrap();
+            writer.add_partition("extra", &[7u8; 1000]).unwrap();
+            writer.finish_with_stats().unwrap()
+        };
+
+        let mut reader = DmgReader::new(Cursor::new(&dmg_buf)).unwrap();
+        let expected = reader.stats().unwrap();
+        assert_eq!(stats.partition_count, expected.partition_count);
+        assert_eq!(stats.data_fork_length, expected.data_fork_length);
+        assert_eq!(stats.total_compressed, expected.total_compressed);
+        assert_eq!(stats.total_uncompressed, expected.total_uncompressed);
+        assert_eq!(stats.method_counts, reader.compression_info().unwrap());
+        assert_eq!(stats.method_counts.zlib_blocks, 4);
+        assert_eq!(stats.method_counts.zero_fill_blocks, 5);
+    }
+
+    #[test]
+    fn test_truncated_data_fork() {
+        let mut dmg_buf = Vec::new();
+        {
+            let mut writer = DmgWriter::new(Cursor::new(&mut dmg_buf));
+            writer.add_partition("disk", &[7u8; 4096]).unwrap();
+            writer.finish().unwrap();
+        }
+        let mut koly = KolyHeader::read(&mut Cursor::new(&dmg_buf)).unwrap();
+        let body = &dmg_buf[..dmg_buf.len() - format::KOLY_SIZE];
+
+        // Declare a data fork 1 MiB longer than the file holds
+        koly.data_fork_length += 1 << 20;
+        let mut image = body.to_vec();
+        koly.write(&mut image).unwrap();
+
+        let err = DmgReader::new(Cursor::new(&image)).err().unwrap();
+        let expected = koly.data_fork_offset + koly.data_fork_length + format::KOLY_SIZE as u64;
+        assert!(matches!(
+            err,
+            DppError::Truncated { expected: e, actual } if e == expected && actual == image.len() as u64
+        ));
+        assert!(err.to_string().contains("truncated"));
+    }
 }
@@ -0,0 +1,91 @@
+//! Reading DMGs from range-addressable sources
+//!
+//! A [`RangeReader`] hands out byte ranges on request, the way an HTTP
+//! server answers `Range:` requests or an object store answers ranged GETs.
+//! [`RangeSource`] adapts one to `Read + Seek` so it can back a
+//! [`DmgReader`](crate::DmgReader): opening fetches only the koly trailer
+//! and the plist, and extraction fetches each block run as it is decoded.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::{DppError, Result};
+
+/// A source that can return arbitrary byte ranges of a DMG
+pub trait RangeReader {
+    /// Read up to `len` bytes starting at `offset`
+    ///
+    /// Returning fewer bytes than asked for is allowed; an empty result
+    /// before the end of the image is treated as a truncated source.
+    fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>>;
+}
+
+impl RangeReader for [u8] {
+    fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let start = usize::try_from(offset).map_or(self.len(), |o| o.min(self.len()));
+        let end = start.saturating_add(len).min(self.len());
+        Ok(self[start..end].to_vec())
+    }
+}
+
+impl<T: RangeReader + ?Sized> RangeReader for &T {
+    fn read_range(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        (**self).read_range(offset, len)
+    }
+}
+
+/// `Read + Seek` view of a [`RangeReader`] of known length
+///
+/// Every `read` issues one range request for exactly the bytes asked for,
+/// so avoid wrapping it in a `BufReader`: the reader already asks for whole
+/// block runs at a time.
+#[derive(Debug, Clone)]
+pub struct RangeSource<S> {
+    source: S,
+    len: u64,
+    pos: u64,
+}
+
+impl<S: RangeReader> RangeSource<S> {
+    /// Wrap `source`, whose image is `len` bytes long
+    pub fn new(source: S, len: u64) -> Self {
+        RangeSource { source, len, pos: 0 }
+    }
+
+    /// Get back the wrapped source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: RangeReader> Read for RangeSource<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let want = buf.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if want == 0 {
+            return Ok(0);
+        }
+
+        let data = self.source.read_range(self.pos, want).map_err(|e| match e {
+            DppError::Io(e) => e,
+            e => io::Error::other(e),
+        })?;
+        let n = data.len().min(want);
+        buf[..n].copy_from_slice(&data[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: RangeReader> Seek for RangeSource<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative or overflowing position")
+        })?;
+        Ok(self.pos)
+    }
+}
@@ -3,15 +3,19 @@
 //! Provides streaming and full decompression of DMG disk images.
 
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::checksum::{has_checksum, verify_crc32};
+use crate::checksum::{extract_crc32, has_checksum, verify_crc32};
 use crate::error::{DppError, Result};
-use crate::format::{BlockType, KolyHeader, MishHeader, PartitionEntry};
+use crate::range::{RangeReader, RangeSource};
+use crate::PartitionInfo;
+use crate::format::{BlockRun, BlockType, ChecksumType, KolyHeader, MishHeader, PartitionEntry, CODE_SIGNATURE_MAGIC};
 
-/// Sector size in bytes
-const SECTOR_SIZE: u64 = 512;
+/// Default sector size in bytes
+pub const DEFAULT_SECTOR_SIZE: u64 = 512;
 
 /// Read from a decoder until the buffer is full or EOF.
 /// Unlike `read()`, this loops to handle decoders that return partial data.
@@ -26,17 +30,199 @@ pub(crate) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Res
     Ok(total)
 }
 
+/// Absolute stream position of a block run's data
+///
+/// Block-run offsets are relative to the start of the (possibly
+/// concatenated) data fork; `running_data_fork_offset` is where this
+/// image's data fork begins within it.
+fn block_offset(koly: &KolyHeader, block_run: &BlockRun) -> Result<u64> {
+    let relative = block_run
+        .compressed_offset
+        .checked_sub(koly.running_data_fork_offset)
+        .ok_or_else(|| {
+            DppError::InvalidBlockMap(format!(
+                "block offset {} precedes running data fork offset {}",
+                block_run.compressed_offset, koly.running_data_fork_offset
+            ))
+        })?;
+    Ok(koly.data_fork_offset + relative)
+}
+
+/// Uncompressed contents of one block run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedBlock {
+    /// This many zero bytes; nothing is stored for the run
+    Zeros(u64),
+    /// Decoded bytes, `sector_count * sector_size` long
+    Data(Vec<u8>),
+}
+
+impl DecodedBlock {
+    /// Number of uncompressed bytes the run covers
+    pub fn len(&self) -> u64 {
+        match self {
+            DecodedBlock::Zeros(len) => *len,
+            DecodedBlock::Data(data) => data.len() as u64,
+        }
+    }
+
+    /// Whether the run covers no bytes (comment and end markers)
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy the run into `out`, which must be at least `len()` bytes and
+    /// already zeroed
+    fn copy_into(&self, out: &mut [u8]) {
+        if let DecodedBlock::Data(data) = self {
+            let n = data.len().min(out.len());
+            out[..n].copy_from_slice(&data[..n]);
+        }
+    }
+
+    /// Write the run's bytes to `writer`
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            DecodedBlock::Zeros(len) => {
+                std::io::copy(&mut std::io::repeat(0).take(*len), writer)?;
+                Ok(())
+            }
+            DecodedBlock::Data(data) => writer.write_all(data),
+        }
+    }
+}
+
+/// Decode one block run of an image
+///
+/// `reader` is the whole image stream and `koly` its trailer, which locates
+/// the data fork. Zero-fill runs, and raw runs with nothing stored, come back
+/// as [`DecodedBlock::Zeros`] without touching the reader; comment and end
+/// markers as `Zeros(0)`. Every extraction method goes through this function.
+pub fn decode_block_run<R: Read + Seek>(
+    reader: &mut R,
+    koly: &KolyHeader,
+    block_run: &BlockRun,
+    sector_size: u64,
+) -> Result<DecodedBlock> {
+    let out_size = block_run.sector_count * sector_size;
+    if let Some(block) = unstored_block(block_run, out_size)? {
+        return Ok(block);
+    }
+    let stored = read_stored(reader, koly, block_run)?;
+    decode_stored(block_run.block_type, stored, out_size)
+}
+
+/// Decoded form of a run that has no stored data to read
+///
+/// Zero fills, comment/end markers, and raw runs with nothing stored are
+/// answered without the reader; ADC runs are rejected up front.
+fn unstored_block(block_run: &BlockRun, out_size: u64) -> Result<Option<DecodedBlock>> {
+    Ok(match block_run.block_type {
+        BlockType::ZeroFill => Some(DecodedBlock::Zeros(out_size)),
+        BlockType::Comment | BlockType::End => Some(DecodedBlock::Zeros(0)),
+        BlockType::Raw | BlockType::Ignore if block_run.compressed_length == 0 => {
+            Some(DecodedBlock::Zeros(out_size))
+        }
+        BlockType::Adc => return Err(DppError::Unsupported("ADC compression".into())),
+        _ => None,
+    })
+}
+
+/// Read the stored (usually compressed) bytes of a block run
+fn read_stored<R: Read + Seek>(reader: &mut R, koly: &KolyHeader, block_run: &BlockRun) -> Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(block_offset(koly, block_run)?))?;
+    let mut stored = vec![0u8; block_run.compressed_length as usize];
+    reader.read_exact(&mut stored)?;
+    Ok(stored)
+}
+
+/// Read up to `out_size` bytes from a streaming decoder
+fn drain<D: Read>(mut decoder: D, out_size: u64) -> Result<Vec<u8>> {
+    let mut decompressed = vec![0u8; out_size as usize];
+    read_full(&mut decoder, &mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Decode a run's stored bytes into `out_size` bytes
+fn decode_stored(block_type: BlockType, stored: Vec<u8>, out_size: u64) -> Result<DecodedBlock> {
+    let mut block = match block_type {
+        // Only compressed_length bytes are stored; the rest of the run is
+        // zero-filled below
+        BlockType::Raw | BlockType::Ignore => stored,
+        BlockType::Zlib => drain(flate2::read::ZlibDecoder::new(&stored[..]), out_size)?,
+        BlockType::Bzip2 => drain(bzip2::read::BzDecoder::new(&stored[..]), out_size)?,
+        BlockType::Xz => drain(xz2::read::XzDecoder::new(&stored[..]), out_size)?,
+        BlockType::Lzfse => {
+            // LZFSE decoder needs extra buffer space beyond the actual output size
+            let mut temp_buf = vec![0u8; out_size as usize * 2];
+            let decoded_size = lzfse::decode_buffer(&stored, &mut temp_buf)
+                .map_err(|e| DppError::Decompression(format!("LZFSE: {:?}", e)))?;
+            temp_buf.truncate(decoded_size.min(out_size as usize));
+            temp_buf
+        }
+        BlockType::Adc => return Err(DppError::Unsupported("ADC compression".into())),
+        BlockType::ZeroFill | BlockType::Comment | BlockType::End => {
+            return Err(DppError::InvalidBlockMap(format!("{:?} run has no stored data", block_type)));
+        }
+    };
+
+    if (block.len() as u64) < out_size {
+        block.resize(out_size as usize, 0);
+    }
+    Ok(DecodedBlock::Data(block))
+}
+
+/// A block run read ahead for decoding on the rayon pool
+#[cfg(feature = "parallel")]
+enum PendingRun {
+    /// Needed no stored data, so already decoded
+    Ready(DecodedBlock),
+    /// Stored bytes, block type and output size still to decode
+    Stored(BlockType, Vec<u8>, u64),
+}
+
+/// Write a decoded run at `out_offset`, zero-filling any gap before it
+#[cfg(feature = "parallel")]
+fn write_run_at<W: Write>(
+    block: &DecodedBlock,
+    out_offset: u64,
+    writer: &mut W,
+    bytes_written: &mut u64,
+) -> Result<()> {
+    if out_offset > *bytes_written {
+        *bytes_written += std::io::copy(&mut std::io::repeat(0).take(out_offset - *bytes_written), writer)?;
+    }
+    block.write_to(writer)?;
+    *bytes_written += block.len();
+    Ok(())
+}
+
 /// Options for DMG reader
 #[derive(Debug, Clone)]
 pub struct DmgReaderOptions {
     /// Whether to verify checksums when opening the DMG
     pub verify_checksums: bool,
+    /// Size in bytes of the sectors that block runs are counted in
+    /// (512, or 4096 for 4K-native images)
+    pub sector_size: u64,
+    /// Defer parsing each partition's block runs until it is first extracted
+    ///
+    /// Only the fixed mish header (sizes and checksums) is parsed on open,
+    /// which speeds up opening images with many partitions. Until a
+    /// partition is loaded, the `block_map.block_runs` of its
+    /// [`DmgReader::partitions`] entry is empty. Accessors that summarize
+    /// block runs (`stats()`, `compression_info()`, `image_format()`,
+    /// `partition_compression_info()`, `partition_readers()`) load the
+    /// maps they need first.
+    pub lazy_partitions: bool,
 }
 
 impl Default for DmgReaderOptions {
     fn default() -> Self {
         Self {
             verify_checksums: true,
+            sector_size: DEFAULT_SECTOR_SIZE,
+            lazy_partitions: false,
         }
     }
 }
@@ -46,8 +232,12 @@ pub struct DmgReader<R> {
     reader: R,
     koly: KolyHeader,
     partitions: Vec<PartitionEntry>,
-    #[allow(dead_code)]
     options: DmgReaderOptions,
+    io_stats: IoStats,
+    comment: Option<String>,
+    /// Raw mish data of partitions whose block runs are not parsed yet,
+    /// indexed like `partitions` (the plist `blkx` order)
+    deferred_block_maps: Vec<Option<Vec<u8>>>,
 }
 
 impl<R: Read + Seek> DmgReader<R> {
@@ -71,11 +261,13 @@ impl<R: Read + Seek> DmgReader<R> {
         let mut plist_data = vec![0u8; koly.plist_length as usize];
         reader.read_exact(&mut plist_data)?;
 
-        let partitions = parse_plist(&plist_data)?;
+        let (partitions, deferred_block_maps, comment) = parse_plist(&plist_data, options.lazy_partitions)?;
 
-        // Verify master checksum (CRC32 of all mish checksums)
+        // Verify master checksum (CRC32 of all mish checksums), and that
+        // checksums declared in the plist agree with the mish ones
         if options.verify_checksums {
             Self::verify_master_checksum(&koly, &partitions)?;
+            Self::verify_plist_checksums(&partitions)?;
         }
 
         Ok(DmgReader {
@@ -83,6 +275,9 @@ impl<R: Read + Seek> DmgReader<R> {
             koly,
             partitions,
             options,
+            io_stats: IoStats::default(),
+            comment,
+            deferred_block_maps,
         })
     }
 
@@ -124,133 +319,185 @@ impl<R: Read + Seek> DmgReader<R> {
         )
     }
 
+    /// Verify each plist-declared partition checksum against its mish checksum
+    fn verify_plist_checksums(partitions: &[PartitionEntry]) -> Result<()> {
+        for partition in partitions {
+            let Some(declared) = &partition.plist_checksum else {
+                continue;
+            };
+            let block_map = &partition.block_map;
+            if block_map.checksum_type() == ChecksumType::None {
+                continue;
+            }
+
+            let len = (block_map.checksum_size as usize / 8).min(block_map.checksum.len());
+            let stored = &block_map.checksum[..len];
+            if declared.as_slice() != stored {
+                let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+                return Err(DppError::InvalidPlist(format!(
+                    "partition '{}' plist checksum {} does not match block map checksum {}",
+                    partition.name,
+                    hex(declared),
+                    hex(stored)
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Get the koly header
     pub fn koly(&self) -> &KolyHeader {
         &self.koly
     }
 
+    /// Size in bytes of the sectors block runs are counted in
+    pub(crate) fn sector_size(&self) -> u64 {
+        self.options.sector_size
+    }
+
     /// Get all partitions
     pub fn partitions(&self) -> &[PartitionEntry] {
         &self.partitions
     }
 
+    /// Parse the block runs of every partition deferred by
+    /// [`DmgReaderOptions::lazy_partitions`]
+    ///
+    /// Does nothing when all block maps are already loaded.
+    pub fn load_partitions(&mut self) -> Result<()> {
+        for index in 0..self.partitions.len() {
+            self.load_block_map(index)?;
+        }
+        Ok(())
+    }
+
+    /// Parse the deferred block runs of the partition at `index`, if any
+    fn load_block_map(&mut self, index: usize) -> Result<()> {
+        if let Some(data) = self.deferred_block_maps.get_mut(index).and_then(Option::take) {
+            self.partitions[index].block_map = MishHeader::from_bytes(&data)?;
+        }
+        Ok(())
+    }
+
+    /// Copy of the partition with `partition_id`, its block runs loaded
+    fn loaded_partition(&mut self, partition_id: i32) -> Result<PartitionEntry> {
+        let index = self
+            .partitions
+            .iter()
+            .position(|p| p.id == partition_id)
+            .ok_or_else(|| DppError::FileNotFound(format!("partition {}", partition_id)))?;
+        self.load_block_map(index)?;
+        Ok(self.partitions[index].clone())
+    }
+
     /// Get partition by name
     pub fn partition(&self, name: &str) -> Option<&PartitionEntry> {
         self.partitions.iter().find(|p| p.name == name)
     }
 
+    /// Get the first partition whose attributes match `value` under `mask`
+    ///
+    /// A partition matches when `attributes & mask == value`.
+    pub fn partition_by_attribute(&self, mask: u32, value: u32) -> Option<&PartitionEntry> {
+        self.partitions.iter().find(|p| p.attributes & mask == value)
+    }
+
+    /// Get the comment stored in the plist, if any
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Read the resource fork region declared in the koly header
+    ///
+    /// Signed images keep their code signature here. Returns `None` when
+    /// the koly declares an empty resource fork.
+    pub fn resource_fork(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.koly.rsrc_fork_length == 0 {
+            return Ok(None);
+        }
+
+        let stream_len = self.reader.seek(SeekFrom::End(0))?;
+        let end = self
+            .koly
+            .rsrc_fork_offset
+            .checked_add(self.koly.rsrc_fork_length)
+            .filter(|&end| end <= stream_len)
+            .ok_or_else(|| {
+                DppError::InvalidKolyHeader(format!(
+                    "resource fork {}+{} extends past end of image ({} bytes)",
+                    self.koly.rsrc_fork_offset, self.koly.rsrc_fork_length, stream_len
+                ))
+            })?;
+
+        self.reader.seek(SeekFrom::Start(self.koly.rsrc_fork_offset))?;
+        let mut data = vec![0u8; (end - self.koly.rsrc_fork_offset) as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+
+    /// Check whether the resource fork holds a code signature
+    ///
+    /// Looks for the embedded-signature SuperBlob magic (`0xFADE0CC0`) at
+    /// the start of the resource fork.
+    pub fn has_signature(&mut self) -> Result<bool> {
+        Ok(self
+            .resource_fork()?
+            .is_some_and(|data| data.starts_with(&CODE_SIGNATURE_MAGIC)))
+    }
+
     /// List all partition names
     pub fn list_partitions(&self) -> Vec<&str> {
         self.partitions.iter().map(|p| p.name.as_str()).collect()
     }
 
+    /// Compressed size of the partition at `index`
+    ///
+    /// A deferred block map is summed from its raw run table without
+    /// being loaded.
+    pub(crate) fn partition_compressed_size(&self, index: usize) -> u64 {
+        match self.deferred_block_maps.get(index) {
+            Some(Some(data)) => MishHeader::compressed_size_from_bytes(data),
+            _ => self.partitions[index].block_map.compressed_size(),
+        }
+    }
+
     /// Get DMG statistics
-    pub fn stats(&self) -> DmgStats {
+    ///
+    /// Loads any block maps deferred by
+    /// [`DmgReaderOptions::lazy_partitions`].
+    pub fn stats(&mut self) -> Result<DmgStats> {
+        self.load_partitions()?;
         let total_sectors: u64 = self.partitions.iter().map(|p| p.block_map.sector_count).sum();
         let total_compressed: u64 = self.partitions.iter().map(|p| p.block_map.compressed_size()).sum();
 
-        DmgStats {
+        Ok(DmgStats {
             version: self.koly.version,
             sector_count: self.koly.sector_count,
             partition_count: self.partitions.len(),
-            total_uncompressed: total_sectors * SECTOR_SIZE,
+            total_uncompressed: total_sectors * self.options.sector_size,
             total_compressed,
             data_fork_length: self.koly.data_fork_length,
-        }
+        })
     }
 
     /// Decompress a specific partition to raw disk data
     pub fn decompress_partition(&mut self, partition_id: i32) -> Result<Vec<u8>> {
-        let partition = self
-            .partitions
-            .iter()
-            .find(|p| p.id == partition_id)
-            .ok_or_else(|| DppError::FileNotFound(format!("partition {}", partition_id)))?
-            .clone();
+        let start = Instant::now();
+        let partition = self.loaded_partition(partition_id)?;
 
-        let total_size = partition.block_map.sector_count * SECTOR_SIZE;
+        let sector_size = self.options.sector_size;
+        let total_size = partition.block_map.sector_count * sector_size;
         let mut output = vec![0u8; total_size as usize];
 
         for block_run in &partition.block_map.block_runs {
-            let out_offset = block_run.sector_number * SECTOR_SIZE;
-            let out_size = block_run.sector_count * SECTOR_SIZE;
+            let out_offset = (block_run.sector_number * sector_size).min(total_size) as usize;
+            let block = decode_block_run(&mut self.reader, &self.koly, block_run, sector_size)?;
+            block.copy_into(&mut output[out_offset..]);
 
-            match block_run.block_type {
-                BlockType::ZeroFill => {
-                    // Already zero-filled
-                }
-                BlockType::Raw | BlockType::Ignore => {
-                    if block_run.compressed_length > 0 {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        // Read only compressed_length bytes (actual stored size)
-                        // remaining bytes in the sector stay zero-filled
-                        let end = out_offset as usize + block_run.compressed_length as usize;
-                        self.reader.read_exact(&mut output[out_offset as usize..end])?;
-                    }
-                }
-                BlockType::Zlib => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = flate2::read::ZlibDecoder::new(&compressed[..]);
-                    let slice = &mut output[out_offset as usize..(out_offset + out_size) as usize];
-                    read_full(&mut decoder, slice)?;
-                }
-                BlockType::Bzip2 => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = bzip2::read::BzDecoder::new(&compressed[..]);
-                    let slice = &mut output[out_offset as usize..(out_offset + out_size) as usize];
-                    read_full(&mut decoder, slice)?;
-                }
-                BlockType::Lzfse => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    // LZFSE decoder needs extra buffer space beyond the actual output size
-                    // Allocate 2x the expected size to be safe
-                    let expected_size = out_size as usize;
-                    let mut temp_buf = vec![0u8; expected_size * 2];
-                    let decoded_size = lzfse::decode_buffer(&compressed, &mut temp_buf)
-                        .map_err(|e| DppError::Decompression(format!("LZFSE: {:?}", e)))?;
-
-                    // Copy only the expected amount to output
-                    let copy_size = decoded_size.min(expected_size);
-                    let end = out_offset as usize + copy_size;
-                    output[out_offset as usize..end].copy_from_slice(&temp_buf[..copy_size]);
-                }
-                BlockType::Xz => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = xz2::read::XzDecoder::new(&compressed[..]);
-                    let slice = &mut output[out_offset as usize..(out_offset + out_size) as usize];
-                    read_full(&mut decoder, slice)?;
-                }
-                BlockType::Adc => {
-                    return Err(DppError::Unsupported("ADC compression".into()));
-                }
-                BlockType::Comment | BlockType::End => {
-                    // No data
-                }
-            }
+            self.io_stats.record(block_run, sector_size);
         }
 
+        self.io_stats.decompress_time += start.elapsed();
         Ok(output)
     }
 
@@ -263,19 +510,197 @@ impl<R: Read + Seek> DmgReader<R> {
         partition_id: i32,
         writer: &mut W,
     ) -> Result<u64> {
-        let partition = self
-            .partitions
-            .iter()
-            .find(|p| p.id == partition_id)
-            .ok_or_else(|| DppError::FileNotFound(format!("partition {}", partition_id)))?
-            .clone();
+        let partition = self.loaded_partition(partition_id)?;
+        self.decompress_entry_to(&partition, writer, |_, _| {}, None)
+    }
+
+    /// Stream a partition to a writer, reporting progress
+    ///
+    /// `progress` is called after each block run is written with
+    /// `(bytes_written, total_size)`. If `cancel` is given and becomes `true`
+    /// (e.g. from another thread), decompression stops before the next block
+    /// run and fails with `Cancelled`.
+    pub fn decompress_partition_to_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        partition_id: i32,
+        writer: &mut W,
+        progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let partition = self.loaded_partition(partition_id)?;
+        self.decompress_entry_to(&partition, writer, progress, cancel)
+    }
+
+    /// Decompress a partition on the rayon pool and stream it to a writer in order
+    ///
+    /// Block runs are handled in windows of two per pool thread: while one
+    /// window decodes on the pool, the calling thread reads the stored data
+    /// of the next, so I/O overlaps with decompression. Waiting for a window
+    /// helps run the pool's jobs, so this also works from inside the pool
+    /// (even a single-threaded one); a panicking decoder is re-raised on the
+    /// calling thread.
+    /// Returns the total number of bytes written.
+    #[cfg(feature = "parallel")]
+    pub fn decompress_partition_to_parallel<W: Write>(
+        &mut self,
+        partition_id: i32,
+        writer: &mut W,
+    ) -> Result<u64> {
+        self.decompress_partition_to_parallel_with_progress(partition_id, writer, |_, _| {}, None)
+    }
+
+    /// [`decompress_partition_to_parallel`](Self::decompress_partition_to_parallel), reporting progress
+    ///
+    /// `progress` and `cancel` behave as in
+    /// [`decompress_partition_to_with_progress`](Self::decompress_partition_to_with_progress);
+    /// the flag is checked before each window of block runs is read.
+    #[cfg(feature = "parallel")]
+    pub fn decompress_partition_to_parallel_with_progress<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        partition_id: i32,
+        writer: &mut W,
+        mut progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let start = Instant::now();
+        let partition = self.loaded_partition(partition_id)?;
+        let cancelled = || cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+
+        let sector_size = self.options.sector_size;
+        let total_size = partition.block_map.sector_count * sector_size;
+        let runs = &partition.block_map.block_runs;
+        let window = rayon::current_num_threads().max(1) * 2;
+        let mut windows = runs.chunks(window);
+        let mut next = 0usize;
+        let mut bytes_written = 0u64;
 
-        let block_size = partition.block_map.sector_count * SECTOR_SIZE;
+        if cancelled() {
+            return Err(DppError::Cancelled);
+        }
+        let mut pending = match windows.next() {
+            Some(first) => self.read_window(first)?,
+            None => Vec::new(),
+        };
+        while !pending.is_empty() {
+            let mut decoded: Vec<Result<DecodedBlock>> = Vec::with_capacity(pending.len());
+            decoded.resize_with(pending.len(), || Ok(DecodedBlock::Zeros(0)));
+
+            let ahead = rayon::in_place_scope(|scope| {
+                for (slot, run) in decoded.iter_mut().zip(pending) {
+                    match run {
+                        PendingRun::Ready(block) => *slot = Ok(block),
+                        PendingRun::Stored(block_type, stored, out_size) => {
+                            scope.spawn(move |_| *slot = decode_stored(block_type, stored, out_size));
+                        }
+                    }
+                }
+                // A cancelled read-ahead still lets this window finish writing
+                match windows.next() {
+                    _ if cancelled() => Err(DppError::Cancelled),
+                    Some(runs) => self.read_window(runs),
+                    None => Ok(Vec::new()),
+                }
+            });
+
+            for block in decoded {
+                let out_offset = runs[next].sector_number * sector_size;
+                write_run_at(&block?, out_offset, writer, &mut bytes_written)?;
+                progress(bytes_written, total_size);
+                next += 1;
+            }
+            pending = ahead?;
+        }
+
+        // Pad to full partition size if needed
+        if bytes_written < total_size {
+            bytes_written += std::io::copy(&mut std::io::repeat(0).take(total_size - bytes_written), writer)?;
+        }
+
+        self.io_stats.decompress_time += start.elapsed();
+        Ok(bytes_written)
+    }
+
+    /// Read the stored data of a window of runs for the parallel decoder
+    #[cfg(feature = "parallel")]
+    fn read_window(&mut self, runs: &[BlockRun]) -> Result<Vec<PendingRun>> {
+        let sector_size = self.options.sector_size;
+        let mut pending = Vec::with_capacity(runs.len());
+        for block_run in runs {
+            let out_size = block_run.sector_count * sector_size;
+            pending.push(match unstored_block(block_run, out_size)? {
+                Some(block) => PendingRun::Ready(block),
+                None => PendingRun::Stored(
+                    block_run.block_type,
+                    read_stored(&mut self.reader, &self.koly, block_run)?,
+                    out_size,
+                ),
+            });
+            self.io_stats.record(block_run, sector_size);
+        }
+        Ok(pending)
+    }
+
+    /// Stream a partition's decompressed bytes block run by block run
+    ///
+    /// The stream yields the same bytes as
+    /// [`decompress_partition_to`](Self::decompress_partition_to), one
+    /// decoded run per item. Decoding still happens synchronously inside
+    /// `poll_next`; see [`PartitionStream`] for driving it from an async runtime.
+    #[cfg(feature = "async")]
+    pub fn partition_stream(&mut self, partition_id: i32) -> Result<PartitionStream<'_, R>> {
+        let partition = self.loaded_partition(partition_id)?;
+        let total_size = partition.block_map.sector_count * self.options.sector_size;
+        let zeros = bytes::Bytes::from(vec![0u8; total_size.min(STREAM_ZERO_CHUNK) as usize]);
+        Ok(PartitionStream {
+            dmg: self,
+            partition,
+            next_run: 0,
+            position: 0,
+            pending_zeros: 0,
+            zeros,
+            failed: false,
+        })
+    }
+
+    /// Decompress only the first `len` bytes of a partition
+    ///
+    /// Block runs that start at or past `len` are never read, so this is
+    /// cheap even for very large partitions. The result is shorter than
+    /// `len` only if the partition itself is.
+    pub fn read_partition_head(&mut self, partition_id: i32, len: u64) -> Result<Vec<u8>> {
+        let mut partition = self.loaded_partition(partition_id)?;
+
+        let sector_size = self.options.sector_size;
+        let head_sectors = len.div_ceil(sector_size);
+        let block_map = &mut partition.block_map;
+        block_map.sector_count = block_map.sector_count.min(head_sectors);
+        block_map
+            .block_runs
+            .retain(|run| run.sector_number < head_sectors);
+
+        let mut output = Vec::new();
+        self.decompress_entry_to(&partition, &mut output, |_, _| {}, None)?;
+        output.truncate(len as usize);
+        Ok(output)
+    }
+
+    fn decompress_entry_to<W: Write, F: FnMut(u64, u64)>(
+        &mut self,
+        partition: &PartitionEntry,
+        writer: &mut W,
+        mut progress: F,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<u64> {
+        let start = Instant::now();
+        let sector_size = self.options.sector_size;
+        let block_size = partition.block_map.sector_count * sector_size;
         let mut bytes_written = 0u64;
 
         for block_run in &partition.block_map.block_runs {
-            let out_offset = block_run.sector_number * SECTOR_SIZE;
-            let out_size = block_run.sector_count * SECTOR_SIZE;
+            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                return Err(DppError::Cancelled);
+            }
+            let out_offset = block_run.sector_number * sector_size;
 
             // Emit zero padding if there's a gap between the current position and this block
             if out_offset > bytes_written {
@@ -285,97 +710,12 @@ impl<R: Read + Seek> DmgReader<R> {
                 bytes_written += gap as u64;
             }
 
-            match block_run.block_type {
-                BlockType::ZeroFill => {
-                    let zeros = vec![0u8; out_size as usize];
-                    writer.write_all(&zeros)?;
-                    bytes_written += out_size;
-                }
-                BlockType::Raw | BlockType::Ignore => {
-                    if block_run.compressed_length > 0 {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        let mut buf = vec![0u8; block_run.compressed_length as usize];
-                        self.reader.read_exact(&mut buf)?;
-                        writer.write_all(&buf)?;
-                        bytes_written += block_run.compressed_length;
-                        let remaining = out_size - block_run.compressed_length;
-                        if remaining > 0 {
-                            let zeros = vec![0u8; remaining as usize];
-                            writer.write_all(&zeros)?;
-                            bytes_written += remaining;
-                        }
-                    } else {
-                        let zeros = vec![0u8; out_size as usize];
-                        writer.write_all(&zeros)?;
-                        bytes_written += out_size;
-                    }
-                }
-                BlockType::Zlib => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = flate2::read::ZlibDecoder::new(&compressed[..]);
-                    let mut decompressed = vec![0u8; out_size as usize];
-                    read_full(&mut decoder, &mut decompressed)?;
-                    writer.write_all(&decompressed)?;
-                    bytes_written += out_size;
-                }
-                BlockType::Bzip2 => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = bzip2::read::BzDecoder::new(&compressed[..]);
-                    let mut decompressed = vec![0u8; out_size as usize];
-                    read_full(&mut decoder, &mut decompressed)?;
-                    writer.write_all(&decompressed)?;
-                    bytes_written += out_size;
-                }
-                BlockType::Lzfse => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let expected_size = out_size as usize;
-                    let mut temp_buf = vec![0u8; expected_size * 2];
-                    let decoded_size = lzfse::decode_buffer(&compressed, &mut temp_buf)
-                        .map_err(|e| DppError::Decompression(format!("LZFSE: {:?}", e)))?;
-
-                    let mut block = vec![0u8; expected_size];
-                    let copy_size = decoded_size.min(expected_size);
-                    block[..copy_size].copy_from_slice(&temp_buf[..copy_size]);
-                    writer.write_all(&block)?;
-                    bytes_written += expected_size as u64;
-                }
-                BlockType::Xz => {
-                    self.reader.seek(SeekFrom::Start(
-                        self.koly.data_fork_offset + block_run.compressed_offset,
-                    ))?;
-                    let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                    self.reader.read_exact(&mut compressed)?;
-
-                    let mut decoder = xz2::read::XzDecoder::new(&compressed[..]);
-                    let mut decompressed = vec![0u8; out_size as usize];
-                    read_full(&mut decoder, &mut decompressed)?;
-                    writer.write_all(&decompressed)?;
-                    bytes_written += out_size;
-                }
-                BlockType::Adc => {
-                    return Err(DppError::Unsupported("ADC compression".into()));
-                }
-                BlockType::Comment | BlockType::End => {
-                    // No data
-                }
-            }
+            let block = decode_block_run(&mut self.reader, &self.koly, block_run, sector_size)?;
+            block.write_to(writer)?;
+            bytes_written += block.len();
+            progress(bytes_written, block_size);
+
+            self.io_stats.record(block_run, sector_size);
         }
 
         // Pad to full partition size if needed
@@ -386,9 +726,55 @@ impl<R: Read + Seek> DmgReader<R> {
             bytes_written += remaining as u64;
         }
 
+        self.io_stats.decompress_time += start.elapsed();
         Ok(bytes_written)
     }
 
+    /// Check every checksum the image records
+    ///
+    /// Unlike the checks made on open, this decompresses each partition and
+    /// compares it against its block map (mish) checksum. Failures are
+    /// collected in the report rather than returned, so one bad partition
+    /// does not hide the state of the others; only errors that leave
+    /// nothing to report are returned as `Err`.
+    pub fn verify_all(&mut self) -> Result<VerifyReport> {
+        let koly = self.koly.clone();
+
+        let data_fork = crc_status(koly.data_checksum_type, &koly.data_checksum, || {
+            self.reader.seek(SeekFrom::Start(koly.data_fork_offset))?;
+            let mut hasher = Crc32Writer::default();
+            std::io::copy(&mut (&mut self.reader).take(koly.data_fork_length), &mut hasher)?;
+            Ok(hasher.0.finalize())
+        });
+
+        let master = crc_status(koly.master_checksum_type, &koly.master_checksum, || {
+            let mish_checksums: Vec<u8> = self
+                .partitions
+                .iter()
+                .flat_map(|p| p.block_map.checksum[..4].iter().copied())
+                .collect();
+            Ok(crate::checksum::crc32(&mish_checksums))
+        });
+
+        self.load_partitions()?;
+        let mut partitions = Vec::with_capacity(self.partitions.len());
+        for partition in self.partitions.clone() {
+            let block_map = &partition.block_map;
+            let status = crc_status(block_map.checksum_type, &block_map.checksum, || {
+                let mut hasher = Crc32Writer::default();
+                self.decompress_entry_to(&partition, &mut hasher, |_, _| {}, None)?;
+                Ok(hasher.0.finalize())
+            });
+            partitions.push((PartitionInfo::from_entry(&partition, self.options.sector_size), status));
+        }
+
+        Ok(VerifyReport {
+            data_fork,
+            master,
+            partitions,
+        })
+    }
+
     /// Decompress the main HFS+ partition (largest one)
     pub fn decompress_main_partition(&mut self) -> Result<Vec<u8>> {
         let id = self.main_partition_id()?;
@@ -439,113 +825,98 @@ impl<R: Read + Seek> DmgReader<R> {
 
     /// Decompress all partitions into a single raw disk image
     pub fn decompress_all(&mut self) -> Result<Vec<u8>> {
+        let start = Instant::now();
         let total_sectors = self.koly.sector_count;
-        let total_size = total_sectors * SECTOR_SIZE;
+        let sector_size = self.options.sector_size;
+        let total_size = total_sectors * sector_size;
         let mut output = vec![0u8; total_size as usize];
 
+        self.load_partitions()?;
         for partition in self.partitions.clone() {
             for block_run in &partition.block_map.block_runs {
-                let out_offset = (partition.block_map.first_sector + block_run.sector_number) * SECTOR_SIZE;
-                let out_size = block_run.sector_count * SECTOR_SIZE;
+                let out_offset = (partition.block_map.first_sector + block_run.sector_number) * sector_size;
+                let out_size = block_run.sector_count * sector_size;
 
                 if out_offset + out_size > total_size {
                     continue; // Skip out-of-bounds blocks
                 }
 
-                match block_run.block_type {
-                    BlockType::ZeroFill => {}
-                    BlockType::Raw | BlockType::Ignore => {
-                        if block_run.compressed_length > 0 {
-                            self.reader.seek(SeekFrom::Start(
-                                self.koly.data_fork_offset + block_run.compressed_offset,
-                            ))?;
-                            // Read only compressed_length bytes (actual stored size)
-                            // remaining bytes in the sector stay zero-filled
-                            let end = out_offset as usize + block_run.compressed_length as usize;
-                            self.reader.read_exact(&mut output[out_offset as usize..end])?;
-                        }
-                    }
-                    BlockType::Zlib => {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                        self.reader.read_exact(&mut compressed)?;
-
-                        let mut decoder = flate2::read::ZlibDecoder::new(&compressed[..]);
-                        let end = (out_offset + out_size) as usize;
-                        let _ = decoder.read(&mut output[out_offset as usize..end])?;
-                    }
-                    BlockType::Bzip2 => {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                        self.reader.read_exact(&mut compressed)?;
-
-                        let mut decoder = bzip2::read::BzDecoder::new(&compressed[..]);
-                        let end = (out_offset + out_size) as usize;
-                        let _ = decoder.read(&mut output[out_offset as usize..end])?;
-                    }
-                    BlockType::Lzfse => {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                        self.reader.read_exact(&mut compressed)?;
-
-                        // LZFSE decoder needs extra buffer space
-                        let expected_size = out_size as usize;
-                        let mut temp_buf = vec![0u8; expected_size * 2];
-                        let decoded_size = lzfse::decode_buffer(&compressed, &mut temp_buf)
-                            .map_err(|e| DppError::Decompression(format!("LZFSE: {:?}", e)))?;
-
-                        let copy_size = decoded_size.min(expected_size);
-                        let end = out_offset as usize + copy_size;
-                        output[out_offset as usize..end].copy_from_slice(&temp_buf[..copy_size]);
-                    }
-                    BlockType::Xz => {
-                        self.reader.seek(SeekFrom::Start(
-                            self.koly.data_fork_offset + block_run.compressed_offset,
-                        ))?;
-                        let mut compressed = vec![0u8; block_run.compressed_length as usize];
-                        self.reader.read_exact(&mut compressed)?;
-
-                        let mut decoder = xz2::read::XzDecoder::new(&compressed[..]);
-                        let slice = &mut output[out_offset as usize..(out_offset + out_size) as usize];
-                        read_full(&mut decoder, slice)?;
-                    }
-                    BlockType::Adc => {
-                        return Err(DppError::Unsupported("ADC compression".into()));
-                    }
-                    BlockType::Comment | BlockType::End => {}
-                }
+                let block = decode_block_run(&mut self.reader, &self.koly, block_run, sector_size)?;
+                block.copy_into(&mut output[out_offset as usize..(out_offset + out_size) as usize]);
+
+                self.io_stats.record(block_run, sector_size);
             }
         }
 
+        self.io_stats.decompress_time += start.elapsed();
         Ok(output)
     }
 
+    /// Get info about block compression types used by one partition
+    ///
+    /// Returns `None` if no partition has the given ID.
+    pub fn partition_compression_info(&mut self, partition_id: i32) -> Result<Option<CompressionInfo>> {
+        let Some(index) = self.partitions.iter().position(|p| p.id == partition_id) else {
+            return Ok(None);
+        };
+        self.load_block_map(index)?;
+        let mut info = CompressionInfo::default();
+        for block_run in &self.partitions[index].block_map.block_runs {
+            info.count(block_run.block_type);
+        }
+        Ok(Some(info))
+    }
+
     /// Get info about block compression types used
-    pub fn compression_info(&self) -> CompressionInfo {
+    pub fn compression_info(&mut self) -> Result<CompressionInfo> {
+        self.load_partitions()?;
         let mut info = CompressionInfo::default();
 
         for partition in &self.partitions {
             for block_run in &partition.block_map.block_runs {
-                match block_run.block_type {
-                    BlockType::ZeroFill => info.zero_fill_blocks += 1,
-                    BlockType::Raw => info.raw_blocks += 1,
-                    BlockType::Zlib => info.zlib_blocks += 1,
-                    BlockType::Bzip2 => info.bzip2_blocks += 1,
-                    BlockType::Lzfse => info.lzfse_blocks += 1,
-                    BlockType::Xz => info.xz_blocks += 1,
-                    BlockType::Adc => info.adc_blocks += 1,
-                    _ => {}
-                }
+                info.count(block_run.block_type);
             }
         }
 
-        info
+        Ok(info)
+    }
+
+    /// Classify the image the way `hdiutil imageinfo` reports its format
+    ///
+    /// The format follows the most common compressed block type; images with
+    /// only raw and zero-fill blocks are [`ImageFormat::Udro`]. Images whose
+    /// koly `image_variant` is neither a device nor a partition image are
+    /// [`ImageFormat::Unknown`].
+    pub fn image_format(&mut self) -> Result<ImageFormat> {
+        if !matches!(
+            self.koly.image_variant,
+            IMAGE_VARIANT_DEVICE | IMAGE_VARIANT_PARTITION
+        ) {
+            return Ok(ImageFormat::Unknown);
+        }
+
+        let info = self.compression_info()?;
+        Ok([
+            (info.zlib_blocks, ImageFormat::Udzo),
+            (info.bzip2_blocks, ImageFormat::Udbz),
+            (info.lzfse_blocks, ImageFormat::Ulfo),
+            (info.xz_blocks, ImageFormat::Ulmo),
+            (info.adc_blocks, ImageFormat::Udco),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .fold(None, |best: Option<(u32, ImageFormat)>, candidate| match best {
+            Some(b) if b.0 >= candidate.0 => Some(b),
+            _ => Some(candidate),
+        })
+        .map_or(ImageFormat::Udro, |(_, format)| format))
+    }
+
+    /// Get counters for the work done by extraction calls so far
+    ///
+    /// Counters accumulate across all `decompress_*` calls on this reader.
+    pub fn io_stats(&self) -> &IoStats {
+        &self.io_stats
     }
 }
 
@@ -563,6 +934,362 @@ impl DmgReader<BufReader<File>> {
     }
 }
 
+#[cfg(feature = "mmap")]
+impl DmgReader<Cursor<memmap2::Mmap>> {
+    /// Memory-map a DMG file with default options (checksum verification enabled)
+    ///
+    /// Block runs are copied straight out of the mapping instead of going
+    /// through buffered reads. The file must not be modified while the
+    /// reader is alive.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_mmap_with_options(path, DmgReaderOptions::default())
+    }
+
+    /// Memory-map a DMG file with custom options
+    pub fn open_mmap_with_options<P: AsRef<Path>>(path: P, options: DmgReaderOptions) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only; callers are told not to modify
+        // the file while the reader is alive.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Self::with_options(Cursor::new(map), options)
+    }
+}
+
+impl DmgReader<Cursor<Vec<u8>>> {
+    /// Open an in-memory DMG with default options (checksum verification enabled)
+    ///
+    /// The bytes are copied into an owned buffer.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::new(Cursor::new(data.as_ref().to_vec()))
+    }
+}
+
+impl<S: RangeReader> DmgReader<RangeSource<S>> {
+    /// Open a DMG served by a range-addressable source of `total_len` bytes
+    ///
+    /// Only the koly trailer and the plist are fetched here; partition data
+    /// is fetched one block run at a time during extraction. The data fork
+    /// checksum would need the whole image, so checksums are not verified
+    /// on open.
+    pub fn from_range_source(src: S, total_len: u64) -> Result<Self> {
+        let options = DmgReaderOptions {
+            verify_checksums: false,
+            ..DmgReaderOptions::default()
+        };
+        Self::with_options(RangeSource::new(src, total_len), options)
+    }
+}
+
+impl<R: Read + Seek + Clone> DmgReader<R> {
+    /// Get an independent seekable reader for every partition
+    ///
+    /// Each [`PartitionReader`] owns a clone of the underlying reader, so
+    /// partitions can be extracted concurrently (e.g. one per thread). Use a
+    /// cheaply cloneable source such as `Cursor<Arc<[u8]>>`; cloning a
+    /// `Cursor<Vec<u8>>` copies the whole image. Loads any block maps
+    /// deferred by [`DmgReaderOptions::lazy_partitions`].
+    pub fn partition_readers(&mut self) -> Result<Vec<(PartitionInfo, PartitionReader<R>)>> {
+        self.load_partitions()?;
+        Ok(self
+            .partitions
+            .iter()
+            .map(|p| {
+                let reader = PartitionReader {
+                    reader: self.reader.clone(),
+                    koly: self.koly.clone(),
+                    partition: p.clone(),
+                    sector_size: self.options.sector_size,
+                    size: p.block_map.sector_count * self.options.sector_size,
+                    pos: 0,
+                    cached: None,
+                };
+                (PartitionInfo::from_entry(p, self.options.sector_size), reader)
+            })
+            .collect())
+    }
+}
+
+/// Largest run of zeros [`PartitionStream`] yields as one item
+#[cfg(feature = "async")]
+const STREAM_ZERO_CHUNK: u64 = 1 << 20;
+
+/// A partition's decompressed bytes as a [`futures_core::Stream`]
+///
+/// Returned by [`DmgReader::partition_stream`]. Each item is one decoded
+/// block run, in partition order. Gaps between runs, zero-fill runs and
+/// the tail padding come as shared zero buffers of at most 1 MiB.
+///
+/// `poll_next` never returns `Pending`: it reads and decodes the next run
+/// before returning, blocking the calling thread. In an async runtime,
+/// drive the stream from a blocking task (e.g. `spawn_blocking`) and
+/// forward the items over a channel. The stream ends after the first error.
+#[cfg(feature = "async")]
+pub struct PartitionStream<'a, R> {
+    dmg: &'a mut DmgReader<R>,
+    partition: PartitionEntry,
+    next_run: usize,
+    /// Bytes yielded so far
+    position: u64,
+    /// Zeros still owed before the next run is decoded
+    pending_zeros: u64,
+    zeros: bytes::Bytes,
+    failed: bool,
+}
+
+#[cfg(feature = "async")]
+impl<R: Read + Seek> PartitionStream<'_, R> {
+    fn next_item(&mut self) -> Option<Result<bytes::Bytes>> {
+        let sector_size = self.dmg.options.sector_size;
+        loop {
+            if self.pending_zeros > 0 {
+                let len = self.pending_zeros.min(self.zeros.len() as u64);
+                self.pending_zeros -= len;
+                self.position += len;
+                return Some(Ok(self.zeros.slice(..len as usize)));
+            }
+            if self.failed {
+                return None;
+            }
+
+            let Some(block_run) = self.partition.block_map.block_runs.get(self.next_run) else {
+                // Pad to full partition size if needed
+                let total_size = self.partition.block_map.sector_count * sector_size;
+                if self.position < total_size {
+                    self.pending_zeros = total_size - self.position;
+                    continue;
+                }
+                return None;
+            };
+
+            let out_offset = block_run.sector_number * sector_size;
+            if out_offset > self.position {
+                self.pending_zeros = out_offset - self.position;
+                continue;
+            }
+            self.next_run += 1;
+
+            let start = Instant::now();
+            let dmg = &mut *self.dmg;
+            let block = decode_block_run(&mut dmg.reader, &dmg.koly, block_run, sector_size);
+            dmg.io_stats.decompress_time += start.elapsed();
+            match block {
+                Ok(DecodedBlock::Zeros(len)) => {
+                    dmg.io_stats.record(block_run, sector_size);
+                    self.pending_zeros = len;
+                }
+                Ok(DecodedBlock::Data(data)) => {
+                    dmg.io_stats.record(block_run, sector_size);
+                    self.position += data.len() as u64;
+                    return Some(Ok(bytes::Bytes::from(data)));
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: Read + Seek> futures_core::Stream for PartitionStream<'_, R> {
+    type Item = Result<bytes::Bytes>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::task::Poll::Ready(self.get_mut().next_item())
+    }
+}
+
+/// Seekable reader over the uncompressed bytes of one partition
+///
+/// Created by [`DmgReader::partition_readers`]. Block runs are decoded on
+/// demand and the most recently used one is cached, so sequential reads
+/// decode each run once.
+pub struct PartitionReader<R> {
+    reader: R,
+    koly: KolyHeader,
+    partition: PartitionEntry,
+    sector_size: u64,
+    size: u64,
+    pos: u64,
+    /// Index and decoded bytes of the last block run read
+    cached: Option<(usize, DecodedBlock)>,
+}
+
+impl<R> PartitionReader<R> {
+    /// Uncompressed size of the partition in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Consume the partition reader and return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> Read for PartitionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.size {
+            return Ok(0);
+        }
+
+        let sector_size = self.sector_size;
+        let pos = self.pos;
+        let runs = &self.partition.block_map.block_runs;
+        let found = runs.iter().position(|r| {
+            let start = r.sector_number * sector_size;
+            !matches!(r.block_type, BlockType::Comment | BlockType::End)
+                && start <= pos
+                && pos < start + r.sector_count * sector_size
+        });
+
+        let n = match found {
+            Some(index) => {
+                if self.cached.as_ref().is_none_or(|(i, _)| *i != index) {
+                    let block =
+                        decode_block_run(&mut self.reader, &self.koly, &runs[index], sector_size)
+                            .map_err(into_io_error)?;
+                    self.cached = Some((index, block));
+                }
+                let offset = pos - runs[index].sector_number * sector_size;
+                match self.cached.as_ref().map(|(_, b)| b) {
+                    Some(DecodedBlock::Data(block)) => {
+                        let offset = offset as usize;
+                        let n = buf.len().min(block.len() - offset);
+                        buf[..n].copy_from_slice(&block[offset..offset + n]);
+                        n
+                    }
+                    Some(DecodedBlock::Zeros(len)) => {
+                        let n = buf.len().min((len - offset) as usize);
+                        buf[..n].fill(0);
+                        n
+                    }
+                    None => 0,
+                }
+            }
+            None => {
+                // Not covered by any block run: zeros up to the next run
+                let next = runs
+                    .iter()
+                    .map(|r| r.sector_number * sector_size)
+                    .filter(|&start| start > pos)
+                    .min()
+                    .unwrap_or(self.size)
+                    .min(self.size);
+                let n = buf.len().min((next - pos) as usize);
+                buf[..n].fill(0);
+                n
+            }
+        };
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R> Seek for PartitionReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before partition start")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Surface a decoding error through the `std::io` interface
+fn into_io_error(e: DppError) -> std::io::Error {
+    match e {
+        DppError::Io(e) => e,
+        other => std::io::Error::other(other),
+    }
+}
+
+/// `Write` sink that only accumulates a CRC32
+#[derive(Default)]
+struct Crc32Writer(crc32fast::Hasher);
+
+impl Write for Crc32Writer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compare a recorded checksum against the CRC32 `actual` computes
+///
+/// `actual` is only run when a CRC32 checksum is recorded.
+fn crc_status(
+    checksum_type: u32,
+    checksum: &[u8; 128],
+    actual: impl FnOnce() -> Result<u32>,
+) -> ChecksumStatus {
+    if !has_checksum(checksum_type, checksum) {
+        return ChecksumStatus::Absent;
+    }
+    let expected = extract_crc32(checksum);
+    match actual() {
+        Ok(actual) if actual == expected => ChecksumStatus::Valid,
+        Ok(actual) => ChecksumStatus::Mismatch { expected, actual },
+        Err(e) => ChecksumStatus::Failed(e),
+    }
+}
+
+/// Outcome of checking one recorded checksum
+#[derive(Debug)]
+pub enum ChecksumStatus {
+    /// No CRC32 checksum is recorded, so nothing was checked
+    Absent,
+    /// The recorded checksum matches the data
+    Valid,
+    /// The recorded checksum does not match the data
+    Mismatch {
+        /// Checksum recorded in the image
+        expected: u32,
+        /// Checksum of the data as read
+        actual: u32,
+    },
+    /// The data could not be read or decompressed
+    Failed(DppError),
+}
+
+impl ChecksumStatus {
+    /// Whether the check passed or there was nothing to check
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ChecksumStatus::Absent | ChecksumStatus::Valid)
+    }
+}
+
+/// Result of [`DmgReader::verify_all`]
+#[derive(Debug)]
+pub struct VerifyReport {
+    /// koly data fork checksum (CRC32 of the stored, compressed data)
+    pub data_fork: ChecksumStatus,
+    /// koly master checksum (CRC32 of the partition checksums)
+    pub master: ChecksumStatus,
+    /// Block map checksum of each partition's decompressed data, in image order
+    pub partitions: Vec<(PartitionInfo, ChecksumStatus)>,
+}
+
+impl VerifyReport {
+    /// Whether every check passed
+    pub fn is_ok(&self) -> bool {
+        self.data_fork.is_ok() && self.master.is_ok() && self.partitions.iter().all(|(_, s)| s.is_ok())
+    }
+}
+
 /// Statistics about a DMG file
 #[derive(Debug, Clone)]
 pub struct DmgStats {
@@ -595,8 +1322,51 @@ impl DmgStats {
     }
 }
 
+/// koly `image_variant` of a whole-device image
+pub const IMAGE_VARIANT_DEVICE: u32 = 1;
+
+/// koly `image_variant` of a single-partition image
+pub const IMAGE_VARIANT_PARTITION: u32 = 2;
+
+/// DMG image format, as named by `hdiutil`
+///
+/// Sparse images (UDSP) have no koly trailer and cannot be opened by this
+/// crate, so they have no variant here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Read-only, uncompressed (UDRO)
+    Udro,
+    /// ADC compressed (UDCO)
+    Udco,
+    /// Zlib compressed (UDZO)
+    Udzo,
+    /// Bzip2 compressed (UDBZ)
+    Udbz,
+    /// LZFSE compressed (ULFO)
+    Ulfo,
+    /// LZMA compressed (ULMO)
+    Ulmo,
+    /// Unrecognized image variant
+    Unknown,
+}
+
+impl ImageFormat {
+    /// The four-letter format name used by `hdiutil` (e.g. "UDZO")
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::Udro => "UDRO",
+            ImageFormat::Udco => "UDCO",
+            ImageFormat::Udzo => "UDZO",
+            ImageFormat::Udbz => "UDBZ",
+            ImageFormat::Ulfo => "ULFO",
+            ImageFormat::Ulmo => "ULMO",
+            ImageFormat::Unknown => "unknown",
+        }
+    }
+}
+
 /// Information about compression methods used
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CompressionInfo {
     pub zero_fill_blocks: u32,
     pub raw_blocks: u32,
@@ -607,8 +1377,56 @@ pub struct CompressionInfo {
     pub adc_blocks: u32,
 }
 
-/// Parse the DMG plist to extract partition info
-fn parse_plist(plist_data: &[u8]) -> Result<Vec<PartitionEntry>> {
+impl CompressionInfo {
+    /// Count one block of the given type
+    pub(crate) fn count(&mut self, block_type: BlockType) {
+        match block_type {
+            BlockType::ZeroFill => self.zero_fill_blocks += 1,
+            BlockType::Raw => self.raw_blocks += 1,
+            BlockType::Zlib => self.zlib_blocks += 1,
+            BlockType::Bzip2 => self.bzip2_blocks += 1,
+            BlockType::Lzfse => self.lzfse_blocks += 1,
+            BlockType::Xz => self.xz_blocks += 1,
+            BlockType::Adc => self.adc_blocks += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Counters for data read and produced by a [`DmgReader`]
+#[derive(Debug, Clone, Default)]
+pub struct IoStats {
+    /// Bytes read from the data fork for block runs
+    pub compressed_bytes_read: u64,
+    /// Bytes produced by decoding block runs (including zero-fill)
+    pub decompressed_bytes: u64,
+    /// Number of block runs processed, per compression method
+    pub blocks: CompressionInfo,
+    /// Time spent in successful extraction calls
+    pub decompress_time: Duration,
+}
+
+impl IoStats {
+    /// Account for one processed block run
+    fn record(&mut self, block_run: &BlockRun, sector_size: u64) {
+        match block_run.block_type {
+            BlockType::Comment | BlockType::End => return,
+            BlockType::ZeroFill => {}
+            _ => self.compressed_bytes_read += block_run.compressed_length,
+        }
+        self.decompressed_bytes += block_run.sector_count * sector_size;
+        self.blocks.count(block_run.block_type);
+    }
+}
+
+/// Partitions, their deferred raw block maps and the optional comment
+type ParsedPlist = (Vec<PartitionEntry>, Vec<Option<Vec<u8>>>, Option<String>);
+
+/// Parse the DMG plist to extract partition info and the optional comment
+///
+/// With `lazy`, each block map gets only its fixed header parsed and its
+/// raw data is returned for [`DmgReader::load_partitions`].
+fn parse_plist(plist_data: &[u8], lazy: bool) -> Result<ParsedPlist> {
     // Parse using plist crate
     let plist: plist::Value = plist::from_bytes(plist_data)
         .map_err(|e| DppError::InvalidPlist(format!("plist parse error: {}", e)))?;
@@ -628,6 +1446,7 @@ fn parse_plist(plist_data: &[u8]) -> Result<Vec<PartitionEntry>> {
         .ok_or_else(|| DppError::InvalidPlist("missing blkx array".into()))?;
 
     let mut partitions = Vec::with_capacity(blkx.len());
+    let mut deferred = Vec::with_capacity(if lazy { blkx.len() } else { 0 });
 
     for entry in blkx {
         let entry_dict = entry
@@ -640,6 +1459,11 @@ fn parse_plist(plist_data: &[u8]) -> Result<Vec<PartitionEntry>> {
             .unwrap_or("")
             .to_string();
 
+        let display_name = entry_dict
+            .get("CFName")
+            .and_then(|v| v.as_string())
+            .map(|s| s.to_string());
+
         let id = entry_dict
             .get("ID")
             .and_then(|v| v.as_string())
@@ -650,8 +1474,8 @@ fn parse_plist(plist_data: &[u8]) -> Result<Vec<PartitionEntry>> {
             .get("Attributes")
             .and_then(|v| v.as_string())
             .and_then(|s| {
-                if s.starts_with("0x") {
-                    u32::from_str_radix(&s[2..], 16).ok()
+                if let Some(hex) = s.strip_prefix("0x") {
+                    u32::from_str_radix(hex, 16).ok()
                 } else {
                     s.parse().ok()
                 }
@@ -663,17 +1487,34 @@ fn parse_plist(plist_data: &[u8]) -> Result<Vec<PartitionEntry>> {
             .and_then(|v| v.as_data())
             .ok_or_else(|| DppError::InvalidPlist("missing Data in blkx entry".into()))?;
 
-        let block_map = MishHeader::from_bytes(data)?;
+        let block_map = if lazy {
+            deferred.push(Some(data.to_vec()));
+            MishHeader::header_from_bytes(data)?
+        } else {
+            MishHeader::from_bytes(data)?
+        };
+
+        let plist_checksum = entry_dict
+            .get("Checksum")
+            .and_then(|v| v.as_data())
+            .map(|d| d.to_vec());
 
         partitions.push(PartitionEntry {
             name,
+            display_name,
             id,
             attributes,
             block_map,
+            plist_checksum,
         });
     }
 
-    Ok(partitions)
+    let comment = dict
+        .get("comment")
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string());
+
+    Ok((partitions, deferred, comment))
 }
 
 /// Convenience function to open a DMG file
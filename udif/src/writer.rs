@@ -15,12 +15,48 @@ use crate::checksum::{create_checksum_array, crc32, CHECKSUM_TYPE_CRC32, CHECKSU
 use crate::error::{DppError, Result};
 use crate::format::{BlockRun, BlockType, KolyHeader, KOLY_MAGIC, KOLY_SIZE, MISH_MAGIC};
 
-/// Sector size in bytes
-const SECTOR_SIZE: u64 = 512;
+use crate::reader::{CompressionInfo, DEFAULT_SECTOR_SIZE};
 
 /// Default chunk size for compression (1 MB)
 const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
 
+/// Number of chunks per partition compressed by `estimate_output_size`
+const ESTIMATE_SAMPLE_CHUNKS: usize = 8;
+
+/// Approximate plist bytes per partition, excluding its block runs
+const PLIST_PARTITION_OVERHEAD: u64 = 700;
+
+/// Approximate fixed plist bytes (XML prologue and enclosing elements)
+const PLIST_BASE_OVERHEAD: u64 = 400;
+
+/// Bytes of history that determine the gear rolling hash
+const GEAR_WINDOW: usize = 64;
+
+/// Random per-byte values for the gear rolling hash (splitmix64 sequence)
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Content-defined chunk size bounds, in bytes (sector multiples)
+#[derive(Debug, Clone, Copy)]
+struct CdcParams {
+    min: usize,
+    max: usize,
+    /// Cut when the top bits of the rolling hash selected by this mask are zero
+    mask: u64,
+}
+
 /// Compression method for DMG creation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CompressionMethod {
@@ -33,17 +69,34 @@ pub enum CompressionMethod {
     Bzip2,
     /// LZFSE compression (fast, Apple-native)
     Lzfse,
+    /// LZMA compression in an XZ container (ULMO; best ratio, slowest)
+    ///
+    /// Block runs are stored as [`BlockType::Xz`], the code `hdiutil` uses
+    /// for ULMO images.
+    Lzma,
+    /// Per-chunk choice of the smallest of zlib and LZFSE
+    ///
+    /// Chunks that neither method shrinks are stored raw. Each block run
+    /// records the method actually used.
+    Auto,
 }
 
-impl CompressionMethod {
-    fn block_type(&self) -> BlockType {
-        match self {
-            CompressionMethod::Raw => BlockType::Raw,
-            CompressionMethod::Zlib => BlockType::Zlib,
-            CompressionMethod::Bzip2 => BlockType::Bzip2,
-            CompressionMethod::Lzfse => BlockType::Lzfse,
-        }
-    }
+/// Summary of an image produced by [`DmgWriter::finish_with_stats`]
+///
+/// The sizes are those [`DmgReader::stats`](crate::DmgReader::stats)
+/// reports for the finished image.
+#[derive(Debug, Clone, Default)]
+pub struct WriteStats {
+    /// Number of partitions written
+    pub partition_count: usize,
+    /// Length of the data fork (compressed block data)
+    pub data_fork_length: u64,
+    /// Total compressed size of all block runs in bytes
+    pub total_compressed: u64,
+    /// Total uncompressed size of all partitions in bytes
+    pub total_uncompressed: u64,
+    /// Number of block runs per compression method
+    pub method_counts: CompressionInfo,
 }
 
 /// Builder for creating DMG files
@@ -58,6 +111,14 @@ pub struct DmgWriter<W> {
     data_fork_hasher: crc32fast::Hasher,
     /// Skip checksum generation for faster DMG creation
     skip_checksums: bool,
+    /// Free-form comment stored in the plist
+    comment: Option<String>,
+    /// Offset of this image's data fork within a concatenated data fork
+    data_fork_base_offset: u64,
+    /// Size in bytes of the sectors block runs are counted in
+    sector_size: u64,
+    /// Content-defined chunking bounds; fixed-size chunks if `None`
+    cdc: Option<(usize, usize, usize)>,
 }
 
 struct PartitionData {
@@ -70,6 +131,34 @@ struct PartitionData {
     checksum: [u8; 128],
 }
 
+/// End offset of the content-defined chunk starting at `start`
+///
+/// Only sector-aligned offsets are candidates, since every block run must
+/// cover whole sectors. The hash at a candidate depends only on the
+/// `GEAR_WINDOW` bytes before it, so boundaries move with the content.
+fn cdc_chunk_end(params: &CdcParams, data: &[u8], start: usize, sector_size: usize) -> usize {
+    let limit = (start + params.max).min(data.len());
+    let first = start + params.min;
+    if first >= limit {
+        return limit;
+    }
+
+    let mut hash = 0u64;
+    for (pos, &byte) in data
+        .iter()
+        .enumerate()
+        .take(limit)
+        .skip(first.saturating_sub(GEAR_WINDOW))
+    {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let end = pos + 1;
+        if end >= first && end.is_multiple_of(sector_size) && hash & params.mask == 0 {
+            return end;
+        }
+    }
+    limit
+}
+
 impl<W: Write + Seek> DmgWriter<W> {
     /// Create a new DMG writer
     pub fn new(writer: W) -> Self {
@@ -82,6 +171,10 @@ impl<W: Write + Seek> DmgWriter<W> {
             current_offset: 0,
             data_fork_hasher: crc32fast::Hasher::new(),
             skip_checksums: false,
+            comment: None,
+            data_fork_base_offset: 0,
+            sector_size: DEFAULT_SECTOR_SIZE,
+            cdc: None,
         }
     }
 
@@ -91,7 +184,7 @@ impl<W: Write + Seek> DmgWriter<W> {
         self
     }
 
-    /// Set compression level (0-9, only applies to zlib/bzip2)
+    /// Set compression level (0-9, only applies to zlib/bzip2/LZMA)
     pub fn compression_level(mut self, level: u32) -> Self {
         self.compression_level = level.min(9);
         self
@@ -109,9 +202,48 @@ impl<W: Write + Seek> DmgWriter<W> {
         self
     }
 
+    /// Cut chunks at content-defined boundaries instead of every `chunk_size` bytes
+    ///
+    /// A gear rolling hash over the data picks sector-aligned cut points, so
+    /// chunks are between `min` and `max` bytes and `avg` on average. An
+    /// insertion or deletion of whole sectors only changes the chunks around
+    /// the edit; later chunks keep their boundaries and compress to the same
+    /// bytes, which lets block-level dedup work across image versions.
+    pub fn content_defined_chunking(mut self, min: usize, avg: usize, max: usize) -> Self {
+        self.cdc = Some((min, avg, max));
+        self
+    }
+
+    /// Set the sector size block runs are counted in (default 512)
+    ///
+    /// Images written with a non-default size must be read back with the
+    /// same `DmgReaderOptions::sector_size`.
+    pub fn sector_size(mut self, size: u64) -> Self {
+        self.sector_size = size.max(1);
+        self
+    }
+
+    /// Set the offset of this image's data fork within a larger, concatenated data fork
+    ///
+    /// Written as the koly `running_data_fork_offset`; block-run offsets are
+    /// shifted by the same amount so they are relative to the combined data fork.
+    pub fn data_fork_base_offset(mut self, offset: u64) -> Self {
+        self.data_fork_base_offset = offset;
+        self
+    }
+
+    /// Embed a comment (e.g. a creator string) in the plist
+    ///
+    /// Stored under a top-level `comment` key and read back by
+    /// [`DmgReader::comment`](crate::DmgReader::comment).
+    pub fn comment(mut self, text: &str) -> Self {
+        self.comment = Some(text.to_string());
+        self
+    }
+
     /// Add raw disk data as a partition
     pub fn add_partition(&mut self, name: &str, data: &[u8]) -> Result<()> {
-        let sector_count = (data.len() as u64 + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        let sector_count = (data.len() as u64).div_ceil(self.sector_size);
         let first_sector = self.partitions.iter().map(|p| p.first_sector + p.sector_count).max().unwrap_or(0);
 
         let mut block_runs = Vec::new();
@@ -122,17 +254,21 @@ impl<W: Write + Seek> DmgWriter<W> {
         let partition_checksum = if self.skip_checksums {
             [0u8; 128]
         } else {
-            let padded_size = (sector_count * SECTOR_SIZE) as usize;
+            let padded_size = (sector_count * self.sector_size) as usize;
             let mut padded_data = data.to_vec();
             padded_data.resize(padded_size, 0);
             create_checksum_array(crc32(&padded_data))
         };
 
         // Process data in chunks
+        let cdc = self.cdc_params();
         while data_offset < data.len() {
-            let chunk_end = (data_offset + self.chunk_size).min(data.len());
+            let chunk_end = match cdc {
+                Some(params) => cdc_chunk_end(&params, data, data_offset, self.sector_size as usize),
+                None => (data_offset + self.chunk_size).min(data.len()),
+            };
             let chunk = &data[data_offset..chunk_end];
-            let chunk_sectors = ((chunk.len() as u64 + SECTOR_SIZE - 1) / SECTOR_SIZE).max(1);
+            let chunk_sectors = (chunk.len() as u64).div_ceil(self.sector_size).max(1);
 
             // Check if chunk is all zeros
             if chunk.iter().all(|&b| b == 0) {
@@ -146,8 +282,8 @@ impl<W: Write + Seek> DmgWriter<W> {
                 });
             } else {
                 // Compress the chunk
-                let compressed = self.compress_chunk(chunk)?;
-                let compressed_offset = self.current_offset;
+                let (block_type, compressed) = self.compress_chunk(self.compression, chunk)?;
+                let compressed_offset = self.data_fork_base_offset + self.current_offset;
                 let compressed_length = compressed.len() as u64;
 
                 // Write compressed data and update data fork checksum
@@ -156,7 +292,7 @@ impl<W: Write + Seek> DmgWriter<W> {
                 self.current_offset += compressed_length;
 
                 block_runs.push(BlockRun {
-                    block_type: self.compression.block_type(),
+                    block_type,
                     comment: 0,
                     sector_number,
                     sector_count: chunk_sectors,
@@ -193,14 +329,71 @@ impl<W: Write + Seek> DmgWriter<W> {
         Ok(())
     }
 
-    /// Compress a chunk of data
-    fn compress_chunk(&self, data: &[u8]) -> Result<Vec<u8>> {
-        match self.compression {
-            CompressionMethod::Raw => Ok(data.to_vec()),
+    /// Estimate the final image size if `partitions` were added and the image finished
+    ///
+    /// Includes data already written by this writer. Up to
+    /// `ESTIMATE_SAMPLE_CHUNKS` non-zero chunks per partition are compressed
+    /// with the configured method and the resulting ratio is applied to the
+    /// rest; all-zero chunks are free. Intended for progress reporting and
+    /// pre-allocation, not as an exact figure.
+    pub fn estimate_output_size(&self, partitions: &[&[u8]]) -> u64 {
+        let mut total = self.current_offset + PLIST_BASE_OVERHEAD + KOLY_SIZE as u64;
+        let mut block_runs = self.partitions.iter().map(|p| p.block_runs.len() as u64).sum::<u64>();
+        total += self.partitions.len() as u64 * PLIST_PARTITION_OVERHEAD;
+
+        for data in partitions {
+            let chunks: Vec<&[u8]> = data
+                .chunks(self.chunk_size)
+                .filter(|chunk| chunk.iter().any(|&b| b != 0))
+                .collect();
+            let data_bytes: u64 = chunks.iter().map(|c| c.len() as u64).sum();
+
+            let step = chunks.len().div_ceil(ESTIMATE_SAMPLE_CHUNKS).max(1);
+            let (mut sampled, mut compressed) = (0u64, 0u64);
+            for chunk in chunks.iter().step_by(step) {
+                sampled += chunk.len() as u64;
+                compressed += self
+                    .compress_chunk(self.compression, chunk)
+                    .map_or(chunk.len(), |(_, out)| out.len()) as u64;
+            }
+
+            if sampled > 0 {
+                total += (data_bytes as f64 * compressed as f64 / sampled as f64).ceil() as u64;
+            }
+            block_runs += (data.len() as u64).div_ceil(self.chunk_size as u64) + 1;
+            total += PLIST_PARTITION_OVERHEAD;
+        }
+
+        // Each 40-byte block run grows by a third when base64-encoded
+        total + block_runs * 40 * 4 / 3
+    }
+
+    /// Content-defined chunking bounds rounded to whole sectors
+    fn cdc_params(&self) -> Option<CdcParams> {
+        let (min, avg, max) = self.cdc?;
+        let sector = self.sector_size as usize;
+        let round = |n: usize| n.div_ceil(sector).max(1) * sector;
+        let min = round(min);
+        let avg = round(avg).max(min);
+        let max = round(max).max(avg);
+
+        // One cut-point candidate per sector past `min`; a hit rate of
+        // 1 / (avg - min) sectors gives the requested average
+        let candidates = ((avg - min) / sector).max(1) as u64;
+        let bits = candidates.next_power_of_two().trailing_zeros();
+        let mask = if bits == 0 { 0 } else { !(u64::MAX >> bits) };
+        Some(CdcParams { min, max, mask })
+    }
+
+    /// Compress a chunk of data, returning the block type it was stored as
+    fn compress_chunk(&self, method: CompressionMethod, data: &[u8]) -> Result<(BlockType, Vec<u8>)> {
+        match method {
+            CompressionMethod::Raw => Ok((BlockType::Raw, data.to_vec())),
             CompressionMethod::Zlib => {
                 let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.compression_level));
                 encoder.write_all(data)?;
-                encoder.finish().map_err(|e| DppError::Compression(e.to_string()))
+                let compressed = encoder.finish().map_err(|e| DppError::Compression(e.to_string()))?;
+                Ok((BlockType::Zlib, compressed))
             }
             CompressionMethod::Bzip2 => {
                 let mut encoder = bzip2::write::BzEncoder::new(
@@ -208,7 +401,8 @@ impl<W: Write + Seek> DmgWriter<W> {
                     bzip2::Compression::new(self.compression_level),
                 );
                 encoder.write_all(data)?;
-                encoder.finish().map_err(|e| DppError::Compression(e.to_string()))
+                let compressed = encoder.finish().map_err(|e| DppError::Compression(e.to_string()))?;
+                Ok((BlockType::Bzip2, compressed))
             }
             CompressionMethod::Lzfse => {
                 // Allocate output buffer with some extra space for overhead
@@ -216,13 +410,34 @@ impl<W: Write + Seek> DmgWriter<W> {
                 let compressed_size = lzfse::encode_buffer(data, &mut output)
                     .map_err(|e| DppError::Compression(format!("LZFSE: {:?}", e)))?;
                 output.truncate(compressed_size);
-                Ok(output)
+                Ok((BlockType::Lzfse, output))
+            }
+            CompressionMethod::Lzma => {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), self.compression_level);
+                encoder.write_all(data)?;
+                let compressed = encoder.finish().map_err(|e| DppError::Compression(e.to_string()))?;
+                Ok((BlockType::Xz, compressed))
+            }
+            CompressionMethod::Auto => {
+                let mut best = (BlockType::Raw, data.to_vec());
+                for candidate in [CompressionMethod::Zlib, CompressionMethod::Lzfse] {
+                    let compressed = self.compress_chunk(candidate, data)?;
+                    if compressed.1.len() < best.1.len() {
+                        best = compressed;
+                    }
+                }
+                Ok(best)
             }
         }
     }
 
     /// Finalize and write the DMG file
-    pub fn finish(mut self) -> Result<()> {
+    pub fn finish(self) -> Result<()> {
+        self.finish_with_stats().map(|_| ())
+    }
+
+    /// Finalize and write the DMG file, returning what was written
+    pub fn finish_with_stats(mut self) -> Result<WriteStats> {
         let data_fork_length = self.current_offset;
         let plist_offset = self.current_offset;
 
@@ -258,7 +473,7 @@ impl<W: Write + Seek> DmgWriter<W> {
             version: 4,
             header_size: KOLY_SIZE as u32,
             flags: 1,
-            running_data_fork_offset: 0,
+            running_data_fork_offset: self.data_fork_base_offset,
             data_fork_offset: 0,
             data_fork_length,
             rsrc_fork_offset: 0,
@@ -282,7 +497,22 @@ impl<W: Write + Seek> DmgWriter<W> {
         koly.write(&mut self.writer)?;
         self.writer.flush()?;
 
-        Ok(())
+        let mut method_counts = CompressionInfo::default();
+        for block_run in self.partitions.iter().flat_map(|p| &p.block_runs) {
+            method_counts.count(block_run.block_type);
+        }
+        Ok(WriteStats {
+            partition_count: self.partitions.len(),
+            data_fork_length,
+            total_compressed: self
+                .partitions
+                .iter()
+                .flat_map(|p| &p.block_runs)
+                .map(|r| r.compressed_length)
+                .sum(),
+            total_uncompressed: self.partitions.iter().map(|p| p.sector_count).sum::<u64>() * self.sector_size,
+            method_counts,
+        })
     }
 
     /// Generate the XML plist for the DMG
@@ -323,6 +553,9 @@ impl<W: Write + Seek> DmgWriter<W> {
 
         plist.push_str("\t\t</array>\n");
         plist.push_str("\t</dict>\n");
+        if let Some(comment) = &self.comment {
+            plist.push_str(&format!("\t<key>comment</key>\n\t<string>{}</string>\n", xml_escape(comment)));
+        }
         plist.push_str("</dict>\n");
         plist.push_str("</plist>\n");
 
@@ -362,6 +595,20 @@ impl<W: Write + Seek> DmgWriter<W> {
     }
 }
 
+/// Escape text for use inside an XML element
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
 impl DmgWriter<BufWriter<File>> {
     /// Create a new DMG file
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
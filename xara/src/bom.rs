@@ -0,0 +1,381 @@
+//! Bill of Materials (`Bom`) parser.
+//!
+//! Every component package carries a `Bom` listing the paths its payload
+//! installs, with their type, mode, owner, size and checksum. The file is a
+//! "BOMStore": a block table plus named variables, one of which (`Paths`)
+//! roots a B-tree of path records.
+
+use std::collections::HashMap;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::{Result, XarError};
+
+/// BOMStore magic: "BOMStore"
+pub const BOM_MAGIC: &[u8; 8] = b"BOMStore";
+
+/// Size of the fixed BOMStore header
+pub const BOM_HEADER_SIZE: usize = 32;
+
+/// Upper bound on tree nodes and parent links followed, to stop cycles
+const MAX_LINKS: usize = 1 << 20;
+
+/// Kind of a BOM path record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BomEntryKind {
+    File,
+    Directory,
+    Symlink,
+    Device,
+    Unknown(u8),
+}
+
+impl From<u8> for BomEntryKind {
+    fn from(v: u8) -> Self {
+        match v {
+            1 => BomEntryKind::File,
+            2 => BomEntryKind::Directory,
+            3 => BomEntryKind::Symlink,
+            4 => BomEntryKind::Device,
+            other => BomEntryKind::Unknown(other),
+        }
+    }
+}
+
+/// One path listed in a BOM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BomEntry {
+    /// Path as it appears in the payload (e.g. `./Applications/Foo.app`)
+    pub path: String,
+    pub kind: BomEntryKind,
+    /// Mode including the file type bits
+    pub mode: u16,
+    pub uid: u32,
+    pub gid: u32,
+    pub mtime: u32,
+    /// Size in bytes (low 32 bits for files of 4 GiB and up)
+    pub size: u32,
+    /// CRC of the file contents (device number for devices)
+    pub checksum: u32,
+    /// Target of a symlink
+    pub link_target: Option<String>,
+}
+
+/// Parsed BOM: every path record, in tree order
+#[derive(Debug, Clone)]
+pub struct Bom {
+    entries: Vec<BomEntry>,
+}
+
+impl Bom {
+    /// Parse a BOMStore from its bytes
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let store = BomStore::parse(data)?;
+        let tree_index = store
+            .variable("Paths")
+            .ok_or_else(|| XarError::InvalidBom("no Paths variable".into()))?;
+
+        // BOMTree: "tree", version, root node, block size, path count, unknown
+        let tree = store.block(tree_index, 21)?;
+        if &tree[0..4] != b"tree" {
+            return Err(XarError::InvalidBom("Paths does not point at a tree".into()));
+        }
+        let mut node_index = BigEndian::read_u32(&tree[8..12]);
+
+        // Descend to the leftmost leaf, then follow the leaf chain
+        let mut records = Vec::new();
+        let mut visited = 0usize;
+        loop {
+            visited += 1;
+            if visited > MAX_LINKS {
+                return Err(XarError::InvalidBom("path tree does not terminate".into()));
+            }
+            let node = store.block(node_index, 12)?;
+            let is_leaf = BigEndian::read_u16(&node[0..2]) != 0;
+            let count = BigEndian::read_u16(&node[2..4]) as usize;
+            let forward = BigEndian::read_u32(&node[4..8]);
+            let pairs = node.get(12..12 + count * 8).ok_or_else(|| {
+                XarError::InvalidBom(format!("node {} too short for {} records", node_index, count))
+            })?;
+
+            if !is_leaf {
+                if count == 0 {
+                    return Err(XarError::InvalidBom(format!("empty branch node {}", node_index)));
+                }
+                node_index = BigEndian::read_u32(&pairs[0..4]);
+                continue;
+            }
+            for pair in pairs.chunks_exact(8) {
+                records.push((BigEndian::read_u32(&pair[0..4]), BigEndian::read_u32(&pair[4..8])));
+            }
+            if forward == 0 {
+                break;
+            }
+            node_index = forward;
+        }
+
+        // Each record: path info (id + attributes block) and file (parent id + name)
+        let mut names: HashMap<u32, (u32, String)> = HashMap::new();
+        let mut parsed = Vec::with_capacity(records.len());
+        for (info_index, file_index) in records {
+            let info = store.block(info_index, 8)?;
+            let id = BigEndian::read_u32(&info[0..4]);
+            let attrs = store.block(BigEndian::read_u32(&info[4..8]), 31)?;
+
+            let file = store.block(file_index, 4)?;
+            let parent = BigEndian::read_u32(&file[0..4]);
+            let name_bytes = &file[4..];
+            let name_end = name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len());
+            let name = String::from_utf8_lossy(&name_bytes[..name_end]).into_owned();
+            names.insert(id, (parent, name));
+
+            let kind = BomEntryKind::from(attrs[0]);
+            let link_target = if kind == BomEntryKind::Symlink {
+                let len = BigEndian::read_u32(&attrs[27..31]) as usize;
+                let target = attrs.get(31..31 + len).ok_or_else(|| {
+                    XarError::InvalidBom(format!("symlink record {} truncated", id))
+                })?;
+                let end = target.iter().position(|&b| b == 0).unwrap_or(target.len());
+                Some(String::from_utf8_lossy(&target[..end]).into_owned())
+            } else {
+                None
+            };
+            parsed.push((
+                id,
+                BomEntry {
+                    path: String::new(),
+                    kind,
+                    mode: BigEndian::read_u16(&attrs[4..6]),
+                    uid: BigEndian::read_u32(&attrs[6..10]),
+                    gid: BigEndian::read_u32(&attrs[10..14]),
+                    mtime: BigEndian::read_u32(&attrs[14..18]),
+                    size: BigEndian::read_u32(&attrs[18..22]),
+                    checksum: BigEndian::read_u32(&attrs[23..27]),
+                    link_target,
+                },
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(parsed.len());
+        for (id, mut entry) in parsed {
+            entry.path = resolve_path(&names, id)?;
+            entries.push(entry);
+        }
+        Ok(Bom { entries })
+    }
+
+    /// Path records in tree order
+    pub fn entries(&self) -> &[BomEntry] {
+        &self.entries
+    }
+
+    /// Look up a path record
+    pub fn find(&self, path: &str) -> Option<&BomEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+}
+
+/// Join names up the parent chain of `id`; parent 0 is the root
+fn resolve_path(names: &HashMap<u32, (u32, String)>, id: u32) -> Result<String> {
+    let mut components = Vec::new();
+    let mut current = id;
+    while current != 0 {
+        if components.len() > MAX_LINKS {
+            return Err(XarError::InvalidBom(format!("parent chain of path {} loops", id)));
+        }
+        let (parent, name) = names
+            .get(&current)
+            .ok_or_else(|| XarError::InvalidBom(format!("unknown parent id {}", current)))?;
+        components.push(name.as_str());
+        current = *parent;
+    }
+    components.reverse();
+    Ok(components.join("/"))
+}
+
+/// BOMStore block table and variables
+struct BomStore<'a> {
+    data: &'a [u8],
+    /// (offset, length) of each block; index 0 is the null block
+    blocks: Vec<(u32, u32)>,
+    variables: Vec<(String, u32)>,
+}
+
+impl<'a> BomStore<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < BOM_HEADER_SIZE || &data[0..8] != BOM_MAGIC {
+            return Err(XarError::InvalidBom("missing BOMStore magic".into()));
+        }
+        let index_offset = BigEndian::read_u32(&data[16..20]) as usize;
+        let vars_offset = BigEndian::read_u32(&data[24..28]) as usize;
+
+        let slice = |offset: usize, len: usize, what: &str| {
+            data.get(offset..offset.saturating_add(len))
+                .ok_or_else(|| XarError::InvalidBom(format!("{} at {} out of bounds", what, offset)))
+        };
+
+        let count = BigEndian::read_u32(slice(index_offset, 4, "block table")?) as usize;
+        let table = slice(index_offset + 4, count.saturating_mul(8), "block table")?;
+        let blocks = table
+            .chunks_exact(8)
+            .map(|e| (BigEndian::read_u32(&e[0..4]), BigEndian::read_u32(&e[4..8])))
+            .collect();
+
+        let var_count = BigEndian::read_u32(slice(vars_offset, 4, "variables")?) as usize;
+        let mut variables = Vec::with_capacity(var_count.min(64));
+        let mut pos = vars_offset + 4;
+        for _ in 0..var_count {
+            let head = slice(pos, 5, "variable")?;
+            let index = BigEndian::read_u32(&head[0..4]);
+            let len = head[4] as usize;
+            let name = slice(pos + 5, len, "variable name")?;
+            variables.push((String::from_utf8_lossy(name).into_owned(), index));
+            pos += 5 + len;
+        }
+
+        Ok(BomStore { data, blocks, variables })
+    }
+
+    fn variable(&self, name: &str) -> Option<u32> {
+        self.variables.iter().find(|(n, _)| n == name).map(|&(_, i)| i)
+    }
+
+    /// Block `index`, which must be at least `min_len` bytes
+    fn block(&self, index: u32, min_len: usize) -> Result<&'a [u8]> {
+        let &(offset, len) = self
+            .blocks
+            .get(index as usize)
+            .filter(|_| index != 0)
+            .ok_or_else(|| XarError::InvalidBom(format!("invalid block index {}", index)))?;
+        let block = self
+            .data
+            .get(offset as usize..offset as usize + len as usize)
+            .ok_or_else(|| XarError::InvalidBom(format!("block {} out of bounds", index)))?;
+        if block.len() < min_len {
+            return Err(XarError::InvalidBom(format!(
+                "block {} is {} bytes, expected at least {}",
+                index,
+                block.len(),
+                min_len
+            )));
+        }
+        Ok(block)
+    }
+}
+
+/// `(id, parent, name, kind, mode, size, link target)` for [`build_bom`]
+#[cfg(test)]
+pub(crate) type TestRecord<'a> = (u32, u32, &'a str, u8, u16, u32, Option<&'a str>);
+
+/// Build a BOMStore from path records, all in one leaf node.
+#[cfg(test)]
+pub(crate) fn build_bom(records: &[TestRecord]) -> Vec<u8> {
+    let mut blocks: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut push = |block: Vec<u8>| {
+        blocks.push(block);
+        blocks.len() as u32 - 1
+    };
+
+    let mut pairs = Vec::new();
+    for &(id, parent, name, kind, mode, size, link) in records {
+        let mut attrs = vec![kind, 1];
+        attrs.extend_from_slice(&0x3u16.to_be_bytes()); // architecture
+        attrs.extend_from_slice(&mode.to_be_bytes());
+        attrs.extend_from_slice(&0u32.to_be_bytes()); // uid
+        attrs.extend_from_slice(&80u32.to_be_bytes()); // gid
+        attrs.extend_from_slice(&1_700_000_000u32.to_be_bytes());
+        attrs.extend_from_slice(&size.to_be_bytes());
+        attrs.push(1);
+        attrs.extend_from_slice(&0xDEADBEEFu32.to_be_bytes()); // checksum
+        let link = link.map(|l| format!("{}\0", l)).unwrap_or_default();
+        attrs.extend_from_slice(&(link.len() as u32).to_be_bytes());
+        attrs.extend_from_slice(link.as_bytes());
+        let attrs_index = push(attrs);
+
+        let mut info = id.to_be_bytes().to_vec();
+        info.extend_from_slice(&attrs_index.to_be_bytes());
+        let info_index = push(info);
+
+        let mut file = parent.to_be_bytes().to_vec();
+        file.extend_from_slice(name.as_bytes());
+        file.push(0);
+        pairs.push((info_index, push(file)));
+    }
+
+    let mut leaf = Vec::new();
+    leaf.extend_from_slice(&1u16.to_be_bytes());
+    leaf.extend_from_slice(&(pairs.len() as u16).to_be_bytes());
+    leaf.extend_from_slice(&[0; 8]); // forward, backward
+    for (info, file) in &pairs {
+        leaf.extend_from_slice(&info.to_be_bytes());
+        leaf.extend_from_slice(&file.to_be_bytes());
+    }
+    let leaf_index = push(leaf);
+
+    let mut tree = b"tree".to_vec();
+    tree.extend_from_slice(&1u32.to_be_bytes());
+    tree.extend_from_slice(&leaf_index.to_be_bytes());
+    tree.extend_from_slice(&4096u32.to_be_bytes());
+    tree.extend_from_slice(&(pairs.len() as u32).to_be_bytes());
+    tree.push(0);
+    let tree_index = push(tree);
+
+    let mut data = vec![0u8; BOM_HEADER_SIZE];
+    let mut table = (blocks.len() as u32).to_be_bytes().to_vec();
+    for block in &blocks {
+        let offset = if block.is_empty() { 0 } else { data.len() as u32 };
+        table.extend_from_slice(&offset.to_be_bytes());
+        table.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        data.extend_from_slice(block);
+    }
+    let index_offset = data.len() as u32;
+    data.extend_from_slice(&table);
+
+    let vars_offset = data.len() as u32;
+    data.extend_from_slice(&1u32.to_be_bytes());
+    data.extend_from_slice(&tree_index.to_be_bytes());
+    data.push(5);
+    data.extend_from_slice(b"Paths");
+
+    let vars_len = data.len() as u32 - vars_offset;
+    data[0..8].copy_from_slice(BOM_MAGIC);
+    data[8..12].copy_from_slice(&1u32.to_be_bytes());
+    data[12..16].copy_from_slice(&(blocks.len() as u32).to_be_bytes());
+    data[16..20].copy_from_slice(&index_offset.to_be_bytes());
+    data[20..24].copy_from_slice(&(table.len() as u32).to_be_bytes());
+    data[24..28].copy_from_slice(&vars_offset.to_be_bytes());
+    data[28..32].copy_from_slice(&vars_len.to_be_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bom() {
+        let data = build_bom(&[
+            (1, 0, ".", 2, 0o40755, 0, None),
+            (2, 1, "Applications", 2, 0o40775, 0, None),
+            (3, 2, "Tool", 1, 0o100755, 1234, None),
+            (4, 2, "tool-link", 3, 0o120755, 4, Some("Tool")),
+        ]);
+        let bom = Bom::parse(&data).unwrap();
+        let paths: Vec<_> = bom.entries().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, [".", "./Applications", "./Applications/Tool", "./Applications/tool-link"]);
+
+        let tool = bom.find("./Applications/Tool").unwrap();
+        assert_eq!(tool.kind, BomEntryKind::File);
+        assert_eq!((tool.mode, tool.size, tool.gid), (0o100755, 1234, 80));
+        assert_eq!(tool.checksum, 0xDEADBEEF);
+
+        let link = bom.find("./Applications/tool-link").unwrap();
+        assert_eq!(link.kind, BomEntryKind::Symlink);
+        assert_eq!(link.link_target.as_deref(), Some("Tool"));
+
+        assert!(matches!(Bom::parse(b"not a bom"), Err(XarError::InvalidBom(_))));
+        let mut corrupt = data.clone();
+        corrupt[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(Bom::parse(&corrupt), Err(XarError::InvalidBom(_))));
+    }
+}
@@ -1,7 +1,9 @@
+use alloc::string::String;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum XarError {
+    #[cfg(feature = "std")]
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -22,6 +24,20 @@ pub enum XarError {
 
     #[error("decompression failed: {0}")]
     DecompressionFailed(String),
+
+    #[error("invalid BOM: {0}")]
+    InvalidBom(String),
+
+    #[cfg(feature = "bom-verify")]
+    #[error("payload error: {0}")]
+    Payload(#[from] pbzx::PbzxError),
+
+    #[error("truncated archive: {name} needs {needed} bytes, only {available} available")]
+    Truncated {
+        name: String,
+        needed: u64,
+        available: u64,
+    },
 }
 
-pub type Result<T> = std::result::Result<T, XarError>;
+pub type Result<T> = core::result::Result<T, XarError>;
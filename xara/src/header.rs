@@ -1,4 +1,5 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder};
+#[cfg(feature = "std")]
 use std::io::Read;
 
 use crate::error::{XarError, Result};
@@ -6,6 +7,9 @@ use crate::error::{XarError, Result};
 /// XAR magic number: "xar!" (0x78617221)
 pub const XAR_MAGIC: u32 = 0x78617221;
 
+/// Size of the fixed XAR header fields
+pub const XAR_HEADER_SIZE: usize = 28;
+
 /// Checksum algorithms
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChecksumAlgo {
@@ -39,32 +43,127 @@ pub struct XarHeader {
     pub checksum_algo: ChecksumAlgo,
 }
 
-/// Parse the XAR header from a reader
-pub fn parse_header<R: Read>(reader: &mut R) -> Result<XarHeader> {
-    let magic = reader.read_u32::<BigEndian>()?;
-    if magic != XAR_MAGIC {
-        return Err(XarError::InvalidMagic(magic));
+impl XarHeader {
+    /// Parse the fixed 28-byte XAR header from a byte slice.
+    ///
+    /// Any extra bytes declared by `header_size` are not consumed; the TOC
+    /// starts at `header_size`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < XAR_HEADER_SIZE {
+            return Err(XarError::InvalidToc(alloc::format!(
+                "header too short: {} bytes",
+                data.len()
+            )));
+        }
+
+        let magic = BigEndian::read_u32(&data[0..4]);
+        if magic != XAR_MAGIC {
+            return Err(XarError::InvalidMagic(magic));
+        }
+
+        Ok(XarHeader {
+            magic,
+            header_size: BigEndian::read_u16(&data[4..6]),
+            version: BigEndian::read_u16(&data[6..8]),
+            toc_compressed_len: BigEndian::read_u64(&data[8..16]),
+            toc_uncompressed_len: BigEndian::read_u64(&data[16..24]),
+            checksum_algo: ChecksumAlgo::from(BigEndian::read_u32(&data[24..28])),
+        })
     }
 
-    let header_size = reader.read_u16::<BigEndian>()?;
-    let version = reader.read_u16::<BigEndian>()?;
-    let toc_compressed_len = reader.read_u64::<BigEndian>()?;
-    let toc_uncompressed_len = reader.read_u64::<BigEndian>()?;
-    let checksum_algo = ChecksumAlgo::from(reader.read_u32::<BigEndian>()?);
+    /// Fuzzing entry point: parse the header at the start of `data` and
+    /// check that the compressed TOC it declares fits in `data`.
+    ///
+    /// Returns the header and the compressed TOC bytes. Never panics.
+    #[cfg(feature = "fuzzing")]
+    pub fn try_parse(data: &[u8]) -> Result<(Self, &[u8])> {
+        let header = Self::from_bytes(data)?;
+        let toc_start = (header.header_size as usize).max(XAR_HEADER_SIZE) as u64;
+        let toc_end = toc_start.saturating_add(header.toc_compressed_len);
+        if toc_end > data.len() as u64 {
+            return Err(XarError::Truncated {
+                name: "toc".into(),
+                needed: toc_end,
+                available: data.len() as u64,
+            });
+        }
+        Ok((header, &data[toc_start as usize..toc_end as usize]))
+    }
+}
+
+/// Parse the XAR header from a reader
+#[cfg(feature = "std")]
+pub fn parse_header<R: Read>(reader: &mut R) -> Result<XarHeader> {
+    let mut buf = [0u8; XAR_HEADER_SIZE];
+    reader.read_exact(&mut buf)?;
+    let header = XarHeader::from_bytes(&buf)?;
 
     // Skip any extra header bytes beyond the 28 we read
-    if header_size > 28 {
-        let extra = header_size as usize - 28;
+    if header.header_size as usize > XAR_HEADER_SIZE {
+        let extra = header.header_size as usize - XAR_HEADER_SIZE;
         let mut skip_buf = vec![0u8; extra];
         reader.read_exact(&mut skip_buf)?;
     }
 
-    Ok(XarHeader {
-        magic,
-        header_size,
-        version,
-        toc_compressed_len,
-        toc_uncompressed_len,
-        checksum_algo,
-    })
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_bytes() -> [u8; XAR_HEADER_SIZE] {
+        let mut data = [0u8; XAR_HEADER_SIZE];
+        data[0..4].copy_from_slice(&XAR_MAGIC.to_be_bytes());
+        data[4..6].copy_from_slice(&28u16.to_be_bytes());
+        data[6..8].copy_from_slice(&1u16.to_be_bytes());
+        data[8..16].copy_from_slice(&100u64.to_be_bytes());
+        data[16..24].copy_from_slice(&400u64.to_be_bytes());
+        data[24..28].copy_from_slice(&1u32.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_header_from_bytes() {
+        let header = XarHeader::from_bytes(&header_bytes()).unwrap();
+        assert_eq!(header.header_size, 28);
+        assert_eq!(header.version, 1);
+        assert_eq!(header.toc_compressed_len, 100);
+        assert_eq!(header.toc_uncompressed_len, 400);
+        assert_eq!(header.checksum_algo, ChecksumAlgo::Sha1);
+    }
+
+    #[test]
+    fn test_header_from_bytes_rejects_bad_input() {
+        let data = header_bytes();
+        assert!(matches!(XarHeader::from_bytes(&data[..10]), Err(XarError::InvalidToc(_))));
+        for len in 0..XAR_HEADER_SIZE {
+            assert!(XarHeader::from_bytes(&data[..len]).is_err(), "{}", len);
+        }
+
+        let mut bad = data;
+        bad[0] = 0;
+        assert!(matches!(XarHeader::from_bytes(&bad), Err(XarError::InvalidMagic(_))));
+    }
+
+    #[cfg(feature = "fuzzing")]
+    #[test]
+    fn test_try_parse() {
+        let mut data = header_bytes().to_vec();
+        data.resize(XAR_HEADER_SIZE + 100, 0xaa);
+        let (header, toc) = XarHeader::try_parse(&data).unwrap();
+        assert_eq!(header.toc_compressed_len, 100);
+        assert_eq!(toc.len(), 100);
+        for len in 0..data.len() {
+            assert!(XarHeader::try_parse(&data[..len]).is_err(), "{}", len);
+        }
+
+        assert!(matches!(
+            XarHeader::try_parse(&data[..60]),
+            Err(XarError::Truncated { needed: 128, available: 60, .. })
+        ));
+        // A TOC length near u64::MAX must not wrap
+        data[8..16].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(matches!(XarHeader::try_parse(&data), Err(XarError::Truncated { .. })));
+    }
 }
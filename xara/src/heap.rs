@@ -4,6 +4,38 @@ use std::io::{Read, Seek, SeekFrom, Write};
 use crate::error::{XarError, Result};
 use crate::toc::XarFile;
 
+/// Read a file entry's archived bytes from the heap, without decoding them.
+/// Returns an empty buffer for entries with no data.
+pub fn read_raw_entry<R: Read + Seek>(
+    reader: &mut R,
+    heap_offset: u64,
+    file: &XarFile,
+) -> Result<Vec<u8>> {
+    let data = match &file.data {
+        Some(d) => d,
+        None => return Ok(Vec::new()), // Directory or entry with no data
+    };
+
+    // Make sure the entry lies within the archive before reading it
+    let abs_offset = heap_offset.saturating_add(data.offset);
+    let needed = abs_offset.saturating_add(data.length);
+    let available = reader.seek(SeekFrom::End(0))?;
+    if needed > available {
+        return Err(XarError::Truncated {
+            name: file.path.clone(),
+            needed,
+            available,
+        });
+    }
+
+    // Seek to the entry in the heap
+    reader.seek(SeekFrom::Start(abs_offset))?;
+
+    let mut archived = vec![0u8; data.length as usize];
+    reader.read_exact(&mut archived)?;
+    Ok(archived)
+}
+
 /// Read a file entry's data from the heap.
 /// Returns number of bytes written to the writer.
 pub fn read_entry<R: Read + Seek, W: Write>(
@@ -17,13 +49,8 @@ pub fn read_entry<R: Read + Seek, W: Write>(
         None => return Ok(0), // Directory or entry with no data
     };
 
-    // Seek to the entry in the heap
-    let abs_offset = heap_offset + data.offset;
-    reader.seek(SeekFrom::Start(abs_offset))?;
-
     // Read the compressed data
-    let mut compressed = vec![0u8; data.length as usize];
-    reader.read_exact(&mut compressed)?;
+    let compressed = read_raw_entry(reader, heap_offset, file)?;
 
     // Decompress based on encoding
     match data.encoding.as_str() {
@@ -33,13 +60,8 @@ pub fn read_entry<R: Read + Seek, W: Write>(
             Ok(data.length)
         }
         "application/x-gzip" => {
-            let mut decoder = flate2::read::GzDecoder::new(&compressed[..]);
-            let mut decompressed = Vec::with_capacity(data.size as usize);
-            decoder.read_to_end(&mut decompressed)
-                .map_err(|e| XarError::DecompressionFailed(format!("gzip: {}", e)))?;
-            let len = decompressed.len() as u64;
-            writer.write_all(&decompressed)?;
-            Ok(len)
+            let decoder = flate2::read::GzDecoder::new(&compressed[..]);
+            copy_decoded(decoder, data.size, "gzip", writer)
         }
         "application/x-bzip2" => {
             let decoded = bzip2_decode(&compressed)?;
@@ -48,18 +70,35 @@ pub fn read_entry<R: Read + Seek, W: Write>(
             Ok(len)
         }
         "application/zlib" | "application/x-zlib" => {
-            let mut decoder = ZlibDecoder::new(&compressed[..]);
-            let mut decompressed = Vec::with_capacity(data.size as usize);
-            decoder.read_to_end(&mut decompressed)
-                .map_err(|e| XarError::DecompressionFailed(format!("zlib: {}", e)))?;
-            let len = decompressed.len() as u64;
-            writer.write_all(&decompressed)?;
-            Ok(len)
+            let decoder = ZlibDecoder::new(&compressed[..]);
+            copy_decoded(decoder, data.size, "zlib", writer)
+        }
+        "application/x-xz" => {
+            let decoder = xz2::read::XzDecoder::new(&compressed[..]);
+            copy_decoded(decoder, data.size, "xz", writer)
+        }
+        "application/x-lzma" => {
+            // Legacy .lzma (LZMA_Alone) stream, not the .xz container
+            let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)
+                .map_err(|e| XarError::DecompressionFailed(format!("lzma: {}", e)))?;
+            let decoder = xz2::read::XzDecoder::new_stream(&compressed[..], stream);
+            copy_decoded(decoder, data.size, "lzma", writer)
         }
         other => Err(XarError::UnsupportedEncoding(other.to_string())),
     }
 }
 
+/// Decode a whole heap entry, then write it out.
+/// `size` is the uncompressed size from the TOC, used only as a capacity hint.
+fn copy_decoded<D: Read, W: Write>(mut decoder: D, size: u64, label: &str, mut writer: W) -> Result<u64> {
+    let mut decompressed = Vec::with_capacity(size as usize);
+    decoder.read_to_end(&mut decompressed)
+        .map_err(|e| XarError::DecompressionFailed(format!("{}: {}", label, e)))?;
+    let len = decompressed.len() as u64;
+    writer.write_all(&decompressed)?;
+    Ok(len)
+}
+
 /// Decompress bzip2 data (if bzip2 support is available)
 fn bzip2_decode(_data: &[u8]) -> Result<Vec<u8>> {
     // Use flate2-style manual decompression approach
@@ -69,3 +108,69 @@ fn bzip2_decode(_data: &[u8]) -> Result<Vec<u8>> {
         "application/x-bzip2 (bzip2 not enabled)".to_string(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toc::{XarFileData, XarFileType};
+    use std::io::{Cursor, Write};
+
+    fn entry(encoding: &str, length: usize, size: usize) -> XarFile {
+        XarFile {
+            id: 1,
+            name: "Payload".to_string(),
+            path: "Payload".to_string(),
+            file_type: XarFileType::File,
+            data: Some(XarFileData {
+                offset: 0,
+                length: length as u64,
+                size: size as u64,
+                encoding: encoding.to_string(),
+                extracted_checksum: None,
+                archived_checksum: None,
+            }),
+            children: Vec::new(),
+            parent: None,
+        }
+    }
+
+    fn read_heap(heap: &[u8], encoding: &str, size: usize) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        read_entry(&mut Cursor::new(heap), 0, &entry(encoding, heap.len(), size), &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_read_xz_entry() {
+        let original = b"xz encoded heap entry ".repeat(200);
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&original).unwrap();
+        let heap = encoder.finish().unwrap();
+
+        assert_eq!(read_heap(&heap, "application/x-xz", original.len()).unwrap(), original);
+        assert!(matches!(
+            read_heap(&heap[..heap.len() / 2], "application/x-xz", original.len()),
+            Err(XarError::DecompressionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_lzma_entry() {
+        let original = b"lzma encoded heap entry ".repeat(200);
+        let options = xz2::stream::LzmaOptions::new_preset(6).unwrap();
+        let stream = xz2::stream::Stream::new_lzma_encoder(&options).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+        encoder.write_all(&original).unwrap();
+        let heap = encoder.finish().unwrap();
+
+        assert_eq!(read_heap(&heap, "application/x-lzma", original.len()).unwrap(), original);
+    }
+
+    #[test]
+    fn test_unsupported_encoding() {
+        assert!(matches!(
+            read_heap(b"data", "application/x-lz4", 4),
+            Err(XarError::UnsupportedEncoding(e)) if e == "application/x-lz4"
+        ));
+    }
+}
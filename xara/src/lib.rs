@@ -1,27 +1,59 @@
+//! Read-only XAR archive and macOS PKG package parser.
+//!
+//! With `default-features = false` the crate builds as `no_std` + `alloc` and
+//! only exposes [`XarHeader::from_bytes`]; TOC parsing, heap access and the
+//! PKG reader require the `std` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod error;
 pub mod header;
+#[cfg(feature = "std")]
 pub mod toc;
+#[cfg(feature = "std")]
 pub mod heap;
+#[cfg(feature = "std")]
 pub mod pkg;
+#[cfg(feature = "std")]
+pub mod bom;
 
 pub use error::{XarError, Result};
 pub use header::XarHeader;
+#[cfg(feature = "std")]
 pub use toc::{XarFile, XarFileType, XarFileData};
+#[cfg(feature = "std")]
 pub use pkg::PkgReader;
+#[cfg(feature = "bom-verify")]
+pub use pkg::{BomMismatch, BomVerifyReport};
+#[cfg(feature = "std")]
+pub use bom::{Bom, BomEntry, BomEntryKind};
 
+#[cfg(feature = "std")]
 use std::io::{Read, Seek, Write};
 
 /// XAR archive reader
-pub struct XarArchive<R: Read + Seek> {
+///
+/// File contents can only be read when `R` is also `Seek`; an archive
+/// opened with [`open_toc_only`](Self::open_toc_only) from a plain stream
+/// exposes just the header and file list.
+#[cfg(feature = "std")]
+pub struct XarArchive<R> {
     reader: R,
     pub(crate) header: XarHeader,
     pub(crate) files: Vec<XarFile>,
     pub(crate) heap_offset: u64,
 }
 
-impl<R: Read + Seek> XarArchive<R> {
-    /// Open and parse a XAR archive
-    pub fn open(mut reader: R) -> Result<Self> {
+#[cfg(feature = "std")]
+impl<R: Read> XarArchive<R> {
+    /// Parse only the header and TOC, for listing a pkg from a stream
+    ///
+    /// Needs neither `Seek` nor the heap: exactly the header and compressed
+    /// TOC are consumed, leaving `reader` at the start of the heap (see
+    /// [`into_reader`](Self::into_reader)).
+    pub fn open_toc_only(mut reader: R) -> Result<Self> {
         let header = header::parse_header(&mut reader)?;
         let (files, heap_offset) = toc::parse_toc(&mut reader, &header)?;
         Ok(XarArchive {
@@ -31,7 +63,10 @@ impl<R: Read + Seek> XarArchive<R> {
             heap_offset,
         })
     }
+}
 
+#[cfg(feature = "std")]
+impl<R> XarArchive<R> {
     /// Access the parsed header
     pub fn header(&self) -> &XarHeader {
         &self.header
@@ -47,6 +82,19 @@ impl<R: Read + Seek> XarArchive<R> {
         toc::find_by_path(&self.files, path)
     }
 
+    /// Recover the underlying reader
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> XarArchive<R> {
+    /// Open and parse a XAR archive
+    pub fn open(reader: R) -> Result<Self> {
+        Self::open_toc_only(reader)
+    }
+
     /// Read a file entry into memory
     pub fn read_file(&mut self, file: &XarFile) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
@@ -58,9 +106,17 @@ impl<R: Read + Seek> XarArchive<R> {
     pub fn read_file_to<W: Write>(&mut self, file: &XarFile, writer: W) -> Result<u64> {
         heap::read_entry(&mut self.reader, self.heap_offset, file, writer)
     }
+
+    /// Read a file entry's bytes exactly as archived in the heap
+    ///
+    /// The TOC encoding (gzip, bzip2, ...) is not undone, so the result is
+    /// `data.length` bytes rather than `data.size`.
+    pub fn read_raw(&mut self, file: &XarFile) -> Result<Vec<u8>> {
+        heap::read_raw_entry(&mut self.reader, self.heap_offset, file)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use std::io::Cursor;
@@ -161,4 +217,108 @@ mod tests {
         let data = archive.read_file(&file_clone).unwrap();
         assert_eq!(&data, b"hello");
     }
+
+    #[test]
+    fn test_open_toc_only() {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let toc_xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<xar>
+  <toc>
+    <file id="1">
+      <name>Distribution</name>
+      <type>file</type>
+      <data>
+        <offset>0</offset>
+        <length>4</length>
+        <size>4</size>
+        <encoding style="application/octet-stream"/>
+      </data>
+    </file>
+    <file id="2">
+      <name>Base.pkg</name>
+      <type>directory</type>
+    </file>
+  </toc>
+</xar>"#;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(toc_xml).unwrap();
+        let compressed_toc = encoder.finish().unwrap();
+
+        let mut xar_buf = Vec::new();
+        xar_buf.extend_from_slice(&0x78617221u32.to_be_bytes());
+        xar_buf.extend_from_slice(&28u16.to_be_bytes());
+        xar_buf.extend_from_slice(&1u16.to_be_bytes());
+        xar_buf.extend_from_slice(&(compressed_toc.len() as u64).to_be_bytes());
+        xar_buf.extend_from_slice(&(toc_xml.len() as u64).to_be_bytes());
+        xar_buf.extend_from_slice(&0u32.to_be_bytes());
+        xar_buf.extend_from_slice(&compressed_toc);
+        xar_buf.extend_from_slice(b"heap");
+
+        // `&[u8]` is `Read` but not `Seek`, like a network stream
+        let archive = XarArchive::open_toc_only(&xar_buf[..]).unwrap();
+        let names: Vec<&str> = archive.files().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Distribution", "Base.pkg"]);
+        assert_eq!(archive.header().toc_compressed_len, compressed_toc.len() as u64);
+        assert_eq!(archive.find("Base.pkg").unwrap().file_type, XarFileType::Directory);
+
+        // The stream is left at the start of the heap
+        assert_eq!(archive.into_reader(), b"heap");
+
+        let archive = XarArchive::open(Cursor::new(&xar_buf)).unwrap();
+        assert_eq!(archive.into_reader().position(), xar_buf.len() as u64 - 4);
+    }
+
+    #[test]
+    fn test_truncated_heap() {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let toc_xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<xar>
+  <toc>
+    <file id="1">
+      <name>Payload</name>
+      <type>file</type>
+      <data>
+        <offset>0</offset>
+        <length>100</length>
+        <size>100</size>
+        <encoding style="application/octet-stream"/>
+      </data>
+    </file>
+  </toc>
+</xar>"#;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(toc_xml).unwrap();
+        let compressed_toc = encoder.finish().unwrap();
+
+        let mut xar_buf = Vec::new();
+        xar_buf.extend_from_slice(&0x78617221u32.to_be_bytes());
+        xar_buf.extend_from_slice(&28u16.to_be_bytes());
+        xar_buf.extend_from_slice(&1u16.to_be_bytes());
+        xar_buf.extend_from_slice(&(compressed_toc.len() as u64).to_be_bytes());
+        xar_buf.extend_from_slice(&(toc_xml.len() as u64).to_be_bytes());
+        xar_buf.extend_from_slice(&0u32.to_be_bytes());
+        xar_buf.extend_from_slice(&compressed_toc);
+        xar_buf.extend_from_slice(&[0u8; 40]); // heap cut short
+
+        let heap_offset = 28 + compressed_toc.len() as u64;
+        let mut archive = XarArchive::open(Cursor::new(&xar_buf)).unwrap();
+        let file = archive.find("Payload").unwrap().clone();
+
+        match archive.read_file(&file) {
+            Err(XarError::Truncated { name, needed, available }) => {
+                assert_eq!(name, "Payload");
+                assert_eq!(needed, heap_offset + 100);
+                assert_eq!(available, heap_offset + 40);
+            }
+            other => panic!("expected Truncated, got {:?}", other.map(|d| d.len())),
+        }
+    }
 }
@@ -1,9 +1,66 @@
+#[cfg(feature = "bom-verify")]
+use std::collections::HashMap;
 use std::io::{Read, Seek, Write};
 
+use crate::bom::Bom;
+#[cfg(feature = "bom-verify")]
+use crate::bom::BomEntryKind;
 use crate::error::{XarError, Result};
 use crate::toc::XarFileType;
 use crate::XarArchive;
 
+/// gzip member magic (RFC 1952)
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// Path of `name` inside a component ("" for a component package)
+fn component_path(component: &str, name: &str) -> String {
+    if component.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", component, name)
+    }
+}
+
+/// Differences between a component's `Bom` and its payload
+#[cfg(feature = "bom-verify")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BomVerifyReport {
+    /// Paths listed in the BOM but absent from the payload
+    pub missing: Vec<String>,
+    /// Paths in the payload that the BOM does not list
+    pub extra: Vec<String>,
+    /// Paths whose size or mode differ
+    pub mismatched: Vec<BomMismatch>,
+}
+
+#[cfg(feature = "bom-verify")]
+impl BomVerifyReport {
+    /// True when the payload matches the BOM exactly
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// A field that differs between a BOM record and its payload entry
+#[cfg(feature = "bom-verify")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BomMismatch {
+    pub path: String,
+    /// `"size"` or `"mode"`
+    pub field: &'static str,
+    pub bom: u64,
+    pub payload: u64,
+}
+
+/// Path key shared by BOM and cpio listings ("./a/b" and "a/b" compare equal)
+#[cfg(feature = "bom-verify")]
+fn normalize_path(path: &str) -> &str {
+    match path.trim_start_matches("./").trim_start_matches('/') {
+        "" => ".",
+        p => p,
+    }
+}
+
 /// High-level reader for macOS .pkg (flat package) files.
 /// Wraps a XarArchive with PKG-specific knowledge.
 pub struct PkgReader<R: Read + Seek> {
@@ -51,10 +108,10 @@ impl<R: Read + Seek> PkgReader<R> {
         }
 
         // If no .pkg directories found, this is a component package
-        if components.is_empty() {
-            if self.xar.find("Payload").is_some() || self.xar.find("PackageInfo").is_some() {
-                components.push(String::new());
-            }
+        if components.is_empty()
+            && (self.xar.find("Payload").is_some() || self.xar.find("PackageInfo").is_some())
+        {
+            components.push(String::new());
         }
 
         components
@@ -62,11 +119,7 @@ impl<R: Read + Seek> PkgReader<R> {
 
     /// Get PackageInfo XML for a component
     pub fn package_info(&mut self, component: &str) -> Result<Option<String>> {
-        let path = if component.is_empty() {
-            "PackageInfo".to_string()
-        } else {
-            format!("{}/PackageInfo", component)
-        };
+        let path = component_path(component, "PackageInfo");
 
         match self.xar.find(&path) {
             Some(file) => {
@@ -80,11 +133,7 @@ impl<R: Read + Seek> PkgReader<R> {
 
     /// Extract Payload (PBZX data) for a component into memory
     pub fn payload(&mut self, component: &str) -> Result<Vec<u8>> {
-        let path = if component.is_empty() {
-            "Payload".to_string()
-        } else {
-            format!("{}/Payload", component)
-        };
+        let path = component_path(component, "Payload");
 
         match self.xar.find(&path) {
             Some(file) => {
@@ -97,11 +146,7 @@ impl<R: Read + Seek> PkgReader<R> {
 
     /// Stream Payload to a writer
     pub fn payload_to<W: Write>(&mut self, component: &str, writer: W) -> Result<u64> {
-        let path = if component.is_empty() {
-            "Payload".to_string()
-        } else {
-            format!("{}/Payload", component)
-        };
+        let path = component_path(component, "Payload");
 
         match self.xar.find(&path) {
             Some(file) => {
@@ -112,6 +157,122 @@ impl<R: Read + Seek> PkgReader<R> {
         }
     }
 
+    /// Get the Payload for a component exactly as stored in the XAR heap
+    ///
+    /// Unlike [`payload`](Self::payload), the XAR encoding is not undone
+    /// either, so this is the byte-for-byte archived entry. Useful for
+    /// inspecting payloads that are not PBZX (e.g. gzip'd cpio).
+    pub fn raw_payload(&mut self, component: &str) -> Result<Vec<u8>> {
+        let path = component_path(component, "Payload");
+
+        match self.xar.find(&path) {
+            Some(file) => {
+                let file = file.clone();
+                self.xar.read_raw(&file)
+            }
+            None => Err(XarError::FileNotFound(path)),
+        }
+    }
+
+    /// Get the `Scripts` archive for a component, decompressed
+    ///
+    /// `Scripts` holds the preinstall/postinstall scripts as a gzip'd odc
+    /// cpio; the returned bytes are the cpio archive itself and can be read
+    /// with `pbzx::cpio::CpioReader`. Data that is not gzip'd is returned as
+    /// stored. Returns `Ok(None)` if the component has no scripts.
+    pub fn scripts(&mut self, component: &str) -> Result<Option<Vec<u8>>> {
+        let path = component_path(component, "Scripts");
+        let file = match self.xar.find(&path) {
+            Some(file) => file.clone(),
+            None => return Ok(None),
+        };
+
+        let data = self.xar.read_file(&file)?;
+        if !data.starts_with(&GZIP_MAGIC) {
+            return Ok(Some(data));
+        }
+
+        let mut decoder = flate2::read::GzDecoder::new(&data[..]);
+        let mut cpio = Vec::new();
+        decoder
+            .read_to_end(&mut cpio)
+            .map_err(|e| XarError::DecompressionFailed(format!("Scripts gzip: {}", e)))?;
+        Ok(Some(cpio))
+    }
+
+    /// Get the parsed `Bom` for a component, or `None` if it has none
+    pub fn bom(&mut self, component: &str) -> Result<Option<Bom>> {
+        let path = component_path(component, "Bom");
+        let file = match self.xar.find(&path) {
+            Some(file) => file.clone(),
+            None => return Ok(None),
+        };
+        let data = self.xar.read_file(&file)?;
+        Bom::parse(&data).map(Some)
+    }
+
+    /// Compare a component's payload listing against its `Bom`
+    ///
+    /// The payload (PBZX, gzip'd or plain cpio) is decompressed and listed;
+    /// every BOM path must appear in it with the same mode, and regular
+    /// files with the same size. Hard links stored without data (newc keeps
+    /// it on one link only) are not size-checked. Fails with `FileNotFound`
+    /// if the component has no `Bom`.
+    #[cfg(feature = "bom-verify")]
+    pub fn verify_payload_against_bom(&mut self, component: &str) -> Result<BomVerifyReport> {
+        let bom = self
+            .bom(component)?
+            .ok_or_else(|| XarError::FileNotFound(component_path(component, "Bom")))?;
+
+        let payload = self.payload(component)?;
+        let cpio = if payload.starts_with(b"pbzx") {
+            pbzx::PbzxReader::new(std::io::Cursor::new(payload))?.decompress()?
+        } else if payload.starts_with(&GZIP_MAGIC) {
+            let mut cpio = Vec::new();
+            flate2::read::GzDecoder::new(&payload[..])
+                .read_to_end(&mut cpio)
+                .map_err(|e| XarError::DecompressionFailed(format!("Payload gzip: {}", e)))?;
+            cpio
+        } else {
+            payload
+        };
+        let headers = pbzx::CpioReader::new(std::io::Cursor::new(cpio)).headers()?;
+
+        let mut payload_entries: HashMap<&str, &pbzx::CpioHeader> =
+            headers.iter().map(|h| (normalize_path(&h.name), h)).collect();
+
+        let mut report = BomVerifyReport::default();
+        for record in bom.entries() {
+            let Some(entry) = payload_entries.remove(normalize_path(&record.path)) else {
+                report.missing.push(record.path.clone());
+                continue;
+            };
+            if record.mode != entry.mode as u16 {
+                report.mismatched.push(BomMismatch {
+                    path: record.path.clone(),
+                    field: "mode",
+                    bom: record.mode as u64,
+                    payload: entry.mode as u64 & 0xFFFF,
+                });
+            }
+            let dataless_link = entry.nlink > 1 && entry.filesize == 0;
+            if record.kind == BomEntryKind::File && !dataless_link && record.size != entry.filesize {
+                report.mismatched.push(BomMismatch {
+                    path: record.path.clone(),
+                    field: "size",
+                    bom: record.size as u64,
+                    payload: entry.filesize as u64,
+                });
+            }
+        }
+        report.extra = headers
+            .iter()
+            .filter(|h| payload_entries.contains_key(normalize_path(&h.name)))
+            .map(|h| h.name.clone())
+            .collect();
+        Ok(report)
+    }
+
     /// Access the underlying XAR archive
     pub fn xar(&self) -> &XarArchive<R> {
         &self.xar
@@ -127,3 +288,185 @@ impl<R: Read + Seek> PkgReader<R> {
         self.xar.files().iter().map(|f| f.path.clone()).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+
+    /// Build a XAR with uncompressed entries at the given paths (at most one
+    /// level of directory nesting).
+    fn build_xar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut files = String::new();
+        let mut dirs: BTreeMap<&str, String> = BTreeMap::new();
+        let mut heap = Vec::new();
+        for (id, (path, data)) in entries.iter().enumerate() {
+            let (dir, name) = match path.split_once('/') {
+                Some((dir, name)) => (Some(dir), name),
+                None => (None, *path),
+            };
+            let file = format!(
+                "<file id=\"{}\"><name>{}</name><type>file</type><data>\
+                 <offset>{}</offset><length>{}</length><size>{}</size>\
+                 <encoding style=\"application/octet-stream\"/></data></file>",
+                id + 1,
+                name,
+                heap.len(),
+                data.len(),
+                data.len()
+            );
+            heap.extend_from_slice(data);
+            match dir {
+                Some(dir) => dirs.entry(dir).or_default().push_str(&file),
+                None => files.push_str(&file),
+            }
+        }
+        for (id, (dir, children)) in dirs.iter().enumerate() {
+            files.push_str(&format!(
+                "<file id=\"{}\"><name>{}</name><type>directory</type>{}</file>",
+                100 + id,
+                dir,
+                children
+            ));
+        }
+        let toc_xml = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?><xar><toc>{}</toc></xar>", files);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(toc_xml.as_bytes()).unwrap();
+        let compressed_toc = encoder.finish().unwrap();
+
+        let mut xar = Vec::new();
+        xar.extend_from_slice(&0x78617221u32.to_be_bytes());
+        xar.extend_from_slice(&28u16.to_be_bytes());
+        xar.extend_from_slice(&1u16.to_be_bytes());
+        xar.extend_from_slice(&(compressed_toc.len() as u64).to_be_bytes());
+        xar.extend_from_slice(&(toc_xml.len() as u64).to_be_bytes());
+        xar.extend_from_slice(&0u32.to_be_bytes());
+        xar.extend_from_slice(&compressed_toc);
+        xar.extend_from_slice(&heap);
+        xar
+    }
+
+    /// A one-entry odc cpio holding `postinstall`, with trailer
+    fn scripts_cpio() -> Vec<u8> {
+        let mut cpio = Vec::new();
+        for (name, mode, data) in [
+            ("./postinstall", 0o100755, &b"#!/bin/sh\nexit 0\n"[..]),
+            ("TRAILER!!!", 0, &b""[..]),
+        ] {
+            cpio.extend_from_slice(
+                format!(
+                    "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+                    0, 0, mode, 0, 0, 1, 0, 0, name.len() + 1, data.len()
+                )
+                .as_bytes(),
+            );
+            cpio.extend_from_slice(name.as_bytes());
+            cpio.push(0);
+            cpio.extend_from_slice(data);
+        }
+        cpio
+    }
+
+    #[test]
+    fn test_scripts() {
+        let cpio = scripts_cpio();
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&cpio).unwrap();
+        let gzipped = gz.finish().unwrap();
+
+        let xar = build_xar(&[
+            ("Distribution", b"<installer-gui-script/>"),
+            ("app.pkg/Payload", b"pbzx"),
+            ("app.pkg/Scripts", &gzipped),
+            ("docs.pkg/Payload", b"pbzx"),
+        ]);
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+
+        let scripts = pkg.scripts("app.pkg").unwrap().unwrap();
+        assert_eq!(scripts, cpio);
+        assert!(pkg.scripts("docs.pkg").unwrap().is_none());
+        assert!(pkg.scripts("missing.pkg").unwrap().is_none());
+        assert_eq!(pkg.components(), vec!["app.pkg", "docs.pkg"]);
+    }
+
+    #[test]
+    fn test_scripts_component_package() {
+        let cpio = scripts_cpio();
+        let xar = build_xar(&[("Payload", b"pbzx"), ("Scripts", &cpio)]);
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+
+        // Stored without gzip: returned as-is
+        assert_eq!(pkg.scripts("").unwrap().unwrap(), cpio);
+    }
+
+    #[test]
+    fn test_raw_payload() {
+        // A gzip'd cpio payload, not PBZX
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&scripts_cpio()).unwrap();
+        let payload = gz.finish().unwrap();
+        let xar = build_xar(&[("foo.pkg/Payload", &payload), ("foo.pkg/PackageInfo", b"<pkg-info/>")]);
+
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+        assert_eq!(pkg.raw_payload("foo.pkg").unwrap(), payload);
+        assert!(matches!(pkg.raw_payload("bar.pkg"), Err(XarError::FileNotFound(p)) if p == "bar.pkg/Payload"));
+    }
+
+    #[cfg(feature = "bom-verify")]
+    #[test]
+    fn test_verify_payload_against_bom() {
+        let mut builder = pbzx::CpioBuilder::new();
+        builder.add_directory(".", 0o755);
+        builder.add_directory("./Applications", 0o775);
+        builder.add_file("./Applications/Tool", &[0x42; 1234], 0o755);
+        builder.add_symlink("./Applications/tool-link", "Tool", 0o755);
+        // Stored with no data; the BOM still records the full size
+        builder.add_hardlink("./Applications/tool-hardlink", "./Applications/Tool").unwrap();
+        let mut writer = pbzx::PbzxWriter::new(Vec::new());
+        writer.write_cpio(&builder.finish()).unwrap();
+        let payload = writer.finish().unwrap();
+
+        let matching = crate::bom::build_bom(&[
+            (1, 0, ".", 2, 0o40755, 0, None),
+            (2, 1, "Applications", 2, 0o40775, 0, None),
+            (3, 2, "Tool", 1, 0o100755, 1234, None),
+            (4, 2, "tool-link", 3, 0o120755, 4, Some("Tool")),
+            (6, 2, "tool-hardlink", 1, 0o100755, 1234, None),
+        ]);
+        let xar = build_xar(&[("app.pkg/Payload", &payload), ("app.pkg/Bom", &matching)]);
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+        let report = pkg.verify_payload_against_bom("app.pkg").unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+
+        // Different size and mode, a path the payload lacks, and no tool-link
+        let mismatching = crate::bom::build_bom(&[
+            (1, 0, ".", 2, 0o40755, 0, None),
+            (2, 1, "Applications", 2, 0o40755, 0, None),
+            (3, 2, "Tool", 1, 0o100755, 999, None),
+            (5, 2, "Helper", 1, 0o100644, 10, None),
+            (6, 2, "tool-hardlink", 1, 0o100755, 1234, None),
+        ]);
+        let xar = build_xar(&[("app.pkg/Payload", &payload), ("app.pkg/Bom", &mismatching)]);
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+        let report = pkg.verify_payload_against_bom("app.pkg").unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.missing, ["./Applications/Helper"]);
+        assert_eq!(report.extra, ["./Applications/tool-link"]);
+        assert_eq!(
+            report.mismatched,
+            [
+                BomMismatch { path: "./Applications".into(), field: "mode", bom: 0o40755, payload: 0o40775 },
+                BomMismatch { path: "./Applications/Tool".into(), field: "size", bom: 999, payload: 1234 },
+            ]
+        );
+
+        let xar = build_xar(&[("app.pkg/Payload", &payload)]);
+        let mut pkg = PkgReader::open(Cursor::new(xar)).unwrap();
+        assert!(pkg.bom("app.pkg").unwrap().is_none());
+        assert!(matches!(pkg.verify_payload_against_bom("app.pkg"), Err(XarError::FileNotFound(_))));
+    }
+}